@@ -0,0 +1,15 @@
+//! Regenerates `include/etherea.h` from the `ffi` module, when building with
+//! the `ffi` feature.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    #[cfg(feature = "ffi")]
+    {
+        let dir = std::env::var("CARGO_MANIFEST_DIR").expect("Cargo sets the manifest directory");
+        cbindgen::generate(&dir)
+            .expect("Could not generate the C header")
+            .write_to_file(std::path::Path::new(&dir).join("include/etherea.h"));
+    }
+}
@@ -0,0 +1,50 @@
+//! Executes random bytes as a ROM without a window, under quirks picked by
+//! the first byte, so that anything the interpreter can be fed only ever
+//! stops it with a fault.
+
+#![no_main]
+
+use etherea::{
+    quirks::{IndexOverflow, JumpOffset, PcOverflow, Quirks},
+    Interpreter,
+};
+use libfuzzer_sys::fuzz_target;
+
+/// How many frames each input runs for.
+const FRAMES: u64 = 10;
+
+fuzz_target!(|data: &[u8]| {
+    let Some((&flags, rom)) = data.split_first() else {
+        return;
+    };
+    let flag = |bit: u8| flags & 1 << bit != 0;
+    let mut intr = Interpreter::new();
+    intr.with_seed(u64::from(flags));
+    intr.with_quirks(Quirks {
+        index_overflow: if flag(0) {
+            IndexOverflow::Fault
+        } else {
+            IndexOverflow::Mask
+        },
+        pc_overflow: if flag(1) {
+            PcOverflow::Fault
+        } else {
+            PcOverflow::Wrap
+        },
+        jump_offset: if flag(2) {
+            JumpOffset::Vx
+        } else {
+            JumpOffset::V0
+        },
+        display_wait: flag(3),
+        stack_depth: usize::from(flags >> 6) * 8,
+    });
+    if flag(4) {
+        intr.with_banking();
+    } else if flag(5) {
+        intr.with_chip8x();
+    }
+    intr.load_rom(rom);
+    let _ = intr.run_frames(FRAMES);
+    let _ = intr.framebuffer().to_string();
+});
@@ -0,0 +1,68 @@
+/* The etherea CHIP-8 interpreter, for C and C++. Generated from src/ffi.rs; don't edit. */
+
+#ifndef ETHEREA_H
+#define ETHEREA_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An interpreter, and the fault that stopped it, if one did. Opaque to C.
+typedef struct EthereaMachine EthereaMachine;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an interpreter with nothing loaded. Free it with
+// [`etherea_destroy`].
+struct EthereaMachine *etherea_create(void);
+
+// Frees an interpreter made with [`etherea_create`]. Does nothing if
+// `machine` is null.
+void etherea_destroy(struct EthereaMachine *machine);
+
+// Loads the `len` bytes at `rom`, resetting the interpreter.
+//
+// # Safety
+// `rom` must point to `len` readable bytes, or be null if `len` is `0`.
+void etherea_load_rom(struct EthereaMachine *machine, const uint8_t *rom, uintptr_t len);
+
+// Executes `instructions` instructions, without ticking the timers.
+// Returns `false` if a fault stopped the ROM, as [`etherea_fault`]
+// describes.
+bool etherea_step(struct EthereaMachine *machine, uint64_t instructions);
+
+// Runs `frames` frames as fast as possible, each executing its share of
+// instructions and ticking the timers once. Returns `false` if a fault
+// stopped the ROM, as [`etherea_fault`] describes.
+bool etherea_run_frames(struct EthereaMachine *machine, uint64_t frames);
+
+// The fault that stopped the ROM, as text, or null if it hasn't faulted.
+// Valid until the interpreter next runs or is destroyed.
+const char *etherea_fault(const struct EthereaMachine *machine);
+
+// The width of the screen, in pixels: 64, unless the ROM switched to a
+// wider resolution.
+uint16_t etherea_width(const struct EthereaMachine *machine);
+
+// The screen's RGBA pixels, row by row, [`etherea_width`] wide and
+// `*height` tall: 64 for HIRES CHIP-8 ROMs, and 32 otherwise, unless the
+// ROM switched resolution. Lit pixels are white, and unlit ones
+// transparent black. Valid until the interpreter next runs or is
+// destroyed.
+//
+// # Safety
+// `height` must be null, or point to a writable byte.
+const uint8_t *etherea_framebuffer(struct EthereaMachine *machine, uint8_t *height);
+
+// Presses (`pressed` true) or lets go of CHIP-8 key `key`, from `0x0` to
+// `0xF`.
+void etherea_set_key(struct EthereaMachine *machine, uint8_t key, bool pressed);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* ETHEREA_H */
@@ -0,0 +1,239 @@
+use crate::{
+    asm::ORIGIN,
+    disasm::{self, successors, word},
+    hash::Hashes,
+    opcode::{Doc, Opcode, Platform},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// The deepest the call stack can get before the original interpreters
+/// ran out of room.
+const STACK_LIMIT: usize = crate::quirks::Quirks::STACK_DEPTH;
+
+/// The extension that introduced the instruction `raw`, if it isn't part of
+/// CHIP-8 itself.
+fn extension(raw: u16) -> Option<Platform> {
+    Doc::lookup(raw)
+        .filter(|doc| !doc.platforms.contains(&Platform::Chip8))
+        .and_then(|doc| doc.platforms.first().copied())
+}
+
+/// Statistics about a ROM, gathered without running it by following
+/// control flow from `0x200`.
+///
+/// Control flow can't be followed past instructions the interpreter doesn't
+/// decode, so for ROMs that need an extension the statistics may only cover
+/// the code reachable before the first extension instruction.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Analysis {
+    /// The size of the ROM, in bytes.
+    pub size: usize,
+    /// Checksums of the ROM, to tell which version of it was analyzed.
+    pub hashes: Hashes,
+    /// The number of reachable instructions.
+    pub instructions: usize,
+    /// The number of reachable instructions of each kind, keyed by pattern.
+    pub histogram: BTreeMap<&'static str, usize>,
+    /// The reachable instructions that need an extension, by address.
+    pub extensions: BTreeMap<u16, (Platform, u16)>,
+    /// The reachable words that aren't instructions at all, by address.
+    pub invalid: BTreeMap<u16, u16>,
+    /// The deepest the call stack gets, or `None` if it can exceed
+    /// [`STACK_LIMIT`], e.g. because of recursion.
+    pub stack_depth: Option<usize>,
+    /// The addresses loaded into `I` by `LD I, addr`.
+    pub references: BTreeSet<u16>,
+    /// Whether memory is written through an `I` pointing into code. This is
+    /// a heuristic: `I` can also be computed at runtime.
+    pub self_modifying: bool,
+}
+
+impl Analysis {
+    /// Analyzes `rom`.
+    #[must_use]
+    pub fn new(rom: &[u8]) -> Self {
+        let flow = disasm::trace(rom);
+        let mut histogram = BTreeMap::new();
+        let mut extensions = BTreeMap::new();
+        let mut references = BTreeSet::new();
+        let mut writes = false;
+        for &address in &flow.code {
+            let raw = word(rom, address).unwrap_or_default();
+            let Ok(opcode) = Opcode::try_from(raw) else {
+                continue;
+            };
+            // Some extension instructions, like 00FF and DXY0, are also
+            // valid (if not very useful) CHIP-8 instructions.
+            if let Some(extension) = extension(raw) {
+                extensions.insert(as_u16(address), (extension, raw));
+            }
+            *histogram.entry(opcode.pattern()).or_default() += 1;
+            match opcode {
+                Opcode::LoadIndex(address) => {
+                    references.insert(address);
+                }
+                Opcode::Bcd { .. } | Opcode::Store { .. } => writes = true,
+                _ => {}
+            }
+        }
+        let mut invalid = BTreeMap::new();
+        for &address in &flow.unknown {
+            let raw = word(rom, address).unwrap_or_default();
+            if let Some(extension) = extension(raw) {
+                extensions.insert(as_u16(address), (extension, raw));
+            } else {
+                invalid.insert(as_u16(address), raw);
+            }
+        }
+        let self_modifying = writes
+            && references.iter().any(|&target| {
+                let target = usize::from(target);
+                flow.code.contains(&target) || flow.code.contains(&target.wrapping_sub(1))
+            });
+
+        Self {
+            size: rom.len(),
+            hashes: Hashes::of(rom),
+            instructions: flow.code.len(),
+            histogram,
+            extensions,
+            invalid,
+            stack_depth: stack_depth(rom),
+            references,
+            self_modifying,
+        }
+    }
+
+    /// The extensions the ROM needs to run.
+    #[must_use]
+    pub fn requirements(&self) -> BTreeSet<Platform> {
+        self.extensions
+            .values()
+            .map(|&(extension, _)| extension)
+            .collect()
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Size:            {} bytes", self.size)?;
+        writeln!(f, "FNV-1a:          {:016x}", self.hashes.fnv)?;
+        writeln!(f, "CRC-32:          {:08x}", self.hashes.crc32)?;
+        writeln!(f, "SHA-1:           {}", self.hashes.sha1)?;
+        writeln!(f, "Instructions:    {} reachable", self.instructions)?;
+
+        let requirements: Vec<String> = self
+            .requirements()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        if requirements.is_empty() {
+            writeln!(f, "Requires:        CHIP-8")?;
+        } else {
+            writeln!(f, "Requires:        {}", requirements.join(", "))?;
+        }
+        for (address, (extension, raw)) in &self.extensions {
+            writeln!(f, "  {address:#05X}  {raw:04X}  {extension}")?;
+        }
+        for (address, raw) in &self.invalid {
+            writeln!(f, "  {address:#05X}  {raw:04X}  invalid instruction")?;
+        }
+
+        match self.stack_depth {
+            Some(depth) => writeln!(f, "Stack depth:     {depth}")?,
+            None => writeln!(f, "Stack depth:     over {STACK_LIMIT} (recursive calls?)")?,
+        }
+        let references: Vec<String> = self
+            .references
+            .iter()
+            .map(|address| format!("{address:#05X}"))
+            .collect();
+        writeln!(f, "I references:    [{}]", references.join(", "))?;
+        let self_modifying = if self.self_modifying { "likely" } else { "no" };
+        writeln!(f, "Self-modifying:  {self_modifying}")?;
+
+        let mut histogram: Vec<(&str, usize)> =
+            self.histogram.iter().map(|(&k, &v)| (k, v)).collect();
+        histogram.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        write!(f, "Opcodes:")?;
+        for (pattern, count) in histogram {
+            write!(f, "\n  {pattern}  {count:>5}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The deepest the call stack can get by following control flow from
+/// `0x200`, or `None` if it can exceed [`STACK_LIMIT`].
+fn stack_depth(rom: &[u8]) -> Option<usize> {
+    let mut deepest: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut pending = vec![(usize::from(ORIGIN), 0)];
+    while let Some((address, depth)) = pending.pop() {
+        if depth > STACK_LIMIT {
+            return None;
+        }
+        // Only revisit an address if it's reached with a deeper stack.
+        if deepest.get(&address).is_some_and(|&seen| seen >= depth) {
+            continue;
+        }
+        let Some(opcode) = word(rom, address).and_then(|raw| Opcode::try_from(raw).ok()) else {
+            continue;
+        };
+        deepest.insert(address, depth);
+        for (next, call) in successors(address, opcode) {
+            pending.push((next, depth + usize::from(call)));
+        }
+    }
+    Some(deepest.into_values().max().unwrap_or_default())
+}
+
+fn as_u16(address: usize) -> u16 {
+    u16::try_from(address).unwrap_or(u16::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(extension(0x00FF), Some(Platform::SuperChip));
+        assert_eq!(extension(0xF385), Some(Platform::SuperChip));
+        assert_eq!(extension(0xD120), Some(Platform::SuperChip));
+        assert_eq!(extension(0x5122), Some(Platform::XoChip));
+        assert_eq!(extension(0xF201), Some(Platform::XoChip));
+        assert_eq!(extension(0x00E0), None);
+        assert_eq!(extension(0x8124), None);
+    }
+
+    #[test]
+    fn analysis() {
+        let rom = [
+            0xA2, 0x08, // LD I, 0x208
+            0x22, 0x06, // CALL 0x206
+            0x00, 0xFF, // HIGH (SUPER-CHIP)
+            0xF1, 0x55, // LD [I], V1
+            0x00, 0xEE, // RET
+        ];
+        let analysis = Analysis::new(&rom);
+        assert_eq!(analysis.instructions, 5);
+        assert_eq!(analysis.histogram["FX55"], 1);
+        assert_eq!(
+            analysis.requirements(),
+            BTreeSet::from([Platform::SuperChip])
+        );
+        assert_eq!(analysis.stack_depth, Some(1));
+        assert_eq!(analysis.references, BTreeSet::from([0x208]));
+        assert!(analysis.self_modifying);
+        assert!(analysis.to_string().contains("\nCRC-32:          "));
+    }
+
+    #[test]
+    fn recursion() {
+        // CALL 0x200
+        assert_eq!(Analysis::new(&[0x22, 0x00]).stack_depth, None);
+    }
+}
@@ -0,0 +1,637 @@
+use crate::opcode::Doc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+/// Assembling Octo's syntax.
+pub mod octo;
+
+/// The address the assembled program is loaded at.
+pub(crate) const ORIGIN: u16 = 0x200;
+
+/// An error encountered while assembling, with the (1-indexed) line it occurred on.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Error {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Where each statement and label of a source file ended up in the
+/// assembled ROM, so debuggers can show source lines and label names, and
+/// set breakpoints on them.
+///
+/// It can be saved alongside a ROM as a symbol file, lines of text naming
+/// the source file, then the address of each label and line:
+///
+/// ```text
+/// # etherea symbols v1
+/// source game.asm
+/// label 0x200 start
+/// line 0x200 3
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    lines: BTreeMap<u16, usize>, // The line of each address that starts a statement
+    labels: BTreeMap<u16, String>, // The first label defined at each address
+    source: Option<PathBuf>,     // The source file
+    text: Vec<String>,           // The lines of the source file, if it could be read
+}
+
+impl SourceMap {
+    /// The first line of a symbol file.
+    const HEADER: &'static str = "# etherea symbols v1";
+
+    /// Where the symbol file of the ROM at `rom` is kept: beside it, with
+    /// the `.sym` extension.
+    #[must_use]
+    pub fn path(rom: &Path) -> PathBuf {
+        rom.with_extension("sym")
+    }
+
+    /// Reads the symbol file at `path`, and the source file it names,
+    /// which is relative to the symbol file. A source file that can't be
+    /// read leaves the map without source lines, rather than failing.
+    ///
+    /// # Errors
+    /// This function will error if the symbol file can't be read or is
+    /// malformed.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut map = Self::parse(&contents)?;
+        if let Some(source) = map.source.take() {
+            let source = path.parent().unwrap_or(Path::new("")).join(source);
+            let text = fs::read_to_string(&source).unwrap_or_default();
+            map = map.with_source(source, &text);
+        }
+        Ok(map)
+    }
+
+    /// Parses the contents of a symbol file.
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut lines = contents.lines();
+        if lines.next().map(str::trim) != Some(Self::HEADER) {
+            return Err("Not an etherea symbol file".into());
+        }
+        let mut map = Self::default();
+        for line in lines.map(str::trim).filter(|line| !line.is_empty()) {
+            let invalid = || format!("Invalid symbol file line '{line}'");
+            let (kind, rest) = line.split_once(' ').ok_or_else(invalid)?;
+            if kind == "source" {
+                map.source = Some(PathBuf::from(rest));
+                continue;
+            }
+            let (address, value) = rest.split_once(' ').ok_or_else(invalid)?;
+            let address = parse_number(address)
+                .and_then(|address| u16::try_from(address).ok())
+                .ok_or_else(invalid)?;
+            match kind {
+                "label" => map.label_at(address, value),
+                "line" => map.insert(address, value.parse().map_err(|_| invalid())?),
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(map)
+    }
+
+    /// Names `path` as the source file, whose contents are `text`.
+    #[must_use]
+    pub fn with_source(mut self, path: PathBuf, text: &str) -> Self {
+        self.source = Some(path);
+        self.text = text.lines().map(str::to_string).collect();
+        self
+    }
+
+    /// The source file, if one was named.
+    #[must_use]
+    pub fn source(&self) -> Option<&Path> {
+        self.source.as_deref()
+    }
+
+    /// The (1-indexed) line the statement at `address` was assembled from.
+    #[must_use]
+    pub fn line(&self, address: u16) -> Option<usize> {
+        self.lines.get(&address).copied()
+    }
+
+    /// The address of the first statement on `line`, or on the nearest line
+    /// after it with one, along with that line.
+    #[must_use]
+    pub fn address(&self, line: usize) -> Option<(u16, usize)> {
+        self.lines
+            .iter()
+            .filter(|&(_, &at)| at >= line)
+            .min_by_key(|&(&address, &at)| (at, address))
+            .map(|(&address, &at)| (address, at))
+    }
+
+    /// The source text of the statement at `address`, without leading or
+    /// trailing whitespace.
+    #[must_use]
+    pub fn text(&self, address: u16) -> Option<&str> {
+        let line = self.line(address)?;
+        self.text.get(line.checked_sub(1)?).map(|text| text.trim())
+    }
+
+    /// The label defined at `address`.
+    #[must_use]
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// The address `name` labels.
+    #[must_use]
+    pub fn resolve(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(_, label)| *label == name)
+            .map(|(&address, _)| address)
+    }
+
+    /// Describes `address` relative to the nearest label at or before it,
+    /// like `draw+4`.
+    #[must_use]
+    pub fn describe(&self, address: u16) -> Option<String> {
+        let (&at, label) = self.labels.range(..=address).next_back()?;
+        Some(match address - at {
+            0 => label.clone(),
+            offset => format!("{label}+{offset}"),
+        })
+    }
+
+    /// Records that the statement at `address` is on `line`.
+    fn insert(&mut self, address: u16, line: usize) {
+        self.lines.entry(address).or_insert(line);
+    }
+
+    /// Records that `name` labels `address`, unless another label came
+    /// first.
+    fn label_at(&mut self, address: u16, name: &str) {
+        self.labels
+            .entry(address)
+            .or_insert_with(|| name.to_string());
+    }
+}
+
+impl fmt::Display for SourceMap {
+    /// Formats the map as a symbol file.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", Self::HEADER)?;
+        if let Some(source) = &self.source {
+            writeln!(f, "source {}", source.display())?;
+        }
+        for (address, label) in &self.labels {
+            writeln!(f, "label {address:#05X} {label}")?;
+        }
+        for (address, line) in &self.lines {
+            writeln!(f, "line {address:#05X} {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single operand of an instruction or directive.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Operand {
+    /// A general purpose register, `V0` through `VF`.
+    V(u8),
+    /// The index register, `I`.
+    I,
+    /// The memory pointed to by the index register, `[I]`.
+    IndirectI,
+    /// The delay timer, `DT`.
+    Dt,
+    /// The sound timer, `ST`.
+    St,
+    /// A key press, `K`.
+    K,
+    /// A font character location, `F`.
+    F,
+    /// A BCD representation, `B`.
+    B,
+    /// The RPL user flags, `R`.
+    R,
+    /// A numeric literal, label, or constant.
+    Value(String),
+}
+
+impl Operand {
+    fn parse(s: &str) -> Self {
+        let upper = s.to_ascii_uppercase();
+        match upper.as_str() {
+            "I" => Self::I,
+            "[I]" => Self::IndirectI,
+            "DT" => Self::Dt,
+            "ST" => Self::St,
+            "K" => Self::K,
+            "F" => Self::F,
+            "B" => Self::B,
+            "R" => Self::R,
+            _ => match upper.strip_prefix('V').map(|n| u8::from_str_radix(n, 16)) {
+                Some(Ok(n)) if upper.len() == 2 => Self::V(n),
+                _ => Self::Value(s.to_string()),
+            },
+        }
+    }
+}
+
+/// A parsed, but not yet encoded, line of source.
+#[derive(Debug)]
+struct Statement {
+    line: usize,
+    mnemonic: String,
+    operands: Vec<Operand>,
+}
+
+impl Statement {
+    /// The number of bytes this statement occupies in the ROM.
+    fn size(&self) -> u16 {
+        let count = u16::try_from(self.operands.len()).unwrap_or(u16::MAX);
+        match self.mnemonic.as_str() {
+            "DB" => count,
+            "DW" => count.saturating_mul(2),
+            _ => 2,
+        }
+    }
+}
+
+/// Names bound to values: labels and constants.
+#[derive(Debug, Default)]
+struct Symbols(HashMap<String, u16>);
+
+impl Symbols {
+    /// Defines `name` as `value`, failing if it is already defined.
+    fn define(&mut self, line: usize, name: &str, value: u16) -> Result<(), Error> {
+        if !is_identifier(name) {
+            return Err(error(line, format!("invalid symbol name '{name}'")));
+        }
+        if self.0.insert(name.to_string(), value).is_some() {
+            return Err(error(line, format!("'{name}' is defined more than once")));
+        }
+        Ok(())
+    }
+
+    /// Evaluates a numeric literal or symbol.
+    fn resolve(&self, line: usize, value: &str) -> Result<u16, Error> {
+        if let Some(n) = parse_number(value) {
+            return u16::try_from(n).map_err(|_| error(line, format!("'{value}' is too large")));
+        }
+        self.0
+            .get(value)
+            .copied()
+            .ok_or_else(|| error(line, format!("undefined symbol '{value}'")))
+    }
+}
+
+/// Assembles `source` into a ROM, to be loaded at `0x200`.
+///
+/// Each line holds an optional `label:`, followed by an optional instruction
+/// or directive. Comments start with `;`. Instructions use the conventional
+/// mnemonics (`CLS`, `RET`, `JP`, `CALL`, `SE`, `SNE`, `LD`, `ADD`, `OR`,
+/// `AND`, `XOR`, `SUB`, `SHR`, `SUBN`, `SHL`, `RND`, `DRW`, `SKP`, `SKNP`,
+/// `SYS`), and the following directives are supported:
+///
+/// - `db 1, 0x2, 0b11` emits bytes
+/// - `dw 0x1234` emits big-endian words
+/// - `NAME equ 5` (or `define NAME 5`) defines a constant
+///
+/// # Errors
+/// This function will return the first error encountered, e.g. an unknown
+/// mnemonic, undefined symbol, or out-of-range operand.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Error> {
+    assemble_with_map(source).map(|(rom, _)| rom)
+}
+
+/// Assembles `source` like [`assemble`], along with where each statement
+/// ended up.
+///
+/// # Errors
+/// This function will return the first error encountered, like [`assemble`].
+pub fn assemble_with_map(source: &str) -> Result<(Vec<u8>, SourceMap), Error> {
+    let mut symbols = Symbols::default();
+    let mut map = SourceMap::default();
+    let mut statements = Vec::new();
+    let mut address = ORIGIN;
+
+    // First pass: collect labels and constants and work out where every
+    // statement ends up.
+    for (idx, line) in source.lines().enumerate() {
+        let line_number = idx + 1;
+        let mut code = line.split(';').next().unwrap_or_default().trim();
+
+        if let Some((label, rest)) = code.split_once(':') {
+            symbols.define(line_number, label.trim(), address)?;
+            map.label_at(address, label.trim());
+            code = rest.trim();
+        }
+        if code.is_empty() {
+            continue;
+        }
+
+        let (head, rest) = split_mnemonic(code);
+        let words: Vec<&str> = code.split_whitespace().collect();
+        match words[..] {
+            [name, equ, value] if equ.eq_ignore_ascii_case("equ") => {
+                let value = symbols.resolve(line_number, value)?;
+                symbols.define(line_number, name, value)?;
+                continue;
+            }
+            [define, name, value] if define.eq_ignore_ascii_case("define") => {
+                let value = symbols.resolve(line_number, value)?;
+                symbols.define(line_number, name, value)?;
+                continue;
+            }
+            _ => {}
+        }
+
+        let statement = Statement {
+            line: line_number,
+            mnemonic: head.to_ascii_uppercase(),
+            operands: rest
+                .split(',')
+                .map(str::trim)
+                .filter(|operand| !operand.is_empty())
+                .map(Operand::parse)
+                .collect(),
+        };
+        map.insert(address, line_number);
+        address = address
+            .checked_add(statement.size())
+            .filter(|&address| address <= 0x1000)
+            .ok_or_else(|| error(line_number, "program does not fit in memory".into()))?;
+        statements.push(statement);
+    }
+
+    // Second pass: encode every statement now that all symbols are known.
+    let mut rom = Vec::new();
+    for statement in &statements {
+        encode(statement, &symbols, &mut rom)?;
+    }
+    Ok((rom, map))
+}
+
+/// Encodes a single statement, appending its bytes to `rom`.
+fn encode(statement: &Statement, symbols: &Symbols, rom: &mut Vec<u8>) -> Result<(), Error> {
+    use Operand::{Dt, IndirectI, St, Value, B, F, I, K, R, V};
+
+    let line = statement.line;
+    let value = |operand: &str, max: u16, kind: &str| -> Result<u16, Error> {
+        let value = symbols.resolve(line, operand)?;
+        if value > max {
+            return Err(error(
+                line,
+                format!("{kind} '{operand}' ({value:#X}) is out of range (max {max:#X})"),
+            ));
+        }
+        Ok(value)
+    };
+    let addr = |operand: &str| value(operand, 0xFFF, "address");
+    let byte = |operand: &str| value(operand, 0xFF, "byte");
+    let nibble = |operand: &str| value(operand, 0xF, "nibble");
+    let xy = |x: u8, y: u8| (u16::from(x) << 8) | (u16::from(y) << 4);
+
+    let opcode: u16 = match (statement.mnemonic.as_str(), &statement.operands[..]) {
+        ("DB", operands) => {
+            for operand in operands {
+                let Value(operand) = operand else {
+                    return Err(error(line, format!("expected a byte, found {operand:?}")));
+                };
+                rom.push(u8::try_from(byte(operand)?).unwrap_or_default());
+            }
+            return Ok(());
+        }
+        ("DW", operands) => {
+            for operand in operands {
+                let Value(operand) = operand else {
+                    return Err(error(line, format!("expected a word, found {operand:?}")));
+                };
+                rom.extend_from_slice(&symbols.resolve(line, operand)?.to_be_bytes());
+            }
+            return Ok(());
+        }
+        ("CLS", []) => 0x00E0,
+        ("RET", []) => 0x00EE,
+        ("SYS", [Value(a)]) => addr(a)?,
+        ("JP", [Value(a)]) => 0x1000 | addr(a)?,
+        ("JP", [V(0), Value(a)]) => 0xB000 | addr(a)?,
+        ("CALL", [Value(a)]) => 0x2000 | addr(a)?,
+        ("SE", [V(x), Value(b)]) => 0x3000 | xy(*x, 0) | byte(b)?,
+        ("SNE", [V(x), Value(b)]) => 0x4000 | xy(*x, 0) | byte(b)?,
+        ("SE", [V(x), V(y)]) => 0x5000 | xy(*x, *y),
+        ("LD", [V(x), Value(b)]) => 0x6000 | xy(*x, 0) | byte(b)?,
+        ("ADD", [V(x), Value(b)]) => 0x7000 | xy(*x, 0) | byte(b)?,
+        ("LD", [V(x), V(y)]) => 0x8000 | xy(*x, *y),
+        ("OR", [V(x), V(y)]) => 0x8001 | xy(*x, *y),
+        ("AND", [V(x), V(y)]) => 0x8002 | xy(*x, *y),
+        ("XOR", [V(x), V(y)]) => 0x8003 | xy(*x, *y),
+        ("ADD", [V(x), V(y)]) => 0x8004 | xy(*x, *y),
+        ("SUB", [V(x), V(y)]) => 0x8005 | xy(*x, *y),
+        ("SHR", [V(x)]) => 0x8006 | xy(*x, 0),
+        ("SHR", [V(x), V(y)]) => 0x8006 | xy(*x, *y),
+        ("SUBN", [V(x), V(y)]) => 0x8007 | xy(*x, *y),
+        ("SHL", [V(x)]) => 0x800E | xy(*x, 0),
+        ("SHL", [V(x), V(y)]) => 0x800E | xy(*x, *y),
+        ("SNE", [V(x), V(y)]) => 0x9000 | xy(*x, *y),
+        ("LD", [I, Value(a)]) => 0xA000 | addr(a)?,
+        ("RND", [V(x), Value(b)]) => 0xC000 | xy(*x, 0) | byte(b)?,
+        ("DRW", [V(x), V(y), Value(n)]) => 0xD000 | xy(*x, *y) | nibble(n)?,
+        ("SKP", [V(x)]) => 0xE09E | xy(*x, 0),
+        ("SKNP", [V(x)]) => 0xE0A1 | xy(*x, 0),
+        ("LD", [V(x), Dt]) => 0xF007 | xy(*x, 0),
+        ("LD", [V(x), K]) => 0xF00A | xy(*x, 0),
+        ("LD", [Dt, V(x)]) => 0xF015 | xy(*x, 0),
+        ("LD", [St, V(x)]) => 0xF018 | xy(*x, 0),
+        ("ADD", [I, V(x)]) => 0xF01E | xy(*x, 0),
+        ("LD", [F, V(x)]) => 0xF029 | xy(*x, 0),
+        ("LD", [B, V(x)]) => 0xF033 | xy(*x, 0),
+        ("LD", [IndirectI, V(x)]) => 0xF055 | xy(*x, 0),
+        ("LD", [V(x), IndirectI]) => 0xF065 | xy(*x, 0),
+        ("LD", [R, V(x)]) => 0xF075 | xy(*x, 0),
+        ("LD", [V(x), R]) => 0xF085 | xy(*x, 0),
+        ("SCD", [Value(n)]) => 0x00C0 | nibble(n)?,
+        ("SCU", [Value(n)]) => 0x00D0 | nibble(n)?,
+        ("SCR", []) => 0x00FB,
+        ("SCL", []) => 0x00FC,
+        (mnemonic, operands) => return Err(error(line, invalid(mnemonic, operands.len()))),
+    };
+    rom.extend_from_slice(&opcode.to_be_bytes());
+    Ok(())
+}
+
+/// Describes why `mnemonic` with `count` operands can't be assembled,
+/// listing the forms it can take if it is a known instruction.
+fn invalid(mnemonic: &str, count: usize) -> String {
+    let docs: Vec<&Doc> = Doc::with_mnemonic(mnemonic).collect();
+    let forms: Vec<&str> = docs
+        .iter()
+        .filter(|doc| doc.supported())
+        .map(|doc| doc.syntax)
+        .collect();
+    match (docs.first(), &forms[..]) {
+        (None, _) => format!("invalid instruction '{mnemonic}' with {count} operand(s)"),
+        (Some(doc), []) => format!(
+            "'{mnemonic}' is a {} instruction, which is not supported",
+            doc.platforms[0]
+        ),
+        (Some(_), forms) => format!(
+            "invalid operands for '{mnemonic}', expected one of: {}",
+            forms.join("; ")
+        ),
+    }
+}
+
+/// Splits a line of code into its mnemonic and the rest of the line.
+fn split_mnemonic(code: &str) -> (&str, &str) {
+    code.split_once(char::is_whitespace)
+        .map_or((code, ""), |(head, rest)| (head, rest.trim()))
+}
+
+/// Parses a decimal, `0x`-prefixed hexadecimal, or `0b`-prefixed binary number.
+pub(crate) fn parse_number(s: &str) -> Option<u32> {
+    let lower = s.to_ascii_lowercase();
+    if let Some(hex) = lower.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = lower.strip_prefix("0b") {
+        u32::from_str_radix(bin, 2).ok()
+    } else {
+        lower.parse().ok()
+    }
+}
+
+/// Whether `s` can be used as a label or constant name.
+pub(crate) fn is_identifier(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn error(line: usize, message: String) -> Error {
+    Error { line, message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn instructions() {
+        let source = "
+            CLS
+            LD V3, 0x2A       ; load
+            LD I, 0x300
+            DRW V1, V2, 5
+            LD [I], VA
+            LD VB, [I]
+            JP V0, 0x250
+            SKNP V4
+            SCD 3
+            SCL
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![
+                0x00, 0xE0, 0x63, 0x2A, 0xA3, 0x00, 0xD1, 0x25, 0xFA, 0x55, 0xFB, 0x65, 0xB2, 0x50,
+                0xE4, 0xA1, 0x00, 0xC3, 0x00, 0xFC
+            ]
+        );
+    }
+
+    #[test]
+    fn labels_constants_and_data() {
+        let source = "
+            SPEED equ 3
+            define ROWS 0b101
+            start:
+                LD V0, SPEED
+                CALL sprite
+            loop: JP loop
+            sprite:
+                db 0xF0, 144, ROWS
+                dw 0x1234
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![0x60, 0x03, 0x22, 0x06, 0x12, 0x04, 0xF0, 0x90, 0x05, 0x12, 0x34]
+        );
+    }
+
+    #[test]
+    fn source_map() {
+        let source = "
+            start:
+                LD V0, 1  ; line 3
+                db 0xFF
+
+            loop: JP loop
+        ";
+        let (_, map) = assemble_with_map(source).unwrap();
+        assert_eq!(map.line(0x200), Some(3));
+        assert_eq!(map.line(0x202), Some(4));
+        assert_eq!(map.line(0x203), Some(6));
+        assert_eq!(map.line(0x204), None);
+        assert_eq!(map.address(2), Some((0x200, 3)));
+        assert_eq!(map.address(5), Some((0x203, 6)));
+        assert_eq!(map.address(7), None);
+    }
+
+    #[test]
+    fn symbols() {
+        let source = "start:\n  LD V0, 1\n  db 0xFF\nloop: JP loop\n";
+        let (_, map) = assemble_with_map(source).unwrap();
+        let map = map.with_source(PathBuf::from("game.asm"), source);
+        assert_eq!(map.label(0x200), Some("start"));
+        assert_eq!(map.resolve("loop"), Some(0x203));
+        assert_eq!(map.describe(0x202).as_deref(), Some("start+2"));
+        assert_eq!(map.text(0x203), Some("loop: JP loop"));
+
+        let file = map.to_string();
+        assert!(file.starts_with("# etherea symbols v1\nsource game.asm\nlabel 0x200 start\n"));
+        let parsed = SourceMap::parse(&file).unwrap();
+        assert_eq!(parsed.resolve("loop"), Some(0x203));
+        assert_eq!(parsed.line(0x202), Some(3));
+        assert_eq!(parsed.source(), Some(Path::new("game.asm")));
+        assert!(SourceMap::parse("label 0x200 start").is_err());
+        assert!(SourceMap::parse("# etherea symbols v1\nlabel start").is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(assemble("JP nowhere").unwrap_err().line, 1);
+        assert!(assemble("\nLD V0, 256")
+            .unwrap_err()
+            .message
+            .contains("out of range"));
+        assert!(assemble("FOO V1")
+            .unwrap_err()
+            .message
+            .contains("invalid instruction"));
+        assert!(assemble("a:\na:")
+            .unwrap_err()
+            .message
+            .contains("more than once"));
+        assert!(assemble("DRW V0, V1, 16").is_err());
+        assert!(assemble("SKP 3")
+            .unwrap_err()
+            .message
+            .ends_with("expected one of: SKP Vx"));
+        assert!(assemble("SCD")
+            .unwrap_err()
+            .message
+            .ends_with("expected one of: SCD nibble"));
+        assert!(assemble("HIGH")
+            .unwrap_err()
+            .message
+            .contains("SUPER-CHIP instruction"));
+    }
+}
@@ -0,0 +1,648 @@
+use super::{error, parse_number, Error, SourceMap, ORIGIN};
+use std::collections::HashMap;
+
+/// A whitespace-separated word of source, with the line it is on.
+#[derive(Copy, Clone, Debug)]
+struct Token<'a> {
+    text: &'a str,
+    line: usize,
+}
+
+/// The instructions a condition compiles to: one that skips the next
+/// instruction when the condition holds, and one that skips it when it
+/// doesn't.
+#[derive(Copy, Clone, Debug)]
+struct Condition {
+    skip_if_true: u16,
+    skip_if_false: u16,
+}
+
+/// An open `loop`: where it starts, and the jumps out of it made by its
+/// `while`s, which are patched once its `again` is reached.
+#[derive(Debug)]
+struct Loop {
+    start: u16,
+    exits: Vec<usize>,
+}
+
+/// A single-pass compiler for Octo source. Addresses that aren't known yet
+/// are patched in at the end, like the jumps out of loops and branches are
+/// once their end is reached.
+#[derive(Debug)]
+struct Compiler<'a> {
+    tokens: Vec<Token<'a>>,
+    next: usize,                          // The index of the next token
+    line: usize,                          // The line of the last token read
+    rom: Vec<u8>,                         // The program so far
+    labels: HashMap<&'a str, u16>,        // Addresses, by label
+    constants: HashMap<&'a str, u16>,     // Values defined with `:const`
+    aliases: HashMap<&'a str, u8>,        // Register names defined with `:alias`
+    fixups: Vec<(usize, &'a str, usize)>, // Instructions using a label, its name, and the line
+    loops: Vec<Loop>,                     // Open `loop`s, innermost last
+    branches: Vec<usize>,                 // Jumps to patch at the next `else` or `end`
+    map: SourceMap,                       // Where each statement ended up
+}
+
+/// Assembles `source`, written in [Octo](https://johnearnest.github.io/Octo/)'s
+/// syntax, into a ROM to be loaded at `0x200`. Comments start with `#`.
+///
+/// The CHIP-8 subset of Octo is supported, along with the SUPER-CHIP and
+/// XO-CHIP instructions etherea runs:
+///
+/// - `: name` labels, `:const name 5`, `:alias name v3`, `:org 0x300`, and
+///   `:byte 5`; bare numbers are also emitted as bytes
+/// - `clear`, `return` (or `;`), `jump`, `jump0`, `:call` (or just the name
+///   of a label), `native`, `bcd`, `save`, `load`, and `sprite`
+/// - `saveflags`, `loadflags`, `scroll-down`, `scroll-up`, `scroll-left`,
+///   and `scroll-right`
+/// - assignments like `v0 := 5`, `v0 := random 0xFF`, `v0 := key`,
+///   `v0 := delay`, `delay := v0`, `buzzer := v0`, `i := label`,
+///   `i := hex v0`, and `i += v0`
+/// - arithmetic like `v0 += 5`, `v0 -= v1`, `v0 =- v1`, `v0 |= v1`,
+///   `v0 &= v1`, `v0 ^= v1`, `v0 >>= v1`, and `v0 <<= v1`
+/// - `if v0 == 5 then ...`, `if v0 != v1 begin ... else ... end`,
+///   `if v0 key then ...`, and `if v0 -key then ...`
+/// - `loop ... while v0 != 0 ... again`
+///
+/// If there is a `main` label, the program starts with a jump to it.
+///
+/// # Errors
+/// This function will return the first error encountered, e.g. an unknown
+/// statement, undefined label, or out-of-range operand.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Error> {
+    assemble_with_map(source).map(|(rom, _)| rom)
+}
+
+/// Assembles `source` like [`assemble`], along with where each statement
+/// ended up.
+///
+/// # Errors
+/// This function will return the first error encountered, like [`assemble`].
+pub fn assemble_with_map(source: &str) -> Result<(Vec<u8>, SourceMap), Error> {
+    let tokens: Vec<Token> = source
+        .lines()
+        .enumerate()
+        .flat_map(|(idx, line)| {
+            let code = line.split('#').next().unwrap_or_default();
+            code.split_whitespace().map(move |text| Token {
+                text,
+                line: idx + 1,
+            })
+        })
+        .collect();
+    let compiler = Compiler {
+        tokens,
+        next: 0,
+        line: 1,
+        rom: Vec::new(),
+        labels: HashMap::new(),
+        constants: HashMap::new(),
+        aliases: HashMap::new(),
+        fixups: Vec::new(),
+        loops: Vec::new(),
+        branches: Vec::new(),
+        map: SourceMap::default(),
+    };
+    compiler.compile()
+}
+
+impl<'a> Compiler<'a> {
+    fn compile(mut self) -> Result<(Vec<u8>, SourceMap), Error> {
+        let main = self
+            .tokens
+            .windows(2)
+            .any(|pair| pair[0].text == ":" && pair[1].text == "main");
+        if main {
+            self.address(0x1000, "main")?;
+        }
+        while self.next < self.tokens.len() {
+            let (here, token) = (self.here(), self.tokens[self.next]);
+            self.statement()?;
+            // `:org` emits padding rather than code.
+            if self.here() > here && token.text != ":org" {
+                self.map.insert(here, token.line);
+            }
+        }
+
+        if !self.loops.is_empty() {
+            return Err(self.error("'loop' without 'again'"));
+        }
+        if !self.branches.is_empty() {
+            return Err(self.error("'begin' without 'end'"));
+        }
+        for (offset, name, line) in std::mem::take(&mut self.fixups) {
+            let address = *self
+                .labels
+                .get(name)
+                .ok_or_else(|| error(line, format!("undefined label '{name}'")))?;
+            self.patch(offset, address);
+        }
+        if ORIGIN as usize + self.rom.len() > 0x1000 {
+            return Err(self.error("program does not fit in memory"));
+        }
+        Ok((self.rom, self.map))
+    }
+
+    /// Compiles the next statement.
+    fn statement(&mut self) -> Result<(), Error> {
+        let token = self.token()?;
+        match token {
+            ":" | ":const" | ":alias" | ":org" | ":byte" => self.directive(token)?,
+            "if" | "else" | "end" | "loop" | "while" | "again" => self.control(token)?,
+            "clear" => self.emit(0x00E0),
+            "return" | ";" => self.emit(0x00EE),
+            "jump" => self.address_operand(0x1000)?,
+            "jump0" => self.address_operand(0xB000)?,
+            ":call" => self.address_operand(0x2000)?,
+            "native" => self.address_operand(0x0000)?,
+            "bcd" => self.register_operand(0xF033)?,
+            "save" => self.register_operand(0xF055)?,
+            "load" => self.register_operand(0xF065)?,
+            "saveflags" => self.register_operand(0xF075)?,
+            "loadflags" => self.register_operand(0xF085)?,
+            "scroll-down" | "scroll-up" => {
+                let n = self.token()?;
+                let n = self.value(n)?;
+                if n > 0xF {
+                    return Err(self.error(&format!("scroll distance {n} is out of range")));
+                }
+                let opcode = if token == "scroll-down" {
+                    0x00C0
+                } else {
+                    0x00D0
+                };
+                self.emit(opcode | n);
+            }
+            "scroll-left" => self.emit(0x00FC),
+            "scroll-right" => self.emit(0x00FB),
+            "sprite" => {
+                let (x, y) = (self.register()?, self.register()?);
+                let n = self.token()?;
+                let n = self.value(n)?;
+                if n > 0xF {
+                    return Err(self.error(&format!("sprite height {n} is out of range")));
+                }
+                self.emit(0xD000 | xy(x, y) | n);
+            }
+            "i" => self.index()?,
+            "delay" | "buzzer" => {
+                self.expect(":=")?;
+                let register = self.register()?;
+                let opcode = if token == "delay" { 0xF015 } else { 0xF018 };
+                self.emit(opcode | xy(register, 0));
+            }
+            "hires" | "lores" | "exit" | "plane" | "audio" | "pitch" | "bighex" => {
+                return Err(self.error(&format!(
+                    "'{token}' is a SUPER-CHIP or XO-CHIP instruction, which is not supported"
+                )));
+            }
+            _ if self.register_named(token).is_some() => self.assignment(token)?,
+            _ if is_number(token) || self.constants.contains_key(token) => {
+                let byte = self.byte(token)?;
+                self.rom.push(byte);
+            }
+            _ if is_name(token) => self.address(0x2000, token)?,
+            _ => return Err(self.error(&format!("unknown statement '{token}'"))),
+        }
+        Ok(())
+    }
+
+    /// Compiles the directive `token`, which defines a name or places data.
+    fn directive(&mut self, token: &str) -> Result<(), Error> {
+        match token {
+            ":" => {
+                let name = self.name()?;
+                let here = self.here();
+                if self.labels.insert(name, here).is_some() {
+                    return Err(self.error(&format!("'{name}' is defined more than once")));
+                }
+                self.map.label_at(here, name);
+            }
+            ":const" => {
+                let name = self.name()?;
+                let value = self.token()?;
+                let value = self.value(value)?;
+                self.constants.insert(name, value);
+            }
+            ":alias" => {
+                let name = self.name()?;
+                let register = self.register()?;
+                self.aliases.insert(name, register);
+            }
+            ":org" => {
+                let address = self.token()?;
+                let address = usize::from(self.value(address)?);
+                let offset = address.checked_sub(usize::from(ORIGIN));
+                match offset {
+                    Some(offset) if offset >= self.rom.len() => self.rom.resize(offset, 0),
+                    _ => return Err(self.error(&format!("can't go back to {address:#X}"))),
+                }
+            }
+            ":byte" => {
+                let value = self.token()?;
+                let byte = self.byte(value)?;
+                self.rom.push(byte);
+            }
+            _ => unreachable!("only called with directives"),
+        }
+        Ok(())
+    }
+
+    /// Compiles the control flow statement `token`.
+    fn control(&mut self, token: &str) -> Result<(), Error> {
+        match token {
+            "if" => {
+                let condition = self.condition()?;
+                match self.token()? {
+                    "then" => self.emit(condition.skip_if_false),
+                    "begin" => {
+                        self.emit(condition.skip_if_true);
+                        self.branches.push(self.rom.len());
+                        self.emit(0x1000);
+                    }
+                    other => {
+                        return Err(
+                            self.error(&format!("expected 'then' or 'begin', found '{other}'"))
+                        )
+                    }
+                }
+            }
+            "else" => {
+                let branch = self
+                    .branches
+                    .pop()
+                    .ok_or_else(|| self.error("'else' without 'begin'"))?;
+                self.branches.push(self.rom.len());
+                self.emit(0x1000);
+                self.patch(branch, self.here());
+            }
+            "end" => {
+                let branch = self
+                    .branches
+                    .pop()
+                    .ok_or_else(|| self.error("'end' without 'begin'"))?;
+                self.patch(branch, self.here());
+            }
+            "loop" => self.loops.push(Loop {
+                start: self.here(),
+                exits: Vec::new(),
+            }),
+            "while" => {
+                if self.loops.is_empty() {
+                    return Err(self.error("'while' outside of a loop"));
+                }
+                let condition = self.condition()?;
+                self.emit(condition.skip_if_true);
+                let exit = self.rom.len();
+                if let Some(inner) = self.loops.last_mut() {
+                    inner.exits.push(exit);
+                }
+                self.emit(0x1000);
+            }
+            "again" => {
+                let Loop { start, exits } = self
+                    .loops
+                    .pop()
+                    .ok_or_else(|| self.error("'again' without 'loop'"))?;
+                self.emit(0x1000 | start);
+                for exit in exits {
+                    self.patch(exit, self.here());
+                }
+            }
+            _ => unreachable!("only called with control flow"),
+        }
+        Ok(())
+    }
+
+    /// Compiles an assignment or arithmetic on the register `target`.
+    fn assignment(&mut self, target: &str) -> Result<(), Error> {
+        let x = self.register_named(target).unwrap_or_default();
+        let operator = self.token()?;
+        let operand = self.token()?;
+        let y = self.register_named(operand);
+        let opcode = match (operator, y) {
+            (":=", Some(y)) => 0x8000 | xy(x, y),
+            (":=", None) => match operand {
+                "random" => {
+                    let mask = self.token()?;
+                    0xC000 | xy(x, 0) | u16::from(self.byte(mask)?)
+                }
+                "key" => 0xF00A | xy(x, 0),
+                "delay" => 0xF007 | xy(x, 0),
+                _ => 0x6000 | xy(x, 0) | u16::from(self.byte(operand)?),
+            },
+            ("+=", Some(y)) => 0x8004 | xy(x, y),
+            ("+=", None) => 0x7000 | xy(x, 0) | u16::from(self.byte(operand)?),
+            ("-=", Some(y)) => 0x8005 | xy(x, y),
+            ("-=", None) => 0x7000 | xy(x, 0) | u16::from(self.byte(operand)?.wrapping_neg()),
+            ("=-", Some(y)) => 0x8007 | xy(x, y),
+            ("|=", Some(y)) => 0x8001 | xy(x, y),
+            ("&=", Some(y)) => 0x8002 | xy(x, y),
+            ("^=", Some(y)) => 0x8003 | xy(x, y),
+            (">>=", Some(y)) => 0x8006 | xy(x, y),
+            ("<<=", Some(y)) => 0x800E | xy(x, y),
+            _ => return Err(self.error(&format!("invalid operands for '{operator}'"))),
+        };
+        self.emit(opcode);
+        Ok(())
+    }
+
+    /// Compiles an assignment to `i`.
+    fn index(&mut self) -> Result<(), Error> {
+        match self.token()? {
+            ":=" => match self.token()? {
+                "hex" => self.register_operand(0xF029),
+                "bighex" | "long" => Err(self.error("only CHIP-8 forms of 'i :=' are supported")),
+                address => self.address(0xA000, address),
+            },
+            "+=" => self.register_operand(0xF01E),
+            other => Err(self.error(&format!("expected ':=' or '+=' after 'i', found '{other}'"))),
+        }
+    }
+
+    /// Parses a condition, like `v0 == 5` or `v1 -key`.
+    fn condition(&mut self) -> Result<Condition, Error> {
+        let x = self.register()?;
+        let operator = self.token()?;
+        let skips = |skip_if_true, skip_if_false| Condition {
+            skip_if_true,
+            skip_if_false,
+        };
+        let condition = match operator {
+            "key" => skips(0xE09E, 0xE0A1),
+            "-key" => skips(0xE0A1, 0xE09E),
+            "==" | "!=" => {
+                let operand = self.token()?;
+                let (equal, unequal) = if let Some(y) = self.register_named(operand) {
+                    (0x5000 | xy(0, y), 0x9000 | xy(0, y))
+                } else {
+                    let byte = u16::from(self.byte(operand)?);
+                    (0x3000 | byte, 0x4000 | byte)
+                };
+                if operator == "==" {
+                    skips(equal, unequal)
+                } else {
+                    skips(unequal, equal)
+                }
+            }
+            _ => return Err(self.error(&format!("unsupported comparison '{operator}'"))),
+        };
+        Ok(Condition {
+            skip_if_true: condition.skip_if_true | xy(x, 0),
+            skip_if_false: condition.skip_if_false | xy(x, 0),
+        })
+    }
+
+    /// Emits `opcode` with the address `operand` (a number, constant, or
+    /// label, which may not be defined yet) in its lower 12 bits.
+    fn address(&mut self, opcode: u16, operand: &'a str) -> Result<(), Error> {
+        if let Some(&address) = self.labels.get(operand) {
+            self.emit(opcode | address);
+        } else if is_number(operand) || self.constants.contains_key(operand) {
+            let address = self.value(operand)?;
+            if address > 0xFFF {
+                return Err(self.error(&format!(
+                    "address '{operand}' ({address:#X}) is out of range"
+                )));
+            }
+            self.emit(opcode | address);
+        } else if is_name(operand) {
+            self.fixups.push((self.rom.len(), operand, self.line));
+            self.emit(opcode);
+        } else {
+            return Err(self.error(&format!("expected an address, found '{operand}'")));
+        }
+        Ok(())
+    }
+
+    /// Emits `opcode` with the address given by the next token.
+    fn address_operand(&mut self, opcode: u16) -> Result<(), Error> {
+        let operand = self.token()?;
+        self.address(opcode, operand)
+    }
+
+    /// Emits `opcode` with the register given by the next token as `X`.
+    fn register_operand(&mut self, opcode: u16) -> Result<(), Error> {
+        let register = self.register()?;
+        self.emit(opcode | xy(register, 0));
+        Ok(())
+    }
+
+    /// The next token, which must be `expected`.
+    fn expect(&mut self, expected: &str) -> Result<(), Error> {
+        match self.token()? {
+            token if token == expected => Ok(()),
+            token => Err(self.error(&format!("expected '{expected}', found '{token}'"))),
+        }
+    }
+
+    /// The next token, which must be a name.
+    fn name(&mut self) -> Result<&'a str, Error> {
+        match self.token()? {
+            name if is_name(name) => Ok(name),
+            name => Err(self.error(&format!("invalid name '{name}'"))),
+        }
+    }
+
+    /// The next token, which must be a register or an alias of one.
+    fn register(&mut self) -> Result<u8, Error> {
+        let token = self.token()?;
+        self.register_named(token)
+            .ok_or_else(|| self.error(&format!("expected a register, found '{token}'")))
+    }
+
+    /// The register `token` names, if it names one.
+    fn register_named(&self, token: &str) -> Option<u8> {
+        if let Some(&register) = self.aliases.get(token) {
+            return Some(register);
+        }
+        let digit = token.strip_prefix(['v', 'V'])?;
+        (digit.len() == 1)
+            .then(|| u8::from_str_radix(digit, 16).ok())
+            .flatten()
+    }
+
+    /// Evaluates a number, constant, or label defined so far.
+    fn value(&self, token: &str) -> Result<u16, Error> {
+        if let Some(&value) = self.constants.get(token).or_else(|| self.labels.get(token)) {
+            return Ok(value);
+        }
+        let (negative, digits) = match token.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, token),
+        };
+        let value = parse_number(digits)
+            .and_then(|n| u16::try_from(n).ok())
+            .ok_or_else(|| self.error(&format!("expected a number, found '{token}'")))?;
+        Ok(if negative {
+            value.wrapping_neg()
+        } else {
+            value
+        })
+    }
+
+    /// Evaluates a byte, which may be negative.
+    fn byte(&self, token: &str) -> Result<u8, Error> {
+        let value = self.value(token)?;
+        match u8::try_from(value) {
+            Ok(byte) => Ok(byte),
+            // -128 to -1, as two's complement.
+            Err(_) if value >= 0xFF80 => Ok(value.to_be_bytes()[1]),
+            Err(_) => Err(self.error(&format!("byte '{token}' ({value:#X}) is out of range"))),
+        }
+    }
+
+    /// Reads the next token.
+    fn token(&mut self) -> Result<&'a str, Error> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .copied()
+            .ok_or_else(|| self.error("unexpected end of file"))?;
+        self.next += 1;
+        self.line = token.line;
+        Ok(token.text)
+    }
+
+    /// The address the next byte is emitted at.
+    fn here(&self) -> u16 {
+        ORIGIN + u16::try_from(self.rom.len()).unwrap_or(u16::MAX)
+    }
+
+    fn emit(&mut self, opcode: u16) {
+        self.rom.extend_from_slice(&opcode.to_be_bytes());
+    }
+
+    /// Fills in the address of the instruction at `offset`.
+    fn patch(&mut self, offset: usize, address: u16) {
+        let opcode = u16::from_be_bytes([self.rom[offset], self.rom[offset + 1]]) | address;
+        self.rom[offset..offset + 2].copy_from_slice(&opcode.to_be_bytes());
+    }
+
+    fn error(&self, message: &str) -> Error {
+        error(self.line, message.to_string())
+    }
+}
+
+/// The `X` and `Y` nibbles of an instruction.
+fn xy(x: u8, y: u8) -> u16 {
+    (u16::from(x) << 8) | (u16::from(y) << 4)
+}
+
+/// Whether `token` is a number, possibly negative.
+fn is_number(token: &str) -> bool {
+    parse_number(token.strip_prefix('-').unwrap_or(token)).is_some()
+}
+
+/// Whether `token` can name a label, constant, or alias. Unlike the
+/// classic syntax, Octo names can contain dashes.
+pub(crate) fn is_name(token: &str) -> bool {
+    let mut chars = token.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements() {
+        let source = "
+            :const SPEED 3
+            :alias x v4
+            : main
+                clear
+                x := SPEED         # a comment
+                v1 := random 0x0F
+                v2 -= 1
+                v2 =- v3
+                i := sprite
+                sprite x v1 5
+                delay := v2
+                draw
+                jump main
+            : draw
+                i := hex v0
+                ;
+            : sprite
+                0xF0 144 -1
+            saveflags v2
+            scroll-down 3
+            scroll-left
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![
+                0x12, 0x02, // jump main
+                0x00, 0xE0, 0x64, 0x03, 0xC1, 0x0F, 0x72, 0xFF, 0x82, 0x37, 0xA2, 0x1A, 0xD4, 0x15,
+                0xF2, 0x15, 0x22, 0x16, 0x12, 0x02, // main
+                0xF0, 0x29, 0x00, 0xEE, // draw
+                0xF0, 0x90, 0xFF, // sprite
+                0xF2, 0x75, 0x00, 0xC3, 0x00, 0xFC,
+            ]
+        );
+    }
+
+    #[test]
+    fn control_flow() {
+        let source = "
+            loop
+                if v0 == 5 then v1 += 1
+                if v0 key begin
+                    v2 := 1
+                else
+                    v2 := 2
+                end
+                while v1 != v2
+            again
+        ";
+        assert_eq!(
+            assemble(source).unwrap(),
+            vec![
+                0x40, 0x05, 0x71, 0x01, // if ... then
+                0xE0, 0x9E, 0x12, 0x0C, 0x62, 0x01, 0x12, 0x0E, 0x62, 0x02, // if ... begin
+                0x91, 0x20, 0x12, 0x14, // while
+                0x12, 0x00, // again
+            ]
+        );
+        let (_, map) = assemble_with_map(source).unwrap();
+        assert_eq!(map.line(0x200), Some(3));
+        assert_eq!(map.line(0x204), Some(4));
+        assert_eq!(map.line(0x20A), Some(6));
+        assert_eq!(map.line(0x212), Some(10));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(assemble("\n\njump nowhere").unwrap_err().line, 3);
+        assert!(assemble("v0 := 256")
+            .unwrap_err()
+            .message
+            .contains("out of range"));
+        assert!(assemble("loop").unwrap_err().message.contains("'again'"));
+        assert!(assemble("end").unwrap_err().message.contains("'begin'"));
+        assert!(assemble(": a : a")
+            .unwrap_err()
+            .message
+            .contains("more than once"));
+        assert!(assemble("if v0 > 1 then clear")
+            .unwrap_err()
+            .message
+            .contains("unsupported comparison"));
+        assert!(assemble("hires")
+            .unwrap_err()
+            .message
+            .contains("SUPER-CHIP"));
+        assert!(assemble("scroll-up 16")
+            .unwrap_err()
+            .message
+            .contains("out of range"));
+        assert!(assemble("v0 +=")
+            .unwrap_err()
+            .message
+            .contains("end of file"));
+    }
+}
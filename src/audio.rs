@@ -0,0 +1,504 @@
+#[cfg(feature = "audio")]
+use crate::Interpreter;
+use alloc::boxed::Box;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use core::fmt;
+#[cfg(feature = "std")]
+use core::{
+    array,
+    f64::consts::TAU,
+    sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+#[cfg(feature = "audio")]
+use log::{error, warn};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "audio")]
+use std::{sync::mpsc, thread};
+
+/// How often the sound timer's state is sampled, once per timer tick.
+#[cfg(feature = "std")]
+const TICK_RATE: u32 = 60;
+/// How many ticks can be queued for the audio thread. More than
+/// [`LATENCY`] are only kept until it catches up.
+#[cfg(feature = "std")]
+const RING: usize = 16;
+/// How many ticks the audio thread may lag behind the interpreter before it
+/// skips the oldest, to keep the beep in time with the screen.
+#[cfg(feature = "std")]
+const LATENCY: usize = 3;
+/// How long the beep takes to fade in, in seconds.
+#[cfg(feature = "std")]
+const ATTACK: f64 = 0.002;
+/// How long the beep takes to fade out, in seconds.
+#[cfg(feature = "std")]
+const RELEASE: f64 = 0.008;
+
+/// The shape of the beep's sound wave.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Waveform {
+    /// A harsh square wave, like the COSMAC VIP's buzzer
+    #[default]
+    Square,
+    /// A softer triangle wave
+    Triangle,
+    /// A pure sine tone
+    Sine,
+    /// Random noise, changing at the frequency
+    Noise,
+}
+
+/// The sound played while the sound timer is running.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+pub struct Beep {
+    /// The shape of the sound wave.
+    pub waveform: Waveform,
+    /// The pitch, in Hz.
+    pub frequency: u32,
+    /// How loud it is, from `0.0` to `1.0`.
+    pub volume: f32,
+}
+
+impl Beep {
+    /// The default pitch, in Hz.
+    pub const FREQUENCY: u32 = 440;
+    /// The default volume.
+    pub const VOLUME: f32 = 0.2;
+}
+
+impl Default for Beep {
+    fn default() -> Self {
+        Self {
+            waveform: Waveform::default(),
+            frequency: Self::FREQUENCY,
+            volume: Self::VOLUME,
+        }
+    }
+}
+
+/// Something that sounds the buzzer, given the state of the sound timer.
+/// [`Speaker`] plays the beep on the default audio device; embedders can
+/// route the sound timer to their own audio engine instead, with
+/// [`Interpreter::with_buzzer`](crate::Interpreter::with_buzzer).
+pub trait Buzzer: Send {
+    /// Turns the buzzer on or off. Called after every timer tick, 60 times
+    /// a second, with whether the sound timer is still running.
+    fn buzz(&mut self, on: bool);
+
+    /// Changes how loud the buzzer is, from `0.0` to `1.0`, e.g. from the
+    /// settings panel. Does nothing unless overridden.
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+impl<F: FnMut(bool) + Send> Buzzer for F {
+    fn buzz(&mut self, on: bool) {
+        self(on);
+    }
+}
+
+/// The buzzer attached to an interpreter, if there is one.
+#[derive(Default)]
+pub(crate) struct Output(Option<Box<dyn Buzzer>>);
+
+impl Output {
+    pub(crate) fn new(buzzer: impl Buzzer + 'static) -> Self {
+        Self(Some(Box::new(buzzer)))
+    }
+
+    pub(crate) fn buzz(&mut self, on: bool) {
+        if let Some(buzzer) = &mut self.0 {
+            buzzer.buzz(on);
+        }
+    }
+
+    pub(crate) fn set_volume(&mut self, volume: f32) {
+        if let Some(buzzer) = &mut self.0 {
+            buzzer.set_volume(volume);
+        }
+    }
+}
+
+impl fmt::Debug for Output {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Output({})",
+            if self.0.is_some() { "buzzer" } else { "none" }
+        )
+    }
+}
+
+/// Generates the samples of a [`Beep`], for an audio engine to play.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Oscillator {
+    waveform: Waveform,
+    volume: f64, // How loud each sample is, from 0 to 1
+    step: f64,   // How far through a period each sample moves
+    phase: f64,  // How far through the current period, from 0 to 1
+    noise: u32,  // The state of the noise generator
+    held: f64,   // The noise sample held until the next half period
+}
+
+#[cfg(feature = "std")]
+impl Oscillator {
+    /// An oscillator for `beep`, at `sample_rate` samples per second.
+    #[must_use]
+    pub fn new(beep: Beep, sample_rate: u32) -> Self {
+        let mut oscillator = Self {
+            waveform: beep.waveform,
+            volume: 0.0,
+            step: f64::from(beep.frequency) / f64::from(sample_rate.max(1)),
+            phase: 0.0,
+            noise: 0x2545_F491,
+            held: 0.0,
+        };
+        oscillator.set_volume(beep.volume);
+        oscillator.hold_noise();
+        oscillator
+    }
+
+    /// Changes the volume, from `0.0` to `1.0`.
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = f64::from(volume.clamp(0.0, 1.0));
+    }
+
+    /// The next sample, from `-1.0` to `1.0` scaled by the volume.
+    pub fn sample(&mut self) -> f32 {
+        let value = match self.waveform {
+            Waveform::Square if self.phase < 0.5 => 1.0,
+            Waveform::Square => -1.0,
+            Waveform::Triangle => 4.0 * (self.phase - 0.5).abs() - 1.0,
+            Waveform::Sine => (self.phase * TAU).sin(),
+            Waveform::Noise => self.held,
+        };
+        let before = self.phase;
+        self.phase = (self.phase + self.step).fract();
+        if self.phase < before || (before < 0.5 && self.phase >= 0.5) {
+            self.hold_noise();
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let sample = (value * self.volume) as f32;
+        sample
+    }
+
+    /// Moves the noise on to a new random value, with xorshift.
+    fn hold_noise(&mut self) {
+        self.noise ^= self.noise << 13;
+        self.noise ^= self.noise >> 17;
+        self.noise ^= self.noise << 5;
+        self.held = f64::from(self.noise) / f64::from(u32::MAX) * 2.0 - 1.0;
+    }
+}
+
+/// The sound timer's state at each tick, queued without locking for the
+/// audio thread, which can't wait on the interpreter.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+struct Ring {
+    states: [AtomicBool; RING], // Whether the timer ran, at each tick
+    written: AtomicUsize,       // The number of ticks written
+    read: AtomicUsize,          // The number of ticks read
+    volume: AtomicU32,          // The volume to play at, as the bits of an f32
+}
+
+#[cfg(feature = "std")]
+impl Ring {
+    fn push(&self, on: bool) {
+        let written = self.written.load(Ordering::Relaxed);
+        self.states[written % RING].store(on, Ordering::Relaxed);
+        self.written.store(written + 1, Ordering::Release);
+    }
+
+    /// The oldest tick not yet read, skipping any more than [`LATENCY`]
+    /// behind the newest.
+    fn pop(&self) -> Option<bool> {
+        let written = self.written.load(Ordering::Acquire);
+        let read = self
+            .read
+            .load(Ordering::Relaxed)
+            .max(written.saturating_sub(LATENCY));
+        if read == written {
+            return None;
+        }
+        let on = self.states[read % RING].load(Ordering::Relaxed);
+        self.read.store(read + 1, Ordering::Relaxed);
+        Some(on)
+    }
+}
+
+/// Creates a [`Synth`] playing `beep` at `sample_rate` samples per second,
+/// and the [`Ticks`] buzzer that feeds it the sound timer's state.
+#[cfg(feature = "std")]
+#[must_use]
+pub fn synth(beep: Beep, sample_rate: u32) -> (Ticks, Synth) {
+    let ring = Arc::new(Ring {
+        states: array::from_fn(|_| AtomicBool::new(false)),
+        written: AtomicUsize::new(0),
+        read: AtomicUsize::new(0),
+        volume: AtomicU32::new(beep.volume.to_bits()),
+    });
+    let synth = Synth {
+        ticks: Arc::clone(&ring),
+        oscillator: Oscillator::new(beep, sample_rate),
+        per_tick: (sample_rate / TICK_RATE).max(1),
+        remaining: 0,
+        on: false,
+        gain: 0.0,
+        attack: 1.0 / (ATTACK * f64::from(sample_rate)).max(1.0),
+        release: 1.0 / (RELEASE * f64::from(sample_rate)).max(1.0),
+    };
+    (Ticks(ring), synth)
+}
+
+/// The buzzer half of [`synth`]: queues the sound timer's state at each
+/// tick for the [`Synth`] to play.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug)]
+pub struct Ticks(Arc<Ring>);
+
+#[cfg(feature = "std")]
+impl Buzzer for Ticks {
+    fn buzz(&mut self, on: bool) {
+        self.0.push(on);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.0.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// The audio half of [`synth`]: plays each tick of the sound timer for a
+/// sixtieth of a second, fading the beep in and out over a few milliseconds
+/// so that rapid beeps don't click. When no tick has arrived in time, the
+/// last one carries on.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Synth {
+    ticks: Arc<Ring>,       // The ticks queued by the interpreter
+    oscillator: Oscillator, // The beep, at full volume
+    per_tick: u32,          // Samples played for each tick
+    remaining: u32,         // Samples left to play of the current tick
+    on: bool,               // Whether the sound timer ran in the current tick
+    gain: f64,              // The envelope's volume, from 0 to 1
+    attack: f64,            // How much the gain rises each sample
+    release: f64,           // How much the gain falls each sample
+}
+
+#[cfg(feature = "std")]
+impl Synth {
+    /// The next sample, from `-1.0` to `1.0` scaled by the volume.
+    pub fn sample(&mut self) -> f32 {
+        if self.remaining == 0 {
+            if let Some(on) = self.ticks.pop() {
+                self.on = on;
+            }
+            let volume = self.ticks.volume.load(Ordering::Relaxed);
+            self.oscillator.set_volume(f32::from_bits(volume));
+            self.remaining = self.per_tick;
+        }
+        self.remaining -= 1;
+        self.gain = if self.on {
+            (self.gain + self.attack).min(1.0)
+        } else {
+            (self.gain - self.release).max(0.0)
+        };
+        if self.gain == 0.0 {
+            return 0.0;
+        }
+        #[allow(clippy::cast_possible_truncation)]
+        let gain = self.gain as f32;
+        self.oscillator.sample() * gain
+    }
+}
+
+/// Plays the beep on the default audio device while the sound timer runs.
+#[cfg(feature = "audio")]
+pub struct Speaker {
+    ticks: Ticks,            // Feeds the stream's synth
+    _stop: mpsc::Sender<()>, // Stops the stream when dropped
+}
+
+#[cfg(feature = "audio")]
+impl Speaker {
+    /// Opens the default audio device to play `beep` on.
+    ///
+    /// # Errors
+    /// This function will error if there is no audio device or it can't
+    /// play the sound.
+    pub fn open(beep: Beep) -> Result<Self, String> {
+        let (ready, opened) = mpsc::channel();
+        let (stop, stopped) = mpsc::channel::<()>();
+        // Streams can't move between threads on every platform, so each
+        // lives on a thread of its own until the speaker is dropped.
+        thread::spawn(move || match Self::stream(beep) {
+            Ok((stream, ticks)) => {
+                let _ = ready.send(Ok(ticks));
+                let _ = stopped.recv();
+                drop(stream);
+            }
+            Err(e) => {
+                let _ = ready.send(Err(e));
+            }
+        });
+        opened
+            .recv()
+            .map_err(|e| e.to_string())?
+            .map(|ticks| Self { ticks, _stop: stop })
+    }
+
+    /// Sounds `intr`'s buzzer with `beep` on the default audio device, or
+    /// warns that there will be no sound.
+    pub fn attach(intr: &mut Interpreter, beep: Beep) {
+        match Self::open(beep) {
+            Ok(speaker) => intr.with_buzzer(speaker),
+            Err(e) => warn!("No sound: {}", e),
+        }
+    }
+
+    /// Starts a stream playing `beep` as the returned [`Ticks`] say.
+    fn stream(beep: Beep) -> Result<(cpal::Stream, Ticks), String> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("No audio output device")?;
+        let config = device.default_output_config().map_err(|e| e.to_string())?;
+        let channels = usize::from(config.channels());
+        let (ticks, mut synth) = synth(beep, config.sample_rate().0);
+        let stream = device
+            .build_output_stream(
+                &config.into(),
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    for frame in data.chunks_mut(channels) {
+                        frame.fill(synth.sample());
+                    }
+                },
+                |e| error!("Audio stream failed: {}", e),
+                None,
+            )
+            .map_err(|e| e.to_string())?;
+        stream.play().map_err(|e| e.to_string())?;
+        Ok((stream, ticks))
+    }
+}
+
+#[cfg(feature = "audio")]
+impl Buzzer for Speaker {
+    fn buzz(&mut self, on: bool) {
+        self.ticks.buzz(on);
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        self.ticks.set_volume(volume);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    fn samples(waveform: Waveform) -> Vec<f32> {
+        let beep = Beep {
+            waveform,
+            frequency: 1000,
+            ..Beep::default()
+        };
+        let mut oscillator = Oscillator::new(beep, 8000);
+        (0..8).map(|_| oscillator.sample()).collect()
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // noise is held exactly
+    fn waveforms() {
+        assert_eq!(
+            samples(Waveform::Square),
+            [0.2, 0.2, 0.2, 0.2, -0.2, -0.2, -0.2, -0.2]
+        );
+        assert_eq!(
+            samples(Waveform::Triangle),
+            [0.2, 0.1, 0.0, -0.1, -0.2, -0.1, 0.0, 0.1]
+        );
+        let sine = samples(Waveform::Sine);
+        assert!((sine[2] - 0.2).abs() < 1e-6 && (sine[6] + 0.2).abs() < 1e-6);
+        // Noise holds each value for half a period.
+        let noise = samples(Waveform::Noise);
+        assert!(noise[..4].iter().all(|&sample| sample == noise[0]));
+        assert!(noise[4..].iter().all(|&sample| sample == noise[4]));
+        assert_ne!(noise[0], noise[4]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // the square wave's volume is exact
+    fn volume() {
+        let sample = |volume| {
+            let beep = Beep {
+                volume,
+                ..Beep::default()
+            };
+            Oscillator::new(beep, 8000).sample()
+        };
+        assert_eq!(sample(0.5), 0.5);
+        assert_eq!(sample(0.0), 0.0);
+        // Out of range volumes are clamped.
+        assert_eq!(sample(2.0), 1.0);
+
+        // The synth picks up a new volume at the next tick.
+        let (mut ticks, mut synth) = synth(Beep::default(), 48_000);
+        ticks.buzz(true);
+        for _ in 0..800 {
+            synth.sample();
+        }
+        ticks.set_volume(0.5);
+        ticks.buzz(true);
+        assert_eq!(synth.sample().abs(), 0.5);
+    }
+
+    #[test]
+    fn ring() {
+        let (mut ticks, synth) = synth(Beep::default(), 48_000);
+        assert_eq!(synth.ticks.pop(), None);
+        for on in [true, false, true, true, false] {
+            ticks.buzz(on);
+        }
+        // Only the latest ticks are kept.
+        let popped: Vec<_> = std::iter::from_fn(|| synth.ticks.pop()).collect();
+        assert_eq!(popped, [true, true, false]);
+    }
+
+    #[test]
+    #[allow(clippy::float_cmp)] // the square wave's volume is exact
+    fn envelope() {
+        let (mut ticks, mut synth) = synth(Beep::default(), 48_000);
+        ticks.buzz(true);
+        ticks.buzz(false);
+        // A tick lasts 800 samples; fading in takes 96 and out 384. The
+        // square wave's volume shows the envelope.
+        let volume: Vec<f32> = (0..2400).map(|_| synth.sample().abs()).collect();
+        assert!(volume[0] < 0.01);
+        assert!(volume[..96].windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(volume[96..800].iter().all(|&sample| sample == 0.2));
+        assert!(volume[800..1183].windows(2).all(|pair| pair[0] > pair[1]));
+        assert!(volume[1184..].iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn buzzers() {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut output = Output::new(move |on| tx.send(on).unwrap());
+        output.buzz(true);
+        output.buzz(false);
+        Output::default().buzz(true);
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [true, false]);
+    }
+}
@@ -0,0 +1,88 @@
+use alloc::vec::Vec;
+use core::ops::Range;
+
+/// The part of memory that banks are mapped into, the upper 2K.
+pub const WINDOW: Range<usize> = 0x800..0x1000;
+/// The size of a bank, and of the window it's mapped into.
+pub const BANK_SIZE: usize = WINDOW.end - WINDOW.start;
+/// The start of the ROM image's first bank. Everything before it is loaded
+/// at `0x200..0x800` and never switched out.
+const BANKED_OFFSET: usize = WINDOW.start - 0x200;
+
+/// An experimental peripheral for ROMs larger than memory. The ROM image is
+/// split into a fixed part, loaded at `0x200` as usual, and 2K banks, one of
+/// which is mapped into [`WINDOW`] at a time. `0BNN` selects bank `NN`.
+///
+/// Bank `0` is mapped when a ROM is loaded, so a ROM that fits in memory
+/// loads exactly as it would without banking. Banks are RAM: what a ROM
+/// writes to the window is kept when it switches away and back.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Banks {
+    store: Vec<u8>,  // Every bank's contents, including the mapped one's
+    selected: usize, // The bank mapped into the window
+}
+
+impl Banks {
+    /// Splits the banked part of `rom` into banks, padding the last one with
+    /// zeros. There is always at least one bank.
+    #[must_use]
+    pub fn new(rom: &[u8]) -> Self {
+        let mut store = rom.get(BANKED_OFFSET..).unwrap_or_default().to_vec();
+        store.resize(store.len().div_ceil(BANK_SIZE).max(1) * BANK_SIZE, 0);
+        Self { store, selected: 0 }
+    }
+
+    /// The number of banks.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.store.len() / BANK_SIZE
+    }
+
+    /// The bank mapped into the window.
+    #[must_use]
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// Maps `bank` into the window of `memory`, saving what the window held
+    /// into the previously selected bank. Returns `false`, changing nothing,
+    /// if there is no such bank.
+    pub fn select(&mut self, bank: usize, memory: &mut [u8]) -> bool {
+        if bank >= self.count() {
+            return false;
+        }
+        let old = self.selected * BANK_SIZE;
+        self.store[old..old + BANK_SIZE].copy_from_slice(&memory[WINDOW]);
+        let new = bank * BANK_SIZE;
+        memory[WINDOW].copy_from_slice(&self.store[new..new + BANK_SIZE]);
+        self.selected = bank;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switches_banks() {
+        // The fixed part, then two banks (the second one partial).
+        let mut rom = vec![0xAA; BANKED_OFFSET];
+        rom.extend([0x01; BANK_SIZE]);
+        rom.extend([0x02; 4]);
+        let mut banks = Banks::new(&rom);
+        assert_eq!(banks.count(), 2);
+
+        let mut memory = [0; 0x1000];
+        memory[WINDOW].fill(0x01);
+        memory[WINDOW.start] = 0xFF; // written by the ROM
+        assert!(banks.select(1, &mut memory));
+        assert_eq!(memory[WINDOW][..5], [0x02, 0x02, 0x02, 0x02, 0x00]);
+        assert!(!banks.select(2, &mut memory));
+        assert_eq!(banks.selected(), 1);
+        assert!(banks.select(0, &mut memory));
+        assert_eq!(memory[WINDOW][..2], [0xFF, 0x01]);
+
+        assert_eq!(Banks::new(&[0x00, 0xE0]).count(), 1);
+    }
+}
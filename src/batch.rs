@@ -0,0 +1,208 @@
+use crate::{debug, opcode::Opcode, quirks::Quirks, timing::Timing, Fault, Interpreter};
+use serde::Serialize;
+use std::fmt;
+
+/// A ROM's [`Report`], in the report on a whole collection written by
+/// `etherea batch`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Entry {
+    /// The path of the ROM.
+    pub path: String,
+    /// The results of running it.
+    #[serde(flatten)]
+    pub report: Report,
+}
+
+/// How a ROM run with [`run`] ended.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum Outcome {
+    /// It ran every instruction it was given.
+    Completed,
+    /// It reached an instruction that isn't one.
+    UnknownOpcode {
+        /// The address of the instruction.
+        pc: usize,
+        /// The instruction.
+        opcode: u16,
+    },
+    /// An instruction faulted under the quirks used.
+    Fault {
+        /// What went wrong.
+        fault: String,
+    },
+    /// An instruction other than `FX0A` left the PC where it was, like a
+    /// jump to itself, so nothing else would ever run.
+    InfiniteLoop {
+        /// The address of the instruction.
+        pc: usize,
+    },
+    /// It waited with `FX0A` for a key, which is never pressed.
+    WaitingForKey {
+        /// The address of the instruction.
+        pc: usize,
+    },
+}
+
+/// The results of running a ROM with [`run`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct Report {
+    /// How the run ended.
+    #[serde(flatten)]
+    pub outcome: Outcome,
+    /// The number of instructions executed.
+    pub instructions: u64,
+    /// The number of frames those instructions took, at the timing used.
+    pub frames: u64,
+    /// The FNV-1a hash of the screen the run ended on, as 16 hex digits, to
+    /// tell ROMs that drew the same thing apart from those that didn't.
+    pub framebuffer: String,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.outcome {
+            Outcome::Completed => write!(f, "completed")?,
+            Outcome::UnknownOpcode { pc, opcode } => {
+                write!(f, "unknown opcode {opcode:04X} at {pc:#05X}")?;
+            }
+            Outcome::Fault { fault } => write!(f, "{fault}")?,
+            Outcome::InfiniteLoop { pc } => write!(f, "infinite loop at {pc:#05X}")?,
+            Outcome::WaitingForKey { pc } => write!(f, "waiting for a key at {pc:#05X}")?,
+        }
+        write!(
+            f,
+            " after {} instructions ({} frames), screen {}",
+            self.instructions, self.frames, self.framebuffer
+        )
+    }
+}
+
+/// Runs `rom` headlessly, as fast as possible, until `cycles` instructions
+/// have executed, or it faults or stops making progress. Frames are counted
+/// at `timing`, the seed is `0`, and no keys are pressed, so every run of
+/// the same ROM ends the same way.
+#[must_use]
+pub fn run(rom: &[u8], cycles: u64, timing: Timing, quirks: Quirks) -> Report {
+    let mut intr = Interpreter::new();
+    intr.with_seed(0);
+    intr.with_timing(timing);
+    intr.with_quirks(quirks);
+    intr.load_rom(rom);
+
+    let mut frames = 0;
+    let outcome = match intr.fault.take() {
+        Some(fault) => outcome(fault),
+        None => 'run: loop {
+            for _ in 0..timing.cycles(frames) {
+                if intr.cycles >= cycles {
+                    break 'run Outcome::Completed;
+                }
+                let pc = intr.pc;
+                intr.step();
+                if let Some(fault) = intr.fault.take() {
+                    break 'run outcome(fault);
+                }
+                if intr.pc == pc {
+                    let next = (pc + 1) % Interpreter::MEMORY_SIZE;
+                    let opcode = u16::from_be_bytes([intr.memory[pc], intr.memory[next]]);
+                    break 'run match Opcode::try_from(opcode) {
+                        Ok(Opcode::WaitKey { .. }) => Outcome::WaitingForKey { pc },
+                        _ => Outcome::InfiniteLoop { pc },
+                    };
+                }
+                if std::mem::take(&mut intr.drew) && quirks.display_wait {
+                    break;
+                }
+            }
+            intr.timers.update();
+            frames += 1;
+        },
+    };
+    let pixels: Vec<u8> = intr
+        .framebuffer()
+        .pixels()
+        .iter()
+        .map(|&on| u8::from(on))
+        .collect();
+    Report {
+        outcome,
+        instructions: intr.cycles,
+        frames,
+        framebuffer: format!("{:016x}", debug::hash(&pixels)),
+    }
+}
+
+/// The outcome of a run stopped by `fault`.
+fn outcome(fault: Fault) -> Outcome {
+    match fault {
+        Fault::UnknownOpcode { pc, opcode } => Outcome::UnknownOpcode { pc, opcode },
+        fault => Outcome::Fault {
+            fault: fault.to_string(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(rom: &[u8]) -> Outcome {
+        run(rom, 1000, Timing::from_ips(700), Quirks::default()).outcome
+    }
+
+    #[test]
+    fn outcomes() {
+        // Count up in V0 forever, drawing nothing.
+        assert_eq!(outcome(&[0x70, 0x01, 0x12, 0x00]), Outcome::Completed);
+        assert_eq!(
+            outcome(&[0x00, 0x00, 0xFF, 0xFF]),
+            Outcome::UnknownOpcode {
+                pc: 0x202,
+                opcode: 0xFFFF
+            }
+        );
+        assert_eq!(
+            outcome(&[0x00, 0xEE]),
+            Outcome::Fault {
+                fault: Fault::StackUnderflow { pc: 0x200 }.to_string()
+            }
+        );
+        assert_eq!(
+            outcome(&[0x00, 0xE0, 0x12, 0x02]),
+            Outcome::InfiniteLoop { pc: 0x202 }
+        );
+        assert_eq!(outcome(&[0xF0, 0x0A]), Outcome::WaitingForKey { pc: 0x200 });
+    }
+
+    #[test]
+    fn reports() {
+        // Draw the font's 0, then loop.
+        let rom = [0xF0, 0x29, 0xD0, 0x05, 0x12, 0x04];
+        let report = run(&rom, 1000, Timing::from_ips(700), Quirks::default());
+        assert_eq!(
+            report,
+            run(&rom, 1000, Timing::from_ips(700), Quirks::default())
+        );
+        assert_eq!((report.instructions, report.frames), (3, 0));
+        let blank = run(
+            &[0x12, 0x00],
+            1000,
+            Timing::from_ips(700),
+            Quirks::default(),
+        );
+        assert_ne!(report.framebuffer, blank.framebuffer);
+
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["outcome"], "infinite_loop");
+        assert_eq!(json["pc"], 0x204);
+        assert_eq!(json["instructions"], 3);
+        assert_eq!(
+            report.to_string(),
+            format!(
+                "infinite loop at 0x204 after 3 instructions (0 frames), screen {}",
+                report.framebuffer
+            )
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{profile::Profile, quirks::Quirks, timing::Timing, Fault, Interpreter};
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// The results of benchmarking a ROM with [`run`].
+#[derive(Debug)]
+pub struct Report {
+    /// The number of instructions executed.
+    pub instructions: u64,
+    /// The number of frames those instructions took, at the timing used.
+    pub frames: u64,
+    /// The time taken to execute them, without profiling.
+    pub elapsed: Duration,
+    /// The time spent on each kind of instruction, measured separately.
+    pub profile: Profile,
+}
+
+impl Report {
+    /// The number of instructions executed per second.
+    #[must_use]
+    pub fn ips(&self) -> f64 {
+        #[allow(clippy::cast_precision_loss)]
+        let instructions = self.instructions as f64;
+        instructions / self.elapsed.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions:  {}", self.instructions)?;
+        writeln!(f, "Frames:        {}", self.frames)?;
+        writeln!(f, "Time:          {:.3}s", self.elapsed.as_secs_f64())?;
+        writeln!(f, "Speed:         {:.0} instructions/s", self.ips())?;
+        self.profile.write_opcodes(f)
+    }
+}
+
+/// Runs `rom` headlessly, as fast as possible, until at least `instructions`
+/// instructions have executed. Frames are run as usual, so timers tick once
+/// per frame of `timing`, but without sleeping between them.
+///
+/// Timing every instruction slows them down, so the [`Profile`] is taken
+/// from a second run of the same length.
+///
+/// # Errors
+/// This function will error with the fault that stopped either run.
+pub fn run(rom: &[u8], instructions: u64, timing: Timing, quirks: Quirks) -> Result<Report, Fault> {
+    let interpreter = || {
+        let mut intr = Interpreter::new();
+        intr.with_seed(0);
+        intr.with_timing(timing);
+        intr.with_quirks(quirks);
+        intr.load_rom(rom);
+        intr
+    };
+    let run = |intr: &mut Interpreter, instructions: u64| {
+        let mut frames = 0;
+        while intr.cycles < instructions {
+            intr.frame(frames)?;
+            frames += 1;
+        }
+        Ok(frames)
+    };
+
+    let mut intr = interpreter();
+    let start = Instant::now();
+    let frames = run(&mut intr, instructions)?;
+    let elapsed = start.elapsed();
+    let instructions = intr.cycles;
+
+    let mut intr = interpreter();
+    intr.profile = Some(Profile::default());
+    run(&mut intr, instructions)?;
+
+    Ok(Report {
+        instructions,
+        frames,
+        elapsed,
+        profile: intr.profile.unwrap_or_default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmarks() {
+        // V0 += 1, then jump back: 3 instructions per frame at 180 IPS.
+        let rom = [0x70, 0x01, 0x12, 0x00];
+        let report = run(&rom, 100, Timing::from_ips(180), Quirks::default()).unwrap();
+        assert_eq!(report.instructions, 102);
+        assert_eq!(report.frames, 34);
+        assert_eq!(report.profile.opcode("7XNN").0, 51);
+        assert_eq!(report.profile.opcode("1NNN").0, 51);
+        let text = report.to_string();
+        assert!(text.starts_with("Instructions:  102\nFrames:        34\n"));
+        assert!(text.contains("\n  1NNN            51"));
+
+        let fault = run(&[0xFF, 0xFF], 100, Timing::default(), Quirks::default());
+        assert!(matches!(fault, Err(Fault::UnknownOpcode { .. })));
+    }
+}
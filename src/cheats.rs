@@ -0,0 +1,205 @@
+use crate::{debug, Interpreter};
+use serde::Deserialize;
+use std::{fmt, fs, path::Path};
+
+/// Game Genie-style cheats, read from a file like:
+///
+/// ```toml
+/// [[cheat]]
+/// name = "Infinite lives"
+/// at = "V3"
+/// value = 3
+///
+/// [[cheat]]
+/// name = "Never fewer than 5 bombs"
+/// at = "0x3F0"
+/// value = 5
+/// when = "below"
+/// ```
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Cheats {
+    /// The cheats, in the order they are listed and applied.
+    #[serde(default, rename = "cheat")]
+    pub cheats: Vec<Cheat>,
+}
+
+/// A value kept in a register or memory address at the end of every frame.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct Cheat {
+    /// A description, shown by the debugger.
+    pub name: String,
+    /// The register or memory address to change.
+    pub at: Target,
+    /// The value to keep it at.
+    pub value: u8,
+    /// When to change it.
+    #[serde(default)]
+    pub when: When,
+    /// Whether the cheat is applied. Toggled from the debugger.
+    #[serde(default = "enabled")]
+    pub enabled: bool,
+}
+
+/// Cheats are enabled unless the file says otherwise.
+const fn enabled() -> bool {
+    true
+}
+
+/// What a [`Cheat`] changes: a register (`V0` through `VF`) or a memory
+/// address in hexadecimal.
+#[derive(Copy, Clone, Debug, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Target {
+    Register(u8),
+    Memory(u16),
+}
+
+impl TryFrom<String> for Target {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.strip_prefix(['V', 'v']) {
+            Some(n) if n.len() == 1 => u8::from_str_radix(n, 16).ok().map(Self::Register),
+            _ => debug::parse_address(&s).map(Self::Memory),
+        }
+        .ok_or_else(|| format!("'{s}' is neither a register nor an address"))
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Register(n) => write!(f, "V{n:X}"),
+            Self::Memory(address) => write!(f, "{address:#05X}"),
+        }
+    }
+}
+
+/// When a [`Cheat`] changes its target.
+#[derive(Copy, Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum When {
+    /// Every frame, freezing it.
+    #[default]
+    Always,
+    /// Only once it has fallen below the value.
+    Below,
+    /// Only once it has risen above the value.
+    Above,
+}
+
+impl fmt::Display for Cheat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let condition = match self.when {
+            When::Always => "",
+            When::Below => " when below",
+            When::Above => " when above",
+        };
+        write!(
+            f,
+            "[{}] {}: {} = {:#04X}{condition}",
+            if self.enabled { "x" } else { " " },
+            self.name,
+            self.at,
+            self.value
+        )
+    }
+}
+
+impl Cheats {
+    /// Reads the cheats file at `path`.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be read or is malformed.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+        toml::from_str(&contents)
+            .map_err(|e| format!("Malformed cheats file '{}': {e}", path.display()))
+    }
+
+    /// Applies the enabled cheats to `intr`, at the end of a frame.
+    pub(crate) fn apply(&self, intr: &mut Interpreter) {
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            let current = match cheat.at {
+                Target::Register(n) => intr.registers[usize::from(n)],
+                Target::Memory(address) => intr.memory[usize::from(address)],
+            };
+            let change = match cheat.when {
+                When::Always => current != cheat.value,
+                When::Below => current < cheat.value,
+                When::Above => current > cheat.value,
+            };
+            if !change {
+                continue;
+            }
+            match cheat.at {
+                Target::Register(n) => intr.registers[usize::from(n)] = cheat.value,
+                Target::Memory(address) => {
+                    let address = usize::from(address);
+                    intr.memory[address] = cheat.value;
+                    intr.forget(address..address + 1);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_cheats() {
+        let cheats: Cheats = toml::from_str(
+            r#"
+            [[cheat]]
+            name = "Lives"
+            at = "V3"
+            value = 3
+
+            [[cheat]]
+            name = "Bombs"
+            at = "0x3F0"
+            value = 5
+            when = "below"
+
+            [[cheat]]
+            name = "Speed"
+            at = "VA"
+            value = 2
+            when = "above"
+            enabled = false
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            cheats.cheats[1].to_string(),
+            "[x] Bombs: 0x3F0 = 0x05 when below"
+        );
+        assert_eq!(
+            cheats.cheats[2].to_string(),
+            "[ ] Speed: VA = 0x02 when above"
+        );
+
+        let mut intr = Interpreter::new();
+        intr.registers[0xA] = 9;
+        intr.memory[0x3F0] = 7;
+        cheats.apply(&mut intr);
+        assert_eq!(intr.registers[3], 3);
+        assert_eq!(intr.registers[0xA], 9);
+        assert_eq!(intr.memory[0x3F0], 7);
+        intr.memory[0x3F0] = 1;
+        cheats.apply(&mut intr);
+        assert_eq!(intr.memory[0x3F0], 5);
+
+        assert!(
+            toml::from_str::<Cheats>("[[cheat]]\nname = \"x\"\nat = \"V10\"\nvalue = 1")
+                .unwrap_err()
+                .to_string()
+                .contains("neither a register nor an address")
+        );
+    }
+}
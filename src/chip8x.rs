@@ -0,0 +1,142 @@
+use crate::config::Color;
+use core::ops::RangeInclusive;
+
+/// The colors of the VP-590 color board, by the 3-bit value CHIP-8X ROMs
+/// select them with: bit 0 is red, bit 1 blue, and bit 2 green.
+pub const COLORS: [Color; 8] = [
+    Color([0x00, 0x00, 0x00]), // black
+    Color([0xFF, 0x00, 0x00]), // red
+    Color([0x00, 0x00, 0xFF]), // blue
+    Color([0xFF, 0x00, 0xFF]), // violet
+    Color([0x00, 0xFF, 0x00]), // green
+    Color([0xFF, 0xFF, 0x00]), // yellow
+    Color([0x00, 0xFF, 0xFF]), // aqua
+    Color([0xFF, 0xFF, 0xFF]), // white
+];
+/// The background colors `02A0` cycles through, in order.
+const BACKGROUNDS: [u8; 4] = [2, 0, 4, 1];
+/// The width of a color zone, in pixels.
+pub const ZONE_WIDTH: usize = 8;
+/// The height of the zones colored by `BXY0`, in pixels. `BXYN` colors
+/// single rows of pixels.
+pub const ZONE_HEIGHT: usize = 4;
+/// The number of zones across the screen.
+const COLUMNS: usize = 64 / ZONE_WIDTH;
+/// The number of rows of pixels on the screen.
+const ROWS: usize = 32;
+/// Where CHIP-8X ROMs are loaded, after the part of memory the original
+/// interpreter used.
+pub const PROGRAM_START: usize = 0x300;
+
+/// The CHIP-8X color board: a background color for the whole screen, and
+/// foreground colors for lit pixels in zones 8 pixels wide. Lit pixels start
+/// out red on blue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ColorBoard {
+    background: usize,            // The index into `BACKGROUNDS`
+    zones: [[u8; COLUMNS]; ROWS], // The foreground color of each zone, by row
+}
+
+impl Default for ColorBoard {
+    fn default() -> Self {
+        Self {
+            background: 0,
+            zones: [[1; COLUMNS]; ROWS],
+        }
+    }
+}
+
+impl ColorBoard {
+    /// `02A0`: switches to the next background color.
+    pub fn cycle_background(&mut self) {
+        self.background = (self.background + 1) % BACKGROUNDS.len();
+    }
+
+    /// `BXYN`: colors zones with `color`. The low nibble of `horizontal` is
+    /// the first zone across, and its high nibble the number of zones after
+    /// it. With `rows` of `0`, `vertical` picks 4-pixel-high zones the same
+    /// way; otherwise, its value is the first row of pixels to color, and
+    /// `rows` the number of rows.
+    pub fn fill(&mut self, horizontal: u8, vertical: u8, rows: u8, color: u8) {
+        let span = |value: u8, unit: usize| {
+            let start = usize::from(value & 0xF) * unit;
+            start..=start + (usize::from(value >> 4) + 1) * unit - 1
+        };
+        let columns = span(horizontal, 1);
+        let rows: RangeInclusive<usize> = if rows == 0 {
+            span(vertical, ZONE_HEIGHT)
+        } else {
+            let start = usize::from(vertical);
+            start..=start + usize::from(rows) - 1
+        };
+        for row in self
+            .zones
+            .iter_mut()
+            .take(*rows.end() + 1)
+            .skip(*rows.start())
+        {
+            for zone in row
+                .iter_mut()
+                .take(*columns.end() + 1)
+                .skip(*columns.start())
+            {
+                *zone = color & 7;
+            }
+        }
+    }
+
+    /// The background color.
+    #[must_use]
+    pub fn background(&self) -> Color {
+        COLORS[usize::from(BACKGROUNDS[self.background])]
+    }
+
+    /// The color of the pixel at (`x`, `y`) when it is lit.
+    #[must_use]
+    pub fn foreground(&self, x: usize, y: usize) -> Color {
+        COLORS[usize::from(self.zones[y % ROWS][x / ZONE_WIDTH % COLUMNS])]
+    }
+}
+
+/// `5XY1`: adds `vy` to `vx` nibble by nibble, with each nibble wrapping
+/// around at 8 rather than carrying.
+#[must_use]
+pub const fn add_nibbles(vx: u8, vy: u8) -> u8 {
+    ((vx & 0x70).wrapping_add(vy & 0x70) & 0x70) | ((vx & 0x7).wrapping_add(vy & 0x7) & 0x7)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_zones() {
+        let mut board = ColorBoard::default();
+        assert_eq!(board.background(), COLORS[2]);
+        assert_eq!(board.foreground(63, 31), COLORS[1]);
+
+        // Zones 1 and 2 across, and the second band of 4 rows, in green.
+        board.fill(0x11, 0x01, 0, 4);
+        assert_eq!(board.foreground(8, 4), COLORS[4]);
+        assert_eq!(board.foreground(23, 7), COLORS[4]);
+        assert_eq!(board.foreground(24, 7), COLORS[1]);
+        assert_eq!(board.foreground(8, 8), COLORS[1]);
+        // Rows 30 and 31 of the last zone, in white; past the bottom is ignored.
+        board.fill(0x07, 30, 5, 7);
+        assert_eq!(board.foreground(56, 29), COLORS[1]);
+        assert_eq!(board.foreground(63, 31), COLORS[7]);
+
+        for _ in 0..3 {
+            board.cycle_background();
+        }
+        assert_eq!(board.background(), COLORS[1]);
+        board.cycle_background();
+        assert_eq!(board.background(), COLORS[2]);
+    }
+
+    #[test]
+    fn adds_nibbles() {
+        assert_eq!(add_nibbles(0x12, 0x34), 0x46);
+        assert_eq!(add_nibbles(0x55, 0x35), 0x02);
+    }
+}
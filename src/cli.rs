@@ -1,10 +1,40 @@
-use clap::{Parser, Subcommand, ValueEnum};
-use log::error;
+use crate::{
+    asm::SourceMap,
+    audio::Waveform,
+    cheats::Cheats,
+    config::{Config, Palette},
+    conformance, dap,
+    database::{Database, Entry, Verdict},
+    debug,
+    detect::{self, Detection},
+    disasm,
+    hash::Hashes,
+    input::{Paddle, Turbo},
+    library::Library,
+    netplay,
+    opcode::{Doc, Platform},
+    patch::{Edit, Patch},
+    quirks::{IndexOverflow, JumpOffset, PcOverflow, Quirks, Shift},
+    replay,
+    screen::Scaling,
+    setup,
+    stats::{self, Session},
+    targets,
+    timing::{Speed, Timing},
+    trace,
+    update::Release,
+    Counters, Fault,
+};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use log::{error, info, warn};
 use std::{
     fmt, fs,
-    io::{self, Write},
+    io::{self, IsTerminal, Write},
     path::{Path, PathBuf},
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
 };
+use tracing_subscriber::layer::SubscriberExt;
 
 /// The etherea CLI.
 #[derive(Parser)]
@@ -17,29 +47,494 @@ pub struct Cli {
     /// Verbosity of debug logging
     #[arg(short, long, value_enum)]
     log_level: Option<LogLevel>,
+
+    /// Levels for each subsystem, on top of --log-level, like cpu=trace,input=debug (cpu,
+    /// display, input, timer, or any module path)
+    #[arg(long, value_name = "TARGET=LEVEL,...")]
+    log_filter: Option<String>,
+
+    /// How log lines are written to standard error
+    #[arg(long, value_enum, default_value_t)]
+    log_format: LogFormat,
 }
 
 /// Possible commands to run.
 #[derive(Subcommand)]
 pub enum Commands {
     /// Runs a ROM.
-    Run {
+    Run(Box<RunArgs>),
+    /// Runs a ROM, playing back key events from a replay file.
+    Replay {
         /// The path to the ROM
         path: String,
 
-        /// The number of instructions to execute per second
+        /// The path to the replay file
+        replay: PathBuf,
+
+        /// Record the session to an animated GIF (or APNG, for `.png` paths)
+        #[arg(short, long)]
+        record: Option<PathBuf>,
+
+        /// Write the state of the machine before every instruction to a trace file, for comparing
+        /// with `diff-trace`
+        #[arg(long)]
+        trace: Option<PathBuf>,
+    },
+    /// Assembles a source file into a ROM.
+    Assemble {
+        /// The path to the assembly source: classic mnemonics, or Octo for `.8o` files and
+        /// Octo cartridges
+        input: PathBuf,
+
+        /// Where to write the ROM (defaults to the input path with a `.ch8` extension)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Also write a symbol file beside the ROM, with a `.sym` extension, so the debugger and
+        /// disassembler show labels and source lines
         #[arg(short, long)]
+        symbols: bool,
+    },
+    /// Prints statistics about a ROM, such as which extensions it needs.
+    Analyze {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// Also run the ROM headlessly and map what every address in memory was used for: the
+        /// font, the ROM, executed code, data reads and writes. Printed in the terminal, or
+        /// written as a PNG to the path given
+        #[arg(long, num_args = 0..=1, value_name = "PATH")]
+        map: Option<Option<PathBuf>>,
+
+        /// The number of instructions to execute for the map (defaults to the length of the
+        /// replay, or 1,000,000)
+        #[arg(short = 'n', long, requires = "map")]
+        instructions: Option<u64>,
+
+        /// The number of instructions per second to count frames at, instead of the config file's
+        #[arg(short, long, requires = "map")]
         ips: Option<u64>,
+
+        /// Press keys as in a replay file, which also sets the seed and speed
+        #[arg(short, long, requires = "map")]
+        replay: Option<PathBuf>,
     },
+    /// Prints what the ROM database knows about a ROM, such as its title and the settings it
+    /// plays best with.
+    Info {
+        /// The path to the ROM
+        path: PathBuf,
+    },
+    /// Prints a ROM's checksums: the FNV-1a hash the ROM database uses, CRC-32, and SHA-1.
+    Hash {
+        /// The path to the ROM
+        path: PathBuf,
+    },
+    /// Checks a ROM against the ROM database's known-good dumps. Exits with 1 unless it is one.
+    Verify {
+        /// The path to the ROM
+        path: PathBuf,
+    },
+    /// Compares two trace files and reports the first instruction where they differ in the
+    /// program counter, registers, timers, or memory. Exits with 1 if they do.
+    DiffTrace {
+        /// The first trace, such as one written with `--trace`
+        first: PathBuf,
+
+        /// The second trace, such as another emulator's log with fields like `PC=0200`
+        second: PathBuf,
+    },
+    /// Runs every ROM in a directory headlessly for a number of instructions, and reports whether
+    /// each hit an unknown opcode, faulted, or got stuck, and a hash of the screen it ended on.
+    Batch {
+        /// The directory to look for ROMs in, including its subdirectories
+        dir: PathBuf,
+
+        /// The number of instructions to execute per ROM
+        #[arg(short = 'n', long, default_value_t = 1_000_000)]
+        cycles: u64,
+
+        /// The number of instructions per second to count frames at, instead of the config file's
+        #[arg(short, long)]
+        ips: Option<u64>,
+
+        /// Also write the results, as JSON, to this path
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Runs a ROM headlessly as fast as possible and reports how fast it ran.
+    Bench {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// The number of instructions to execute
+        #[arg(short = 'n', long, default_value_t = 10_000_000)]
+        instructions: u64,
+
+        /// The number of instructions per second to count frames at, instead of the config file's
+        #[arg(short, long)]
+        ips: Option<u64>,
+    },
+    /// Runs a ROM headlessly and reports which instructions it spends its time on.
+    Profile {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// The number of instructions to execute (defaults to the length of the replay, or
+        /// 1,000,000)
+        #[arg(short = 'n', long)]
+        instructions: Option<u64>,
+
+        /// The number of instructions per second to count frames at, instead of the config file's
+        #[arg(short, long)]
+        ips: Option<u64>,
+
+        /// Press keys as in a replay file, which also sets the seed and speed
+        #[arg(short, long)]
+        replay: Option<PathBuf>,
+
+        /// Also write a heatmap of the disassembly, as a PNG, to this path
+        #[arg(long)]
+        heatmap: Option<PathBuf>,
+    },
+    /// Runs a ROM headlessly alongside a plain reference core, and reports the first instruction
+    /// after which their state differs. Exits with 1 if it does.
+    Compare {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// The number of instructions to execute (defaults to the length of the replay, or
+        /// 1,000,000)
+        #[arg(short = 'n', long)]
+        instructions: Option<u64>,
+
+        /// The number of instructions per second to count frames at, instead of the config file's
+        #[arg(short, long)]
+        ips: Option<u64>,
+
+        /// Press keys as in a replay file, which also sets the seed and speed
+        #[arg(short, long)]
+        replay: Option<PathBuf>,
+    },
+    /// Describes an instruction, given as a raw opcode (D125), pattern (8XY6), or mnemonic (DRW).
+    Explain {
+        /// The instruction to describe
+        query: String,
+    },
+    /// Lists the ROMs in a directory to choose one to play, returning to the list on Escape.
+    Library {
+        /// The directory to look for ROMs in (defaults to the config file's `rom_dir`, or the
+        /// current directory)
+        dir: Option<PathBuf>,
+    },
+    /// Shows ROMs one after another in a borderless fullscreen window, for demo installations.
+    /// Each runs for a while, then the next takes over, once nobody is playing.
+    Kiosk {
+        /// The ROMs to show: ROMs, directories of them, or `.txt` or `.m3u` playlists listing them
+        #[arg(required = true)]
+        playlist: Vec<PathBuf>,
+
+        /// How long to show each ROM, in seconds
+        #[arg(short, long, default_value_t = 120)]
+        duration: u64,
+
+        /// How long nobody has to have pressed a key for before switching, in seconds
+        #[arg(long, default_value_t = 30)]
+        idle: u64,
+    },
+    /// Chooses the window scale and palette, and checks the keymap.
+    Setup,
+    /// Shows or wipes the local usage statistics.
+    Stats {
+        #[command(subcommand)]
+        command: StatsCommand,
+    },
+    /// Checks for a newer release and downloads it to a staging directory.
+    Update {
+        /// Only report whether a newer release is available
+        #[arg(long)]
+        check: bool,
+    },
+    /// Runs the built-in test ROMs headlessly, checking their final screens.
+    /// Exits with 1 if any test fails, or 2 if none match the filter.
+    Test {
+        /// Only run the tests whose names contain this
+        filter: Option<String>,
+
+        /// Print the final screen of every failing test
+        #[arg(long)]
+        screens: bool,
+    },
+    /// Runs Timendus' CHIP-8 test suite headlessly, grading each line of the result screens
+    /// against known-good ones. Exits with 1 if any test fails, or 2 if none could be graded.
+    Conformance {
+        /// A copy of the suite's `bin` directory, with known-good screens in `expected`. Screens
+        /// shipped with etherea are used for tests without one
+        dir: PathBuf,
+
+        /// Download the suite's ROMs into the directory first, from GitHub
+        #[arg(long)]
+        download: bool,
+
+        /// Save the screens of this run as the known-good ones, once checked by eye
+        #[arg(long)]
+        bless: bool,
+
+        /// Print the final screen of every failing or ungraded test
+        #[arg(long)]
+        screens: bool,
+    },
+    /// Serves the Debug Adapter Protocol on standard input and output, for editors to debug
+    /// ROMs and assembly source with.
+    Dap,
     /// Disassembles a ROM.
     Disassemble {
         /// The path to the ROM
         path: PathBuf,
 
-        /// Where to output the disassembled ROM
+        /// Where to output the disassembled ROM (standard output if omitted)
         #[arg(short, long)]
         output_file: Option<PathBuf>,
+
+        /// The output format
+        #[arg(short, long, value_enum, default_value_t)]
+        format: Format,
+    },
+    /// Decompiles a ROM into Octo source that assembles back into the same bytes.
+    Decompile {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// Where to write the source (standard output if omitted)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
     },
+    /// Edits a ROM with IPS patches and raw bytes, writing the result to a new file.
+    Patch {
+        /// The path to the ROM
+        path: PathBuf,
+
+        /// Bytes to write at an address in memory, where the ROM starts at 0x200, like
+        /// `0x200:6A02`. Applied after the patches, in order
+        #[arg(
+            long = "set",
+            value_name = "ADDR:BYTES",
+            required_unless_present = "apply"
+        )]
+        edits: Vec<Edit>,
+
+        /// An IPS patch to apply, such as one exported from the debugger with `patch`. Applied in
+        /// order
+        #[arg(long, value_name = "PATH")]
+        apply: Vec<PathBuf>,
+
+        /// Where to write the patched ROM
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+/// Subcommands of the `stats` command.
+#[derive(Subcommand)]
+pub enum StatsCommand {
+    /// Shows the total playtime of every ROM played.
+    History,
+    /// Deletes all usage statistics.
+    Wipe,
+}
+
+/// The output format of the `disassemble` command.
+#[derive(Copy, Clone, Debug, Default, ValueEnum)]
+pub enum Format {
+    /// Source that can be assembled again
+    #[default]
+    Text,
+    /// A JSON array of lines
+    Json,
+    /// Comma-separated values, one row per line
+    Csv,
+}
+
+/// Arguments to the `run` command. Settings left out are taken from the
+/// config file.
+#[derive(Args)]
+#[allow(clippy::struct_excessive_bools)] // they're command-line switches
+pub struct RunArgs {
+    /// The path to the ROM, which is also looked for in the config file's `rom_dir`
+    pub path: String,
+
+    /// The number of instructions to execute per second
+    #[arg(short, long)]
+    pub ips: Option<u64>,
+
+    /// A named speed for the platform the ROM was written for, instead of --ips
+    #[arg(long, value_enum, conflicts_with = "ips")]
+    pub speed: Option<Speed>,
+
+    /// Record the session to an animated GIF (or APNG, for `.png` paths)
+    #[arg(short, long)]
+    pub record: Option<PathBuf>,
+
+    /// Seed for the random number generator, for reproducible runs
+    #[arg(short, long)]
+    pub seed: Option<u64>,
+
+    /// Record key events to a replay file, for playback with `replay`
+    #[arg(long)]
+    pub record_input: Option<PathBuf>,
+
+    /// Write the state of the machine before every instruction to a trace file, for comparing
+    /// with `diff-trace`
+    #[arg(long)]
+    pub trace: Option<PathBuf>,
+
+    /// Record how long each frame, fetch, decode, execution, and draw takes to a Chrome trace
+    /// file, for `chrome://tracing`, Perfetto, or speedscope's flame graphs
+    #[arg(long, value_name = "PATH")]
+    pub profile_out: Option<PathBuf>,
+
+    /// Print the instructions executed, frames drawn, speed, timer ticks, and key events when
+    /// the window is closed
+    #[arg(long)]
+    pub exit_stats: bool,
+
+    /// Write the statistics printed by --exit-stats to a JSON file instead
+    #[arg(long, value_name = "PATH")]
+    pub exit_stats_out: Option<PathBuf>,
+
+    /// Start paused in the debugger, restoring this ROM's saved breakpoints
+    #[arg(short, long)]
+    pub debug: bool,
+
+    /// Pause in the debugger at the start of a frame or the next time an address executes
+    #[arg(long, value_name = "frame=<n>|pc=<addr>")]
+    pub pause_at: Vec<debug::PauseAt>,
+
+    /// How many window pixels wide and tall each CHIP-8 pixel is
+    #[arg(long)]
+    pub scale: Option<u32>,
+
+    /// The colors to draw in: classic, amber, green, or lcd
+    #[arg(long, value_parser = palette)]
+    pub palette: Option<Palette>,
+
+    /// How the screen is scaled to fill the window, switched with F7 [default: integer]
+    #[arg(long, value_enum)]
+    pub scaling: Option<Scaling>,
+
+    /// Pause, and silence the beep, while the window doesn't have focus
+    #[arg(long)]
+    pub pause_unfocused: bool,
+
+    /// Save the RPL user flags set with FX75, where SUPER-CHIP games keep high scores, and
+    /// restore them the next time the ROM is played
+    #[arg(long)]
+    pub save_flags: bool,
+
+    /// Time instructions by what each costs on the COSMAC VIP, in machine cycles, instead of
+    /// running a set number each frame; drawing waits for the display, as on the VIP
+    #[arg(long)]
+    pub cycle_accurate: bool,
+
+    /// The shape of the beep's sound wave [default: square]
+    #[arg(long, value_enum)]
+    pub waveform: Option<Waveform>,
+
+    /// The pitch of the beep, in Hz [default: 440]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(20..=20_000))]
+    pub frequency: Option<u32>,
+
+    /// How loud the beep is, from 0.0 to 1.0 [default: 0.2]
+    #[arg(long, value_parser = volume)]
+    pub volume: Option<f32>,
+
+    /// Hold one key with the mouse in the left of the window and another in the right, like 4,6,
+    /// to play paddle games with the mouse
+    #[arg(long, value_name = "LEFT,RIGHT")]
+    pub paddle: Option<Paddle>,
+
+    /// Press this key over and over while Space (or the config file's turbo modifier) is held
+    #[arg(long, value_name = "KEY", value_parser = hex_key)]
+    pub turbo: Option<u8>,
+
+    /// How many times a second turbo presses its key [default: 10]
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(1..=30))]
+    pub turbo_rate: Option<u32>,
+
+    /// What happens when FX1E moves I past the end of memory [default: mask]
+    #[arg(long, value_enum)]
+    pub index_overflow: Option<IndexOverflow>,
+
+    /// What happens when the PC moves past the end of memory [default: wrap]
+    #[arg(long, value_enum)]
+    pub pc_overflow: Option<PcOverflow>,
+
+    /// Which register BNNN adds to its address: V0, or VX for CHIP-48 and SUPER-CHIP ROMs [default: v0]
+    #[arg(long, value_enum)]
+    pub jump_offset: Option<JumpOffset>,
+
+    /// Which register 8XY6 and 8XYE shift: VX, or VY for COSMAC VIP ROMs [default: vx]
+    #[arg(long, value_enum)]
+    pub shift: Option<Shift>,
+
+    /// Reset VF to 0 after 8XY1, 8XY2, and 8XY3, as the COSMAC VIP did
+    #[arg(long)]
+    pub vf_reset: bool,
+
+    /// Set VF to 1 when FX1E moves I past 0xFFF, and to 0 when it doesn't, as the Amiga
+    /// interpreter did
+    #[arg(long)]
+    pub index_carry: bool,
+
+    /// Scroll half as far with 00CN, 00DN, 00FB, and 00FC in low resolution, as SUPER-CHIP 1.1
+    /// did
+    #[arg(long)]
+    pub half_scroll: bool,
+
+    /// How many subroutine calls can be nested before the ROM faults [default: 16]
+    #[arg(long)]
+    pub stack_depth: Option<usize>,
+
+    /// Map ROMs bigger than memory in 2K banks, selected with 0BNN (experimental)
+    #[arg(long)]
+    pub banking: bool,
+
+    /// The platform the ROM was written for, instead of working it out from its metadata file,
+    /// extension, header, size, and instructions
+    #[arg(long, value_enum)]
+    pub platform: Option<Platform>,
+
+    /// Reload the ROM, restarting it, whenever the file changes
+    #[arg(short, long)]
+    pub watch: bool,
+
+    /// Keep registers and memory at the values listed in a cheats file, toggled from the debugger
+    #[arg(long)]
+    pub cheats: Option<PathBuf>,
+
+    /// Run a Rhai script that hooks into each instruction, frame, key, and draw, and can read and
+    /// change registers and memory
+    #[arg(long, value_name = "FILE")]
+    pub script: Option<PathBuf>,
+
+    /// Run a second instance beside the first, of the same ROM or (with --twin=<ROM>) another,
+    /// to race or compare quirks. Tab switches which window gets the keys
+    #[arg(long, value_name = "ROM", num_args = 0..=1, require_equals = true)]
+    pub twin: Option<Option<String>>,
+
+    /// Wait for another player to join at this address, e.g. 0.0.0.0:7777, and play together.
+    /// They get this side's seed, speed, quirks, and cycle-accurate timing
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["join", "twin", "watch"])]
+    pub host: Option<String>,
+
+    /// Join another player hosting the same ROM at this address, and play together
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["seed", "twin", "watch"])]
+    pub join: Option<String>,
+
+    /// Serve an HTTP API on this local port, for other programs to load ROMs, pause, take
+    /// screenshots, read registers and memory, and press keys
+    #[arg(long, value_name = "PORT")]
+    pub control_port: Option<u16>,
 }
 
 /// The logging level passed to [`env_logger`](env_logger).
@@ -51,79 +546,1543 @@ enum LogLevel {
     Error,
 }
 
-impl ToString for LogLevel {
-    fn to_string(&self) -> String {
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Info => "info".into(),
-            Self::Debug => "debug".into(),
-            Self::Trace => "trace".into(),
-            Self::Error => "error".into(),
+            Self::Info => write!(f, "info"),
+            Self::Debug => write!(f, "debug"),
+            Self::Trace => write!(f, "trace"),
+            Self::Error => write!(f, "error"),
         }
     }
 }
 
-/// Parses the command-line args and configures the logging level.
+/// How log lines are written.
+#[derive(Copy, Clone, Default, ValueEnum)]
+enum LogFormat {
+    /// For people: the time, level, target, and message
+    #[default]
+    Text,
+    /// For tools: a JSON object per line, with `time`, `level`, `target`, and `message`
+    Json,
+}
+
+/// Parses the command-line args and configures logging.
 #[must_use]
 pub fn init() -> Cli {
     let cli = Cli::parse();
-    std::env::set_var(
-        "RUST_LOG",
-        format!(
-            "etherea={}",
-            cli.log_level.unwrap_or(LogLevel::Error).to_string()
-        ),
+    let mut logger = env_logger::Builder::new();
+    logger.parse_filters(&format!(
+        "etherea={}",
+        cli.log_level.unwrap_or(LogLevel::Error)
+    ));
+    if let Some(filter) = &cli.log_filter {
+        logger.parse_filters(&log_filter(filter));
+    }
+    if let LogFormat::Json = cli.log_format {
+        logger.format(|buf, record| {
+            let line = serde_json::json!({
+                "time": buf.timestamp().to_string(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+            writeln!(buf, "{line}")
+        });
+    }
+    logger.init();
+
+    cli
+}
+
+/// Expands the short names of subsystems in the `--log-filter` directives
+/// of `filter` to their [`targets`], so `cpu=trace` means
+/// `etherea::cpu=trace`. Other names are kept, as module paths.
+fn log_filter(filter: &str) -> String {
+    let directives: Vec<String> = filter
+        .split(',')
+        .map(|directive| {
+            let (name, level) = directive
+                .split_once('=')
+                .map_or((directive, None), |(name, level)| (name, Some(level)));
+            let target = targets::ALL
+                .iter()
+                .find(|(short, _)| short.eq_ignore_ascii_case(name.trim()))
+                .map_or(name, |&(_, target)| target);
+            match level {
+                Some(level) => format!("{target}={level}"),
+                None => target.to_string(),
+            }
+        })
+        .collect();
+    directives.join(",")
+}
+
+/// Runs the ROM at `args.path` with the options provided in `args`.
+pub fn run(args: RunArgs) {
+    let mut config = config();
+    let path = rom_path(&args.path, config.rom_dir.as_deref());
+    let rom = read(&path).unwrap_or_else(|err| exit(&err));
+
+    let database = Database::load();
+    let known = database.lookup(&rom);
+    if let Some(entry) = known {
+        info!("ROM: {}", entry);
+    }
+    let detection = platform(&args, &path, &rom, known);
+    let (timing, quirks) = settings(&args, &mut config, detection, known);
+    config.profiles.extend(database.profiles());
+    if !args.banking {
+        check_fits(&rom, detection.platform);
+    }
+
+    let debugger = debugger(&args, Path::new(&path), &rom);
+
+    // The seed has to be known up front so it can be written to the journal
+    // and agreed with the other player, if there is one.
+    let seed = args.seed.unwrap_or_else(rand::random);
+    let terms = netplay::Terms {
+        seed,
+        timing,
+        quirks,
+        cycle_costs: config.cycle_accurate.then(|| config.cycle_costs.clone()),
+    };
+    let (
+        netplay,
+        netplay::Terms {
+            seed,
+            timing,
+            quirks,
+            cycle_costs,
+        },
+    ) = connect(&args, &rom, terms);
+    let journal = args.record_input.map(|path| {
+        replay::Journal::create(&path, seed, timing, cycle_costs.as_ref()).unwrap_or_else(|err| {
+            error!("Could not create replay file '{}': {}", path.display(), err);
+            std::process::exit(1);
+        })
+    });
+
+    let twin = args.twin.map(|twin| match twin {
+        Some(path) => {
+            read(rom_path(&path, config.rom_dir.as_deref())).unwrap_or_else(|err| exit(&err))
+        }
+        None => rom.clone(),
+    });
+    let cheats = args
+        .cheats
+        .map(|path| Cheats::read(&path).unwrap_or_else(|err| exit(&err)));
+    let script = args
+        .script
+        .map(|path| crate::script::Rhai::open(&path).unwrap_or_else(|err| exit(&err)));
+
+    let ips = timing.cycles_before(u64::from(timing.frames_per_second));
+    let session = config.usage_stats.then(|| {
+        let name = Path::new(&args.path)
+            .file_name()
+            .map_or_else(|| args.path.clone(), |name| name.to_string_lossy().into());
+        Session::start(&name, &rom, format!("{ips} IPS, {quirks}"))
+    });
+    if config.check_for_updates {
+        check_for_updates();
+    }
+    let profiling = profile_out(args.profile_out.as_deref());
+    let counters = (args.exit_stats || args.exit_stats_out.is_some()).then(Arc::default);
+    let started = Instant::now();
+
+    let result = crate::run(
+        &rom,
+        crate::Options {
+            timing,
+            seed: Some(seed),
+            record: args.record,
+            journal,
+            trace: trace_file(args.trace.as_deref()),
+            replay: None,
+            debugger,
+            quirks,
+            cycle_costs,
+            config,
+            path: Some(PathBuf::from(&path)),
+            session,
+            banking: args.banking,
+            platform: detection.platform,
+            library: None,
+            watch: args.watch.then(|| PathBuf::from(path)),
+            cheats,
+            script,
+            twin,
+            netplay,
+            control_port: args.control_port,
+            dap: None,
+            kiosk: None,
+            counters: counters.clone(),
+        },
     );
+    // Written when dropped, which exiting would skip.
+    drop(profiling);
+    if let Some(counters) = counters {
+        exit_stats(&counters, started, ips, args.exit_stats_out.as_deref());
+    }
+    exit_on_fault(&result);
+}
 
-    env_logger::init();
+/// Logs `err`, and exits with a failure.
+fn exit(err: &str) -> ! {
+    error!("{}", err);
+    std::process::exit(1);
+}
 
-    cli
+/// Prints what a run that started at `started`, meant to run at
+/// `requested_ips`, did according to `counters`, or writes it to `out` as
+/// JSON if there is a path.
+fn exit_stats(
+    counters: &Mutex<Counters>,
+    started: Instant,
+    requested_ips: u64,
+    out: Option<&Path>,
+) {
+    let counters = *counters.lock().unwrap_or_else(PoisonError::into_inner);
+    let totals = stats::Totals::new(counters, started.elapsed(), requested_ips);
+    let Some(path) = out else {
+        println!("{totals}");
+        return;
+    };
+    let written = serde_json::to_string_pretty(&totals)
+        .map_err(io::Error::from)
+        .and_then(|json| fs::write(path, json + "\n"));
+    if let Err(e) = written {
+        error!("Could not write statistics to '{}': {}", path.display(), e);
+    }
+}
+
+/// Records the spans the interpreter enters to `path`, if there is one, in
+/// the Chrome trace format, until the returned guard is dropped. Exits if
+/// they can't be.
+fn profile_out(path: Option<&Path>) -> Option<tracing_chrome::FlushGuard> {
+    let path = path?;
+    let (layer, guard) = tracing_chrome::ChromeLayerBuilder::new().file(path).build();
+    if let Err(e) =
+        tracing::subscriber::set_global_default(tracing_subscriber::registry().with(layer))
+    {
+        error!("Could not record a profile: {}", e);
+        std::process::exit(1);
+    }
+    info!("Recording a profile to '{}'", path.display());
+    Some(guard)
+}
+
+/// Exits if `rom`, written for `platform`, is too big for memory.
+fn check_fits(rom: &[u8], platform: Platform) {
+    let capacity = if platform == Platform::Chip8X {
+        crate::Interpreter::MEMORY_SIZE - crate::chip8x::PROGRAM_START
+    } else {
+        crate::Interpreter::ROM_CAPACITY
+    };
+    if rom.len() > capacity {
+        error!(
+            "{}",
+            crate::Fault::RomTooLarge {
+                size: rom.len(),
+                capacity,
+            }
+        );
+        std::process::exit(1);
+    }
+}
+
+/// Prints a message in the background if a newer release is available.
+fn check_for_updates() {
+    std::thread::spawn(|| match Release::latest() {
+        Ok(release) if release.is_newer() => println!(
+            "etherea {} is available; run `etherea update` to download it.",
+            release.version()
+        ),
+        Ok(_) => {}
+        Err(e) => warn!("{}", e),
+    });
+}
+
+/// Waits for another player to join with `--host`, or joins one with
+/// `--join`, so both play `rom` on the host's `terms`. Returns the session,
+/// if there is one, and the terms to play on.
+fn connect(
+    args: &RunArgs,
+    rom: &[u8],
+    terms: netplay::Terms,
+) -> (Option<netplay::Session>, netplay::Terms) {
+    let connected = if let Some(addr) = &args.host {
+        println!("Waiting for another player to join at {addr}...");
+        netplay::Session::host(addr.as_str(), rom, &terms).map(|session| (session, terms))
+    } else if let Some(addr) = &args.join {
+        netplay::Session::join(addr.as_str(), rom)
+    } else {
+        return (None, terms);
+    };
+    match connected {
+        Ok((session, terms)) => (Some(session), terms),
+        Err(e) => {
+            error!("Could not start netplay: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Creates the trace file at `path`, if there is one, exiting if it can't
+/// be.
+fn trace_file(path: Option<&Path>) -> Option<trace::Writer> {
+    path.map(|path| {
+        trace::Writer::create(path).unwrap_or_else(|err| {
+            error!("Could not create trace file '{}': {}", path.display(), err);
+            std::process::exit(1);
+        })
+    })
 }
 
-/// Runs the ROM at `path` with the provided `ips`.
-pub fn run(path: &String, ips: Option<u64>) {
+/// Runs the ROM at `path`, playing back the key events from the replay file
+/// at `replay` with the seed and timing it was recorded with, writing a
+/// trace to `trace` if given.
+pub fn replay(path: &String, replay: &Path, record: Option<PathBuf>, trace: Option<&Path>) {
     let rom = read(path).unwrap_or_else(|err| {
         error!("{}", err);
         std::process::exit(1);
     });
+    let replay = replay::Replay::read(replay).unwrap_or_else(|err| {
+        error!("{}", err);
+        std::process::exit(1);
+    });
 
-    crate::run(&rom, ips.unwrap_or(700));
+    let config = Config::load();
+    let result = crate::run(
+        &rom,
+        crate::Options {
+            timing: replay.timing,
+            seed: Some(replay.seed),
+            record,
+            journal: None,
+            trace: trace_file(trace),
+            replay: Some(replay.entries),
+            debugger: None,
+            quirks: Quirks {
+                display_wait: config.quirks.display_wait || replay.cycle_costs.is_some(),
+                ..config.quirks
+            },
+            cycle_costs: replay.cycle_costs,
+            config,
+            path: Some(PathBuf::from(path)),
+            session: None,
+            banking: false,
+            platform: detect::detect(Path::new(path), &rom, Database::load().lookup(&rom)).platform,
+            library: None,
+            watch: None,
+            cheats: None,
+            script: None,
+            twin: None,
+            netplay: None,
+            control_port: None,
+            dap: None,
+            kiosk: None,
+            counters: None,
+        },
+    );
+    exit_on_fault(&result);
 }
 
-/// Disassembles the ROM at `input_path`.
+/// Serves the Debug Adapter Protocol on standard input and output, then
+/// runs the program the editor launches with the settings from the config
+/// file, until the window is closed or the editor disconnects.
 ///
 /// # Errors
-/// This function will error if `output_file` is not a file or the file at `input_path`
-/// cannot be read.
-pub fn disassemble(input_path: &PathBuf, output_file: Option<PathBuf>) -> Result<(), io::Error> {
-    if let Some(mut f) = output_file.clone() {
-        if f.extension().is_none() {
-            error!("{} is not a file", f.display());
-            std::process::exit(1);
+/// This function will error if the editor disconnects without launching a
+/// program.
+pub fn dap() -> Result<(), String> {
+    let launch = dap::start(io::BufReader::new(io::stdin()), io::stdout())?;
+    let done = launch.done;
+    std::thread::spawn(move || {
+        // Ends once the editor disconnects.
+        let _ = done.recv();
+        std::process::exit(0);
+    });
+
+    let mut config = Config::load();
+    let database = Database::load();
+    config.profiles.extend(database.profiles());
+    let known = database.lookup(&launch.rom);
+    let result = crate::run(
+        &launch.rom,
+        crate::Options {
+            timing: Timing::from_ips(config.ips),
+            seed: None,
+            record: None,
+            journal: None,
+            trace: None,
+            replay: None,
+            debugger: None,
+            quirks: config.quirks,
+            cycle_costs: config.cycle_accurate.then(|| config.cycle_costs.clone()),
+            config,
+            path: Some(launch.path.clone()),
+            session: None,
+            banking: false,
+            platform: detect::detect(&launch.path, &launch.rom, known).platform,
+            library: None,
+            watch: None,
+            cheats: None,
+            script: None,
+            twin: None,
+            netplay: None,
+            control_port: None,
+            dap: Some(launch.target),
+            kiosk: None,
+            counters: None,
+        },
+    );
+    launch.client.terminated();
+    exit_on_fault(&result);
+    Ok(())
+}
+
+/// Shows a menu of the ROMs in `dir` and runs the chosen one with the
+/// settings from the config file. Pressing `Escape` shows the menu again.
+///
+/// # Errors
+/// This function will error if `dir` cannot be read or has no ROMs.
+pub fn library(dir: Option<PathBuf>) -> Result<(), String> {
+    let mut config = config();
+    config.profiles.extend(Database::load().profiles());
+    let dir = dir
+        .or_else(|| config.rom_dir.clone())
+        .unwrap_or_else(|| PathBuf::from("."));
+    let library = Library::scan(&dir)
+        .map_err(|e| format!("Could not read directory '{}': {e}", dir.display()))?;
+    if library.roms().is_empty() {
+        return Err(format!(
+            "No ROMs (.ch8 or Octo source files) in '{}'",
+            dir.display()
+        ));
+    }
+    let Some((path, rom)) = library.pick() else {
+        return Ok(());
+    };
+
+    let result = crate::run(
+        &rom,
+        crate::Options {
+            timing: Timing::from_ips(config.ips),
+            seed: None,
+            record: None,
+            journal: None,
+            trace: None,
+            replay: None,
+            debugger: None,
+            quirks: config.quirks,
+            cycle_costs: config.cycle_accurate.then(|| config.cycle_costs.clone()),
+            config,
+            path: Some(path),
+            // Sessions are kept per ROM, which changes whenever the menu is used.
+            session: None,
+            banking: false,
+            platform: Platform::default(),
+            library: Some(library),
+            watch: None,
+            cheats: None,
+            script: None,
+            twin: None,
+            netplay: None,
+            control_port: None,
+            dap: None,
+            kiosk: None,
+            counters: None,
+        },
+    );
+    exit_on_fault(&result);
+    Ok(())
+}
+
+/// Shows the ROMs in `playlist` one after another, fullscreen, with the
+/// settings from the config file, giving each `duration` seconds once
+/// nobody has played for `idle` seconds.
+///
+/// # Errors
+/// This function will error if a directory or playlist in `playlist` cannot
+/// be read, or none of its ROMs can.
+pub fn kiosk(playlist: &[PathBuf], duration: u64, idle: u64) -> Result<(), String> {
+    let mut config = Config::load();
+    config.profiles.extend(Database::load().profiles());
+    let roms =
+        crate::kiosk::playlist(playlist).map_err(|e| format!("Could not read playlist: {e}"))?;
+    let mut kiosk = crate::kiosk::Kiosk::new(
+        roms,
+        Duration::from_secs(duration),
+        Duration::from_secs(idle),
+    );
+    let Some((path, rom)) = kiosk.advance(Instant::now()) else {
+        return Err("None of the ROMs in the playlist could be read".to_string());
+    };
+
+    let result = crate::run(
+        &rom,
+        crate::Options {
+            timing: Timing::from_ips(config.ips),
+            seed: None,
+            record: None,
+            journal: None,
+            trace: None,
+            replay: None,
+            debugger: None,
+            quirks: config.quirks,
+            cycle_costs: config.cycle_accurate.then(|| config.cycle_costs.clone()),
+            config,
+            path: Some(path),
+            session: None,
+            banking: false,
+            platform: Platform::default(),
+            library: None,
+            watch: None,
+            cheats: None,
+            script: None,
+            twin: None,
+            netplay: None,
+            control_port: None,
+            dap: None,
+            kiosk: Some(kiosk),
+            counters: None,
+        },
+    );
+    exit_on_fault(&result);
+    Ok(())
+}
+
+/// Exits with [`EXIT_FAILURE`] if the ROM faulted. The fault has already
+/// been logged, with where its crash report was written.
+fn exit_on_fault(result: &Result<(), Fault>) {
+    if result.is_err() {
+        std::process::exit(EXIT_FAILURE);
+    }
+}
+
+/// Prints the usage statistics of every ROM played, most played first.
+///
+/// # Errors
+/// This function will error if the history file cannot be read.
+pub fn stats_history() -> io::Result<()> {
+    let history = stats::history()?;
+    if history.is_empty() {
+        println!("No ROMs have been played yet.");
+        return Ok(());
+    }
+    let summaries = stats::summarize(&history);
+    for summary in &summaries {
+        println!("{summary}");
+    }
+    let total = summaries.iter().map(|summary| summary.seconds).sum();
+    println!(
+        "Total: {} over {} session(s)",
+        stats::duration(total),
+        history.len()
+    );
+    Ok(())
+}
+
+/// Deletes the usage statistics.
+///
+/// # Errors
+/// This function will error if the history file cannot be deleted.
+pub fn stats_wipe() -> io::Result<()> {
+    if stats::wipe()? {
+        println!("Deleted all usage statistics.");
+    } else {
+        println!("There were no usage statistics to delete.");
+    }
+    Ok(())
+}
+
+/// Reports whether a newer release is available, along with highlights of
+/// its release notes, then downloads it to the staging directory unless
+/// `check` is set.
+///
+/// # Errors
+/// This function will error if the latest release cannot be fetched or
+/// downloaded.
+pub fn update(check: bool) -> Result<(), String> {
+    let release = Release::latest()?;
+    if !release.is_newer() {
+        println!("etherea {} is up to date.", env!("CARGO_PKG_VERSION"));
+        return Ok(());
+    }
+    println!(
+        "etherea {} is available (you have {}).",
+        release.version(),
+        env!("CARGO_PKG_VERSION")
+    );
+    for highlight in release.highlights() {
+        println!("  - {highlight}");
+    }
+    if check {
+        return Ok(());
+    }
+    let path = release.download()?;
+    println!(
+        "Downloaded to {}; replace your etherea binary with it to finish updating.",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Runs the setup wizard in the terminal and saves the resulting config.
+///
+/// # Errors
+/// This function will error if the terminal cannot be read or written, or
+/// the config file cannot be saved.
+pub fn setup() -> io::Result<Config> {
+    let config = setup::wizard(&mut io::stdin().lock(), &mut io::stdout())?;
+    let path = config.save()?;
+    println!(
+        "\nSaved settings to {}. Run `etherea setup` to change them.",
+        path.display()
+    );
+    Ok(config)
+}
+
+/// The debugger asked for by `--debug` or `--pause-at`, if any, for the
+/// ROM at `path`.
+fn debugger(args: &RunArgs, path: &Path, rom: &[u8]) -> Option<debug::Debugger> {
+    (args.debug || !args.pause_at.is_empty()).then(|| {
+        let mut debugger = debug::Debugger::new(rom);
+        debugger.use_symbols(symbols(path));
+        if !args.debug {
+            debugger.resume();
+        }
+        for &at in &args.pause_at {
+            debugger.pause_at(at);
         }
-        f.pop();
-        fs::create_dir_all(f)?;
+        debugger
+    })
+}
+
+/// Assembles the source file at `input` into a ROM written to `output`.
+/// Octo source files and cartridges are assembled with Octo's syntax, and
+/// anything else with the classic mnemonics. If `symbols` is set, the
+/// ROM's symbol file is written beside it.
+///
+/// # Errors
+/// This function will error if `input` cannot be read, fails to assemble, or
+/// the ROM or symbol file cannot be written.
+pub fn assemble(input: &Path, output: Option<PathBuf>, symbols: bool) -> Result<(), String> {
+    let err = || format!("Could not read file: '{}'", input.display());
+    let file = fs::read(input).map_err(|_| err())?;
+    let assembled = match crate::octo::source(input, &file) {
+        Some(source) => crate::asm::octo::assemble_with_map(&source?),
+        None => crate::asm::assemble_with_map(&String::from_utf8(file).map_err(|_| err())?),
+    };
+    let (rom, map) = assembled.map_err(|e| format!("{}: {e}", input.display()))?;
+
+    let path = output.unwrap_or_else(|| input.with_extension("ch8"));
+    fs::write(&path, &rom).map_err(|e| format!("Could not write '{}': {e}", path.display()))?;
+
+    println!(
+        "Wrote assembled ROM ({} bytes) to {}",
+        rom.len(),
+        path.display()
+    );
+
+    if symbols {
+        let path = SourceMap::path(&path);
+        let map = map.with_source(source_name(input, &path), "");
+        fs::write(&path, map.to_string())
+            .map_err(|e| format!("Could not write '{}': {e}", path.display()))?;
+        println!("Wrote symbols to {}", path.display());
     }
 
-    let path = output_file.unwrap_or_else(|| PathBuf::from("output.txt"));
-    let mut file = fs::File::create(&path)?;
-    let rom = fs::read(input_path)?;
+    Ok(())
+}
+
+/// How the symbol file at `symbols` names the source file at `input`: by
+/// its file name if they're in the same directory, and its full path if
+/// not.
+fn source_name(input: &Path, symbols: &Path) -> PathBuf {
+    let Ok(input) = fs::canonicalize(input) else {
+        return input.to_path_buf();
+    };
+    let dir = symbols
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    match (fs::canonicalize(dir), input.parent(), input.file_name()) {
+        (Ok(dir), Some(parent), Some(name)) if dir == parent => PathBuf::from(name),
+        _ => input,
+    }
+}
+
+/// Reads the symbol file beside the ROM at `rom`, if there is one.
+fn symbols(rom: &Path) -> SourceMap {
+    let path = SourceMap::path(rom);
+    if !path.exists() {
+        return SourceMap::default();
+    }
+    SourceMap::read(&path).unwrap_or_else(|e| {
+        warn!("Ignoring symbol file '{}': {}", path.display(), e);
+        SourceMap::default()
+    })
+}
+
+/// Analyzes the ROM at `path` and prints the results. With `map`, the ROM
+/// is then profiled for `instructions` instructions, with keys pressed as in
+/// the replay file at `replay`, and a map of the memory it used is printed,
+/// or written as a PNG to the path in `map`. Frames are counted at `ips`, or
+/// the config file's speed, unless the replay sets it.
+///
+/// # Errors
+/// This function will error if the ROM or replay file cannot be read, the
+/// ROM faults while mapping, or the map cannot be written.
+pub fn analyze(
+    path: &Path,
+    map: Option<Option<&Path>>,
+    instructions: Option<u64>,
+    ips: Option<u64>,
+    replay: Option<&Path>,
+) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    println!("{}", crate::analyze::Analysis::new(&rom));
+    let Some(out) = map else {
+        return Ok(());
+    };
+
+    let replay = replay.map(replay::Replay::read).transpose()?;
+    let instructions = instructions
+        .or_else(|| Some(replay.as_ref()?.entries.back()?.cycle))
+        .unwrap_or(1_000_000);
+    let config = Config::load();
+    let timing = Timing::from_ips(ips.unwrap_or(config.ips));
+    let map = crate::profile::run(&rom, instructions, timing, config.quirks, replay)
+        .map_err(|fault| fault.to_string())?
+        .map();
+    println!();
+    match out {
+        Some(out) => {
+            let err = |e: io::Error| format!("Could not write '{}': {e}", out.display());
+            let file = fs::File::create(out).map_err(err)?;
+            map.png(io::BufWriter::new(file)).map_err(err)?;
+            println!("Wrote memory map to {}", out.display());
+        }
+        None if io::stdout().is_terminal() => map
+            .write_blocks(io::stdout().lock())
+            .map_err(|e| format!("Could not print memory map: {e}"))?,
+        None => println!("{map}"),
+    }
+    Ok(())
+}
+
+/// Prints what the ROM database knows about the ROM at `path`, and the
+/// platform it runs as.
+///
+/// # Errors
+/// This function will error if the file at `path` cannot be read.
+pub fn info(path: &Path) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    let known = Database::load().lookup(&rom).cloned();
+    println!("{}", describe(path, &rom, known.as_ref()));
+    Ok(())
+}
+
+/// Describes the ROM `rom`, read from `path`, given its entry in the ROM
+/// database, if it has one.
+fn describe(path: &Path, rom: &[u8], known: Option<&Entry>) -> String {
+    let mut lines = Vec::new();
+    match known {
+        Some(entry) => {
+            lines.push(format!("Title:     {}", entry.title));
+            if let Some(author) = &entry.author {
+                lines.push(format!("Author:    {author}"));
+            }
+        }
+        None => lines.push("Title:     unknown (not in the ROM database)".into()),
+    }
+    lines.push(format!("Hash:      {:016x}", debug::hash(rom)));
+    lines.push(format!("Platform:  {}", detect::detect(path, rom, known)));
+    if let Some(entry) = known {
+        if let Some(ips) = entry.ips {
+            lines.push(format!("Speed:     {ips} IPS"));
+        }
+        if let Some(quirks) = entry.quirks {
+            lines.push(format!("Quirks:    {quirks}"));
+        }
+        if let Some(keymap) = &entry.keymap {
+            lines.push(format!(
+                "Keymap:    {}",
+                Vec::from(keymap.clone()).join(" ")
+            ));
+        }
+        if !entry.keys.is_empty() {
+            lines.push(format!("Keys:      {}", entry.keys));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Prints the checksums of the ROM at `path`.
+///
+/// # Errors
+/// This function will error if the file at `path` cannot be read.
+pub fn hash(path: &Path) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    println!("{}", Hashes::of(&rom));
+    Ok(())
+}
+
+/// Checks the ROM at `path` against the known-good dumps in the ROM
+/// database, printing the outcome. Returns whether it is one.
+///
+/// # Errors
+/// This function will error if the file at `path` cannot be read.
+pub fn verify(path: &Path) -> Result<bool, String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    let hashes = Hashes::of(&rom);
+    let (good, message) = match Database::load().verify(&hashes) {
+        Verdict::Good(entry) => (true, format!("Good: a known dump of {entry}")),
+        Verdict::Unchecked(entry) => (
+            false,
+            format!("Unchecked: {entry} is known, but has no SHA-1 to check against"),
+        ),
+        Verdict::Bad(entry) => (
+            false,
+            format!("Bad: the hash of {entry} matches, but not its SHA-1"),
+        ),
+        Verdict::Unknown => (false, "Unknown: not in the ROM database".to_string()),
+    };
+    println!("{message}\n{hashes}");
+    Ok(good)
+}
+
+/// Compares the trace files at `first` and `second`, and prints where they
+/// first differ. Returns whether they agree.
+///
+/// # Errors
+/// This function will error if either file cannot be read.
+pub fn diff_trace(first: &Path, second: &Path) -> Result<bool, String> {
+    let open = |path: &Path| {
+        fs::File::open(path)
+            .map(io::BufReader::new)
+            .map_err(|_| format!("Could not read file: '{}'", path.display()))
+    };
+    let divergence = trace::diff(open(first)?, open(second)?).map_err(|e| e.to_string())?;
+    if let Some(divergence) = &divergence {
+        println!("{divergence}");
+    } else {
+        println!("The traces agree");
+    }
+    Ok(divergence.is_none())
+}
+
+/// Runs every ROM in `dir` for `cycles` instructions and prints how each
+/// run ended, writing the results as JSON to `out`. Frames are counted at
+/// `ips`, or the config file's speed. ROMs that can't be read or assembled
+/// are logged and left out.
+///
+/// # Errors
+/// This function will error if `dir` cannot be read or has no ROMs, or the
+/// results cannot be written.
+pub fn batch(dir: &Path, cycles: u64, ips: Option<u64>, out: Option<&Path>) -> Result<(), String> {
+    let library = Library::scan(dir)
+        .map_err(|e| format!("Could not read directory '{}': {e}", dir.display()))?;
+    if library.roms().is_empty() {
+        return Err(format!(
+            "No ROMs (.ch8 or Octo source files) in '{}'",
+            dir.display()
+        ));
+    }
+    let config = Config::load();
+    let timing = Timing::from_ips(ips.unwrap_or(config.ips));
+    let mut entries = Vec::new();
+    for path in library.roms() {
+        let rom = match read(&*path.to_string_lossy()) {
+            Ok(rom) => rom,
+            Err(e) => {
+                error!("{}", e);
+                continue;
+            }
+        };
+        let report = crate::batch::run(&rom, cycles, timing, config.quirks);
+        println!("{}: {report}", path.display());
+        entries.push(crate::batch::Entry {
+            path: path.display().to_string(),
+            report,
+        });
+    }
+
+    if let Some(out) = out {
+        let json = serde_json::to_string_pretty(&entries).unwrap_or_default();
+        fs::write(out, json + "\n")
+            .map_err(|e| format!("Could not write '{}': {e}", out.display()))?;
+        println!("Wrote results to {}", out.display());
+    }
+    Ok(())
+}
+
+/// Benchmarks the ROM at `path` for `instructions` instructions and prints
+/// the results. Frames are counted at `ips`, or the config file's speed.
+///
+/// # Errors
+/// This function will error if the file at `path` cannot be read, or the ROM
+/// faults.
+pub fn bench(path: &Path, instructions: u64, ips: Option<u64>) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    let config = Config::load();
+    let timing = Timing::from_ips(ips.unwrap_or(config.ips));
+    let report = crate::bench::run(&rom, instructions, timing, config.quirks)
+        .map_err(|fault| fault.to_string())?;
+    println!("{report}");
+    Ok(())
+}
+
+/// Profiles the ROM at `path` for `instructions` instructions, with keys
+/// pressed as in the replay file at `replay`, and prints the results.
+/// Frames are counted at `ips`, or the config file's speed, unless the
+/// replay sets it. A heatmap of the disassembly is written to `heatmap`.
+///
+/// # Errors
+/// This function will error if the ROM or replay file cannot be read, the
+/// ROM faults, or the heatmap cannot be written.
+pub fn profile(
+    path: &Path,
+    instructions: Option<u64>,
+    ips: Option<u64>,
+    replay: Option<&Path>,
+    heatmap: Option<&Path>,
+) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    let replay = replay.map(replay::Replay::read).transpose()?;
+    let instructions = instructions
+        .or_else(|| Some(replay.as_ref()?.entries.back()?.cycle))
+        .unwrap_or(1_000_000);
+    let config = Config::load();
+    let timing = Timing::from_ips(ips.unwrap_or(config.ips));
+    let mut report = crate::profile::run(&rom, instructions, timing, config.quirks, replay)
+        .map_err(|fault| fault.to_string())?;
+    report.symbols = symbols(path);
+    println!("{report}");
 
-    writeln!(file, "== {} ==", path.display())?;
-    for chunk in rom.chunks_exact(2) {
-        let inst = crate::Instruction::from(u16::from_be_bytes([chunk[0], chunk[1]]));
-        writeln!(file, "{inst:?}")?;
+    if let Some(heatmap) = heatmap {
+        let err = |e: io::Error| format!("Could not write '{}': {e}", heatmap.display());
+        let file = fs::File::create(heatmap).map_err(err)?;
+        report.heatmap(io::BufWriter::new(file)).map_err(err)?;
+        println!("Wrote heatmap to {}", heatmap.display());
     }
+    Ok(())
+}
+
+/// Runs the ROM at `path` in lockstep with the reference core for
+/// `instructions` instructions, with keys pressed as in the replay file at
+/// `replay`, and prints where they diverged, if they did. Frames are counted
+/// at `ips`, or the config file's speed, unless the replay sets it. Returns
+/// whether they agreed.
+///
+/// # Errors
+/// This function will error if a file cannot be read, or the reference core
+/// can't run the ROM.
+pub fn compare(
+    path: &Path,
+    instructions: Option<u64>,
+    ips: Option<u64>,
+    replay: Option<&Path>,
+) -> Result<bool, String> {
+    let rom = fs::read(path).map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+    let replay = replay.map(replay::Replay::read).transpose()?;
+    let instructions = instructions
+        .or_else(|| Some(replay.as_ref()?.entries.back()?.cycle))
+        .unwrap_or(1_000_000);
+    let config = Config::load();
+    let timing = Timing::from_ips(ips.unwrap_or(config.ips));
+    let report = crate::compare::run(&rom, instructions, timing, config.quirks, replay)?;
+    println!("{report}");
+    Ok(!matches!(
+        report.outcome,
+        crate::compare::Outcome::Diverged(_)
+    ))
+}
 
-    file.flush()?;
+/// The exit code of a check that found problems, like a failing test.
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit code of a check that couldn't run, e.g. because nothing matched
+/// what it was asked to check. `clap` uses the same code for invalid
+/// arguments.
+pub const EXIT_USAGE: i32 = 2;
+
+/// Runs the built-in conformance tests whose names contain `filter`,
+/// printing one line per test, with the reason on failing ones. The screens
+/// of failing tests are printed in full if `screens` is set.
+///
+/// Returns the process exit code: `0` if every test passed,
+/// [`EXIT_FAILURE`] if any failed, and [`EXIT_USAGE`] if no test matched.
+#[must_use]
+pub fn test(filter: Option<&str>, screens: bool) -> i32 {
+    let tests: Vec<&conformance::Test> = conformance::TESTS
+        .iter()
+        .filter(|test| filter.is_none_or(|filter| test.name.contains(filter)))
+        .collect();
+    if tests.is_empty() {
+        error!("No test matches '{}'", filter.unwrap_or_default());
+        return EXIT_USAGE;
+    }
+    let mut failed = 0;
+    for test in &tests {
+        let outcome = test.run();
+        if outcome.passed {
+            println!("PASS  {}", test.name);
+            continue;
+        }
+        failed += 1;
+        match outcome.fault {
+            Some(fault) => println!("FAIL  {}: {fault}", test.name),
+            None => println!("FAIL  {}: the screen doesn't match the snapshot", test.name),
+        }
+        if screens {
+            println!("{}", outcome.screen);
+        }
+    }
+    if failed > 0 {
+        error!("{failed} of {} test(s) failed", tests.len());
+        return EXIT_FAILURE;
+    }
+    0
+}
+
+/// Runs the tests of Timendus' suite found in `dir`, downloaded there first
+/// if `download` is set, printing a scorecard
+/// with a line per test and, under each, a line per line of its result
+/// screen, or a table with a row per check, such as each quirk, for tests
+/// that name them. With `bless`, the screens are saved as the
+/// known-good ones instead. The screens of failing and ungraded tests are
+/// printed in full if `screens` is set.
+///
+/// Returns the process exit code: `0` if every test found passed,
+/// [`EXIT_FAILURE`] if any failed or the suite couldn't be downloaded, and
+/// [`EXIT_USAGE`] if none could be graded.
+#[must_use]
+pub fn conformance(dir: &Path, download: bool, bless: bool, screens: bool) -> i32 {
+    if download {
+        if let Err(e) = conformance::download(dir) {
+            error!("{}", e);
+            return EXIT_FAILURE;
+        }
+    }
+    let (mut graded, mut failed) = (0, 0);
+    for test in conformance::SUITE {
+        let outcome = match test.run(dir) {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                println!("SKIP  {}: could not read {}: {e}", test.name, test.file);
+                continue;
+            }
+        };
+        if bless {
+            let path = test.expected(dir);
+            let saved = path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|()| fs::write(&path, &outcome.screen));
+            match saved {
+                Ok(()) => println!("SAVE  {}: {}", test.name, path.display()),
+                Err(e) => println!(
+                    "SKIP  {}: could not save {}: {e}",
+                    test.name,
+                    path.display()
+                ),
+            }
+            continue;
+        }
+        let Some(lines) = &outcome.lines else {
+            println!("SKIP  {}: no known-good screen (see --bless)", test.name);
+            if screens {
+                println!("{}", outcome.screen);
+            }
+            continue;
+        };
+        graded += 1;
+        if outcome.passed() {
+            println!("PASS  {}", test.name);
+        } else {
+            failed += 1;
+            match &outcome.fault {
+                Some(fault) => println!("FAIL  {}: {fault}", test.name),
+                None => println!("FAIL  {}", test.name),
+            }
+        }
+        let grade = |passed: bool| if passed { "pass" } else { "fail" };
+        if let Some(checks) = &outcome.scorecard {
+            // A table with a row per check, such as each quirk.
+            let width = checks.iter().map(|(check, _)| check.len()).max();
+            let width = width.unwrap_or_default().max("CHECK".len());
+            println!("      {:width$}  RESULT", "CHECK");
+            for &(check, passed) in checks {
+                println!("      {check:width$}  {}", grade(passed));
+            }
+        } else {
+            for (check, passed) in lines {
+                println!("      {}  {check}", grade(*passed));
+            }
+        }
+        if screens && !outcome.passed() {
+            println!("{}", outcome.screen);
+        }
+    }
+    if bless {
+        return 0;
+    }
+    if graded == 0 {
+        error!(
+            "No test of the suite in '{}' could be graded",
+            dir.display()
+        );
+        return EXIT_USAGE;
+    }
+    if failed > 0 {
+        error!("{failed} of {graded} test(s) failed");
+        return EXIT_FAILURE;
+    }
+    0
+}
+
+/// Prints the documentation for the instructions matching `query`.
+///
+/// # Errors
+/// This function will error if no instruction matches `query`.
+pub fn explain(query: &str) -> Result<(), String> {
+    let docs = Doc::search(query);
+    if docs.is_empty() {
+        return Err(format!("No instruction matches '{query}'"));
+    }
+    let docs: Vec<String> = docs.iter().map(ToString::to_string).collect();
+    println!("{}", docs.join("\n\n"));
+    Ok(())
+}
+
+/// Decompiles the ROM at `path` into Octo source, writing it to `output`
+/// or, if there is none, to standard output. Comments attached to addresses
+/// in the debugger and labels from the ROM's symbol file are used as in
+/// [`disassemble`].
+///
+/// # Errors
+/// This function will error if the ROM cannot be read, the source doesn't
+/// assemble back into it, or `output` cannot be written.
+pub fn decompile(path: &Path, output: Option<PathBuf>) -> Result<(), String> {
+    let rom = fs::read(path).map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+    let project = debug::Project::load(&rom);
+    let source = format!(
+        "# {}\n{}",
+        path.display(),
+        disasm::octo(&rom, &project.comments, &symbols(path))?
+    );
+
+    let Some(output) = output else {
+        print!("{source}");
+        return Ok(());
+    };
+    fs::write(&output, source)
+        .map_err(|e| format!("Could not write '{}': {e}", output.display()))?;
+    println!("Wrote Octo source to {}", output.display());
+    Ok(())
+}
+
+/// Applies the IPS patches at `patches`, then `edits`, to the ROM at
+/// `path`, writing the result to `output`.
+///
+/// # Errors
+/// This function will error if the ROM or a patch cannot be read, a patch
+/// isn't an IPS patch, or `output` cannot be written.
+pub fn patch(
+    path: &Path,
+    edits: &[Edit],
+    patches: &[PathBuf],
+    output: &Path,
+) -> Result<(), String> {
+    let read = |path: &Path| {
+        fs::read(path).map_err(|e| format!("Could not read '{}': {e}", path.display()))
+    };
+    let mut rom = read(path)?;
+    for patch in patches {
+        Patch::ips(&read(patch)?)
+            .map_err(|e| format!("{}: {e}", patch.display()))?
+            .apply(&mut rom);
+    }
+    let mut set = Patch::default();
+    for edit in edits {
+        set.edit(edit);
+    }
+    set.apply(&mut rom);
+    if rom.len() > crate::Interpreter::ROM_CAPACITY {
+        warn!(
+            "The patched ROM ({} bytes) is too big to fit in memory",
+            rom.len()
+        );
+    }
+
+    fs::write(output, &rom).map_err(|e| format!("Could not write '{}': {e}", output.display()))?;
+    println!(
+        "Wrote patched ROM ({} bytes) to {}",
+        rom.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+/// Disassembles the ROM at `input_path` in the given `format`, writing it
+/// to `output_file` or, if there is none, to standard output. Comments
+/// attached to addresses in the debugger are emitted alongside their
+/// instructions, and labels and source lines from the ROM's symbol file
+/// are used if it has one.
+///
+/// # Errors
+/// This function will error if `output_file` is not a file or cannot be
+/// written, or the file at `input_path` cannot be read.
+pub fn disassemble(
+    input_path: &PathBuf,
+    output_file: Option<PathBuf>,
+    format: Format,
+) -> Result<(), io::Error> {
+    let rom = fs::read(input_path)?;
+    let project = debug::Project::load(&rom);
+    let symbols = symbols(input_path);
+    let output = match format {
+        Format::Text => format!(
+            "; {}\n{}",
+            input_path.display(),
+            disasm::disassemble(&rom, &project.comments, &symbols)
+        ),
+        Format::Json => disasm::json(&rom, &project.comments, &symbols),
+        Format::Csv => disasm::csv(&rom, &project.comments, &symbols).map_err(io::Error::other)?,
+    };
+
+    let Some(path) = output_file else {
+        io::stdout().write_all(output.as_bytes())?;
+        return Ok(());
+    };
+    if path.extension().is_none() {
+        error!("{} is not a file", path.display());
+        std::process::exit(1);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, output)?;
 
     println!("Wrote disassembled ROM to {}", path.display());
 
     Ok(())
 }
 
-/// Reads the file at `path` as bytes, returning an error if it could not be read.
+/// Loads the config file, first running the setup if there is none and
+/// there's a terminal to run it in.
+fn config() -> Config {
+    if !Config::exists() && io::stdin().is_terminal() {
+        setup().unwrap_or_else(|err| {
+            error!("Could not complete setup: {}", err);
+            Config::load()
+        })
+    } else {
+        Config::load()
+    }
+}
+
+/// Parses a CHIP-8 key, from `0` to `F`.
+fn hex_key(key: &str) -> Result<u8, String> {
+    u8::from_str_radix(key, 16)
+        .ok()
+        .filter(|&key| key < 0x10)
+        .ok_or_else(|| format!("Invalid key '{key}', expected 0 to F"))
+}
+
+/// Parses a volume from 0.0 to 1.0.
+fn volume(volume: &str) -> Result<f32, String> {
+    volume
+        .parse()
+        .ok()
+        .filter(|volume| (0.0..=1.0).contains(volume))
+        .ok_or_else(|| format!("Invalid volume '{volume}', expected 0.0 to 1.0"))
+}
+
+/// Parses the name of a built-in [`Palette`].
+fn palette(name: &str) -> Result<Palette, String> {
+    Palette::preset(name).ok_or_else(|| format!("Unknown palette '{name}'"))
+}
+
+/// Applies the flags in `args` over `config`, returning the timing and quirks
+/// to run with. A flag always takes precedence over the speed and quirks the
+/// ROM database recommends for the ROM (`known`), which take precedence over
+/// those of the `detection`'s platform, then the config file, then the
+/// defaults; the speed also sets the display wait quirk.
+fn settings(
+    args: &RunArgs,
+    config: &mut Config,
+    detection: Detection,
+    known: Option<&Entry>,
+) -> (Timing, Quirks) {
+    config.scale = args.scale.unwrap_or(config.scale);
+    config.palette = args.palette.unwrap_or(config.palette);
+    config.scaling = args.scaling.unwrap_or(config.scaling);
+    config.pause_unfocused |= args.pause_unfocused;
+    config.save_flags |= args.save_flags;
+    config.cycle_accurate |= args.cycle_accurate;
+    config.beep.waveform = args.waveform.unwrap_or(config.beep.waveform);
+    config.beep.frequency = args.frequency.unwrap_or(config.beep.frequency);
+    config.beep.volume = args.volume.unwrap_or(config.beep.volume);
+    config.paddle = args.paddle.or(config.paddle);
+    if let Some(key) = args.turbo {
+        let modifier = config.turbo.take().map(|turbo| turbo.modifier);
+        config.turbo = Some(Turbo {
+            modifier: modifier.unwrap_or_default(),
+            ..Turbo::new(key)
+        });
+    }
+    if let (Some(turbo), Some(rate)) = (config.turbo.as_mut(), args.turbo_rate) {
+        turbo.rate = rate;
+    }
+    // The platform's speed only applies if no speed was asked for at all.
+    let ips = args.ips.or(known.and_then(|entry| entry.ips));
+    let speed = args
+        .speed
+        .or_else(|| detection.speed().filter(|_| ips.is_none()));
+    let timing = match speed {
+        Some(speed) => speed.timing(),
+        None => Timing::from_ips(ips.unwrap_or(config.ips)),
+    };
+    let recommended = known.and_then(|entry| entry.quirks);
+    let defaults = recommended.unwrap_or(config.quirks);
+    let quirks = Quirks {
+        index_overflow: args.index_overflow.unwrap_or(defaults.index_overflow),
+        pc_overflow: args.pc_overflow.unwrap_or(defaults.pc_overflow),
+        jump_offset: args
+            .jump_offset
+            .or(recommended.map(|quirks| quirks.jump_offset))
+            .or(detection.jump_offset())
+            .unwrap_or(defaults.jump_offset),
+        shift: args.shift.unwrap_or(defaults.shift),
+        vf_reset: args.vf_reset || defaults.vf_reset,
+        index_carry: args.index_carry || defaults.index_carry,
+        display_wait: config.cycle_accurate
+            || speed.map_or(defaults.display_wait, Speed::display_wait),
+        half_scroll: args.half_scroll || defaults.half_scroll,
+        stack_depth: args.stack_depth.unwrap_or(defaults.stack_depth),
+    };
+    (timing, quirks)
+}
+
+/// Works out the platform the ROM `rom` at `path` (known to the ROM database
+/// as `known`) was written for, unless `--platform` says, exiting if it
+/// can't be run with `args`.
+fn platform(args: &RunArgs, path: &str, rom: &[u8], known: Option<&Entry>) -> Detection {
+    let detection = match args.platform {
+        Some(platform) => Detection {
+            platform,
+            source: detect::Source::Flag,
+        },
+        None => detect::detect(Path::new(path), rom, known),
+    };
+    info!("Platform: {}", detection);
+    if matches!(detection.platform, Platform::SuperChip | Platform::XoChip) {
+        warn!(
+            "{} instructions aren't supported yet; running as CHIP-8 at {} speed",
+            detection.platform, detection.platform
+        );
+    }
+    if args.banking && detection.platform == Platform::Chip8X {
+        error!("--banking can't be used with CHIP-8X ROMs");
+        std::process::exit(1);
+    }
+    detection
+}
+
+/// The path of the ROM at `path`, which is looked for in `rom_dir` if it is
+/// relative and doesn't exist.
+fn rom_path(path: &str, rom_dir: Option<&Path>) -> String {
+    match rom_dir {
+        Some(dir) if Path::new(path).is_relative() && !Path::new(path).exists() => {
+            dir.join(path).to_string_lossy().into_owned()
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// Reads the ROM at `path`, assembling it first if it is an Octo cartridge
+/// or source file, returning an error if it could not be read or assembled.
 fn read<P: AsRef<Path> + fmt::Display>(path: P) -> Result<Vec<u8>, String> {
     let err = |_: io::Error| format!("Could not read file: '{path}'");
     let path = fs::canonicalize(&path).map_err(err)?;
-    fs::read(path).map_err(err)
+    let file = fs::read(&path).map_err(err)?;
+    crate::octo::load(&path, file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_args(args: &[&str]) -> RunArgs {
+        let cli = Cli::parse_from(["etherea", "run", "rom.ch8"].iter().chain(args));
+        match cli.command {
+            Commands::Run(args) => *args,
+            _ => unreachable!("parsed a run command"),
+        }
+    }
+
+    #[test]
+    fn log_filters() {
+        assert_eq!(
+            log_filter("cpu=trace,Input=debug,etherea::watch=info,display"),
+            "etherea::cpu=trace,etherea::input=debug,etherea::watch=info,etherea::display"
+        );
+    }
+
+    #[test]
+    fn flags_override_config() {
+        let mut config = Config {
+            ips: 500,
+            quirks: Quirks {
+                jump_offset: JumpOffset::Vx,
+                stack_depth: 12,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        };
+
+        let chip8 = Detection {
+            platform: Platform::Chip8,
+            source: detect::Source::Default,
+        };
+        let (timing, quirks) = settings(&run_args(&[]), &mut config.clone(), chip8, None);
+        assert_eq!(timing, Timing::from_ips(500));
+        assert_eq!(quirks, config.quirks);
+
+        let args = run_args(&[
+            "--ips",
+            "900",
+            "--stack-depth",
+            "32",
+            "--shift",
+            "vy",
+            "--vf-reset",
+            "--half-scroll",
+            "--palette",
+            "lcd",
+            "--scaling",
+            "stretch",
+            "--pause-unfocused",
+            "--save-flags",
+            "--cycle-accurate",
+            "--waveform",
+            "sine",
+            "--volume",
+            "0.5",
+            "--paddle",
+            "C,D",
+            "--turbo",
+            "a",
+            "--turbo-rate",
+            "15",
+        ]);
+        let (timing, quirks) = settings(&args, &mut config, chip8, None);
+        assert_eq!(timing, Timing::from_ips(900));
+        assert_eq!(quirks.stack_depth, 32);
+        assert_eq!(quirks.jump_offset, JumpOffset::Vx);
+        assert_eq!(quirks.shift, Shift::Vy);
+        assert!(quirks.vf_reset && !quirks.index_carry && quirks.half_scroll);
+        assert_eq!(config.palette, Palette::preset("lcd").unwrap());
+        assert_eq!(config.scaling, Scaling::Stretch);
+        assert!(config.pause_unfocused);
+        assert!(config.save_flags);
+        assert!(config.cycle_accurate && quirks.display_wait);
+        assert_eq!(config.beep.waveform, Waveform::Sine);
+        assert_eq!(config.beep.frequency, crate::audio::Beep::FREQUENCY);
+        assert!((config.beep.volume - 0.5).abs() < f32::EPSILON);
+        assert!(Cli::try_parse_from(["etherea", "run", "rom.ch8", "--volume", "2"]).is_err());
+        assert_eq!(config.paddle, "C,D".parse().ok());
+        assert_eq!(
+            config.turbo,
+            Some(Turbo {
+                rate: 15,
+                ..Turbo::new(0xA)
+            })
+        );
+
+        let (timing, quirks) = settings(&run_args(&["--speed", "vip"]), &mut config, chip8, None);
+        assert_eq!(timing, Speed::Vip.timing());
+        assert!(quirks.display_wait);
+
+        let args = run_args(&["--platform", "schip"]);
+        let schip = Detection {
+            platform: args.platform.unwrap(),
+            source: detect::Source::Flag,
+        };
+        let (timing, quirks) = settings(&args, &mut config.clone(), schip, None);
+        assert_eq!(timing, Speed::Schip.timing());
+        assert_eq!(quirks.jump_offset, JumpOffset::Vx);
+        let args = run_args(&["--ips", "900", "--jump-offset", "v0"]);
+        let (timing, quirks) = settings(&args, &mut config, schip, None);
+        assert_eq!(timing, Timing::from_ips(900));
+        assert_eq!(quirks.jump_offset, JumpOffset::V0);
+    }
+
+    #[test]
+    fn describes_roms() {
+        let breakout = include_bytes!("../roms/breakout.ch8");
+        let known = Database::built_in().lookup(breakout).cloned();
+        let path = Path::new("breakout.ch8");
+        assert_eq!(
+            describe(path, breakout, known.as_ref()),
+            "Title:     Breakout\n\
+             Hash:      2671acb470b32f3c\n\
+             Platform:  CHIP-8 (from ROM database)\n\
+             Keys:      Left = 0x4, Right = 0x6"
+        );
+        assert!(describe(path, &[0x12, 0x00], None).starts_with("Title:     unknown"));
+
+        let known = Entry {
+            ips: Some(1000),
+            quirks: Some(Quirks {
+                jump_offset: JumpOffset::Vx,
+                ..Quirks::default()
+            }),
+            ..Entry::default()
+        };
+        let chip8 = Detection {
+            platform: Platform::Chip8,
+            source: detect::Source::Database,
+        };
+        let (timing, quirks) =
+            settings(&run_args(&[]), &mut Config::default(), chip8, Some(&known));
+        assert_eq!(timing, Timing::from_ips(1000));
+        assert_eq!(quirks.jump_offset, JumpOffset::Vx);
+        let args = run_args(&["--ips", "500"]);
+        let (timing, _) = settings(&args, &mut Config::default(), chip8, Some(&known));
+        assert_eq!(timing, Timing::from_ips(500));
+    }
+
+    #[test]
+    fn twin_flag() {
+        assert_eq!(run_args(&[]).twin, None);
+        assert_eq!(run_args(&["--twin"]).twin, Some(None));
+        assert_eq!(
+            run_args(&["--twin=other.ch8"]).twin,
+            Some(Some("other.ch8".into()))
+        );
+        // Without `=`, the next argument is the ROM rather than the twin.
+        let cli = Cli::parse_from(["etherea", "run", "--twin", "rom.ch8"]);
+        let Commands::Run(args) = cli.command else {
+            unreachable!("parsed a run command")
+        };
+        assert_eq!((args.path.as_str(), args.twin), ("rom.ch8", Some(None)));
+    }
+
+    #[test]
+    fn rom_paths() {
+        assert_eq!(rom_path("pong.ch8", None), "pong.ch8");
+        assert_eq!(
+            rom_path("pong.ch8", Some(Path::new("/roms"))),
+            "/roms/pong.ch8"
+        );
+        assert_eq!(rom_path("/pong.ch8", Some(Path::new("/roms"))), "/pong.ch8");
+        assert_eq!(
+            rom_path("Cargo.toml", Some(Path::new("/roms"))),
+            "Cargo.toml"
+        );
+    }
 }
@@ -0,0 +1,309 @@
+use crate::{
+    golden::{self, Core},
+    opcode::Opcode,
+    quirks::Quirks,
+    replay::Replay,
+    timing::Timing,
+    Fault, Interpreter,
+};
+use std::{collections::VecDeque, fmt};
+
+/// Where the interpreter and the reference core disagreed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The number of the instruction after which they disagreed, from 1.
+    pub instruction: u64,
+    /// The address of the instruction.
+    pub pc: usize,
+    /// The instruction.
+    pub opcode: u16,
+    /// What differed, a line each, like `VF: 00 in etherea, 01 in the
+    /// reference core`.
+    pub differences: Vec<String>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Diverged at instruction {}, {:#05X}: {:04X}",
+            self.instruction, self.pc, self.opcode
+        )?;
+        if let Ok(opcode) = Opcode::try_from(self.opcode) {
+            write!(f, " ({opcode})")?;
+        }
+        for difference in &self.differences {
+            write!(f, "\n  {difference}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How a run with [`run`] ended.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every instruction left both in the same state.
+    Agreed,
+    /// Both stopped with the same fault.
+    Faulted(Fault),
+    /// An instruction left them in different states.
+    Diverged(Divergence),
+}
+
+/// The results of comparing the interpreter with the reference core.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Report {
+    /// The number of instructions executed.
+    pub instructions: u64,
+    /// The number of frames those instructions took, at the timing used.
+    pub frames: u64,
+    /// How the run ended.
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (instructions, frames) = (self.instructions, self.frames);
+        match &self.outcome {
+            Outcome::Agreed => write!(
+                f,
+                "Agreed with the reference core for {instructions} instructions ({frames} frames)"
+            ),
+            Outcome::Faulted(fault) => write!(
+                f,
+                "Agreed with the reference core for {instructions} instructions, until both \
+                 stopped: {fault}"
+            ),
+            Outcome::Diverged(divergence) => write!(f, "{divergence}"),
+        }
+    }
+}
+
+/// Runs `rom` headlessly on the interpreter and the [reference
+/// core](golden::Core) in lockstep, until at least `instructions`
+/// instructions have executed, and compares their registers, stack, timers, and memory after every one, and
+/// the screen after every `00E0` and `DXYN`. Keys are pressed as in
+/// `replay`, which also sets the seed and timing; otherwise frames are
+/// counted at `timing`, and no keys are pressed.
+///
+/// `CXNN` gives the reference core the interpreter's random number, so only
+/// the masking is checked.
+///
+/// # Errors
+/// This function will error if `rom` isn't one the reference core can run:
+/// it only knows CHIP-8, and has no room for ROMs bigger than memory.
+pub fn run(
+    rom: &[u8],
+    instructions: u64,
+    timing: Timing,
+    quirks: Quirks,
+    replay: Option<Replay>,
+) -> Result<Report, String> {
+    let mut intr = Interpreter::new();
+    intr.with_quirks(quirks);
+    let mut entries = VecDeque::new();
+    let timing = if let Some(replay) = replay {
+        intr.with_seed(replay.seed);
+        entries = replay.entries;
+        replay.timing
+    } else {
+        intr.with_seed(0);
+        timing
+    };
+    intr.with_timing(timing);
+    intr.load_rom(rom);
+    if let Some(fault) = intr.fault.take() {
+        return Err(fault.to_string());
+    }
+    if intr.hires {
+        return Err("The reference core can't run HIRES CHIP-8 ROMs".into());
+    }
+    let mut core = Core::new(rom, quirks);
+
+    let mut frames = 0;
+    let outcome = 'run: loop {
+        if intr.cycles >= instructions {
+            break Outcome::Agreed;
+        }
+        for _ in 0..timing.cycles(frames) {
+            while let Some(entry) = entries.front().filter(|e| e.cycle <= intr.cycles) {
+                let event = entry.event;
+                entries.pop_front();
+                intr.handle_key(event);
+                core.key(event.key, event.pressed);
+            }
+            if let Some(outcome) = step(&mut intr, &mut core) {
+                break 'run outcome;
+            }
+            if std::mem::take(&mut intr.drew) && quirks.display_wait {
+                break;
+            }
+        }
+        intr.timers.update();
+        core.tick();
+        frames += 1;
+    };
+    Ok(Report {
+        instructions: intr.cycles,
+        frames,
+        outcome,
+    })
+}
+
+/// Executes the next instruction on both `intr` and `core`, and compares
+/// them. Returns how the run ended, if it did.
+fn step(intr: &mut Interpreter, core: &mut Core) -> Option<Outcome> {
+    let (pc, opcode) = (core.pc, core.opcode().unwrap_or_default());
+    intr.step();
+    let random = match opcode >> 12 {
+        0xC => intr.registers[usize::from(opcode >> 8 & 0xF)],
+        _ => 0,
+    };
+    let faults = (intr.fault.take(), core.step(random).err());
+    let differences = if faults.0 == faults.1 {
+        differences(intr, core, opcode)
+    } else {
+        let describe = |fault: &Option<Fault>| {
+            fault
+                .as_ref()
+                .map_or_else(|| "none".into(), ToString::to_string)
+        };
+        vec![format!(
+            "fault: {} in etherea, {} in the reference core",
+            describe(&faults.0),
+            describe(&faults.1)
+        )]
+    };
+    if !differences.is_empty() {
+        return Some(Outcome::Diverged(Divergence {
+            instruction: intr.cycles,
+            pc,
+            opcode,
+            differences,
+        }));
+    }
+    faults.0.map(Outcome::Faulted)
+}
+
+/// What differs between the state of `intr` and `core`, after both executed
+/// `opcode`.
+fn differences(intr: &Interpreter, core: &Core, opcode: u16) -> Vec<String> {
+    let mut differences = Vec::new();
+    let mut compare = |name: &str, ours: String, theirs: String| {
+        if ours != theirs {
+            differences.push(format!(
+                "{name}: {ours} in etherea, {theirs} in the reference core"
+            ));
+        }
+    };
+    compare(
+        "PC",
+        format!("{:#05X}", intr.pc),
+        format!("{:#05X}", core.pc),
+    );
+    compare("I", format!("{:#05X}", intr.i), format!("{:#05X}", core.i));
+    for (x, (ours, theirs)) in intr.registers.iter().zip(core.v).enumerate() {
+        compare(
+            &format!("V{x:X}"),
+            format!("{ours:02X}"),
+            format!("{theirs:02X}"),
+        );
+    }
+    let stack = |stack: &[u16]| format!("{stack:03X?}");
+    compare("stack", stack(&intr.stack), stack(&core.stack));
+    let (dt, st) = (intr.timers.delay, intr.timers.sound);
+    compare("DT", dt.to_string(), core.delay.to_string());
+    compare("ST", st.to_string(), core.sound.to_string());
+
+    if intr.memory[..] != core.memory[..] {
+        let changed: Vec<usize> = (0..core.memory.len())
+            .filter(|&address| intr.memory[address] != core.memory[address])
+            .collect();
+        let address = changed[0];
+        compare(
+            &format!("memory at {address:#05X} ({} bytes differ)", changed.len()),
+            format!("{:02X}", intr.memory[address]),
+            format!("{:02X}", core.memory[address]),
+        );
+    }
+
+    if opcode == 0x00E0 || opcode >> 12 == 0xD {
+        let pixels: Vec<(usize, usize)> = (0..golden::HEIGHT)
+            .flat_map(|y| (0..golden::WIDTH).map(move |x| (x, y)))
+            .filter(|&(x, y)| pixel(intr, x, y) != core.screen[y][x])
+            .collect();
+        if let Some(&(x, y)) = pixels.first() {
+            let state = |on: bool| if on { "on" } else { "off" };
+            compare(
+                &format!("pixel ({x}, {y}) ({} pixels differ)", pixels.len()),
+                state(pixel(intr, x, y)).into(),
+                state(core.screen[y][x]).into(),
+            );
+        }
+    }
+    differences
+}
+
+/// Whether the pixel at (`x`, `y`) on the interpreter's screen is on.
+fn pixel(intr: &Interpreter, x: usize, y: usize) -> bool {
+    match (u16::try_from(x), u16::try_from(y)) {
+        (Ok(x), Ok(y)) => intr.screen.get_at(x, y),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    #[test]
+    fn bundled_roms_agree() {
+        for file in std::fs::read_dir("roms").unwrap() {
+            let path = file.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "ch8") {
+                let rom = std::fs::read(&path).unwrap();
+                let report = run(&rom, 20_000, Timing::from_ips(700), Quirks::default(), None);
+                let report = report.unwrap();
+                assert_eq!(
+                    report.outcome,
+                    Outcome::Agreed,
+                    "{}: {report}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn divergence() {
+        let rom = asm::assemble("LD V0, 5\nADD V0, 1\n").unwrap();
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        let mut core = Core::new(&rom, Quirks::default());
+        assert_eq!(step(&mut intr, &mut core), None);
+        // A core that has gone wrong is caught after its next instruction.
+        core.v[0] = 6;
+        let Some(Outcome::Diverged(divergence)) = step(&mut intr, &mut core) else {
+            panic!("the cores agreed");
+        };
+        assert_eq!((divergence.instruction, divergence.pc), (2, 0x202));
+        assert_eq!(
+            divergence.to_string(),
+            "Diverged at instruction 2, 0x202: 7001 (ADD V0, 0x01)\n  \
+             V0: 06 in etherea, 07 in the reference core"
+        );
+
+        let report = run(
+            &[0x00, 0xEE],
+            10,
+            Timing::from_ips(700),
+            Quirks::default(),
+            None,
+        );
+        assert_eq!(
+            report.unwrap().outcome,
+            Outcome::Faulted(Fault::StackUnderflow { pc: 0x200 })
+        );
+    }
+}
@@ -0,0 +1,446 @@
+#[cfg(feature = "gui")]
+use crate::{
+    audio::Beep,
+    debug,
+    input::{Bindings, Keymap, Paddle, Turbo},
+    quirks::Quirks,
+    save::Span,
+    screen::Scaling,
+    vip,
+};
+use alloc::{
+    format,
+    string::{String, ToString},
+};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "gui")]
+use log::error;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "gui")]
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// User settings, saved to `config.toml` in the user's config directory.
+/// Flags given to `etherea run` take precedence over them.
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+#[allow(clippy::struct_excessive_bools)] // they're switches in the config file
+pub struct Config {
+    /// How many window pixels wide and tall each CHIP-8 pixel is.
+    pub scale: u32,
+    /// The number of instructions executed per second.
+    pub ips: u64,
+    /// Where to look for a ROM whose relative path doesn't exist.
+    pub rom_dir: Option<PathBuf>,
+    /// Whether to check for a newer release when a ROM is run.
+    pub check_for_updates: bool,
+    /// Whether to keep local statistics of which ROMs are played, for how
+    /// long, and with what settings.
+    pub usage_stats: bool,
+    /// The keyboard keys that stand for the CHIP-8 keypad.
+    pub keymap: Keymap,
+    /// The colors pixels are drawn in.
+    pub palette: Palette,
+    /// How the screen is scaled to fill the window.
+    pub scaling: Scaling,
+    /// Whether to pause, and silence the beep, while the window doesn't
+    /// have focus.
+    pub pause_unfocused: bool,
+    /// Whether to save the RPL user flags of each ROM, where SUPER-CHIP
+    /// games keep high scores, for the next time it's played.
+    pub save_flags: bool,
+    /// Whether to time instructions by what they cost on the COSMAC VIP,
+    /// instead of running a set number of them each frame.
+    pub cycle_accurate: bool,
+    /// Changes to what instructions cost in cycle-accurate mode, in machine
+    /// cycles by pattern.
+    pub cycle_costs: vip::Costs,
+    /// Behavior that differs between interpreters.
+    pub quirks: Quirks,
+    /// The sound played while the sound timer runs.
+    pub beep: Beep,
+    /// Keys held by moving the mouse, for paddle games, if any.
+    pub paddle: Option<Paddle>,
+    /// Auto-fire for a key, if any.
+    pub turbo: Option<Turbo>,
+    /// Keys for particular ROMs, used instead of the keymap when a ROM they
+    /// match is loaded.
+    pub profiles: Vec<Profile>,
+}
+
+#[cfg(feature = "gui")]
+impl Config {
+    /// The default window scale.
+    pub const SCALE: u32 = 10;
+    /// The default number of instructions executed per second.
+    pub const IPS: u64 = 700;
+
+    /// The path of the config file, inside the user's config directory.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("etherea").join("config.toml"))
+    }
+
+    /// Whether a config file has been written yet.
+    #[must_use]
+    pub fn exists() -> bool {
+        Self::path().is_some_and(|path| path.exists())
+    }
+
+    /// Loads the config file, returning the default config if there is none
+    /// or it cannot be read.
+    #[must_use]
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                error!("Could not parse config file '{}': {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Saves the config file, creating the config directory if needed.
+    ///
+    /// # Errors
+    /// This function will error if there is no config directory or the file
+    /// cannot be written.
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self).map_err(io::Error::other)?)?;
+        Ok(path)
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            scale: Self::SCALE,
+            ips: Self::IPS,
+            rom_dir: None,
+            check_for_updates: false,
+            usage_stats: true,
+            keymap: Keymap::default(),
+            palette: Palette::default(),
+            scaling: Scaling::default(),
+            pause_unfocused: false,
+            save_flags: false,
+            cycle_accurate: false,
+            cycle_costs: vip::Costs::default(),
+            quirks: Quirks::default(),
+            beep: Beep::default(),
+            paddle: None,
+            turbo: None,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+/// The keys to play a particular ROM with, and the memory to save for it,
+/// matched by its file name or its hash, e.g. so Tetris is played with the
+/// arrow keys and keeps its high scores:
+///
+/// ```toml
+/// [[profiles]]
+/// file = "tetris.ch8"
+/// keys = { Left = 0x5, Right = 0x6, Up = 0x4, Down = 0x7 }
+/// save = ["0x3A0-0x3AF"]
+/// ```
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// The file name of the ROM, like `tetris.ch8`, in any case.
+    pub file: Option<String>,
+    /// The hash of the ROM, as 16 hex digits, for a ROM by any name.
+    pub hash: Option<String>,
+    /// The keymap to use instead of the one above, if any.
+    pub keymap: Option<Keymap>,
+    /// Keys for CHIP-8 keys on top of the keymap.
+    pub keys: Bindings,
+    /// Ranges of memory, like a score table, saved when the ROM stops and
+    /// restored when it's next loaded.
+    pub save: Vec<Span>,
+}
+
+#[cfg(feature = "gui")]
+impl Profile {
+    /// Whether the profile is for the ROM `rom`, read from `path` if it was
+    /// read from a file.
+    #[must_use]
+    pub fn matches(&self, path: Option<&Path>, rom: &[u8]) -> bool {
+        let name = path
+            .and_then(Path::file_name)
+            .and_then(|name| name.to_str());
+        let file = self
+            .file
+            .as_ref()
+            .is_some_and(|file| name.is_some_and(|name| file.eq_ignore_ascii_case(name)));
+        let hash = self
+            .hash
+            .as_ref()
+            .is_some_and(|hash| hash.eq_ignore_ascii_case(&format!("{:016x}", debug::hash(rom))));
+        file || hash
+    }
+}
+
+/// The colors of lit and unlit pixels, and of pixels lit in XO-CHIP's
+/// second plane.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Palette {
+    /// The color of lit pixels.
+    pub foreground: Color,
+    /// The color of unlit pixels.
+    pub background: Color,
+    /// The color of pixels lit in only the second plane. Halfway between
+    /// the other two, if not set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub second: Option<Color>,
+    /// The color of pixels lit in both planes. The foreground, if not set.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub both: Option<Color>,
+}
+
+impl Palette {
+    /// Built-in palettes, by name. The first is the default.
+    pub const PRESETS: &'static [(&'static str, Palette)] = &[
+        ("classic", Palette::new(0xFF_FF_FF, 0x00_00_00)),
+        ("amber", Palette::new(0xFF_B0_00, 0x1A_10_00)),
+        ("green", Palette::new(0x33_FF_66, 0x0A_1A_0A)),
+        ("lcd", Palette::new(0x0F_38_0F, 0x9B_BC_0F)),
+    ];
+
+    /// The built-in palette named `name`.
+    #[must_use]
+    pub fn preset(name: &str) -> Option<Self> {
+        Self::PRESETS
+            .iter()
+            .find(|(preset, _)| preset.eq_ignore_ascii_case(name))
+            .map(|&(_, palette)| palette)
+    }
+
+    const fn new(foreground: u32, background: u32) -> Self {
+        Self {
+            foreground: Color::from_rgb(foreground),
+            background: Color::from_rgb(background),
+            second: None,
+            both: None,
+        }
+    }
+
+    /// The RGBA value of a pixel that is lit (`on`) or not.
+    #[must_use]
+    pub fn rgba(&self, on: bool) -> [u8; 4] {
+        if on {
+            self.foreground.rgba()
+        } else {
+            self.background.rgba()
+        }
+    }
+
+    /// The color of a pixel lit in `planes`, a bit a plane with the first
+    /// in the lowest bit, as [`View::pixel`](crate::screen::View::pixel)
+    /// gives.
+    #[must_use]
+    pub fn color(&self, planes: u8) -> Color {
+        match planes & 0b11 {
+            0 => self.background,
+            1 => self.foreground,
+            2 => self.second.unwrap_or_else(|| {
+                let (Color(fg), Color(bg)) = (self.foreground, self.background);
+                Color([0, 1, 2].map(|c| fg[c] / 2 + bg[c] / 2 + (fg[c] & bg[c] & 1)))
+            }),
+            _ => self.both.unwrap_or(self.foreground),
+        }
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self::PRESETS[0].1
+    }
+}
+
+/// An RGB color, written as `#RRGGBB`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Color(pub [u8; 3]);
+
+impl Color {
+    const fn from_rgb(rgb: u32) -> Self {
+        let [_, r, g, b] = rgb.to_be_bytes();
+        Self([r, g, b])
+    }
+
+    /// The color as an opaque RGBA pixel.
+    #[must_use]
+    pub const fn rgba(self) -> [u8; 4] {
+        let Self([r, g, b]) = self;
+        [r, g, b, 0xFF]
+    }
+}
+
+impl FromStr for Color {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .map(Self::from_rgb)
+            .ok_or_else(|| format!("Invalid color '{s}', expected #RRGGBB"))
+    }
+}
+
+impl TryFrom<String> for Color {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Color> for String {
+    fn from(color: Color) -> Self {
+        color.to_string()
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [r, g, b] = self.0;
+        write!(f, "#{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors() {
+        assert_eq!("#FFB000".parse(), Ok(Color([0xFF, 0xB0, 0x00])));
+        assert_eq!(Color([0x0F, 0x38, 0x0F]).to_string(), "#0F380F");
+        assert!("FFB000".parse::<Color>().is_err());
+        assert!("#FFB00".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn presets() {
+        assert_eq!(Palette::preset("Amber"), Some(Palette::PRESETS[1].1));
+        assert_eq!(Palette::preset("sepia"), None);
+    }
+
+    #[test]
+    fn plane_colors() {
+        let mut palette = Palette::preset("amber").unwrap();
+        assert_eq!(palette.color(0), palette.background);
+        assert_eq!(palette.color(1), palette.foreground);
+        assert_eq!(palette.color(2), Color([0x8C, 0x60, 0x00]));
+        assert_eq!(palette.color(3), palette.foreground);
+        palette.second = Some(Color([1, 2, 3]));
+        palette.both = Some(Color([4, 5, 6]));
+        assert_eq!(palette.color(2), Color([1, 2, 3]));
+        assert_eq!(palette.color(3), Color([4, 5, 6]));
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn round_trips() {
+        let config = Config {
+            scale: 4,
+            ips: 1000,
+            rom_dir: Some(PathBuf::from("/roms")),
+            check_for_updates: true,
+            usage_stats: false,
+            keymap: Keymap::try_from(vec![
+                "1234".into(),
+                "AZER".into(),
+                "QSDF".into(),
+                "WXCV".into(),
+            ])
+            .unwrap(),
+            palette: Palette {
+                second: Some(Color([0x30, 0x62, 0x30])),
+                ..Palette::PRESETS[3].1
+            },
+            scaling: Scaling::Stretch,
+            pause_unfocused: true,
+            save_flags: true,
+            cycle_accurate: true,
+            cycle_costs: vip::Costs::new([("DXYN".into(), 30)].into()).unwrap(),
+            quirks: Quirks {
+                jump_offset: crate::quirks::JumpOffset::Vx,
+                ..Quirks::default()
+            },
+            beep: Beep {
+                waveform: crate::audio::Waveform::Noise,
+                frequency: 220,
+                volume: 0.5,
+            },
+            paddle: Some("1,4".parse().unwrap()),
+            turbo: Some(Turbo::new(0x5)),
+            profiles: vec![Profile {
+                file: Some("tetris.ch8".into()),
+                keys: toml::from_str("Up = 0x5").unwrap(),
+                save: vec!["0x3A0-0x3AF".parse().unwrap()],
+                ..Profile::default()
+            }],
+        };
+        let s = toml::to_string(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&s).unwrap(), config);
+        assert_eq!(toml::from_str::<Config>("").unwrap(), Config::default());
+
+        let config: Config = toml::from_str("ips = 500\n[quirks]\nstack_depth = 12").unwrap();
+        assert_eq!(config.ips, 500);
+        assert_eq!(config.quirks.stack_depth, 12);
+        assert_eq!(config.quirks.jump_offset, crate::quirks::JumpOffset::V0);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn profiles() {
+        let rom = [0x12, 0x00];
+        let config: Config = toml::from_str(&format!(
+            "[[profiles]]\nfile = \"Tetris.ch8\"\nkeys = {{ Left = 0x5, Right = 0x6 }}\n\
+             [[profiles]]\nhash = \"{:016X}\"\nkeymap = [\"1234\", \"AZER\", \"QSDF\", \"WXCV\"]",
+            debug::hash(&rom)
+        ))
+        .unwrap();
+        let [tetris, hashed] = &config.profiles[..] else {
+            panic!("expected two profiles");
+        };
+        assert!(tetris.matches(Some(Path::new("roms/tetris.ch8")), &[]));
+        assert!(!tetris.matches(None, &rom));
+        assert!(hashed.matches(None, &rom));
+        assert!(!hashed.matches(Some(Path::new("pong.ch8")), &[]));
+        let codes = tetris.keys.codes();
+        assert_eq!(codes.get(&winit::event::VirtualKeyCode::Right), Some(&0x6));
+
+        assert!(toml::from_str::<Config>("[[profiles]]\nkeys = { Up = 0x10 }").is_err());
+        assert!(toml::from_str::<Config>("[[profiles]]\nkeys = { F1 = 0x1 }").is_err());
+    }
+}
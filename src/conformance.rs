@@ -0,0 +1,356 @@
+use crate::{quirks::Quirks, Fault, Interpreter};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A test ROM, run headlessly for a fixed number of frames, whose final
+/// screen is compared against a stored snapshot.
+#[derive(Debug)]
+pub struct Test {
+    /// A short, unique name.
+    pub name: &'static str,
+    rom: &'static [u8],
+    frames: u64,            // frames to run before comparing the screen
+    expected: &'static str, // the expected screen, as ASCII art
+}
+
+/// The outcome of running a [`Test`].
+#[derive(Debug)]
+pub struct Outcome {
+    /// Whether the ROM ran without faulting and the screen matched the
+    /// snapshot.
+    pub passed: bool,
+    /// The fault that stopped the ROM early, if any.
+    pub fault: Option<Fault>,
+    /// The screen at the end of the run, as ASCII art.
+    pub screen: String,
+}
+
+impl Test {
+    /// Runs the test. Every run uses the same seed and timing, so the
+    /// outcome is deterministic.
+    #[must_use]
+    pub fn run(&self) -> Outcome {
+        let mut intr = Interpreter::new();
+        intr.with_seed(0);
+        intr.load_rom(self.rom);
+        let fault = intr.run_frames(self.frames).err();
+        let screen = intr.framebuffer().to_string();
+        Outcome {
+            passed: fault.is_none() && screen == self.expected,
+            fault,
+            screen,
+        }
+    }
+}
+
+/// The built-in conformance tests.
+pub static TESTS: &[Test] = &[
+    Test {
+        name: "ibm-logo",
+        rom: include_bytes!("../roms/ibm-logo.ch8"),
+        frames: 60,
+        expected: include_str!("../roms/expected/ibm-logo.txt"),
+    },
+    Test {
+        name: "corax89-opcode-test",
+        rom: include_bytes!("../roms/opcode-test.ch8"),
+        frames: 60,
+        expected: include_str!("../roms/expected/opcode-test.txt"),
+    },
+    Test {
+        name: "bc-test",
+        rom: include_bytes!("../roms/bc-test.ch8"),
+        frames: 60,
+        expected: include_str!("../roms/expected/bc-test.txt"),
+    },
+];
+
+/// Where the ROMs of Timendus' test suite are downloaded from.
+#[cfg(feature = "gui")]
+const SUITE_URL: &str = "https://raw.githubusercontent.com/Timendus/chip8-test-suite/main/bin";
+
+/// A ROM of Timendus' [CHIP-8 test suite](https://github.com/Timendus/chip8-test-suite),
+/// which isn't bundled: it's run from a copy of the suite's `bin` directory
+/// (see [`download`]), and its final screen graded line by line against a
+/// known-good screen kept beside it, in `expected/<name>.txt`, or else the
+/// one shipped in `roms/expected`, if there is one.
+#[derive(Debug)]
+pub struct SuiteTest {
+    /// A short, unique name.
+    pub name: &'static str,
+    /// The name of the ROM's file in the suite.
+    pub file: &'static str,
+    frames: u64,                    // frames to run before grading the screen
+    platform: Option<u8>,           // picked in the ROM's menu by setting 0x1FF
+    lines: &'static [&'static str], // what each line of the result screen checks
+    shipped: Option<&'static str>,  // the known-good screen checked in, if any
+}
+
+/// The outcome of running a [`SuiteTest`].
+#[derive(Debug)]
+pub struct SuiteOutcome {
+    /// The fault that stopped the ROM early, if any.
+    pub fault: Option<Fault>,
+    /// The screen at the end of the run, as ASCII art.
+    pub screen: String,
+    /// What each line of the screen checks, and whether it matched the
+    /// known-good screen, if there is one to grade against.
+    pub lines: Option<Vec<(String, bool)>>,
+    /// Whether each check the test names, such as each quirk, passed, if
+    /// the screen was graded check by check.
+    pub scorecard: Option<Vec<(&'static str, bool)>>,
+}
+
+impl SuiteOutcome {
+    /// Whether the ROM ran without faulting and every line matched.
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.fault.is_none()
+            && self
+                .lines
+                .as_ref()
+                .is_some_and(|lines| lines.iter().all(|&(_, passed)| passed))
+    }
+}
+
+impl SuiteTest {
+    /// The path of the ROM in a copy of the suite in `dir`.
+    #[must_use]
+    pub fn path(&self, dir: &Path) -> PathBuf {
+        dir.join(self.file)
+    }
+
+    /// The path of the known-good screen, in a copy of the suite in `dir`.
+    #[must_use]
+    pub fn expected(&self, dir: &Path) -> PathBuf {
+        dir.join("expected").join(format!("{}.txt", self.name))
+    }
+
+    /// Runs the test from a copy of the suite in `dir`, grading it if there
+    /// is a known-good screen, in `dir` or shipped. Runs are as deterministic as
+    /// [`Test::run`]'s, with the COSMAC VIP's display wait, which the suite
+    /// checks for.
+    ///
+    /// # Errors
+    /// This function will error if the ROM, or the known-good screen if it
+    /// exists, cannot be read.
+    pub fn run(&self, dir: &Path) -> io::Result<SuiteOutcome> {
+        let rom = fs::read(self.path(dir))?;
+        let expected = match fs::read_to_string(self.expected(dir)) {
+            Ok(expected) => Some(expected),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => self.shipped.map(str::to_string),
+            Err(e) => return Err(e),
+        };
+        let mut intr = Interpreter::new();
+        intr.with_seed(0);
+        intr.with_quirks(Quirks {
+            display_wait: true,
+            ..Quirks::default()
+        });
+        intr.load_rom(&rom);
+        if let Some(platform) = self.platform {
+            intr.memory[0x1FF] = platform;
+        }
+        let fault = intr.run_frames(self.frames).err();
+        let screen = intr.framebuffer().to_string();
+        let grades = expected.map(|expected| grade(&screen, &expected));
+        let scorecard = grades
+            .as_deref()
+            .and_then(|grades| scorecard(self.lines, grades));
+        let lines = grades.map(|grades| match &scorecard {
+            Some(checks) => (checks.iter())
+                .map(|&(check, passed)| (check.to_string(), passed))
+                .collect(),
+            None => (grades.into_iter().enumerate())
+                .map(|(n, passed)| (format!("line {}", n + 1), passed))
+                .collect(),
+        });
+        Ok(SuiteOutcome {
+            fault,
+            screen,
+            lines,
+            scorecard,
+        })
+    }
+}
+
+/// Pairs each of `checks` with its line's grade in `grades`, if the result
+/// screen has a line per check, as the quirks test's has one per quirk.
+#[must_use]
+pub fn scorecard(checks: &[&'static str], grades: &[bool]) -> Option<Vec<(&'static str, bool)>> {
+    (!checks.is_empty() && checks.len() == grades.len())
+        .then(|| checks.iter().copied().zip(grades.iter().copied()).collect())
+}
+
+/// Grades `screen` against `expected`, both ASCII art, line by line of the
+/// text on it: each run of rows with lit pixels in `expected` is a line,
+/// which passes if `screen` has the same rows there. Anything drawn
+/// between the lines fails the line above it.
+#[must_use]
+pub fn grade(screen: &str, expected: &str) -> Vec<bool> {
+    let mut grades: Vec<bool> = Vec::new();
+    let mut in_line = false;
+    let mut rows = screen.lines();
+    for want in expected.lines() {
+        let matches = rows.next() == Some(want);
+        if want.contains('#') {
+            if !in_line {
+                grades.push(true);
+            }
+            in_line = true;
+        } else {
+            in_line = false;
+        }
+        if let Some(last) = grades.last_mut() {
+            *last &= matches;
+        } else if !matches {
+            // Before the first line, blame the whole screen.
+            grades.push(false);
+            in_line = true;
+        }
+    }
+    grades
+}
+
+/// The tests of the suite that run without input, on CHIP-8.
+pub static SUITE: &[SuiteTest] = &[
+    SuiteTest {
+        name: "chip8-logo",
+        file: "1-chip8-logo.ch8",
+        frames: 60,
+        platform: None,
+        lines: &[],
+        shipped: None,
+    },
+    SuiteTest {
+        name: "ibm-logo",
+        file: "2-ibm-logo.ch8",
+        frames: 60,
+        platform: None,
+        lines: &[],
+        // The classic ROM, whose snapshot is known good.
+        shipped: Some(include_str!("../roms/expected/ibm-logo.txt")),
+    },
+    SuiteTest {
+        name: "corax+",
+        file: "3-corax+.ch8",
+        frames: 120,
+        platform: None,
+        lines: &[],
+        shipped: None,
+    },
+    SuiteTest {
+        name: "flags",
+        file: "4-flags.ch8",
+        frames: 120,
+        platform: None,
+        lines: &[],
+        shipped: None,
+    },
+    SuiteTest {
+        name: "quirks",
+        file: "5-quirks.ch8",
+        frames: 600,
+        platform: Some(1),
+        lines: &[
+            "vF reset",
+            "memory",
+            "display wait",
+            "clipping",
+            "shifting",
+            "jumping",
+        ],
+        shipped: None,
+    },
+];
+
+/// Downloads the ROMs of [`SUITE`] into `dir`, creating it if needed, and
+/// replacing any copies already there.
+///
+/// # Errors
+/// This function will error if `dir` can't be created, or a ROM can't be
+/// downloaded or written.
+#[cfg(feature = "gui")]
+pub fn download(dir: &Path) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Could not create '{}': {e}", dir.display()))?;
+    for test in SUITE {
+        let url = format!("{SUITE_URL}/{}", test.file);
+        let write = || -> io::Result<()> {
+            let response = ureq::get(&url).call().map_err(io::Error::other)?;
+            io::copy(
+                &mut response.into_reader(),
+                &mut fs::File::create(test.path(dir))?,
+            )?;
+            Ok(())
+        };
+        write().map_err(|e| format!("Could not download '{url}': {e}"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grades_lines() {
+        let expected = "....\n.##.\n.##.\n....\n#...\n....\n";
+        assert_eq!(grade(expected, expected), [true, true]);
+        assert_eq!(
+            grade("....\n.##.\n.#..\n....\n#...\n....\n", expected),
+            [false, true]
+        );
+        // Stray pixels fail the line above, or the first if there is none.
+        assert_eq!(
+            grade("....\n.##.\n.##.\n....\n#...\n...#\n", expected),
+            [true, false]
+        );
+        assert_eq!(
+            grade("#...\n.##.\n.##.\n....\n#...\n....\n", expected),
+            [false, true]
+        );
+    }
+
+    #[test]
+    fn scores_quirks() {
+        let quirks = SUITE.iter().find(|test| test.name == "quirks").unwrap();
+        let grades = [true, true, false, true, true, true];
+        let checks = scorecard(quirks.lines, &grades).unwrap();
+        assert_eq!(checks[2], ("display wait", false));
+        assert_eq!(checks[5], ("jumping", true));
+        // A screen with another layout is graded line by line instead.
+        assert_eq!(scorecard(quirks.lines, &grades[1..]), None);
+        assert_eq!(scorecard(&[], &[true]), None);
+    }
+
+    #[test]
+    fn runs_the_suite() {
+        let dir = std::env::temp_dir().join(format!("etherea-suite-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // The suite's IBM logo is the classic ROM, bundled here too, and is
+        // graded against the shipped screen without one in `dir`.
+        let ibm = &SUITE[1];
+        fs::copy("roms/ibm-logo.ch8", ibm.path(&dir)).unwrap();
+        let outcome = ibm.run(&dir).unwrap();
+        assert!(outcome.passed(), "{:?}", outcome.lines);
+        assert_eq!(outcome.lines.unwrap()[0].0, "line 1");
+        assert_eq!(outcome.scorecard, None);
+
+        // A known-good screen in `dir` takes precedence.
+        fs::create_dir_all(dir.join("expected")).unwrap();
+        fs::write(ibm.expected(&dir), "#\n").unwrap();
+        assert!(!ibm.run(&dir).unwrap().passed());
+        assert!(SUITE[0].run(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn suite_passes() {
+        for test in TESTS {
+            let outcome = test.run();
+            assert!(outcome.passed, "{}:\n{}", test.name, outcome.screen);
+        }
+    }
+}
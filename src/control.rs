@@ -0,0 +1,358 @@
+use crate::{debug, input::KeyEvent, record, Event, Interpreter, SwapMode};
+use log::{error, info};
+use serde_json::json;
+use std::{
+    io,
+    net::SocketAddr,
+    sync::mpsc::{self, Sender},
+    thread,
+    time::Duration,
+};
+use tiny_http::{Header, Method, Response, Server};
+
+/// How long to wait for the interpreter to answer, which it can't while
+/// paused in the debugger.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Something only the interpreter thread can do, asked of it by the
+/// control server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Query {
+    /// Replaces the running ROM.
+    Load(Vec<u8>),
+    /// Restores the power-on state, keeping the ROM.
+    Reset,
+    /// Stops running frames until resumed.
+    Pause,
+    /// Starts running frames again.
+    Resume,
+    /// The screen, as a PNG.
+    Screenshot,
+    /// The registers, timers, and stack.
+    State,
+    /// Memory from `address` up to, but not including, `end`.
+    Memory { address: usize, end: usize },
+}
+
+/// A [`Query`], and where to send its answer.
+#[derive(Clone, Debug)]
+pub struct Request {
+    pub query: Query,
+    pub reply: Sender<Reply>,
+}
+
+// Requests asking the same thing are equal, wherever their answers go.
+impl PartialEq for Request {
+    fn eq(&self, other: &Self) -> bool {
+        self.query == other.query
+    }
+}
+
+impl Eq for Request {}
+
+/// The answer to a [`Query`], or to any other request: an HTTP response.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Reply {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Reply {
+    fn ok() -> Self {
+        Self {
+            status: 204,
+            content_type: "text/plain",
+            body: Vec::new(),
+        }
+    }
+
+    fn json(value: &serde_json::Value) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body: value.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain",
+            body: message.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// Starts a server on `port` of the loopback interface, on its own thread,
+/// that lets other programs drive the interpreter listening to `tx`:
+///
+/// | Request                        | Does                                             |
+/// | ------------------------------ | ------------------------------------------------ |
+/// | `POST /load`                   | runs the ROM in the body                         |
+/// | `POST /reset`                  | restarts the ROM from its power-on state         |
+/// | `POST /pause`, `POST /resume`  | pauses or resumes execution                      |
+/// | `GET /screenshot`              | returns the screen as a PNG                      |
+/// | `GET /state`                   | returns the registers, timers, and stack as JSON |
+/// | `GET /memory?address=&length=` | returns bytes of memory as a JSON array          |
+/// | `POST /keys/<key>/down` (`up`) | presses or releases a key, `0` to `F`            |
+///
+/// Requests from web pages, which carry an `Origin` header, and requests
+/// naming another host, as DNS rebinding does, are refused, so the pages
+/// open in a browser can't drive the interpreter.
+///
+/// Returns the address the server is listening on.
+///
+/// # Errors
+/// This function will error if the port can't be listened on.
+pub fn serve(port: u16, tx: Sender<Event>) -> io::Result<SocketAddr> {
+    let server = Server::http(("127.0.0.1", port)).map_err(io::Error::other)?;
+    let addr = server
+        .server_addr()
+        .to_ip()
+        .ok_or_else(|| io::Error::other("Not listening on an IP address"))?;
+    info!("Listening for control requests on {addr}");
+    let port = addr.port();
+    thread::spawn(move || {
+        for request in server.incoming_requests() {
+            respond(request, port, &tx);
+        }
+    });
+    Ok(addr)
+}
+
+/// Why `request`, made to the server on `port`, is refused, if it is: it
+/// comes from a web page, or isn't addressed to the loopback interface.
+fn forbidden(request: &tiny_http::Request, port: u16) -> Option<&'static str> {
+    let header = |name: &'static str| {
+        (request.headers().iter())
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str())
+    };
+    if header("Origin").is_some() {
+        return Some("Requests from web pages aren't allowed");
+    }
+    let hosts = [format!("127.0.0.1:{port}"), format!("localhost:{port}")];
+    match header("Host") {
+        Some(host) if hosts.iter().any(|h| h.eq_ignore_ascii_case(host)) => None,
+        _ => Some("Requests must be addressed to 127.0.0.1 or localhost"),
+    }
+}
+
+/// Answers `request`, made to the server on `port`, with the help of the
+/// interpreter listening to `tx`.
+fn respond(mut request: tiny_http::Request, port: u16, tx: &Sender<Event>) {
+    let mut body = Vec::new();
+    let reply = if let Some(reason) = forbidden(&request, port) {
+        Reply::error(403, reason)
+    } else {
+        match request.as_reader().read_to_end(&mut body) {
+            Ok(_) => route(request.method(), request.url(), body, tx),
+            Err(e) => Reply::error(400, &e.to_string()),
+        }
+    };
+    let header = Header::from_bytes("Content-Type", reply.content_type)
+        .expect("content types are valid headers");
+    let response = Response::from_data(reply.body)
+        .with_status_code(reply.status)
+        .with_header(header);
+    if let Err(e) = request.respond(response) {
+        error!("Could not answer a control request: {}", e);
+    }
+}
+
+/// Handles a request for `url`, sending what it asks for to the
+/// interpreter through `tx`.
+fn route(method: &Method, url: &str, body: Vec<u8>, tx: &Sender<Event>) -> Reply {
+    let (path, params) = url.split_once('?').unwrap_or((url, ""));
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    let query = match (method, segments.as_slice()) {
+        (Method::Post, ["load"]) => Query::Load(body),
+        (Method::Post, ["reset"]) => Query::Reset,
+        (Method::Post, ["pause"]) => Query::Pause,
+        (Method::Post, ["resume"]) => Query::Resume,
+        (Method::Get, ["screenshot"]) => Query::Screenshot,
+        (Method::Get, ["state"]) => Query::State,
+        (Method::Get, ["memory"]) => match memory(params) {
+            Some(query) => query,
+            None => return Reply::error(400, "Invalid address or length"),
+        },
+        (Method::Post, ["keys", key, state]) => {
+            let key = u8::from_str_radix(key, 16).ok().filter(|&key| key < 0x10);
+            let pressed = match *state {
+                "down" => Some(true),
+                "up" => Some(false),
+                _ => None,
+            };
+            let (Some(key), Some(pressed)) = (key, pressed) else {
+                return Reply::error(400, "Expected /keys/<0-F>/down or /keys/<0-F>/up");
+            };
+            return match tx.send(Event::Key(KeyEvent { key, pressed })) {
+                Ok(()) => Reply::ok(),
+                Err(_) => Reply::error(503, "The interpreter has stopped"),
+            };
+        }
+        _ => return Reply::error(404, "No such endpoint"),
+    };
+
+    let (reply, answer) = mpsc::channel();
+    if tx.send(Event::Control(Request { query, reply })).is_err() {
+        return Reply::error(503, "The interpreter has stopped");
+    }
+    answer
+        .recv_timeout(TIMEOUT)
+        .unwrap_or_else(|_| Reply::error(503, "The interpreter is busy, e.g. in the debugger"))
+}
+
+/// Parses the `address` and `length` parameters of a memory request, which
+/// default to all of memory.
+fn memory(params: &str) -> Option<Query> {
+    let (mut address, mut length) = (0, None);
+    for param in params.split('&').filter(|param| !param.is_empty()) {
+        match param.split_once('=')? {
+            ("address", value) => address = debug::parse_address(value)?.into(),
+            ("length", value) => length = Some(value.parse().ok()?),
+            _ => return None,
+        }
+    }
+    let length = length.unwrap_or(Interpreter::MEMORY_SIZE.saturating_sub(address));
+    let end = address
+        .checked_add(length)
+        .filter(|&end| end <= Interpreter::MEMORY_SIZE)?;
+    Some(Query::Memory { address, end })
+}
+
+/// Answers `query` on the interpreter thread.
+pub(crate) fn answer(intr: &mut Interpreter, query: Query) -> Reply {
+    match query {
+        Query::Load(rom) => match intr.swap_rom(&rom, SwapMode::Reset) {
+            Ok(()) => Reply::ok(),
+            Err(fault) => Reply::error(400, &fault.to_string()),
+        },
+        Query::Reset => {
+            intr.reset();
+            Reply::ok()
+        }
+        Query::Pause => {
+            intr.paused = true;
+            Reply::ok()
+        }
+        Query::Resume => {
+            intr.paused = false;
+            Reply::ok()
+        }
+        Query::Screenshot => {
+            let mut png = Vec::new();
+            match record::encode_png(&intr.framebuffer(), &mut png) {
+                Ok(()) => Reply {
+                    status: 200,
+                    content_type: "image/png",
+                    body: png,
+                },
+                Err(e) => Reply::error(500, &e.to_string()),
+            }
+        }
+        Query::State => Reply::json(&json!({
+            "pc": intr.pc,
+            "i": intr.i,
+            "v": intr.registers.to_vec(),
+            "delay": intr.timers.delay,
+            "sound": intr.timers.sound,
+            "stack": intr.stack,
+            "cycles": intr.cycles,
+            "paused": intr.paused,
+        })),
+        Query::Memory { address, end } => Reply::json(&json!(intr.memory[address..end])),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn requests() {
+        let (tx, rx) = mpsc::channel();
+        let interpreter = thread::spawn(move || {
+            let mut intr = Interpreter::new();
+            intr.load_rom(&[0x6A, 0x2A]);
+            for event in rx {
+                match event {
+                    Event::Control(Request { query, reply }) => {
+                        reply.send(answer(&mut intr, query)).unwrap();
+                    }
+                    Event::Key(event) => intr.handle_key(event),
+                    _ => {}
+                }
+            }
+            intr
+        });
+        let addr = serve(0, tx).unwrap();
+        let url = |path: &str| format!("http://{addr}{path}");
+
+        let state: serde_json::Value = ureq::get(&url("/state"))
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(
+            (state["pc"].as_u64(), state["paused"].as_bool()),
+            (Some(0x200), Some(false))
+        );
+        let memory: Vec<u8> = ureq::get(&url("/memory?address=0x200&length=2"))
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(memory, [0x6A, 0x2A]);
+        let response = ureq::get(&url("/screenshot")).call().unwrap();
+        assert_eq!(response.content_type(), "image/png");
+
+        let status = |request: ureq::Request| match request.call() {
+            Ok(response) => response.status(),
+            Err(ureq::Error::Status(status, _)) => status,
+            Err(e) => panic!("{e}"),
+        };
+        assert_eq!(status(ureq::post(&url("/reset"))), 204);
+        assert_eq!(status(ureq::post(&url("/pause"))), 204);
+        assert_eq!(status(ureq::post(&url("/keys/A/down"))), 204);
+        assert_eq!(status(ureq::post(&url("/keys/10/down"))), 400);
+        assert_eq!(
+            status(ureq::get(&url("/memory?address=0xFFF&length=2"))),
+            400
+        );
+        assert_eq!(
+            status(ureq::get(&url(&format!(
+                "/memory?address=1&length={}",
+                usize::MAX
+            )))),
+            400
+        );
+        assert_eq!(status(ureq::get(&url("/nowhere"))), 404);
+        assert_eq!(
+            ureq::post(&url("/load"))
+                .send_bytes(&[0x00, 0xE0])
+                .unwrap()
+                .status(),
+            204
+        );
+        let state: serde_json::Value = ureq::get(&url("/state"))
+            .call()
+            .unwrap()
+            .into_json()
+            .unwrap();
+        assert_eq!(state["paused"].as_bool(), Some(true));
+
+        // Web pages can't drive the interpreter, even by DNS rebinding.
+        let host = format!("localhost:{}", addr.port());
+        assert_eq!(status(ureq::get(&url("/state")).set("Host", &host)), 200);
+        let origin = ureq::post(&url("/reset")).set("Origin", "https://example.com");
+        assert_eq!(status(origin), 403);
+        let rebound = ureq::get(&url("/state")).set("Host", "example.com");
+        assert_eq!(status(rebound), 403);
+
+        // The server thread keeps the last sender alive, so the interpreter
+        // thread is left to end with the test.
+        drop(interpreter);
+    }
+}
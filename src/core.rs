@@ -0,0 +1,3220 @@
+use crate::{
+    audio, bank, chip8x, font, frame, input, keypad,
+    opcode::{Opcode, UnknownOpcode},
+    platform, quirks, replay,
+    screen::{self, Resolution},
+    script, settings, targets, timing, vip,
+};
+#[cfg(feature = "gui")]
+use crate::{cheats, control, crash, dap, debug, flags, hash, netplay, profile, save, trace};
+use alloc::{boxed::Box, collections::VecDeque, format, string::String, vec, vec::Vec};
+#[cfg(feature = "std")]
+use core::ops::ControlFlow;
+use core::{
+    fmt,
+    ops::{Deref, DerefMut, Range},
+    time::Duration,
+};
+#[cfg(feature = "std")]
+use log::error;
+use log::{debug, info, trace};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+#[cfg(feature = "std")]
+use std::{sync::mpsc::Receiver, thread};
+
+/// A workaround for calling [`Default`](core::default::Default) on
+/// an arbitrarily sized slice. Implements [`Deref`](core::ops::Deref)
+/// and [`DerefMut`](core::ops::DerefMut) for ease of use.
+macro_rules! wrapper {
+    ($($(#[$($attrs:meta)*])* $name:ident => $size:expr),*) => {
+        $(
+            $(#[$($attrs)*])*
+            #[derive(Debug)]
+            pub(crate) struct $name([u8; $size]);
+
+            impl Default for $name {
+                fn default() -> Self {
+                    Self([0; $size])
+                }
+            }
+
+            impl Deref for $name {
+                type Target = [u8; $size];
+
+                fn deref(&self) -> &Self::Target {
+                    &self.0
+                }
+            }
+
+            impl DerefMut for $name {
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    &mut self.0
+                }
+            }
+        )*
+    };
+}
+
+/// Enters a [`tracing`](https://docs.rs/tracing) span named `$name` until
+/// the end of the enclosing block, for profiling the interpreter loop. Does
+/// nothing without the `tracing` feature.
+macro_rules! span {
+    ($name:literal) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($name).entered();
+    };
+}
+
+/// The CHIP-8 interpreter state.
+/// [Specifications](https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#specifications).
+#[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)] // they're separate flags of the machine
+pub struct Interpreter {
+    pub(crate) i: u16,                        // Index register
+    pub(crate) pc: usize,                     // Program counter
+    pub(crate) stack: Vec<u16>,               // Stack
+    pub(crate) memory: Memory,                // Memory
+    rom: Vec<u8>,                             // The ROM loaded, to load again on reset
+    display: Option<Box<dyn screen::Screen>>, // Where the screen is shown
+    pub(crate) screen: Canvas,                // Pixels on the screen
+    pub(crate) timers: Timers,                // Timers
+    pub(crate) registers: RegisterArray,      // Variable registers (V0..=VF)
+    pub(crate) flags: [u8; Self::FLAG_COUNT], // RPL user flags, set by FX75
+    #[cfg(feature = "gui")]
+    battery: Option<flags::Battery>, // Where the flags are saved for the next session
+    #[cfg(feature = "gui")]
+    save: Option<save::Save>, // Memory saved when the ROM stops, if any
+    pub(crate) timing: timing::Timing,        // Instructions per frame and frames per second
+    costs: Option<vip::Costs>,                // What instructions cost, in cycle-accurate mode
+    budget: i64,                              // Machine cycles left this frame, in that mode
+    rng: Random,                              // Random number generator
+    clock: platform::Attached,                // Paces frames and measures rates
+    hooks: FrameHooks,                        // Callbacks invoked at the end of every frame
+    buzzer: audio::Output,                    // Sounds the beep while the sound timer runs
+    pub(crate) scripts: script::Scripts,      // Scripts hooked into execution
+    #[cfg(feature = "gui")]
+    pub(crate) cheats: Option<cheats::Cheats>, // Cheats applied at the end of every frame
+    #[cfg(feature = "gui")]
+    pub(crate) netplay: Option<netplay::Session>, // Key events exchanged with another player
+    pub(crate) cycles: u64,                   // Instructions executed since the ROM was loaded
+    counters: Counters,                       // Totals since the interpreter was created
+    frames: u64,                              // Frames run by run_frames since the ROM was loaded
+    keypad: [bool; 32],                       // Keys currently held down, on both keypads
+    key_wait: KeyWait,                        // Progress of a pending FX0A
+    #[cfg(feature = "std")]
+    pub(crate) journal: Option<replay::Journal>, // Records applied key events
+    pub(crate) replay: Option<VecDeque<replay::Entry>>, // Key events to play back
+    #[cfg(feature = "gui")]
+    debugger: Option<debug::Debugger>, // Interactive debugger
+    #[cfg(feature = "gui")]
+    pub(crate) dap: Option<dap::Target>, // Debugging session with an editor
+    pub(crate) quirks: quirks::Quirks,        // Behavior that differs between interpreters
+    pub(crate) fault: Option<Fault>,          // Set by an instruction the quirks don't allow
+    banks: Option<bank::Banks>,               // Memory banks, if banking is enabled
+    colors: Option<chip8x::ColorBoard>,       // The CHIP-8X color board, if enabled
+    pub(crate) hires: bool,                   // Set by the header of a 64x64 HIRES CHIP-8 ROM
+    pub(crate) drew: bool,                    // Set by DXYN, for the display wait quirk
+    hud: Option<Hud>,                         // The on-screen debug overlay, if shown
+    panel: Option<Vec<String>>,               // The lines of the settings panel, if shown
+    pad: Option<keypad::Overlay>,             // The on-screen keypad, if shown
+    #[cfg(feature = "std")]
+    pub(crate) paused: bool, // Stops running frames until resumed or advanced
+    #[cfg(feature = "std")]
+    blurred: bool, // Paused because the window lost focus, until it gets it back
+    #[cfg(feature = "std")]
+    advance: Option<Vec<input::KeyEvent>>, // Key releases held back, in frame-advance mode
+    #[cfg(feature = "std")]
+    autofire: Option<Autofire>, // The turbo key being pressed, while held
+    #[cfg(feature = "gui")]
+    pub(crate) profile: Option<profile::Profile>, // Time spent on each instruction, when profiling
+    decoded: Vec<Option<Opcode>>,             // Instructions decoded so far, by address
+    #[cfg(feature = "gui")]
+    pub(crate) hashes: Option<hash::Hashes>, // Checksums of the ROM loaded, for crash reports
+    #[cfg(feature = "gui")]
+    pub(crate) trace: Option<trace::Writer>, // Records the state before each instruction
+}
+
+impl Interpreter {
+    /// The size of memory, in bytes.
+    pub const MEMORY_SIZE: usize = 4096;
+    /// The start location for program-accessible memory.
+    pub(crate) const MEMORY_OFFSET: usize = 0x200;
+    const REGISTER_COUNT: usize = 16;
+    /// The number of RPL user flags, as on XO-CHIP. SUPER-CHIP only has the
+    /// first 8, which ROMs written for it stay within.
+    pub const FLAG_COUNT: usize = 16;
+    /// The size of the biggest ROM that fits in memory without banking.
+    pub const ROM_CAPACITY: usize = Self::MEMORY_SIZE - Self::MEMORY_OFFSET;
+    /// The first instruction of HIRES CHIP-8 ROMs, `1260`, which jumps to
+    /// the part of the original interpreter that switched to 64x64 pixels.
+    pub(crate) const HIRES_HEADER: [u8; 2] = [0x12, 0x60];
+    /// Where HIRES CHIP-8 programs start, after that part of the interpreter.
+    const HIRES_START: usize = 0x2C0;
+
+    /// Creates a new CHIP-8 instance with all fields zero-initialized.
+    /// To attach a display to the interpreter, use
+    /// [`attach_display`](Self::attach_display).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Attaches the display to the interpreter, to show its screen: a
+    /// [`Display`](crate::Display) window, or any other [`Screen`](screen::Screen).
+    pub fn attach_display(&mut self, display: Box<dyn screen::Screen>) {
+        self.display = Some(display);
+        info!(target: targets::DISPLAY, "Attached display [success: true]");
+    }
+
+    /// Sets the number of instructions to execute per second, at the
+    /// default of 60 frames per second.
+    pub fn with_ips(&mut self, ips: u64) {
+        self.timing = timing::Timing::from_ips(ips);
+    }
+
+    /// Sets the timing model used to schedule instructions and timer ticks.
+    pub fn with_timing(&mut self, timing: timing::Timing) {
+        self.timing = timing;
+    }
+
+    /// Times instructions by what `costs` says each costs on the COSMAC VIP:
+    /// every frame runs as many as fit in the machine cycles the display
+    /// leaves, rather than the number the timing model gives, which then only
+    /// sets the frame rate.
+    pub fn with_cycle_costs(&mut self, costs: vip::Costs) {
+        self.costs = Some(costs);
+    }
+
+    /// Sets the behavior that differs between interpreters.
+    pub fn with_quirks(&mut self, quirks: quirks::Quirks) {
+        self.quirks = quirks;
+    }
+
+    /// Enables the [`Banks`](bank::Banks) peripheral for the next ROM
+    /// loaded, which lets it be bigger than memory.
+    pub fn with_banking(&mut self) {
+        self.banks = Some(bank::Banks::default());
+    }
+
+    /// Enables the CHIP-8X [`ColorBoard`](chip8x::ColorBoard), second keypad,
+    /// and instructions for the next ROM loaded, which is loaded at `0x300`.
+    /// CHIP-8X reuses `BNNN` to color the screen, so ROMs can't jump with an
+    /// offset.
+    pub fn with_chip8x(&mut self) {
+        self.colors = Some(chip8x::ColorBoard::default());
+    }
+
+    /// Seeds the random number generator, making runs reproducible.
+    pub fn with_seed(&mut self, seed: u64) {
+        self.rng = Random::new(seed);
+    }
+
+    /// Draws the random numbers of `CXNN` from `rng`, such as a
+    /// microcontroller's hardware generator, instead of from the seed.
+    /// Runs can't be reproduced from the seed then.
+    pub fn with_rng(&mut self, rng: impl RngCore + Send + 'static) {
+        self.rng.generator = Generator::Attached(Box::new(rng));
+    }
+
+    /// Paces frames and measures the rates in the debug overlay with
+    /// `clock`, instead of the system's clock. Without `std`, time stands
+    /// still until a clock is attached.
+    pub fn with_clock(&mut self, clock: impl platform::Clock + 'static) {
+        self.clock = platform::Attached(Box::new(clock));
+    }
+
+    /// Saves the RPL user flags `FX75` sets to `battery`, and loads the ones
+    /// saved for each ROM as it is loaded, so they outlast the session.
+    #[cfg(feature = "gui")]
+    pub fn with_battery(&mut self, battery: flags::Battery) {
+        self.battery = Some(battery);
+    }
+
+    /// Restores the memory `save` saved for the ROM loaded, if it has been
+    /// saved, and saves it again when the ROM stops: when the interpreter
+    /// shuts down or faults, or another ROM is swapped in.
+    #[cfg(feature = "gui")]
+    pub fn with_save(&mut self, save: save::Save) {
+        match save.restore(&mut self.memory[..]) {
+            Ok(true) => self.decoded.fill(None),
+            Ok(false) => {}
+            Err(e) => error!("Could not restore saved memory: {}", e),
+        }
+        self.save = Some(save);
+    }
+
+    /// Saves the memory of the ROM loaded, if it has a [`save::Save`].
+    #[cfg(feature = "gui")]
+    fn store(&self) {
+        if let Some(Err(e)) = self.save.as_ref().map(|save| save.write(&self.memory[..])) {
+            error!("Could not save memory: {}", e);
+        }
+    }
+
+    /// Attaches a debugger, which is consulted before every instruction.
+    #[cfg(feature = "gui")]
+    pub fn attach_debugger(&mut self, debugger: debug::Debugger) {
+        self.debugger = Some(debugger);
+        info!("Attached debugger");
+    }
+
+    /// Records every key event applied to the interpreter to `journal`.
+    #[cfg(feature = "std")]
+    pub fn with_journal(&mut self, journal: replay::Journal) {
+        self.journal = Some(journal);
+    }
+
+    /// Writes the state of the machine before every instruction to `trace`.
+    #[cfg(feature = "gui")]
+    pub fn with_trace(&mut self, trace: trace::Writer) {
+        self.trace = Some(trace);
+    }
+
+    /// Plays back `entries` instead of taking live input. Each entry is
+    /// applied right before the instruction with its cycle number executes.
+    pub fn with_replay(&mut self, entries: VecDeque<replay::Entry>) {
+        self.replay = Some(entries);
+    }
+
+    /// Applies `cheats` at the end of every frame.
+    #[cfg(feature = "gui")]
+    pub fn with_cheats(&mut self, cheats: cheats::Cheats) {
+        self.cheats = Some(cheats);
+    }
+
+    /// Exchanges key events with another player through `session`, instead
+    /// of applying them as they arrive.
+    #[cfg(feature = "gui")]
+    pub fn with_netplay(&mut self, session: netplay::Session) {
+        self.netplay = Some(session);
+    }
+
+    /// What the interpreter has done since it was created, across every
+    /// ROM it has run.
+    #[must_use]
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+
+    /// The seed of the random number generator.
+    #[must_use]
+    pub fn seed(&self) -> u64 {
+        self.rng.seed
+    }
+
+    /// Applies a key press or release, journaling it if a journal is attached.
+    pub fn handle_key(&mut self, event: input::KeyEvent) {
+        let key = usize::from(event.key & 0x1F);
+        self.keypad[key] = event.pressed;
+        self.counters.key_events += 1;
+        if !event.pressed && event.key < input::KEYPAD_2 && self.key_wait == KeyWait::Waiting {
+            self.key_wait = KeyWait::Released(event.key & 0xF);
+        }
+        trace!(target: targets::INPUT, "handle_key: {:?} at cycle {}", event, self.cycles);
+        if self.pad.is_some() {
+            self.render();
+        }
+
+        #[cfg(feature = "std")]
+        if let Some(journal) = self.journal.as_mut() {
+            let entry = replay::Entry {
+                cycle: self.cycles,
+                event,
+            };
+            if let Err(e) = journal.record(entry) {
+                error!(target: targets::INPUT, "Could not write to input journal: {}", e);
+                self.journal = None;
+            }
+        }
+        script::Scripts::run(self, |script, machine| script.on_key(machine, event));
+    }
+
+    /// A snapshot of the screen, at its current [`resolution`](Self::resolution).
+    #[must_use]
+    pub fn framebuffer(&self) -> frame::Frame {
+        let Resolution { width, height } = self.resolution();
+        frame::Frame::new(
+            self.resolution(),
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| self.screen.get_at(x, y))
+                .collect(),
+        )
+    }
+
+    /// Executes `count` instructions without a window, applying replayed key
+    /// events as they come due. Timers aren't ticked; use the
+    /// [`Timing`](timing::Timing) model's frames for that.
+    ///
+    /// # Errors
+    /// This function will error with the fault that stopped execution, if an
+    /// instruction isn't allowed by the interpreter's quirks.
+    pub fn run_instructions(&mut self, count: u64) -> Result<(), Fault> {
+        for _ in 0..count {
+            self.replay_input();
+            self.step();
+            self.flush();
+            if let Some(fault) = self.fault.take() {
+                return Err(fault);
+            }
+        }
+        Ok(())
+    }
+
+    /// Runs `frames` frames without a window, as fast as possible. Each
+    /// executes its share of instructions under the
+    /// [`Timing`](timing::Timing) model and ticks the timers once, as when
+    /// running in a window, so the screen afterwards is the same every time
+    /// for a given seed. Frames carry on from those run by earlier calls.
+    ///
+    /// # Errors
+    /// This function will error with the fault that stopped execution, if an
+    /// instruction isn't allowed by the interpreter's quirks.
+    pub fn run_frames(&mut self, frames: u64) -> Result<(), Fault> {
+        for _ in 0..frames {
+            self.frame(self.frames)?;
+            self.frames += 1;
+        }
+        Ok(())
+    }
+
+    /// Executes `opcode` as the next instruction, as if the ROM held it at
+    /// the PC. It is written to memory there first, so the program counter,
+    /// faults, and traces all behave as for any other instruction.
+    ///
+    /// # Errors
+    /// This function will error with the fault `opcode` caused, if it isn't
+    /// an instruction or isn't allowed by the interpreter's quirks.
+    pub fn execute_opcode(&mut self, opcode: u16) -> Result<(), Fault> {
+        if let Some(pc) = self.quirks.pc_overflow.resolve(self.pc, Self::MEMORY_SIZE) {
+            let next = (pc + 1) % Self::MEMORY_SIZE;
+            [self.memory[pc], self.memory[next]] = opcode.to_be_bytes();
+            self.forget(pc..pc + 1);
+            self.forget(next..next + 1);
+        }
+        self.run_instructions(1)
+    }
+
+    /// The variable registers, `V0` to `VF`.
+    #[must_use]
+    pub fn registers(&self) -> &[u8; 16] {
+        &self.registers
+    }
+
+    /// The program counter: the address of the next instruction.
+    #[must_use]
+    pub fn pc(&self) -> usize {
+        self.pc
+    }
+
+    /// The index register, `I`.
+    #[must_use]
+    pub fn index(&self) -> u16 {
+        self.i
+    }
+
+    /// The return addresses of the subroutines being run, innermost last.
+    #[must_use]
+    pub fn stack(&self) -> &[u16] {
+        &self.stack
+    }
+
+    /// All of memory, including the font and the ROM.
+    #[must_use]
+    pub fn memory(&self) -> &[u8] {
+        &self.memory[..]
+    }
+
+    /// All of memory, to change. Instructions are decoded again after this,
+    /// so changes to the ROM take effect.
+    pub fn memory_mut(&mut self) -> &mut [u8] {
+        self.decoded = vec![None; Self::MEMORY_SIZE];
+        &mut self.memory[..]
+    }
+
+    /// Registers `hook` to be called exactly once at the end of every frame
+    /// (i.e. every timer tick), after the frame's instructions have run and
+    /// the timers have been updated. Ticks come at the frame rate of the
+    /// timing model, paced by absolute deadlines so they don't drift, which
+    /// makes this the place for anything that follows the timers, like a
+    /// buzzer or a display of its own.
+    pub fn on_frame(&mut self, hook: impl FnMut(&FrameInfo) + Send + Sync + 'static) {
+        self.hooks.0.push(Box::new(hook));
+    }
+
+    /// Routes the sound timer to `buzzer`, which is turned on or off after
+    /// every timer tick.
+    pub fn with_buzzer(&mut self, buzzer: impl audio::Buzzer + 'static) {
+        self.buzzer = audio::Output::new(buzzer);
+    }
+
+    /// Attaches `script`, whose hooks are called as the interpreter runs.
+    pub fn attach_script(&mut self, script: impl script::Script + 'static) {
+        self.scripts.attach(Box::new(script));
+    }
+
+    /// Moves the interpreter to a new thread for the fetch/decode/execute
+    /// loop, which runs in real time as in a window, rendering to the
+    /// attached display if there is one. It is owned by that thread from
+    /// then on, and controlled only by the [`Event`]s sent to `rx`, until
+    /// [`Event::Shutdown`] or a fault stops it. The thread returns the fault.
+    #[cfg(feature = "std")]
+    #[must_use]
+    pub fn spawn(mut self, rx: Receiver<Event>) -> thread::JoinHandle<Result<(), Fault>> {
+        thread::spawn(move || {
+            let result = self.execute(&rx);
+            #[cfg(feature = "gui")]
+            self.store();
+            result
+        })
+    }
+
+    /// Loads the rom into the CHIP-8 interpreter's memory buffer. ROMs
+    /// starting with the HIRES CHIP-8 header run at 64x64 pixels, from
+    /// `0x2C0`.
+    pub fn load_rom(&mut self, rom: &[u8]) {
+        self.i = 0;
+        self.stack = Vec::new();
+        self.memory = Memory::default();
+        self.timers = Timers::default();
+        self.registers = RegisterArray::default();
+        self.flags = [0; Self::FLAG_COUNT];
+        #[cfg(feature = "gui")]
+        if let Some(battery) = &self.battery {
+            self.flags = battery.load(rom);
+        }
+        self.rng.reseed();
+        self.cycles = 0;
+        self.frames = 0;
+        self.budget = 0;
+        self.keypad = [false; 32];
+        self.key_wait = KeyWait::Idle;
+        self.fault = None;
+        self.decoded = vec![None; Self::MEMORY_SIZE];
+        if let Some(colors) = self.colors.as_mut() {
+            *colors = chip8x::ColorBoard::default();
+        }
+        let start = self.program_start();
+
+        self.memory[font::MEMORY_RANGE].copy_from_slice(font::FONT);
+        let capacity = Self::MEMORY_SIZE - start;
+        if let Some(banks) = self.banks.as_mut() {
+            *banks = bank::Banks::new(rom);
+        } else if rom.len() > capacity {
+            self.fault = Some(Fault::RomTooLarge {
+                size: rom.len(),
+                capacity,
+            });
+        }
+        let loaded = &rom[..rom.len().min(capacity)];
+        self.memory[start..start + loaded.len()].copy_from_slice(loaded);
+        #[cfg(feature = "gui")]
+        {
+            self.hashes = Some(hash::Hashes::of(rom));
+        }
+        self.rom = rom.to_vec();
+        self.hires = start == Self::MEMORY_OFFSET && rom.starts_with(&Self::HIRES_HEADER);
+        self.pc = if self.hires { Self::HIRES_START } else { start };
+        self.screen = Canvas::new(if self.hires {
+            Resolution::HIRES
+        } else {
+            Resolution::LORES
+        });
+        info!(
+            target: targets::CPU,
+            "Loaded ROM [size: {}] [seed: {}] [hires: {}]",
+            rom.len(),
+            self.rng.seed,
+            self.hires
+        );
+        self.render();
+    }
+
+    /// Where ROMs are loaded and start executing.
+    fn program_start(&self) -> usize {
+        if self.colors.is_some() {
+            chip8x::PROGRAM_START
+        } else {
+            Self::MEMORY_OFFSET
+        }
+    }
+
+    /// The size of the screen: 64x64 pixels for HIRES CHIP-8 ROMs, and
+    /// 64x32 for everything else, unless the ROM has switched it.
+    #[must_use]
+    pub fn resolution(&self) -> Resolution {
+        self.screen.resolution()
+    }
+
+    /// Switches the screen to `resolution`, clearing it, as SUPER-CHIP ROMs
+    /// do to switch between its low and high resolution modes. The display
+    /// is resized to match the next time it's rendered.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        if resolution != self.resolution() {
+            info!(target: targets::DISPLAY, "Switched the screen to {}", resolution);
+        }
+        let selected = self.screen.selected();
+        self.screen = Canvas::new(resolution);
+        self.screen.select(selected);
+    }
+
+    /// Selects the planes sprites are drawn to and the screen is cleared
+    /// in, a bit a plane, as XO-CHIP's `FN01` does. Sprites drawn to two
+    /// planes take their rows for the second from after those for the
+    /// first. Only the first plane is selected to start with.
+    pub fn select_planes(&mut self, planes: u8) {
+        self.screen.select(planes);
+    }
+
+    /// Replaces the running ROM with `rom`, resetting the interpreter as
+    /// [`load_rom`](Self::load_rom) does but keeping its display, hooks,
+    /// timing, and quirks. Replays, input journals, and traces belong to the
+    /// old ROM, so they are dropped, and an attached debugger switches to the new
+    /// ROM's project. With [`SwapMode::PreserveDisplay`], the screen is kept
+    /// until the new ROM draws over it.
+    ///
+    /// While the interpreter is running, send an [`Event::SwapRom`] to its
+    /// thread instead, which calls this between frames.
+    ///
+    /// # Errors
+    /// This function will error if `rom` doesn't fit in memory (and banking
+    /// isn't enabled), in which case the running ROM is kept.
+    pub fn swap_rom(&mut self, rom: &[u8], mode: SwapMode) -> Result<(), Fault> {
+        let capacity = Self::MEMORY_SIZE - self.program_start();
+        if self.banks.is_none() && rom.len() > capacity {
+            return Err(Fault::RomTooLarge {
+                size: rom.len(),
+                capacity,
+            });
+        }
+        #[cfg(feature = "gui")]
+        {
+            self.store();
+            self.save = None;
+        }
+        let screen = core::mem::take(&mut self.screen);
+        self.load_rom(rom);
+        if mode == SwapMode::PreserveDisplay && self.resolution() == screen.resolution() {
+            self.screen = screen;
+        }
+        self.replay = None;
+        #[cfg(feature = "std")]
+        {
+            self.journal = None;
+        }
+        #[cfg(feature = "gui")]
+        {
+            self.trace = None;
+            if self.debugger.is_some() {
+                let mut debugger = debug::Debugger::new(rom);
+                debugger.resume();
+                self.debugger = Some(debugger);
+            }
+        }
+        self.render();
+        Ok(())
+    }
+
+    /// Restores the power-on state, loading the same ROM again as
+    /// [`load_rom`](Self::load_rom) does: memory, registers, timers, the
+    /// stack, and the screen start afresh, and the random number generator
+    /// from its seed. The display, hooks, timing, quirks, and debugger are
+    /// kept; replays, input journals, and traces are dropped, as they would
+    /// no longer match.
+    ///
+    /// While the interpreter is running, send an [`Event::Reset`] to its
+    /// thread instead, which calls this between frames.
+    pub fn reset(&mut self) {
+        let rom = core::mem::take(&mut self.rom);
+        #[cfg(feature = "gui")]
+        self.store();
+        self.load_rom(&rom);
+        #[cfg(feature = "gui")]
+        if let Some(save) = self.save.take() {
+            self.with_save(save);
+        }
+        self.replay = None;
+        #[cfg(feature = "std")]
+        {
+            self.journal = None;
+        }
+        #[cfg(feature = "gui")]
+        {
+            self.trace = None;
+        }
+        self.render();
+        info!(target: targets::CPU, "Reset the interpreter");
+    }
+
+    /// Renders the whole screen to the attached display, if there is one,
+    /// with the debug overlay and on-screen keypad on top if they are shown.
+    fn render(&mut self) {
+        self.screen.touch();
+        self.flush();
+    }
+
+    /// Renders the rows of the screen changed since it was last rendered, if
+    /// any were. Drawing only marks rows dirty, so however many sprites an
+    /// instruction or frame draws, they're shown at once.
+    fn flush(&mut self) {
+        if !self.screen.is_dirty() {
+            return;
+        }
+        span!("render");
+        let hud = (self.panel.clone()).or_else(|| self.hud.as_ref().map(|hud| self.hud_lines(hud)));
+        if let Some(display) = self.display.as_mut() {
+            display.render(&screen::View {
+                resolution: self.screen.resolution(),
+                rows: self.screen.rows(),
+                dirty: self.screen.dirty(),
+                hud: hud.as_deref(),
+                keypad: self.pad.as_ref().map(|pad| (pad, &self.keypad[..])),
+                colors: self.colors.as_ref(),
+            });
+        }
+        self.screen.settle();
+        trace!(target: targets::DISPLAY, "\n{}", self.framebuffer());
+    }
+
+    /// The lines of the debug overlay: the measured frame and instruction
+    /// rates, then the PC, `I`, timers, and registers, in hexadecimal.
+    fn hud_lines(&self, hud: &Hud) -> Vec<String> {
+        let mut lines = vec![
+            format!("FPS {} IPS {}", hud.fps, hud.ips),
+            format!("PC {:03X} I {:03X}", self.pc, self.i),
+            format!("DT {:02X} ST {:02X}", self.timers.delay, self.timers.sound),
+        ];
+        for (row, values) in self.registers.chunks(4).enumerate() {
+            let values: Vec<String> = values
+                .iter()
+                .enumerate()
+                .map(|(n, value)| format!("V{:X} {value:02X}", row * 4 + n))
+                .collect();
+            lines.push(values.join(" "));
+        }
+        lines
+    }
+
+    /// Fetches the instruction at the PC (program counter) from memory. A PC
+    /// past the end of memory is resolved through the
+    /// [`PcOverflow`](quirks::PcOverflow) quirk; if that records a fault, the
+    /// instruction fetched is `0000`, which does nothing.
+    fn fetch(&mut self) -> u16 {
+        span!("fetch");
+        let Some(pc) = self.quirks.pc_overflow.resolve(self.pc, Self::MEMORY_SIZE) else {
+            self.fault
+                .get_or_insert(Fault::PcOutOfBounds { pc: self.pc });
+            return 0x0000;
+        };
+        let next = (pc + 1) % Self::MEMORY_SIZE;
+        let inst = u16::from_be_bytes([self.memory[pc], self.memory[next]]);
+        self.pc = pc + 2;
+        inst
+    }
+
+    /// Decodes the instruction fetched with [`fetch`](Self::fetch). Each
+    /// address is only decoded once, until memory there is written (see
+    /// [`forget`](Self::forget)), so hot loops skip fetching and decoding.
+    fn decode(&mut self) -> Result<Opcode, UnknownOpcode> {
+        span!("decode");
+        if let Some(opcode) = self.decoded.get(self.pc).copied().flatten() {
+            self.pc += 2;
+            return Ok(opcode);
+        }
+        let pc = self.pc;
+        let opcode = Opcode::try_from(self.fetch())?;
+        // PCs resolved by the `PcOverflow` quirk are rare enough not to cache.
+        if self.pc == pc + 2 {
+            if let Some(decoded) = self.decoded.get_mut(pc) {
+                *decoded = Some(opcode);
+            }
+        }
+        Ok(opcode)
+    }
+
+    /// Forgets the decoded instructions overlapping `range` of memory, which
+    /// has been written to. Must be called after any write to memory once the
+    /// ROM is running, or self-modifying code would run stale instructions.
+    pub(crate) fn forget(&mut self, range: Range<usize>) {
+        // The instruction at the address before the range ends inside it.
+        let before = (range.start + Self::MEMORY_SIZE - 1) % Self::MEMORY_SIZE;
+        for address in core::iter::once(before).chain(range) {
+            if let Some(decoded) = self.decoded.get_mut(address) {
+                *decoded = None;
+            }
+        }
+    }
+
+    /// Runs the interpreter frame by frame, as dictated by the
+    /// [`Timing`](timing::Timing) model. Each frame applies pending key
+    /// events, executes its share of instructions, ticks the timers once,
+    /// then sleeps until the frame's deadline. Returns on
+    /// [`Event::Shutdown`], or with the fault that stopped the ROM.
+    #[cfg(feature = "std")]
+    fn execute(&mut self, rx: &Receiver<Event>) -> Result<(), Fault> {
+        let mut start = self.clock.0.now();
+        // The frame `start` corresponds to. Moved forward after pausing in
+        // the debugger or switching ROMs, so execution doesn't race to catch
+        // up afterwards.
+        let mut first = 0;
+        for frame in 0.. {
+            loop {
+                // While paused, wait for the event that resumes execution.
+                let event = if self.paused {
+                    // Nothing ticks the sound timer while paused.
+                    self.buzzer.buzz(false);
+                    match rx.recv() {
+                        Ok(event) => event,
+                        Err(_) => return Ok(()),
+                    }
+                } else {
+                    match rx.try_recv() {
+                        Ok(event) => event,
+                        Err(_) => break,
+                    }
+                };
+                match self.handle_event(event) {
+                    ControlFlow::Break(()) => return Ok(()),
+                    ControlFlow::Continue(true) => {
+                        start = self.clock.0.now();
+                        first = frame;
+                    }
+                    ControlFlow::Continue(false) => {}
+                }
+                // Later events wait until the frame advanced to has run.
+                if self.advance.is_some() && !self.paused {
+                    break;
+                }
+            }
+            self.autofire();
+            #[cfg(feature = "gui")]
+            self.netplay_input(frame);
+            match self.frame(frame) {
+                Ok(true) => {
+                    start = self.clock.0.now();
+                    first = frame;
+                }
+                Ok(false) => {}
+                Err(fault) => {
+                    error!(target: targets::CPU, "{}", fault);
+                    #[cfg(feature = "gui")]
+                    match crash::save(&crash::report(self, &fault)) {
+                        Ok(path) => error!("Wrote a crash report to {}", path.display()),
+                        Err(e) => error!("Could not write a crash report: {}", e),
+                    }
+                    return Err(fault);
+                }
+            }
+            self.hold();
+
+            let deadline = self.timing.deadline(start, frame + 1 - first);
+            let wait = deadline.saturating_sub(self.clock.0.now());
+            if !wait.is_zero() {
+                self.clock.0.sleep(wait);
+            }
+        }
+        Ok(())
+    }
+
+    /// Handles `event` between frames. Breaks on [`Event::Shutdown`], and
+    /// otherwise continues with whether execution should start timing
+    /// frames afresh, e.g. after switching ROMs, rather than race to catch up.
+    #[cfg(feature = "std")]
+    fn handle_event(&mut self, event: Event) -> ControlFlow<(), bool> {
+        match event {
+            Event::Key(event) => self.input(event),
+            Event::Turbo(turbo) => {
+                if let Some(Autofire {
+                    turbo, down: true, ..
+                }) = self.autofire.take()
+                {
+                    self.input(input::KeyEvent {
+                        key: turbo.key,
+                        pressed: false,
+                    });
+                }
+                self.autofire = turbo.map(|turbo| Autofire {
+                    turbo,
+                    frames: 0,
+                    down: false,
+                });
+            }
+            Event::Shutdown => return ControlFlow::Break(()),
+            Event::SwapRom { rom, mode } => match self.swap_rom(&rom, mode) {
+                Ok(()) => return ControlFlow::Continue(true),
+                Err(e) => error!(target: targets::CPU, "Could not switch ROMs: {}", e),
+            },
+            Event::Focus(true) if self.blurred => {
+                self.blurred = false;
+                self.paused = false;
+                return ControlFlow::Continue(true);
+            }
+            // Execution already paused stays paused when focus comes back.
+            Event::Focus(false) if !self.paused => {
+                info!(target: targets::CPU, "Paused until the window has focus again");
+                self.blurred = true;
+                self.paused = true;
+            }
+            Event::Focus(_) => {}
+            Event::Reset => {
+                self.reset();
+                return ControlFlow::Continue(true);
+            }
+            Event::ToggleHud => {
+                self.hud = match self.hud {
+                    Some(_) => None,
+                    None => Some(Hud::new(self.clock.0.now(), self.cycles)),
+                };
+                self.render();
+            }
+            Event::Settings(settings) => {
+                self.apply(settings);
+                return ControlFlow::Continue(true);
+            }
+            Event::Panel(lines) => {
+                self.panel = lines;
+                self.render();
+            }
+            Event::Keypad(pad) => {
+                self.pad = pad;
+                self.render();
+            }
+            Event::Rescale(scaling) => {
+                if let Some(display) = self.display.as_mut() {
+                    display.set_scaling(scaling);
+                }
+                self.render();
+            }
+            Event::ToggleFrameAdvance => {
+                if let Some(released) = self.advance.take() {
+                    info!(target: targets::CPU, "Leaving frame-advance mode");
+                    for event in released {
+                        self.handle_key(event);
+                    }
+                    self.paused = false;
+                    return ControlFlow::Continue(true);
+                }
+                info!(target: targets::CPU, "Frame-advance mode: F6 runs a frame, F5 resumes");
+                self.advance = Some(Vec::new());
+                self.paused = true;
+            }
+            Event::AdvanceFrame if self.advance.is_some() => {
+                self.paused = false;
+                return ControlFlow::Continue(true);
+            }
+            Event::AdvanceFrame => {}
+            #[cfg(feature = "gui")]
+            Event::Control(control::Request { query, reply }) => {
+                let restart = matches!(
+                    query,
+                    control::Query::Load(_) | control::Query::Reset | control::Query::Resume
+                );
+                // The requester may have given up waiting.
+                let _ = reply.send(control::answer(self, query));
+                return ControlFlow::Continue(restart);
+            }
+            #[cfg(feature = "gui")]
+            Event::Save(save) => self.with_save(save),
+        }
+        ControlFlow::Continue(false)
+    }
+
+    /// Applies `event` from the keyboard, or another source of input, unless
+    /// input is being replayed. Over the network, it's sent to the other
+    /// player and applied a few frames later.
+    #[cfg(feature = "std")]
+    fn input(&mut self, event: input::KeyEvent) {
+        if self.replay.is_some() {
+            return;
+        }
+        #[cfg(feature = "gui")]
+        if let Some(session) = &mut self.netplay {
+            session.queue(event);
+            return;
+        }
+        self.latch(event);
+    }
+
+    /// Presses or lets go of the turbo key, if its modifier is held, as its
+    /// rate says for this frame.
+    #[cfg(feature = "std")]
+    fn autofire(&mut self) {
+        let frames_per_second = self.timing.frames_per_second;
+        let Some(autofire) = &mut self.autofire else {
+            return;
+        };
+        let pressed = autofire.turbo.pressed(autofire.frames, frames_per_second);
+        autofire.frames += 1;
+        if pressed != autofire.down {
+            autofire.down = pressed;
+            let key = autofire.turbo.key;
+            self.input(input::KeyEvent { key, pressed });
+        }
+    }
+
+    /// Applies `event`, except that in frame-advance mode a key let go of is
+    /// held down until the next frame has run, so even a key tapped while
+    /// paused counts for a whole frame.
+    #[cfg(feature = "std")]
+    fn latch(&mut self, event: input::KeyEvent) {
+        let Some(released) = &mut self.advance else {
+            return self.handle_key(event);
+        };
+        released.retain(|release| release.key != event.key);
+        if event.pressed {
+            self.handle_key(event);
+        } else {
+            released.push(event);
+        }
+    }
+
+    /// In frame-advance mode, pauses again once the frame advanced to has
+    /// run, letting go of the keys released while it was paused.
+    #[cfg(feature = "std")]
+    fn hold(&mut self) {
+        let Some(released) = self.advance.as_mut().map(core::mem::take) else {
+            return;
+        };
+        for event in released {
+            self.handle_key(event);
+        }
+        self.paused = true;
+    }
+
+    /// Exchanges key events with the other player, if playing over the
+    /// network, and applies both players' events for frame `frame`. Waits
+    /// for the other player to reach the frame, keeping the two in
+    /// lockstep. Carries on alone if they disconnect.
+    #[cfg(feature = "gui")]
+    fn netplay_input(&mut self, frame: u64) {
+        let Some(session) = &mut self.netplay else {
+            return;
+        };
+        match session.exchange(frame) {
+            Ok(events) => {
+                for event in events {
+                    self.handle_key(event);
+                }
+            }
+            Err(e) => {
+                error!("Netplay stopped: {}", e);
+                self.netplay = None;
+            }
+        }
+    }
+
+    /// Executes frame number `frame`: its share of instructions followed by
+    /// a single timer tick. Returns whether the debugger paused execution,
+    /// or the fault that stopped the frame early.
+    pub(crate) fn frame(&mut self, frame: u64) -> Result<bool, Fault> {
+        span!("frame");
+        let mut paused = false;
+        let count = if self.costs.is_some() {
+            // Time left over is spent waiting for the display, but time
+            // overrun is taken from the next frame.
+            let cycles = vip::cycles_per_frame(self.timing.frames_per_second);
+            self.budget = self.budget.min(0) + i64::try_from(cycles).unwrap_or(i64::MAX);
+            u64::MAX
+        } else {
+            self.timing.cycles(frame)
+        };
+        for _ in 0..count {
+            if self.costs.is_some() && self.budget <= 0 {
+                break;
+            }
+            self.replay_input();
+            paused |= self.debug();
+            self.step();
+            if let Some(fault) = self.fault.take() {
+                self.flush();
+                return Err(fault);
+            }
+            if core::mem::take(&mut self.drew) && self.quirks.display_wait {
+                break;
+            }
+        }
+        if self.timers.delay > 0 || self.timers.sound > 0 {
+            self.counters.timer_ticks += 1;
+        }
+        self.timers.update();
+        self.buzzer.buzz(self.timers.sound > 0);
+        self.counters.frames += 1;
+        self.end_frame(frame);
+        Ok(paused)
+    }
+
+    /// Invokes the [`on_frame`](Self::on_frame) hooks for frame number
+    /// `frame`, and renders what the frame drew.
+    pub(crate) fn end_frame(&mut self, frame: u64) {
+        #[cfg(feature = "gui")]
+        if let Some(cheats) = self.cheats.take() {
+            cheats.apply(self);
+            self.cheats = Some(cheats);
+        }
+        let info = FrameInfo {
+            frame,
+            resolution: self.resolution(),
+            framebuffer: self.screen.rows(),
+            delay: self.timers.delay,
+            sound: self.timers.sound,
+            counters: self.counters,
+        };
+        for hook in &mut self.hooks.0 {
+            hook(&info);
+        }
+        script::Scripts::run(self, |script, machine| script.on_frame(machine, frame));
+        #[cfg(feature = "gui")]
+        if let Some(debugger) = &mut self.debugger {
+            debugger.end_frame(&self.memory[..], &self.registers[..]);
+        }
+        if let Some(hud) = &mut self.hud {
+            hud.update(self.clock.0.now(), self.cycles);
+            self.screen.touch();
+        }
+        self.flush();
+    }
+
+    /// Applies settings changed in the settings panel, which take effect
+    /// from the next frame.
+    #[cfg(feature = "std")]
+    pub(crate) fn apply(&mut self, settings: settings::Settings) {
+        self.timing = timing::Timing::from_ips(settings.ips);
+        self.quirks = settings.quirks;
+        self.buzzer.set_volume(settings.volume);
+        if let Some(display) = self.display.as_mut() {
+            display.set_palette(settings.palette);
+        }
+        self.render();
+    }
+
+    /// Lets the debugger or editor (if any) inspect the interpreter before the next
+    /// instruction. Returns whether execution was paused.
+    #[cfg_attr(not(feature = "gui"), allow(clippy::unused_self))]
+    fn debug(&mut self) -> bool {
+        #[cfg(feature = "gui")]
+        if self.dap.is_some() || self.debugger.is_some() {
+            // Either may pause here, so show what's been drawn so far.
+            self.flush();
+        }
+        #[cfg(feature = "gui")]
+        if let Some(mut target) = self.dap.take() {
+            let paused = target.check(self);
+            self.dap = Some(target);
+            return paused;
+        }
+        #[cfg(feature = "gui")]
+        if let Some(mut debugger) = self.debugger.take() {
+            let paused = debugger.check(self);
+            self.debugger = Some(debugger);
+            return paused;
+        }
+        false
+    }
+
+    /// Applies the replayed key events due at the current cycle.
+    fn replay_input(&mut self) {
+        while let Some(entries) = self.replay.as_mut() {
+            match entries.front() {
+                Some(entry) if entry.cycle <= self.cycles => {
+                    let event = entry.event;
+                    entries.pop_front();
+                    self.handle_key(event);
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Fetches, decodes, and executes a single instruction, unless a fault
+    /// is waiting to be reported.
+    pub(crate) fn step(&mut self) {
+        if self.fault.is_some() {
+            return;
+        }
+        self.cycles += 1;
+        self.counters.instructions += 1;
+        #[cfg(feature = "gui")]
+        if let Some(mut trace) = self.trace.take() {
+            match trace.record(self) {
+                Ok(()) => self.trace = Some(trace),
+                Err(e) => error!("Could not write to trace file: {}", e),
+            }
+        }
+        let inst = match self.decode() {
+            Ok(inst) => inst,
+            Err(UnknownOpcode(opcode)) if self.colors.is_some() && self.chip8x(opcode) => return,
+            Err(UnknownOpcode(opcode)) => {
+                self.fault = Some(Fault::UnknownOpcode {
+                    pc: self.pc - 2,
+                    opcode,
+                });
+                return;
+            }
+        };
+        debug!(target: targets::CPU, "Processing instruction [{}]", inst);
+        trace!(
+            target: targets::CPU,
+            "Timers: [sound: {}] [delay: {}]",
+            self.timers.sound,
+            self.timers.delay
+        );
+        trace!(target: targets::CPU, "Registers: {:?}", self.registers);
+        script::Scripts::run(self, |script, machine| script.on_instruction(machine, inst));
+        if let Some(costs) = &self.costs {
+            self.budget -= i64::from(costs.cost(inst, &self.registers));
+        }
+        #[cfg(feature = "gui")]
+        let start = self
+            .profile
+            .is_some()
+            .then(|| (self.pc - 2, self.i, std::time::Instant::now()));
+        span!("execute");
+        let r = |x: u8| usize::from(x);
+        match inst {
+            Opcode::ClearScreen => self.clear_screen(),
+            Opcode::Jump(addr) => self.jump(addr),
+            Opcode::Return => self.subroutine_return(),
+            Opcode::Call(addr) => self.call_subroutine(addr),
+            Opcode::SkipEq { x, nn } => self.skip_vx(r(x), nn, true),
+            Opcode::SkipNe { x, nn } => self.skip_vx(r(x), nn, false),
+            Opcode::SkipEqReg { x, y } => self.skip_vxy(r(x), r(y), true),
+            Opcode::SkipNeReg { x, y } => self.skip_vxy(r(x), r(y), false),
+            Opcode::Load { x, nn } => self.set_register(r(x), nn),
+            Opcode::AddImm { x, nn } => self.add_to_register(r(x), nn),
+            Opcode::Move { x, y } => self.set(r(x), r(y)),
+            Opcode::Or { x, y } => self.or(r(x), r(y)),
+            Opcode::And { x, y } => self.and(r(x), r(y)),
+            Opcode::Xor { x, y } => self.xor(r(x), r(y)),
+            Opcode::Add { x, y } => self.add(r(x), r(y)),
+            Opcode::Sub { x, y } => self.sub(r(x), r(x), r(y)),
+            Opcode::SubN { x, y } => self.sub(r(x), r(y), r(x)),
+            Opcode::ShiftRight { x, y } => self.shift_right(r(x), r(y)),
+            Opcode::ShiftLeft { x, y } => self.shift_left(r(x), r(y)),
+            Opcode::LoadIndex(addr) => self.set_memory_ptr(addr),
+            Opcode::JumpOffset(addr) if self.colors.is_some() => self.color_zones(addr),
+            Opcode::JumpOffset(addr) => self.jump_with_offset(addr),
+            Opcode::Random { x, nn } => self.random(r(x), nn),
+            Opcode::Draw { x, y, n } => {
+                self.draw_sprite(r(x), r(y), n);
+                script::Scripts::run(self, |script, machine| script.on_draw(machine));
+            }
+            Opcode::SkipKey { x } => self.skip_key(r(x), true),
+            Opcode::SkipNotKey { x } => self.skip_key(r(x), false),
+            Opcode::LoadDelay { x } => self.timer_to_vx(r(x)),
+            Opcode::SetDelay { x } => self.vx_to_timer(r(x), true),
+            Opcode::SetSound { x } => self.vx_to_timer(r(x), false),
+            Opcode::AddIndex { x } => self.add_to_index(r(x)),
+            Opcode::WaitKey { x } => self.get_key(r(x)),
+            Opcode::Font { x } => self.font_character(r(x)),
+            Opcode::Bcd { x } => self.conversion(r(x)),
+            Opcode::Store { x } => self.store_to_memory(r(x)),
+            Opcode::LoadMemory { x } => self.load_from_memory(r(x)),
+            Opcode::SaveFlags { x } => self.save_flags(r(x)),
+            Opcode::LoadFlags { x } => self.load_flags(r(x)),
+            Opcode::ScrollDown { n } => self.scroll(Canvas::scroll_down, n),
+            Opcode::ScrollUp { n } => self.scroll(Canvas::scroll_up, n),
+            Opcode::ScrollRight => self.scroll(Canvas::scroll_right, 4),
+            Opcode::ScrollLeft => self.scroll(Canvas::scroll_left, 4),
+            Opcode::Sys(0x2A0) if self.colors.is_some() => self.cycle_background(),
+            Opcode::Sys(0x230) if self.hires => self.clear_screen(),
+            Opcode::Sys(addr) if self.banks.is_some() && addr >> 8 == 0xB => {
+                self.select_bank(addr.to_be_bytes()[1]);
+            }
+            Opcode::Sys(_) => {}
+        }
+        #[cfg(feature = "gui")]
+        if let (Some(profile), Some((pc, i, start))) = (&mut self.profile, start) {
+            let pc = u16::try_from(pc).unwrap_or(u16::MAX);
+            profile.record(pc, inst, start.elapsed());
+            profile.access(inst, i);
+        }
+    }
+
+    /// Executes `raw` if it is one of the CHIP-8X instructions that isn't
+    /// decoded as an [`Opcode`], returning whether it was.
+    fn chip8x(&mut self, raw: u16) -> bool {
+        let [hi, lo] = raw.to_be_bytes();
+        let (x, y) = (usize::from(hi & 0xF), usize::from(lo >> 4));
+        match (hi >> 4, lo) {
+            // 5XY1
+            (0x5, _) if lo & 0xF == 1 => {
+                self.registers[x] = chip8x::add_nibbles(self.registers[x], self.registers[y]);
+            }
+            // EXF2 and EXF5
+            (0xE, 0xF2 | 0xF5) => {
+                let key = usize::from(input::KEYPAD_2 + (self.registers[x] & 0xF));
+                if self.keypad[key] == (lo == 0xF2) {
+                    self.pc += 2;
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// `02A0`: switches the CHIP-8X background to its next color.
+    fn cycle_background(&mut self) {
+        if let Some(colors) = self.colors.as_mut() {
+            colors.cycle_background();
+        }
+        self.render();
+    }
+
+    /// `BXYN`: colors CHIP-8X color zones with `VY`, at the position in `VX`
+    /// and `VX + 1`. See [`ColorBoard::fill`](chip8x::ColorBoard::fill).
+    fn color_zones(&mut self, addr: u16) {
+        let [hi, lo] = addr.to_be_bytes();
+        let x = usize::from(hi);
+        let (horizontal, vertical) = (self.registers[x], self.registers[(x + 1) % 16]);
+        let color = self.registers[usize::from(lo >> 4)];
+        if let Some(colors) = self.colors.as_mut() {
+            colors.fill(horizontal, vertical, lo & 0xF, color);
+        }
+        self.render();
+    }
+
+    /// Maps bank `NN` into memory, with the [`Banks`](bank::Banks)
+    /// peripheral enabled.
+    fn select_bank(&mut self, nn: u8) {
+        let bank = usize::from(nn);
+        let Some(banks) = self.banks.as_mut() else {
+            return;
+        };
+        if banks.select(bank, &mut self.memory[..]) {
+            self.forget(bank::WINDOW);
+            trace!(target: targets::CPU, "select_bank: mapped bank {bank}");
+        } else {
+            self.fault = Some(Fault::NoSuchBank {
+                pc: self.pc - 2,
+                bank,
+                count: banks.count(),
+            });
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#00ee-and-2nnn-subroutines>
+    fn call_subroutine(&mut self, addr: u16) {
+        if self.stack.len() >= self.quirks.stack_depth {
+            self.fault = Some(Fault::StackOverflow {
+                pc: self.pc - 2,
+                depth: self.stack.len(),
+            });
+            return;
+        }
+        self.stack.push(u16::try_from(self.pc).unwrap());
+        let pc = usize::from(addr);
+        self.pc = pc;
+        trace!(target: targets::CPU, "call_subroutine: set PC to {pc}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#00ee-and-2nnn-subroutines>
+    fn subroutine_return(&mut self) {
+        let Some(pc) = self.stack.pop() else {
+            self.fault = Some(Fault::StackUnderflow { pc: self.pc - 2 });
+            return;
+        };
+        let pc = usize::from(pc);
+        self.pc = pc;
+        trace!(target: targets::CPU, "subroutine_return: set PC to {pc}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#3xnn-4xnn-5xy0-and-9xy0-skip>
+    fn skip_vx(&mut self, register: usize, nn: u8, equality: bool) {
+        let vx = self.registers[register];
+        if (equality && vx == nn) || (!equality && vx != nn) {
+            trace!(target: targets::CPU, "skip_vx: incremented pc by 2");
+            self.pc += 2;
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#3xnn-4xnn-5xy0-and-9xy0-skip>
+    fn skip_vxy(&mut self, vx: usize, vy: usize, equality: bool) {
+        let vx = self.registers[vx];
+        let vy = self.registers[vy];
+        if (equality && vx == vy) || (!equality && vx != vy) {
+            trace!(target: targets::CPU, "skip_vxy: incremented pc by 2");
+            self.pc += 2;
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy0-set>
+    fn set(&mut self, vx: usize, vy: usize) {
+        self.registers[vx] = self.registers[vy];
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy1-binary-or>
+    fn or(&mut self, vx: usize, vy: usize) {
+        self.registers[vx] |= self.registers[vy];
+        self.reset_vf();
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy2-binary-and>
+    fn and(&mut self, vx: usize, vy: usize) {
+        self.registers[vx] &= self.registers[vy];
+        self.reset_vf();
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy3-logical-xor>
+    fn xor(&mut self, vx: usize, vy: usize) {
+        self.registers[vx] ^= self.registers[vy];
+        self.reset_vf();
+    }
+
+    /// Resets `VF` after a logical instruction, if the
+    /// [`vf_reset`](quirks::Quirks::vf_reset) quirk is on.
+    fn reset_vf(&mut self) {
+        if self.quirks.vf_reset {
+            self.registers[0xF] = 0;
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy4-add>
+    fn add(&mut self, vx: usize, vy: usize) {
+        let x = usize::from(self.registers[vx]);
+        let y = usize::from(self.registers[vy]);
+        self.registers[vx] = self.registers[vx].wrapping_add(self.registers[vy]);
+        self.registers[0xF] = u8::from(x + y > 255);
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy5-and-8xy7-subtract>
+    fn sub(&mut self, vx: usize, lhs: usize, rhs: usize) {
+        let lhs = self.registers[lhs];
+        let rhs = self.registers[rhs];
+        self.registers[vx] = lhs.wrapping_sub(rhs);
+        self.registers[0xF] = u8::from(lhs >= rhs);
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy6-and-8xye-shift>
+    fn shift_left(&mut self, vx: usize, vy: usize) {
+        let value = self.shifted(vx, vy);
+        self.registers[vx] = value << 1;
+        self.registers[0xF] = u8::from(bits::set(7, value));
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#8xy6-and-8xye-shift>
+    fn shift_right(&mut self, vx: usize, vy: usize) {
+        let value = self.shifted(vx, vy);
+        self.registers[vx] = value >> 1;
+        self.registers[0xF] = u8::from(bits::set(0, value));
+    }
+
+    /// The value a shift shifts, from the register the
+    /// [`Shift`](quirks::Shift) quirk says.
+    fn shifted(&self, vx: usize, vy: usize) -> u8 {
+        match self.quirks.shift {
+            quirks::Shift::Vx => self.registers[vx],
+            quirks::Shift::Vy => self.registers[vy],
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#cxnn-random>
+    fn random(&mut self, vx: usize, nn: u8) {
+        let r = self.rng.byte();
+        self.registers[vx] = nn & r;
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx07-fx15-and-fx18-timers>
+    fn timer_to_vx(&mut self, vx: usize) {
+        self.registers[vx] = self.timers.delay;
+        trace!(
+            target: targets::CPU,
+            "timer_to_vx: written value {} to register V{vx:01X}",
+            self.timers.delay
+        );
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx07-fx15-and-fx18-timers>
+    fn vx_to_timer(&mut self, vx: usize, delay: bool) {
+        let value = self.registers[vx];
+        let timer = if delay {
+            &mut self.timers.delay
+        } else {
+            &mut self.timers.sound
+        };
+        *timer = value;
+        trace!(target: targets::TIMER, "vx_to_timer: set timer [delay: {}] to {}", delay, value);
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx1e-add-to-index>
+    /// What happens when `I` passes the end of memory depends on the
+    /// [`IndexOverflow`](quirks::IndexOverflow) quirk, and whether `VF` says
+    /// so on the [`index_carry`](quirks::Quirks::index_carry) quirk.
+    fn add_to_index(&mut self, vx: usize) {
+        let i = self.i.wrapping_add(u16::from(self.registers[vx]));
+        if self.quirks.index_carry {
+            self.registers[0xF] = u8::from(usize::from(i) >= Self::MEMORY_SIZE);
+        }
+        self.i = match self.quirks.index_overflow {
+            quirks::IndexOverflow::Mask => i & 0xFFF,
+            quirks::IndexOverflow::Fault => i,
+        };
+        trace!(
+            target: targets::CPU,
+            "add_to_index: added {} to index register",
+            self.registers[vx]
+        );
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#1nnn-jump>
+    fn jump(&mut self, addr: u16) {
+        let pc = usize::from(addr);
+        self.pc = pc;
+        trace!(target: targets::CPU, "jump: set PC to {pc}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#bnnn-jump-with-offset>
+    ///
+    /// The register added depends on the [`JumpOffset`](quirks::JumpOffset) quirk.
+    fn jump_with_offset(&mut self, addr: u16) {
+        let offset = match self.quirks.jump_offset {
+            quirks::JumpOffset::V0 => self.registers[0x0],
+            quirks::JumpOffset::Vx => self.registers[usize::from(addr >> 8)],
+        };
+        let pc = usize::from(addr) + usize::from(offset);
+        self.pc = pc;
+        trace!(target: targets::CPU, "jump_with_offset: set PC to {pc}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx29-font-character>
+    fn font_character(&mut self, vx: usize) {
+        let c = self.registers[vx];
+        trace!(target: targets::CPU, "font [char: {:#X}]", c);
+        let start = u16::try_from(*font::MEMORY_RANGE.start()).unwrap();
+        // Only the low nibble names a character.
+        self.i = start + u16::from(c & 0xF) * 5;
+        trace!(target: targets::CPU, "font [i: {:#X}]", self.i);
+        trace!(target: targets::CPU, "font_character: set I to {}", self.i);
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx33-binary-coded-decimal-conversion>
+    fn conversion(&mut self, vx: usize) {
+        let x = usize::from(self.registers[vx]);
+        for (offset, place) in (0..3).rev().enumerate() {
+            let Some(i) = self.address(offset) else {
+                return;
+            };
+            self.memory[i] = u8::try_from(digit(place, x)).unwrap();
+            self.forget(i..i + 1);
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx55-and-fx65-store-and-load-memory>
+    fn store_to_memory(&mut self, vx: usize) {
+        for register in 0x0..=vx {
+            let Some(i) = self.address(register) else {
+                return;
+            };
+            self.memory[i] = self.registers[register];
+            self.forget(i..i + 1);
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#fx55-and-fx65-store-and-load-memory>
+    fn load_from_memory(&mut self, vx: usize) {
+        for register in 0x0..=vx {
+            let Some(i) = self.address(register) else {
+                return;
+            };
+            self.registers[register] = self.memory[i];
+        }
+    }
+
+    /// `FX75`: stores `V0` through `VX` in the RPL user flags, saving them
+    /// if a [`Battery`](flags::Battery) is attached.
+    fn save_flags(&mut self, vx: usize) {
+        let len = (vx + 1).min(Self::FLAG_COUNT);
+        self.flags[..len].copy_from_slice(&self.registers[..len]);
+        #[cfg(feature = "gui")]
+        if let Some(battery) = &self.battery {
+            if let Err(e) = battery.save(&self.rom, &self.flags) {
+                error!("Could not save flags: {}", e);
+            }
+        }
+    }
+
+    /// `FX85`: loads `V0` through `VX` from the RPL user flags.
+    fn load_flags(&mut self, vx: usize) {
+        let len = (vx + 1).min(Self::FLAG_COUNT);
+        self.registers[..len].copy_from_slice(&self.flags[..len]);
+    }
+
+    /// Resolves `I + offset` to an index into memory according to the
+    /// [`IndexOverflow`](quirks::IndexOverflow) quirk, recording a fault if
+    /// it is out of bounds.
+    fn address(&mut self, offset: usize) -> Option<usize> {
+        let address = usize::from(self.i) + offset;
+        let index = self
+            .quirks
+            .index_overflow
+            .resolve(address, Self::MEMORY_SIZE);
+        if index.is_none() {
+            self.fault.get_or_insert(Fault::IndexOutOfBounds {
+                pc: self.pc - 2,
+                i: self.i,
+                address,
+            });
+        }
+        index
+    }
+
+    /// Sets the variable register `register` to `value`, as `6XNN` does.
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#6xnn-set>
+    ///
+    /// # Panics
+    /// This function panics if `register` isn't one, from `0x0` to `0xF`.
+    pub fn set_register(&mut self, register: usize, value: u8) {
+        self.registers[register] = value;
+        trace!(target: targets::CPU, "set_register: V{register:01X} => {value}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#7xnn-add>
+    fn add_to_register(&mut self, register: usize, value: u8) {
+        self.registers[register] = self.registers[register].wrapping_add(value);
+        trace!(target: targets::CPU, "add_to_register: V{register:01X} + {value}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#annn-set-index>
+    fn set_memory_ptr(&mut self, value: u16) {
+        self.i = value;
+        trace!(target: targets::CPU, "set_memory_ptr: set index register I to {value}");
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#00e0-clear-screen>
+    fn clear_screen(&mut self) {
+        self.screen.clear();
+    }
+
+    /// Scrolls the selected planes `n` pixels with `scroll`, or half as far
+    /// in low resolution with the [`half_scroll`](quirks::Quirks::half_scroll)
+    /// quirk.
+    fn scroll(&mut self, scroll: fn(&mut Canvas, u8), n: u8) {
+        let n = if self.quirks.half_scroll && self.resolution() == Resolution::LORES {
+            n / 2
+        } else {
+            n
+        };
+        scroll(&mut self.screen, n);
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#dxyn-display>
+    fn draw_sprite(&mut self, vx: usize, vy: usize, height: u8) {
+        span!("draw");
+        let Resolution {
+            width,
+            height: rows,
+        } = self.resolution();
+        let x = u16::from(self.registers[vx]) % width;
+        let y = u16::from(self.registers[vy]) % rows;
+        trace!(target: targets::DISPLAY, "x: {x} y: {y} height: {height}");
+        self.drew = true;
+        self.registers[0xF] = 0;
+        // Each selected plane is drawn from the rows after the last one's.
+        let planes: Vec<usize> = self.screen.planes().collect();
+        for (n, plane) in planes.into_iter().enumerate() {
+            for (idx, y) in (y..y + u16::from(height)).enumerate() {
+                let Some(address) = self.address(n * usize::from(height) + idx) else {
+                    return;
+                };
+                if self.screen.draw(plane, x, y, self.memory[address]) {
+                    self.registers[0xF] = 1;
+                }
+                if y >= rows - 1 {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#ex9e-and-exa1-skip-if-key>
+    /// Waits for a key to be pressed and released by re-executing the
+    /// instruction until it is.
+    fn get_key(&mut self, vx: usize) {
+        if let KeyWait::Released(key) = self.key_wait {
+            self.registers[vx] = key;
+            self.key_wait = KeyWait::Idle;
+            trace!(target: targets::INPUT, "Stored key {key:01X} in register V{vx:01X}");
+        } else {
+            self.key_wait = KeyWait::Waiting;
+            self.pc -= 2;
+        }
+    }
+
+    /// <https://tobiasvl.github.io/blog/write-a-chip-8-emulator/#ex9e-and-exa1-skip-if-key>
+    fn skip_key(&mut self, vx: usize, press: bool) {
+        let key = self.registers[vx] & 0xF;
+        let pressed = self.keypad[usize::from(key)];
+        trace!(target: targets::INPUT, "Key {key:01X} [pressed: {pressed}]");
+        if pressed == press {
+            self.pc += 2;
+            trace!(target: targets::INPUT, "Incremented PC by 2");
+        }
+    }
+}
+
+/// A message to the thread running the interpreter, handled at the start of
+/// the next frame.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A key was pressed or released.
+    Key(input::KeyEvent),
+    /// Replaces the running ROM, as [`Interpreter::swap_rom`] does.
+    SwapRom {
+        /// The new ROM.
+        rom: Vec<u8>,
+        /// What to keep from the old ROM.
+        mode: SwapMode,
+    },
+    /// Restores the power-on state, keeping the ROM, as
+    /// [`Interpreter::reset`] does.
+    Reset,
+    /// The window gained focus (`true`) or lost it, for pausing while it
+    /// isn't played. Silences the beep while paused.
+    Focus(bool),
+    /// Shows the on-screen debug overlay, or hides it if it is shown.
+    ToggleHud,
+    /// Applies settings changed in the settings panel.
+    Settings(settings::Settings),
+    /// Shows the lines of the settings panel over the screen, or hides it.
+    Panel(Option<Vec<String>>),
+    /// Shows the on-screen keypad over the screen, or hides it.
+    Keypad(Option<keypad::Overlay>),
+    /// Changes how the screen is scaled to fill the window, and redraws it,
+    /// e.g. after the window is resized.
+    Rescale(screen::Scaling),
+    /// Starts pressing a key over and over, as the turbo modifier was
+    /// pressed, or stops when it was let go of.
+    Turbo(Option<input::Turbo>),
+    /// Pauses in frame-advance mode, or resumes if already in it.
+    ToggleFrameAdvance,
+    /// Runs a single frame, in frame-advance mode: a frame's worth of
+    /// instructions and one timer tick.
+    AdvanceFrame,
+    /// A request from the control server.
+    #[cfg(feature = "gui")]
+    Control(control::Request),
+    /// Restores memory saved for the ROM just loaded, and saves it when the
+    /// ROM stops, as [`Interpreter::with_save`] does.
+    #[cfg(feature = "gui")]
+    Save(save::Save),
+    /// Stops the interpreter, as the window was closed.
+    Shutdown,
+}
+
+/// What [`Interpreter::swap_rom`] keeps from the old ROM.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum SwapMode {
+    /// Nothing: the new ROM starts from a blank screen.
+    #[default]
+    Reset,
+    /// The screen, until the new ROM draws over it.
+    PreserveDisplay,
+}
+
+/// Information about a completed frame, passed to the hooks registered with
+/// [`Interpreter::on_frame`].
+#[derive(Debug)]
+pub struct FrameInfo<'a> {
+    /// The frame number, starting from `0`.
+    pub frame: u64,
+    /// The size of the screen at the end of the frame.
+    pub resolution: Resolution,
+    /// The rows of the screen at the end of the frame, top to bottom, packed
+    /// as [`View::rows`](screen::View::rows) are.
+    pub framebuffer: &'a [u64],
+    /// The value of the delay timer after the frame's tick.
+    pub delay: u8,
+    /// The value of the sound timer after the frame's tick.
+    pub sound: u8,
+    /// What the interpreter has done so far, this frame included.
+    pub counters: Counters,
+}
+
+/// Running totals of what an [`Interpreter`] has done since it was created.
+/// Unlike its instruction count, they carry on across ROM swaps and resets.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Counters {
+    /// Instructions executed.
+    pub instructions: u64,
+    /// Frames run, each of which ticks the timers once.
+    pub frames: u64,
+    /// Timer ticks that counted the delay or sound timer down.
+    pub timer_ticks: u64,
+    /// Key presses and releases applied, from any source of input.
+    pub key_events: u64,
+}
+
+/// An error raised by an instruction that can't be executed, or that the
+/// interpreter's [`Quirks`](quirks::Quirks) don't allow, which stops
+/// execution.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Fault {
+    /// The instruction isn't one the interpreter knows.
+    UnknownOpcode {
+        /// The address of the instruction.
+        pc: usize,
+        /// The instruction.
+        opcode: u16,
+    },
+    /// Memory was read or written through `I` past the end of memory.
+    IndexOutOfBounds {
+        /// The address of the faulting instruction.
+        pc: usize,
+        /// The value of `I`.
+        i: u16,
+        /// The address that was accessed.
+        address: usize,
+    },
+    /// `2NNN` was called with the stack already at its
+    /// [depth limit](quirks::Quirks::stack_depth).
+    StackOverflow {
+        /// The address of the instruction.
+        pc: usize,
+        /// The number of return addresses on the stack.
+        depth: usize,
+    },
+    /// `00EE` returned with nothing on the stack.
+    StackUnderflow {
+        /// The address of the instruction.
+        pc: usize,
+    },
+    /// An instruction was fetched from past the end of memory.
+    PcOutOfBounds {
+        /// The PC.
+        pc: usize,
+    },
+    /// The ROM is bigger than the memory it's loaded into, so only part of
+    /// it was loaded.
+    RomTooLarge {
+        /// The ROM's size, in bytes.
+        size: usize,
+        /// The number of bytes that fit.
+        capacity: usize,
+    },
+    /// `0BNN` selected a bank past the end of the ROM.
+    NoSuchBank {
+        /// The address of the instruction.
+        pc: usize,
+        /// The bank selected.
+        bank: usize,
+        /// The number of banks the ROM has.
+        count: usize,
+    },
+}
+
+impl Fault {
+    /// The address of the faulting instruction.
+    #[must_use]
+    pub fn pc(&self) -> usize {
+        match self {
+            Self::UnknownOpcode { pc, .. }
+            | Self::IndexOutOfBounds { pc, .. }
+            | Self::StackOverflow { pc, .. }
+            | Self::StackUnderflow { pc }
+            | Self::PcOutOfBounds { pc }
+            | Self::NoSuchBank { pc, .. } => *pc,
+            Self::RomTooLarge { .. } => Interpreter::MEMORY_OFFSET,
+        }
+    }
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownOpcode { pc, opcode } => {
+                write!(f, "Unknown opcode {opcode:04X} at {pc:#05X}")
+            }
+            Self::IndexOutOfBounds { pc, i, address } => write!(
+                f,
+                "The instruction at {pc:#05X} accessed memory out of bounds at {address:#05X} (I = {i:#05X})"
+            ),
+            Self::StackOverflow { pc, depth } => write!(
+                f,
+                "Stack overflow at {pc:#05X}: a subroutine call with {depth} calls already nested"
+            ),
+            Self::StackUnderflow { pc } => write!(
+                f,
+                "Stack underflow at {pc:#05X}: a return with no subroutine call to return from"
+            ),
+            Self::PcOutOfBounds { pc } => write!(
+                f,
+                "The PC moved out of bounds to {pc:#05X}, past the end of memory"
+            ),
+            Self::RomTooLarge { size, capacity } => write!(
+                f,
+                "The ROM is {size} bytes, but only {capacity} fit in memory (try --banking)"
+            ),
+            Self::NoSuchBank { pc, bank, count } => write!(
+                f,
+                "The instruction at {pc:#05X} selected bank {bank}, but the ROM has {count} bank(s)"
+            ),
+        }
+    }
+}
+
+impl core::error::Error for Fault {}
+
+/// A callback registered with [`Interpreter::on_frame`].
+pub(crate) type FrameHook = Box<dyn FnMut(&FrameInfo) + Send + Sync>;
+
+/// The frame hooks attached to an interpreter.
+#[derive(Default)]
+pub(crate) struct FrameHooks(Vec<FrameHook>);
+
+impl fmt::Debug for FrameHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "FrameHooks({})", self.0.len())
+    }
+}
+
+/// The on-screen debug overlay toggled with `F3`, along with the frame and
+/// instruction rates it shows, measured over about a second.
+#[derive(Debug)]
+pub(crate) struct Hud {
+    since: Duration, // when the current measurement started, by the clock
+    frames: u64,     // frames run since then
+    cycles: u64,     // the interpreter's instruction count then
+    fps: u64,        // frames per second in the last measurement
+    ips: u64,        // instructions per second in the last measurement
+}
+
+impl Hud {
+    fn new(now: Duration, cycles: u64) -> Self {
+        Self {
+            since: now,
+            frames: 0,
+            cycles,
+            fps: 0,
+            ips: 0,
+        }
+    }
+
+    /// Counts a frame, given the time by the clock and the interpreter's
+    /// instruction count at its end, and updates the rates once a second
+    /// has passed.
+    fn update(&mut self, now: Duration, cycles: u64) {
+        self.frames += 1;
+        let elapsed = now.saturating_sub(self.since);
+        if elapsed >= Duration::from_secs(1) {
+            let millis = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+            self.fps = self.frames * 1000 / millis;
+            self.ips = cycles.saturating_sub(self.cycles) * 1000 / millis;
+            *self = Self {
+                fps: self.fps,
+                ips: self.ips,
+                ..Self::new(now, cycles)
+            };
+        }
+    }
+}
+
+/// The CHIP-8 screen, independent of any window: a bit a pixel, packed into
+/// [`Resolution::stride`] `u64`s a row with the leftmost pixel in the
+/// highest bit, in [`screen::PLANES`] planes, as XO-CHIP has. Sprites are
+/// drawn to, and the screen cleared in, the planes selected; ROMs that never
+/// select any others only use the first. Rows are marked dirty as they
+/// change, so a display only has to redraw those.
+#[derive(Clone, Debug)]
+pub(crate) struct Canvas {
+    resolution: Resolution,
+    rows: Vec<u64>,  // `stride` words a row, top to bottom, plane after plane
+    selected: u8,    // The planes drawn to and cleared, a bit a plane
+    dirty: Vec<u64>, // A bit a row, set for rows changed since they were last shown
+}
+
+impl Default for Canvas {
+    fn default() -> Self {
+        Self::new(Resolution::LORES)
+    }
+}
+
+impl Canvas {
+    /// A blank screen of `resolution`, with the first plane selected and
+    /// every row dirty.
+    fn new(resolution: Resolution) -> Self {
+        let height = usize::from(resolution.height);
+        let mut canvas = Self {
+            resolution,
+            rows: vec![0; resolution.stride() * height * screen::PLANES],
+            selected: 1,
+            dirty: vec![0; height.div_ceil(64)],
+        };
+        canvas.touch();
+        canvas
+    }
+
+    /// The size of the screen.
+    pub(crate) fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// The planes selected, a bit a plane.
+    fn selected(&self) -> u8 {
+        self.selected
+    }
+
+    /// Selects the planes `planes`, a bit a plane, to be drawn to and
+    /// cleared. Bits past the last plane are ignored.
+    fn select(&mut self, planes: u8) {
+        self.selected = planes & !(u8::MAX << screen::PLANES);
+    }
+
+    /// The selected planes, first to last.
+    fn planes(&self) -> impl Iterator<Item = usize> {
+        let selected = self.selected;
+        (0..screen::PLANES).filter(move |plane| selected >> plane & 1 == 1)
+    }
+
+    /// The `u64`s of each plane.
+    fn plane_len(&self) -> usize {
+        self.resolution.stride() * usize::from(self.resolution.height)
+    }
+
+    /// Flips the pixels of row `y` of `plane` lit in `sprite`, a row of a
+    /// sprite drawn with its leftmost pixel at `x`. Pixels past the right
+    /// edge are clipped. Returns whether any pixel was turned off.
+    fn draw(&mut self, plane: usize, x: u16, y: u16, sprite: u8) -> bool {
+        let (x, y) = (usize::from(x), usize::from(y));
+        let (width, stride) = (usize::from(self.resolution.width), self.resolution.stride());
+        let start = plane * self.plane_len() + y * stride;
+        // The sprite's row spills into the next word unless it's aligned.
+        let (shift, sprite) = (x % 64, u64::from(sprite));
+        let spill = if shift > 56 {
+            sprite << (120 - shift)
+        } else {
+            0
+        };
+        let mut collision = false;
+        for (word, bits) in [(x / 64, sprite << 56 >> shift), (x / 64 + 1, spill)] {
+            let on = width.saturating_sub(word * 64).min(64);
+            let visible = u64::MAX
+                .checked_shl(u32::try_from(64 - on).unwrap_or(64))
+                .unwrap_or(0);
+            let bits = bits & visible;
+            if bits == 0 {
+                continue;
+            }
+            let row = &mut self.rows[start + word];
+            collision |= *row & bits != 0;
+            *row ^= bits;
+            self.mark(y);
+        }
+        collision
+    }
+
+    /// Turns every pixel of the selected planes off, marking the rows that
+    /// were lit dirty.
+    fn clear(&mut self) {
+        let (stride, len) = (self.resolution.stride(), self.plane_len());
+        for plane in self.planes().collect::<Vec<_>>() {
+            for y in 0..usize::from(self.resolution.height) {
+                let start = plane * len + y * stride;
+                let row = &mut self.rows[start..start + stride];
+                if row.iter().any(|&word| word != 0) {
+                    row.fill(0);
+                    self.mark(y);
+                }
+            }
+        }
+    }
+
+    /// Moves the selected planes down by `n` pixels.
+    fn scroll_down(&mut self, n: u8) {
+        self.scroll(0, isize::from(n));
+    }
+
+    /// Moves the selected planes up by `n` pixels.
+    fn scroll_up(&mut self, n: u8) {
+        self.scroll(0, -isize::from(n));
+    }
+
+    /// Moves the selected planes right by `n` pixels.
+    fn scroll_right(&mut self, n: u8) {
+        self.scroll(isize::from(n), 0);
+    }
+
+    /// Moves the selected planes left by `n` pixels.
+    fn scroll_left(&mut self, n: u8) {
+        self.scroll(-isize::from(n), 0);
+    }
+
+    /// Moves the selected planes `dx` pixels right and `dy` down, either of
+    /// which may be negative. Pixels moved off the screen are lost, those
+    /// moved onto it are off, and the rows that change are marked dirty.
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        let (stride, len) = (self.resolution.stride(), self.plane_len());
+        let (width, height) = (
+            usize::from(self.resolution.width),
+            usize::from(self.resolution.height),
+        );
+        for plane in self.planes().collect::<Vec<_>>() {
+            let old = self.rows[plane * len..(plane + 1) * len].to_vec();
+            for y in 0..height {
+                let start = plane * len + y * stride;
+                let from = y
+                    .checked_add_signed(-dy)
+                    .filter(|&from| from < height)
+                    .map(|from| &old[from * stride..(from + 1) * stride]);
+                for word in 0..stride {
+                    let on = width.saturating_sub(word * 64).min(64);
+                    let visible = u64::MAX
+                        .checked_shl(u32::try_from(64 - on).unwrap_or(64))
+                        .unwrap_or(0);
+                    let bits = from.map_or(0, |row| shifted(row, word, dx));
+                    self.rows[start + word] = bits & visible;
+                }
+                if self.rows[start..start + stride] != old[y * stride..(y + 1) * stride] {
+                    self.mark(y);
+                }
+            }
+        }
+    }
+
+    /// Whether the pixel at (`x`, `y`) is lit, in any plane.
+    pub(crate) fn get_at(&self, x: u16, y: u16) -> bool {
+        screen::lit(&self.rows, self.resolution, usize::from(x), usize::from(y))
+    }
+
+    /// Every row, top to bottom, plane after plane.
+    pub(crate) fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    /// The rows changed since they were last shown, a bit a row.
+    fn dirty(&self) -> &[u64] {
+        &self.dirty
+    }
+
+    /// Whether any row changed since the screen was last shown.
+    fn is_dirty(&self) -> bool {
+        self.dirty.iter().any(|&word| word != 0)
+    }
+
+    /// Marks row `y` dirty.
+    fn mark(&mut self, y: usize) {
+        self.dirty[y / 64] |= 1 << (y % 64);
+    }
+
+    /// Marks every row dirty, for when whatever is drawn over them changes.
+    fn touch(&mut self) {
+        let height = usize::from(self.resolution.height);
+        for (n, word) in self.dirty.iter_mut().enumerate() {
+            *word = u64::MAX >> (64 - (height - n * 64).min(64));
+        }
+    }
+
+    /// Marks every row as shown.
+    fn settle(&mut self) {
+        self.dirty.fill(0);
+    }
+}
+
+/// Word `word` of `row` moved `dx` pixels right, or left if `dx` is
+/// negative, with the pixels moved in off.
+fn shifted(row: &[u64], word: usize, dx: isize) -> u64 {
+    let at = |n: isize| {
+        usize::try_from(n)
+            .ok()
+            .and_then(|n| row.get(n))
+            .copied()
+            .unwrap_or(0)
+    };
+    // The pixel that ends up leftmost in the word, and where it comes from.
+    let first = isize::try_from(word * 64).unwrap_or(isize::MAX) - dx;
+    let (from, shift) = (
+        first.div_euclid(64),
+        u32::try_from(first.rem_euclid(64)).unwrap_or(0),
+    );
+    at(from) << shift | at(from + 1).checked_shr(64 - shift).unwrap_or(0)
+}
+
+/// The CHIP-8 delay and sound timers.
+#[derive(Debug, Default)]
+pub(crate) struct Timers {
+    pub(crate) delay: u8,
+    pub(crate) sound: u8,
+}
+
+impl Timers {
+    /// Updates the timers, decrementing both by one if
+    /// greater than 0. The buzzer sounds as long as the sound
+    /// timer is greater than 0.
+    pub(crate) fn update(&mut self) {
+        if self.delay > 0 {
+            self.delay -= 1;
+        }
+        if self.sound > 0 {
+            self.sound -= 1;
+        }
+        trace!(
+            target: targets::TIMER,
+            "Updated timers: [sound: {}] [delay: {}]",
+            self.sound,
+            self.delay
+        );
+    }
+}
+
+/// Turbo, while its modifier is held.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub(crate) struct Autofire {
+    turbo: input::Turbo, // The key pressed, and how often
+    frames: u64,         // Frames since the modifier was pressed
+    down: bool,          // Whether the key is pressed now
+}
+
+/// The progress of an FX0A instruction waiting for a key.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum KeyWait {
+    /// No FX0A is waiting.
+    #[default]
+    Idle,
+    /// An FX0A is waiting for a key to be released.
+    Waiting,
+    /// The key which was released while an FX0A was waiting.
+    Released(u8),
+}
+
+/// A seedable random number generator. Seeded from entropy by default with
+/// `std`, and from `0` without it; the seed is kept so a run can be
+/// reproduced later.
+#[derive(Debug)]
+pub(crate) struct Random {
+    seed: u64,
+    generator: Generator,
+}
+
+/// Where [`Random`] draws its numbers from.
+enum Generator {
+    /// A generator seeded from the seed.
+    Seeded(Box<StdRng>),
+    /// One attached with [`Interpreter::with_rng`], which ignores the seed.
+    Attached(Box<dyn RngCore + Send>),
+}
+
+impl fmt::Debug for Generator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Seeded(_) => write!(f, "Seeded"),
+            Self::Attached(_) => write!(f, "Attached"),
+        }
+    }
+}
+
+impl Random {
+    fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            generator: Generator::Seeded(Box::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+
+    /// Starts the numbers over from the seed, unless a generator is attached.
+    fn reseed(&mut self) {
+        if let Generator::Seeded(generator) = &mut self.generator {
+            **generator = StdRng::seed_from_u64(self.seed);
+        }
+    }
+
+    /// The next random byte.
+    fn byte(&mut self) -> u8 {
+        match &mut self.generator {
+            Generator::Seeded(generator) => generator.gen(),
+            Generator::Attached(generator) => generator.gen(),
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        #[cfg(feature = "std")]
+        let seed = rand::thread_rng().gen();
+        #[cfg(not(feature = "std"))]
+        let seed = 0;
+        Self::new(seed)
+    }
+}
+
+wrapper! {
+    /// The CHIP-8 memory buffer.
+    Memory => Interpreter::MEMORY_SIZE,
+    /// The CHIP-8 registers.
+    RegisterArray => Interpreter::REGISTER_COUNT
+}
+
+/// Helper functions for bit operations.
+mod bits {
+    /// Returns a bool indicating whether the bit at index n is set.
+    /// Bits are indexed from the least-significant bit to the
+    /// most-significant bit.
+    pub const fn set(n: u8, bits: u8) -> bool {
+        (bits & (1 << n)) != 0
+    }
+}
+
+/// Returns the digit at index `i` in the number `n`. Numbers are indexed from
+/// least-significant to most-significant.
+fn digit(i: u32, n: usize) -> usize {
+    (n / (10usize.pow(i))) % 10
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Mutex,
+    };
+
+    #[test]
+    fn seeded_random() {
+        let sequence = |seed| {
+            let mut intr = Interpreter::new();
+            intr.with_seed(seed);
+            intr.load_rom(&[]);
+            (0..16)
+                .map(|_| {
+                    intr.random(0, 0xFF);
+                    intr.registers[0]
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(sequence(42), sequence(42));
+        assert_ne!(sequence(42), sequence(43));
+    }
+
+    #[test]
+    fn attached_rng() {
+        let mut intr = Interpreter::new();
+        intr.with_rng(rand::rngs::mock::StepRng::new(0x2A, 1));
+        intr.load_rom(&[]);
+        intr.random(0, 0xFF);
+        intr.random(1, 0x0F);
+        assert_eq!(intr.registers[..2], [0x2A, 0x0B]);
+    }
+
+    #[test]
+    fn frame_hooks() {
+        let frames = Arc::new(Mutex::new(Vec::new()));
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[]);
+        intr.timers.delay = 3;
+        let seen = Arc::clone(&frames);
+        intr.on_frame(move |info| seen.lock().unwrap().push((info.frame, info.delay)));
+        for frame in 0..2 {
+            intr.timers.update();
+            intr.end_frame(frame);
+        }
+        assert_eq!(*frames.lock().unwrap(), vec![(0, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn buzzer() {
+        let (tx, rx) = mpsc::channel();
+        let mut intr = Interpreter::new();
+        intr.with_ips(120);
+        // V0 = 2, ST = V0, loop.
+        intr.load_rom(&[0x60, 0x02, 0xF0, 0x18, 0x12, 0x04]);
+        intr.with_buzzer(move |on| tx.send(on).unwrap());
+        for frame in 0..3 {
+            intr.frame(frame).unwrap();
+        }
+        assert_eq!(rx.try_iter().collect::<Vec<_>>(), [true, false, false]);
+    }
+
+    #[test]
+    fn wait_for_key() {
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[0xF3, 0x0A]); // FX0A
+        intr.step();
+        assert_eq!(intr.pc, Interpreter::MEMORY_OFFSET);
+        intr.handle_key(input::KeyEvent {
+            key: 0x5,
+            pressed: true,
+        });
+        intr.step();
+        assert_eq!(intr.pc, Interpreter::MEMORY_OFFSET);
+        intr.handle_key(input::KeyEvent {
+            key: 0x5,
+            pressed: false,
+        });
+        intr.step();
+        assert_eq!(intr.pc, Interpreter::MEMORY_OFFSET + 2);
+        assert_eq!(intr.registers[3], 0x5);
+    }
+
+    #[test]
+    fn replayed_keys() {
+        let mut intr = Interpreter::new();
+        // 6007: V0 = 7, E09E: skip if key V0 pressed, repeated.
+        intr.load_rom(&[0x60, 0x07, 0xE0, 0x9E, 0x00, 0x00, 0xE0, 0x9E]);
+        intr.with_replay(VecDeque::from([replay::Entry {
+            cycle: 3,
+            event: input::KeyEvent {
+                key: 0x7,
+                pressed: true,
+            },
+        }]));
+        for _ in 0..3 {
+            intr.replay_input();
+            intr.step();
+        }
+        assert_eq!(intr.pc, 0x206);
+        intr.replay_input();
+        intr.step();
+        assert_eq!(intr.pc, 0x20A);
+    }
+
+    #[test]
+    fn banking() {
+        // JP 0x800, padding, then two banks: bank 0 selects bank 1, which
+        // continues at the next instruction and selects a bank that doesn't
+        // exist.
+        let mut rom = vec![0x18, 0x00];
+        rom.resize(0x600, 0);
+        rom.extend([0x0B, 0x01]);
+        rom.resize(0x600 + bank::BANK_SIZE, 0);
+        rom.extend([0x00, 0x00, 0x61, 0x2A, 0x0B, 0x05]);
+
+        let mut intr = Interpreter::new();
+        intr.with_banking();
+        intr.load_rom(&rom);
+        assert_eq!(intr.run_instructions(3), Ok(()));
+        assert_eq!(intr.registers[1], 0x2A);
+        assert_eq!(
+            intr.run_instructions(1),
+            Err(Fault::NoSuchBank {
+                pc: 0x804,
+                bank: 5,
+                count: 2
+            })
+        );
+    }
+
+    #[test]
+    fn pc_overflow() {
+        // JP 0xFFE, where V0 = 1 then wraps around to a JP 0xFFE at 0x000.
+        let mut rom = vec![0x1F, 0xFE];
+        rom.resize(0xDFE, 0);
+        rom.extend([0x60, 0x01]);
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.memory[0x000..0x002].copy_from_slice(&[0x1F, 0xFE]);
+        assert_eq!(intr.run_instructions(3), Ok(()));
+        assert_eq!(intr.registers[0], 1);
+        assert_eq!(intr.pc, 0xFFE);
+
+        intr.with_quirks(quirks::Quirks {
+            pc_overflow: quirks::PcOverflow::Fault,
+            ..quirks::Quirks::default()
+        });
+        intr.load_rom(&rom);
+        assert_eq!(
+            intr.run_instructions(3),
+            Err(Fault::PcOutOfBounds { pc: 0x1000 })
+        );
+    }
+
+    #[test]
+    fn rom_too_large() {
+        let rom = vec![0x12; Interpreter::ROM_CAPACITY + 1];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        assert_eq!(intr.memory[0xFFF], 0x12);
+        assert_eq!(
+            intr.run_instructions(1),
+            Err(Fault::RomTooLarge {
+                size: 0xE01,
+                capacity: 0xE00
+            })
+        );
+        assert_eq!(intr.cycles, 0);
+    }
+
+    #[test]
+    fn jump_offset() {
+        // V0 = 2, V3 = 4, JP V0, 0x310
+        let rom = [0x60, 0x02, 0x63, 0x04, 0xB3, 0x10];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.run_instructions(3).unwrap();
+        assert_eq!(intr.pc, 0x312);
+
+        intr.with_quirks(quirks::Quirks {
+            jump_offset: quirks::JumpOffset::Vx,
+            ..quirks::Quirks::default()
+        });
+        intr.load_rom(&rom);
+        intr.run_instructions(3).unwrap();
+        assert_eq!(intr.pc, 0x314);
+    }
+
+    #[test]
+    fn display_wait() {
+        // DRW V0, V0, 1, then JP 0x200.
+        let mut intr = Interpreter::new();
+        intr.with_timing(timing::Speed::Vip.timing());
+        intr.with_quirks(quirks::Quirks {
+            display_wait: true,
+            ..quirks::Quirks::default()
+        });
+        intr.load_rom(&[0xD0, 0x01, 0x12, 0x00]);
+        intr.frame(0).unwrap();
+        assert_eq!(intr.cycles, 1);
+        intr.frame(1).unwrap();
+        assert_eq!(intr.cycles, 3);
+    }
+
+    #[test]
+    fn hud() {
+        let mut intr = Interpreter::new();
+        // V3 = 0x2A, I = 0x2F0
+        intr.load_rom(&[0x63, 0x2A, 0xA2, 0xF0]);
+        intr.run_instructions(2).unwrap();
+        let mut hud = Hud::new(Duration::ZERO, 0);
+        hud.update(Duration::from_millis(16), intr.cycles);
+        let lines = intr.hud_lines(&hud);
+        assert_eq!(lines[0], "FPS 0 IPS 0");
+        assert_eq!(lines[1], "PC 204 I 2F0");
+        assert_eq!(lines[3], "V0 00 V1 00 V2 00 V3 2A");
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn stack_overflow() {
+        let mut intr = Interpreter::new();
+        intr.with_quirks(quirks::Quirks {
+            stack_depth: 2,
+            ..quirks::Quirks::default()
+        });
+        // CALL 0x202, CALL 0x204, CALL 0x206
+        intr.load_rom(&[0x22, 0x02, 0x22, 0x04, 0x22, 0x06]);
+        assert_eq!(
+            intr.run_instructions(3),
+            Err(Fault::StackOverflow {
+                pc: 0x204,
+                depth: 2
+            })
+        );
+        assert_eq!(intr.stack, [0x202, 0x204]);
+    }
+
+    #[test]
+    fn stack_underflow() {
+        let mut intr = Interpreter::new();
+        // CALL 0x204, RET, RET
+        intr.load_rom(&[0x22, 0x04, 0x00, 0xEE, 0x00, 0xEE]);
+        assert_eq!(
+            intr.run_instructions(3),
+            Err(Fault::StackUnderflow { pc: 0x202 })
+        );
+        assert!(intr.stack.is_empty());
+    }
+
+    #[test]
+    fn index_overflow_masks() {
+        let mut intr = Interpreter::new();
+        // V0 = 3, V1 = 2, I = 0xFFF, I += V1, store V0..=V1.
+        intr.load_rom(&[0x60, 0x03, 0x61, 0x02, 0xAF, 0xFF, 0xF1, 0x1E, 0xF1, 0x55]);
+        for _ in 0..4 {
+            intr.step();
+        }
+        assert_eq!(intr.i, 0x001);
+        assert_eq!(intr.registers[0xF], 0);
+        intr.step();
+        assert_eq!(intr.fault, None);
+        assert_eq!(intr.memory[0x001..0x003], [0x03, 0x02]);
+    }
+
+    /// An interpreter following `quirks`, with nothing loaded.
+    fn with_quirks(quirks: quirks::Quirks) -> Interpreter {
+        let mut intr = Interpreter::new();
+        intr.with_quirks(quirks);
+        intr.load_rom(&[]);
+        intr
+    }
+
+    /// Runs `opcode`, a `8XYN` or `FX1E` instruction with `X` = 1 or `F`
+    /// and `Y` = 2, with `V1` = `a`, `V2` = `b`, `VF` = `0xAA`, and
+    /// `I` = `0xFFF`, and returns `VX` and `VF` afterwards.
+    fn flagged(intr: &mut Interpreter, opcode: u16, a: u8, b: u8) -> (u8, u8) {
+        intr.pc = Interpreter::MEMORY_OFFSET;
+        intr.registers[1] = a;
+        intr.registers[2] = b;
+        intr.registers[0xF] = 0xAA;
+        intr.i = 0xFFF;
+        intr.execute_opcode(opcode).unwrap();
+        let x = usize::from(opcode >> 8 & 0xF);
+        (intr.registers[x], intr.registers[0xF])
+    }
+
+    #[test]
+    fn arithmetic_flags() {
+        let mut intr = with_quirks(quirks::Quirks::default());
+        for a in 0..=u8::MAX {
+            for b in 0..=u8::MAX {
+                let (sum, carry) = a.overflowing_add(b);
+                assert_eq!(flagged(&mut intr, 0x8124, a, b), (sum, u8::from(carry)));
+                let borrow = |lhs: u8, rhs| (lhs.wrapping_sub(rhs), u8::from(lhs >= rhs));
+                assert_eq!(flagged(&mut intr, 0x8125, a, b), borrow(a, b));
+                assert_eq!(flagged(&mut intr, 0x8127, a, b), borrow(b, a));
+            }
+            assert_eq!(flagged(&mut intr, 0x8126, a, 0), (a >> 1, a & 1));
+            assert_eq!(flagged(&mut intr, 0x812E, a, 0), (a << 1, a >> 7));
+        }
+    }
+
+    #[test]
+    fn flag_wins_as_destination() {
+        let mut intr = with_quirks(quirks::Quirks::default());
+        // VF = 0xAA to start with.
+        assert_eq!(flagged(&mut intr, 0x8F24, 0, 0x60), (1, 1));
+        assert_eq!(flagged(&mut intr, 0x8F25, 0, 0x0A), (1, 1));
+        assert_eq!(flagged(&mut intr, 0x8F25, 0, 0xAB), (0, 0));
+        assert_eq!(flagged(&mut intr, 0x8F27, 0, 0xAB), (1, 1));
+        assert_eq!(flagged(&mut intr, 0x8F26, 0, 0), (0, 0));
+        assert_eq!(flagged(&mut intr, 0x8F2E, 0, 0), (1, 1));
+        assert_eq!(flagged(&mut intr, 0x8F21, 0, 0x01), (0xAB, 0xAB));
+    }
+
+    #[test]
+    fn logic_flags() {
+        let mut intr = with_quirks(quirks::Quirks::default());
+        let mut vf_reset = with_quirks(quirks::Quirks {
+            vf_reset: true,
+            ..quirks::Quirks::default()
+        });
+        for (opcode, result) in [(0x8121, 0b1110), (0x8122, 0b1000), (0x8123, 0b0110)] {
+            let (a, b) = (0b1100, 0b1010);
+            assert_eq!(flagged(&mut intr, opcode, a, b), (result, 0xAA));
+            assert_eq!(flagged(&mut vf_reset, opcode, a, b), (result, 0));
+        }
+        assert_eq!(flagged(&mut intr, 0x8120, 1, 2), (2, 0xAA));
+        assert_eq!(flagged(&mut vf_reset, 0x8120, 1, 2), (2, 0xAA));
+    }
+
+    #[test]
+    fn shift_flags() {
+        let mut vy = with_quirks(quirks::Quirks {
+            shift: quirks::Shift::Vy,
+            ..quirks::Quirks::default()
+        });
+        for b in 0..=u8::MAX {
+            assert_eq!(flagged(&mut vy, 0x8126, 0x5A, b), (b >> 1, b & 1));
+            assert_eq!(flagged(&mut vy, 0x812E, 0x5A, b), (b << 1, b >> 7));
+        }
+        // With VX, VY is left out of it.
+        let mut vx = with_quirks(quirks::Quirks::default());
+        assert_eq!(flagged(&mut vx, 0x8126, 0x81, 0xFF), (0x40, 1));
+        assert_eq!(flagged(&mut vx, 0x812E, 0x7F, 0xFF), (0xFE, 0));
+    }
+
+    #[test]
+    fn index_flags() {
+        let mut intr = with_quirks(quirks::Quirks::default());
+        let mut carry = with_quirks(quirks::Quirks {
+            index_carry: true,
+            ..quirks::Quirks::default()
+        });
+        // I = 0xFFF to start with.
+        assert_eq!(flagged(&mut intr, 0xF11E, 1, 0).1, 0xAA);
+        assert_eq!(flagged(&mut carry, 0xF11E, 0, 0).1, 0);
+        assert_eq!(flagged(&mut carry, 0xF11E, 1, 0).1, 1);
+    }
+
+    #[test]
+    fn index_overflow_faults() {
+        let mut intr = Interpreter::new();
+        intr.with_quirks(quirks::Quirks {
+            index_overflow: quirks::IndexOverflow::Fault,
+            index_carry: true,
+            ..quirks::Quirks::default()
+        });
+        // V0 = 1, I = 0xFFF, I += V0, store V0.
+        intr.load_rom(&[0x60, 0x01, 0xAF, 0xFF, 0xF0, 0x1E, 0xF0, 0x55]);
+        assert_eq!(
+            intr.frame(0),
+            Err(Fault::IndexOutOfBounds {
+                pc: 0x206,
+                i: 0x1000,
+                address: 0x1000,
+            })
+        );
+        assert_eq!(intr.registers[0xF], 1);
+
+        // Accesses that stay in bounds are unaffected.
+        intr.load_rom(&[0x60, 0x01, 0xAF, 0xFE, 0xF0, 0x1E, 0xF0, 0x65]);
+        for _ in 0..4 {
+            intr.step();
+        }
+        assert_eq!(intr.i, 0xFFF);
+        assert_eq!(intr.fault, None);
+    }
+
+    #[test]
+    fn framebuffer() {
+        let mut intr = Interpreter::new();
+        // V0 = 1, I = font character V0, draw it at (V1, V1) = (0, 0).
+        intr.load_rom(&[0x60, 0x01, 0xF0, 0x29, 0xD1, 0x15]);
+        assert_eq!(intr.framebuffer().lit(), 0);
+        intr.run_instructions(3).unwrap();
+        let frame = intr.framebuffer();
+        let ascii = frame.to_string();
+        let rows: Vec<&str> = ascii.lines().map(|row| &row[..4]).collect();
+        assert_eq!(rows[..6], ["..#.", ".##.", "..#.", "..#.", ".###", "...."]);
+        assert_eq!(frame.lit(), 8);
+    }
+
+    #[test]
+    fn self_modifying_code() {
+        // V0 = 0x62, V1 = 1, then store them over the first instruction,
+        // turning it into V2 = 1, and jump back to it.
+        let rom = [0x60, 0x62, 0x61, 0x01, 0xA2, 0x00, 0xF1, 0x55, 0x12, 0x00];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.run_instructions(6).unwrap();
+        assert_eq!(intr.decoded[0x200], Some(Opcode::Load { x: 2, nn: 1 }));
+        assert_eq!(intr.registers[..3], [0x62, 0x01, 0x01]);
+    }
+
+    #[test]
+    fn chip8x() {
+        let rom = [
+            0x60, 0x10, // V0 = 0x10: zones 0 and 1 across
+            0x61, 0x00, // V1 = 0: the first 4 rows
+            0x62, 0x04, // V2 = green
+            0xB0, 0x20, // color them with V2
+            0x52, 0x01, // V2 += V0, nibble by nibble
+            0xE3, 0xF2, // skip if key V3 is pressed on the second keypad
+            0x65, 0x05, // V5 = 5
+            0x66, 0x06, // V6 = 6
+        ];
+        let mut intr = Interpreter::new();
+        intr.with_chip8x();
+        intr.load_rom(&rom);
+        assert_eq!(intr.pc, chip8x::PROGRAM_START);
+        intr.handle_key(input::KeyEvent {
+            key: input::KEYPAD_2,
+            pressed: true,
+        });
+        intr.run_instructions(7).unwrap();
+        let colors = intr.colors.as_ref().unwrap();
+        assert_eq!(colors.foreground(15, 3), chip8x::COLORS[4]);
+        assert_eq!(colors.foreground(16, 3), chip8x::COLORS[1]);
+        assert_eq!(intr.registers[2], 0x14);
+        assert_eq!(intr.registers[5..7], [0, 6]);
+    }
+
+    #[test]
+    fn hires() {
+        let mut rom = vec![0; 0xC0];
+        rom[..2].copy_from_slice(&[0x12, 0x60]);
+        rom.extend([
+            0x61, 0x00, // V1 = 0
+            0x62, 0x28, // V2 = 40, below the usual screen
+            0xF1, 0x29, // I = the font character 0
+            0xD1, 0x25, // draw it at (V1, V2)
+            0x02, 0x30, // clear the screen
+        ]);
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        assert_eq!(intr.pc, 0x2C0);
+        intr.run_instructions(4).unwrap();
+        let frame = intr.framebuffer();
+        assert_eq!(frame.height(), 64);
+        assert!(frame.get(0, 40) && frame.get(0, 44));
+        intr.run_instructions(1).unwrap();
+        assert_eq!(intr.framebuffer().lit(), 0);
+
+        intr.load_rom(&[0x12, 0x00]);
+        assert_eq!(intr.pc, 0x200);
+        assert_eq!(intr.framebuffer().height(), 32);
+    }
+
+    #[test]
+    fn resolutions() {
+        // Draw the 8-wide sprite 0xFF at (V0, V1) = (124, 63).
+        let rom = [0x60, 0x7C, 0x61, 0x3F, 0xA2, 0x08, 0xD0, 0x11, 0xFF];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.set_resolution(Resolution::SUPER);
+        intr.run_instructions(4).unwrap();
+        let frame = intr.framebuffer();
+        assert_eq!((frame.width(), frame.height()), (128, 64));
+        // Only the 4 pixels left of the right edge are drawn.
+        assert_eq!(frame.lit(), 4);
+        assert!(frame.get(124, 63) && frame.get(127, 63));
+
+        // Sprites straddling two words of a row are drawn across both, and
+        // collide in either.
+        intr.load_rom(&rom);
+        intr.set_resolution(Resolution::MEGA);
+        (intr.pc, intr.registers[0]) = (0x202, 60);
+        intr.run_instructions(3).unwrap();
+        let frame = intr.framebuffer();
+        assert_eq!((frame.width(), frame.height()), (256, 192));
+        assert!((60..68).all(|x| frame.get(x, 63)) && frame.lit() == 8);
+        intr.pc = 0x206;
+        intr.registers[0] = 66;
+        intr.run_instructions(1).unwrap();
+        assert_eq!(intr.registers[0xF], 1);
+        let overlap = intr.framebuffer();
+        assert!(!overlap.get(66, 63) && !overlap.get(67, 63) && overlap.get(73, 63));
+        assert_eq!(overlap.lit(), 12);
+
+        // Switching resolution clears the screen.
+        intr.set_resolution(Resolution::LORES);
+        assert_eq!(
+            intr.framebuffer(),
+            frame::Frame::new(Resolution::LORES, vec![false; 2048])
+        );
+    }
+
+    #[test]
+    fn planes() {
+        // Draw a 1-row sprite in both planes: 0xF0 in the first, and 0x3C in
+        // the second.
+        let rom = [0xA2, 0x06, 0xD0, 0x01, 0x12, 0x04, 0xF0, 0x3C];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.select_planes(0b11);
+        intr.run_instructions(2).unwrap();
+        let pixels = |intr: &Interpreter| -> Vec<u8> {
+            (0..8)
+                .map(|x| screen::pixel(intr.screen.rows(), intr.resolution(), x, 0))
+                .collect()
+        };
+        assert_eq!(pixels(&intr), [1, 1, 3, 3, 2, 2, 0, 0]);
+        assert_eq!(intr.framebuffer().lit(), 6);
+
+        // Drawing again collides in either plane, and turns both off.
+        intr.pc = 0x202;
+        intr.run_instructions(1).unwrap();
+        assert_eq!(intr.registers[0xF], 1);
+        assert_eq!(pixels(&intr), [0; 8]);
+
+        // Clearing only clears the planes selected, and drawing to only the
+        // second takes its rows from I.
+        intr.pc = 0x202;
+        intr.run_instructions(1).unwrap();
+        intr.select_planes(0b10);
+        intr.execute_opcode(0x00E0).unwrap();
+        assert_eq!(pixels(&intr), [1, 1, 1, 1, 0, 0, 0, 0]);
+        intr.pc = 0x202;
+        intr.run_instructions(1).unwrap();
+        assert_eq!(pixels(&intr), [3, 3, 3, 3, 0, 0, 0, 0]);
+        assert_eq!(intr.registers[0xF], 0);
+    }
+
+    #[test]
+    fn scrolls() {
+        let lit = |canvas: &Canvas| -> Vec<(u16, u16)> {
+            let Resolution { width, height } = canvas.resolution();
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .filter(|&(x, y)| canvas.get_at(x, y))
+                .collect()
+        };
+        let row = |x: core::ops::Range<u16>, y| x.map(|x| (x, y)).collect::<Vec<_>>();
+        // A row of 8 pixels straddling the first two words of row 5.
+        let mut canvas = Canvas::new(Resolution::SUPER);
+        canvas.draw(0, 60, 5, 0xFF);
+        canvas.settle();
+        canvas.scroll_right(4);
+        assert_eq!(lit(&canvas), row(64..72, 5));
+        assert_eq!(canvas.dirty(), [1 << 5]);
+        canvas.scroll_left(8);
+        assert_eq!(lit(&canvas), row(56..64, 5));
+        canvas.settle();
+        canvas.scroll_down(3);
+        assert_eq!(lit(&canvas), row(56..64, 8));
+        assert_eq!(canvas.dirty(), [1 << 5 | 1 << 8]);
+        canvas.scroll_up(10);
+        assert!(lit(&canvas).is_empty());
+
+        // Pixels scrolled past the edges are lost.
+        canvas.draw(0, 124, 0, 0xF0);
+        canvas.draw(0, 0, 1, 0x80);
+        canvas.scroll_right(4);
+        canvas.scroll_left(8);
+        assert!(lit(&canvas).is_empty());
+
+        // Only the selected planes move.
+        canvas.draw(0, 0, 0, 0x80);
+        canvas.draw(1, 0, 0, 0x80);
+        canvas.select(0b10);
+        canvas.scroll_down(1);
+        assert_eq!(lit(&canvas), [(0, 0), (0, 1)]);
+    }
+
+    #[test]
+    fn scroll_opcodes() {
+        // Draw the 0 of the font at (8, 8).
+        let rom = [0x60, 0x08, 0xF0, 0x29, 0xD0, 0x05];
+        let mut full = Interpreter::new();
+        let mut half = Interpreter::new();
+        half.with_quirks(quirks::Quirks {
+            half_scroll: true,
+            ..quirks::Quirks::default()
+        });
+        for intr in [&mut full, &mut half] {
+            intr.load_rom(&rom);
+            intr.run_instructions(3).unwrap();
+            for opcode in [0x00C3, 0x00FB, 0x00FB, 0x00D1, 0x00FC] {
+                intr.execute_opcode(opcode).unwrap();
+            }
+        }
+        // Down 3, right 8, up 1, and left 4: the 0's top left corner moves
+        // to (12, 10), or only half as far with the quirk, where 3 rounds
+        // down and 1 to nothing.
+        assert!(full.framebuffer().get(12, 10) && !full.framebuffer().get(8, 8));
+        assert!(half.framebuffer().get(10, 9));
+        assert_eq!(full.framebuffer().lit(), half.framebuffer().lit());
+
+        // High resolution always scrolls the whole way.
+        half.set_resolution(Resolution::SUPER);
+        half.pc = 0x204;
+        half.run_instructions(1).unwrap();
+        half.execute_opcode(0x00C3).unwrap();
+        assert!(half.framebuffer().get(8, 11));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn shutdown() {
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[0x12, 0x00]);
+        let (tx, rx) = mpsc::channel();
+        tx.send(Event::Shutdown).unwrap();
+        intr.execute(&rx).unwrap();
+        assert_eq!(intr.cycles, 0);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn frame_advance() {
+        let mut intr = Interpreter::new();
+        intr.with_ips(60);
+        // Skip if key 0 is pressed, else loop; then loop.
+        intr.load_rom(&[0xE0, 0x9E, 0x12, 0x02, 0x12, 0x04]);
+        intr.timers.delay = 5;
+        let (tx, rx) = mpsc::channel();
+        let key = |pressed| Event::Key(input::KeyEvent { key: 0x0, pressed });
+        for event in [
+            Event::AdvanceFrame, // Ignored outside frame-advance mode
+            Event::ToggleFrameAdvance,
+            key(true),
+            key(false),
+            Event::AdvanceFrame,
+            Event::AdvanceFrame,
+            Event::Shutdown,
+        ] {
+            tx.send(event).unwrap();
+        }
+        intr.execute(&rx).unwrap();
+        // The tapped key was held for the first frame, then let go.
+        assert_eq!(intr.pc, 0x204);
+        assert!(!intr.keypad[0x0]);
+        assert_eq!((intr.cycles, intr.timers.delay), (2, 3));
+        assert!(intr.paused);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn turbo() {
+        let mut intr = Interpreter::new();
+        intr.with_ips(600);
+        let turbo = input::Turbo {
+            rate: 15,
+            ..input::Turbo::new(0x5)
+        };
+        let _ = intr.handle_event(Event::Turbo(Some(turbo)));
+        let mut held = Vec::new();
+        for _ in 0..8 {
+            intr.autofire();
+            held.push(intr.keypad[0x5]);
+        }
+        // 60 frames a second at 15 presses a second: pressed for two frames
+        // out of every four.
+        assert_eq!(held, [true, true, false, false, true, true, false, false]);
+
+        intr.autofire();
+        let _ = intr.handle_event(Event::Turbo(None));
+        assert!(!intr.keypad[0x5]);
+        intr.autofire();
+        assert!(!intr.keypad[0x5]);
+    }
+
+    #[test]
+    fn swap_rom() {
+        let mut intr = Interpreter::new();
+        // V0 = 1, I = font character V0, draw it.
+        let draw = [0x60, 0x01, 0xF0, 0x29, 0xD0, 0x05];
+        for (mode, lit) in [(SwapMode::Reset, 0), (SwapMode::PreserveDisplay, 8)] {
+            intr.load_rom(&draw);
+            intr.with_replay(VecDeque::new());
+            intr.run_instructions(3).unwrap();
+            intr.swap_rom(&[0x12, 0x00], mode).unwrap();
+            assert_eq!(intr.pc, Interpreter::MEMORY_OFFSET);
+            assert_eq!(intr.registers[0], 0);
+            assert_eq!(intr.memory[0x200..0x204], [0x12, 0x00, 0x00, 0x00]);
+            assert!(intr.replay.is_none());
+            assert_eq!(intr.framebuffer().lit(), lit);
+        }
+
+        // A ROM that doesn't fit is refused, keeping the running one.
+        let rom = vec![0; Interpreter::ROM_CAPACITY + 1];
+        let fault = intr.swap_rom(&rom, SwapMode::Reset).unwrap_err();
+        assert!(matches!(fault, Fault::RomTooLarge { .. }));
+        assert_eq!(intr.memory[0x200..0x202], [0x12, 0x00]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn reset() {
+        let mut intr = Interpreter::new();
+        intr.with_seed(7);
+        // V0 = random, I = font character V0, draw it, save V0 over the ROM.
+        let rom = [0xC0, 0xFF, 0xF0, 0x29, 0xD0, 0x05, 0xA2, 0x00, 0xF0, 0x55];
+        intr.load_rom(&rom);
+        intr.with_replay(VecDeque::new());
+        intr.run_instructions(5).unwrap();
+        let random = intr.registers[0];
+        assert_eq!(intr.memory[0x200], random);
+
+        let _ = intr.handle_event(Event::Reset);
+        assert_eq!(intr.pc, Interpreter::MEMORY_OFFSET);
+        assert_eq!((intr.i, intr.registers[0], intr.cycles), (0, 0, 0));
+        assert_eq!(intr.memory[0x200..0x20A], rom);
+        assert_eq!(intr.framebuffer().lit(), 0);
+        assert!(intr.replay.is_none());
+        // The same seed gives the same numbers again.
+        intr.run_instructions(1).unwrap();
+        assert_eq!(intr.registers[0], random);
+    }
+
+    /// A clock that only moves when slept on, and always oversleeps by a
+    /// millisecond, as a busy system's does.
+    struct Oversleeping(Arc<AtomicU64>);
+
+    impl platform::Clock for Oversleeping {
+        fn now(&self) -> Duration {
+            Duration::from_nanos(self.0.load(Ordering::Relaxed))
+        }
+
+        fn sleep(&self, duration: Duration) {
+            let nanos = (duration + Duration::from_millis(1)).as_nanos();
+            self.0
+                .fetch_add(u64::try_from(nanos).unwrap(), Ordering::Relaxed);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn timers_keep_time() {
+        let now = Arc::new(AtomicU64::new(0));
+        let mut intr = Interpreter::new();
+        intr.with_clock(Oversleeping(Arc::clone(&now)));
+        intr.load_rom(&[0x12, 0x00]);
+        let (tx, rx) = mpsc::channel();
+        intr.on_frame(move |info| {
+            if info.frame == 3599 {
+                let _ = tx.send(Event::Shutdown);
+            }
+        });
+        intr.execute(&rx).unwrap();
+        // A minute of ticks took a minute, give or take the last oversleep,
+        // rather than a millisecond more for each.
+        let elapsed = Duration::from_nanos(now.load(Ordering::Relaxed));
+        assert_eq!(elapsed, Duration::from_mins(1) + Duration::from_millis(1));
+    }
+
+    #[test]
+    fn cycle_accurate() {
+        let mut intr = Interpreter::new();
+        intr.with_cycle_costs(vip::Costs::default());
+        // V0 = 1, then V0 += 1 forever.
+        intr.load_rom(&[0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        intr.run_frames(1).unwrap();
+        // 6XNN costs 46 cycles, and each 7XNN and 1NNN 50 and 63.
+        let budget = vip::cycles_per_frame(60) - 46;
+        let loops = budget.div_ceil(50 + 63);
+        assert_eq!(u64::from(intr.registers[0]), 1 + loops);
+        assert!(intr.budget <= 0);
+
+        // Overrun time is taken from the next frame.
+        let cycles = intr.cycles;
+        intr.run_frames(1).unwrap();
+        assert!(intr.cycles - cycles < cycles);
+    }
+
+    #[test]
+    fn counters() {
+        let mut intr = Interpreter::new();
+        intr.with_timing(timing::Timing::from_ips(600));
+        // DT = 2, then loop.
+        intr.load_rom(&[0x60, 0x02, 0xF0, 0x15, 0x12, 0x04]);
+        intr.run_frames(3).unwrap();
+        intr.handle_key(input::KeyEvent {
+            key: 0x5,
+            pressed: true,
+        });
+        let counters = Counters {
+            instructions: 30,
+            frames: 3,
+            timer_ticks: 2,
+            key_events: 1,
+        };
+        assert_eq!(intr.counters(), counters);
+
+        // They carry on across resets.
+        intr.reset();
+        assert_eq!(intr.cycles, 0);
+        assert_eq!(intr.counters(), counters);
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn flags() {
+        let dir = std::env::temp_dir().join(format!("etherea-battery-{}", std::process::id()));
+        // V0 = 1, V1 = 2, save V0..=V1, V0 = 0, V1 = 0, load V0.
+        let rom = [
+            0x60, 0x01, 0x61, 0x02, 0xF1, 0x75, 0x60, 0x00, 0x61, 0x00, 0xF0, 0x85,
+        ];
+        let mut intr = Interpreter::new();
+        intr.with_battery(flags::Battery::new(dir.clone()));
+        intr.load_rom(&rom);
+        intr.run_instructions(6).unwrap();
+        assert_eq!(intr.registers[..2], [1, 0]);
+        assert_eq!(intr.flags[..3], [1, 2, 0]);
+
+        // The flags are still there the next time the ROM is loaded.
+        let mut intr = Interpreter::new();
+        intr.with_battery(flags::Battery::new(dir.clone()));
+        intr.load_rom(&rom);
+        assert_eq!(intr.flags[..3], [1, 2, 0]);
+        intr.load_rom(&[0x00, 0xE0]);
+        assert_eq!(intr.flags, [0; Interpreter::FLAG_COUNT]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn saves_memory() {
+        let dir = std::env::temp_dir().join(format!("etherea-save-{}", std::process::id()));
+        // I = 0x300, V0 = V0 + 1, save V0 there, loop.
+        let rom = [0xA3, 0x00, 0xF0, 0x65, 0x70, 0x01, 0xF0, 0x55, 0x12, 0x08];
+        let save = || save::Save::new(&dir, &rom, vec!["0x300".parse().unwrap()]);
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.with_save(save());
+        intr.run_instructions(4).unwrap();
+        assert_eq!(intr.memory[0x300], 1);
+        // Resetting saves the memory, and restores it.
+        intr.reset();
+        assert_eq!(intr.memory[0x300], 1);
+        intr.run_instructions(4).unwrap();
+        assert_eq!(intr.memory[0x300], 2);
+
+        // Swapping ROMs saves it too, for the next time the ROM is loaded.
+        intr.swap_rom(&[0x12, 0x00], SwapMode::Reset).unwrap();
+        assert_eq!(intr.memory[0x300], 0);
+        intr.load_rom(&rom);
+        intr.with_save(save());
+        assert_eq!(intr.memory[0x300], 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn soft_pause() {
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[0x12, 0x00]);
+        let _ = intr.handle_event(Event::Focus(false));
+        assert!(intr.paused);
+        assert_eq!(
+            intr.handle_event(Event::Focus(true)),
+            ControlFlow::Continue(true)
+        );
+        assert!(!intr.paused);
+
+        // Pausing by other means isn't undone by getting focus back.
+        let _ = intr.handle_event(Event::ToggleFrameAdvance);
+        let _ = intr.handle_event(Event::Focus(false));
+        let _ = intr.handle_event(Event::Focus(true));
+        assert!(intr.paused);
+    }
+
+    #[test]
+    fn random_roms() {
+        // Random instructions must only ever fault, never panic, whatever
+        // the quirks. Most random bytes aren't instructions, and end a run at
+        // once, so the ones that need them get a valid last nibble or NN.
+        const N8: [u8; 9] = [0x0, 0x1, 0x2, 0x3, 0x4, 0x5, 0x6, 0x7, 0xE];
+        const EX: [u8; 2] = [0x9E, 0xA1];
+        const FX: [u8; 9] = [0x07, 0x0A, 0x15, 0x18, 0x1E, 0x29, 0x33, 0x55, 0x65];
+        let mut rng = StdRng::seed_from_u64(0);
+        for n in 0..2000 {
+            let mut rom = vec![0; rng.gen_range(0..256) * 2];
+            rng.fill(&mut rom[..]);
+            for instruction in rom.chunks_exact_mut(2) {
+                match instruction[0] >> 4 {
+                    0x5 | 0x9 => instruction[1] &= 0xF0,
+                    0x8 => instruction[1] = instruction[1] & 0xF0 | N8[rng.gen_range(0..N8.len())],
+                    0xE => instruction[1] = EX[rng.gen_range(0..EX.len())],
+                    0xF => instruction[1] = FX[rng.gen_range(0..FX.len())],
+                    _ => {}
+                }
+            }
+            let mut intr = Interpreter::new();
+            intr.with_seed(n);
+            if n % 2 == 0 {
+                intr.with_quirks(quirks::Quirks {
+                    index_overflow: quirks::IndexOverflow::Fault,
+                    pc_overflow: quirks::PcOverflow::Fault,
+                    jump_offset: quirks::JumpOffset::Vx,
+                    shift: quirks::Shift::Vy,
+                    vf_reset: true,
+                    index_carry: true,
+                    display_wait: true,
+                    half_scroll: true,
+                    stack_depth: 4,
+                });
+            }
+            match n % 3 {
+                0 => intr.with_banking(),
+                1 => intr.with_chip8x(),
+                _ => {}
+            }
+            intr.load_rom(&rom);
+            let _ = intr.run_frames(10);
+        }
+    }
+
+    #[test]
+    fn accessors() {
+        let mut intr = Interpreter::new();
+        // I = 0x123, call 0x206, then add 1 to V0 forever.
+        intr.load_rom(&[0xA1, 0x23, 0x22, 0x06, 0x00, 0x00, 0x70, 0x01, 0x12, 0x06]);
+        intr.run_instructions(4).unwrap();
+        assert_eq!((intr.pc(), intr.index()), (0x206, 0x123));
+        assert_eq!(intr.stack(), [0x204]);
+        assert_eq!(intr.registers()[0], 1);
+        assert_eq!(intr.memory()[0x200..0x202], [0xA1, 0x23]);
+
+        // The instruction at 0x206 was decoded, but changing it still counts.
+        intr.memory_mut()[0x207] = 0x05;
+        intr.run_instructions(2).unwrap();
+        assert_eq!(intr.registers()[0], 6);
+        intr.set_register(0xF, 0xAB);
+        assert_eq!(intr.registers()[0xF], 0xAB);
+    }
+
+    #[test]
+    fn to_digits() {
+        let n = 456;
+        assert_eq!(digit(0, n), 6);
+        assert_eq!(digit(1, n), 5);
+        assert_eq!(digit(2, n), 4);
+    }
+}
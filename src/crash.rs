@@ -0,0 +1,97 @@
+use crate::{
+    debug::{self, hexdump},
+    opcode::disassemble_word,
+    Fault, Interpreter,
+};
+use std::{
+    fmt::Write as _,
+    fs, io,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The number of instructions disassembled on either side of the faulting
+/// one.
+const CONTEXT: usize = 8;
+
+/// Describes `fault` along with the interpreter's state when it happened:
+/// the registers and stack, the code around the faulting instruction, and a
+/// dump of all of memory.
+#[must_use]
+pub fn report(intr: &Interpreter, fault: &Fault) -> String {
+    let mut s = String::new();
+    let _ = writeln!(s, "etherea {} crash report", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(s, "{fault}\n");
+    if let Some(hashes) = &intr.hashes {
+        let _ = writeln!(s, "ROM:\n{hashes}\n");
+    }
+    let _ = writeln!(s, "{}\n", debug::registers(intr));
+
+    let _ = writeln!(s, "Code:");
+    let pc = fault.pc();
+    let start = pc.saturating_sub(CONTEXT * 2);
+    for address in (start..=pc + CONTEXT * 2).step_by(2) {
+        let (Some(&hi), Some(&lo)) = (intr.memory.get(address), intr.memory.get(address + 1))
+        else {
+            break;
+        };
+        let marker = if address == pc { "->" } else { "  " };
+        let line = disassemble_word(
+            u16::try_from(address).unwrap_or(u16::MAX),
+            u16::from_be_bytes([hi, lo]),
+        );
+        let _ = writeln!(s, "{marker} {line}");
+    }
+
+    let _ = writeln!(s, "\nMemory:");
+    s += &hexdump(&intr.memory[..], 0..intr.memory.len());
+    s
+}
+
+/// Writes `report` to a new file in the crash report directory, returning
+/// its path.
+///
+/// # Errors
+/// This function will error if there is no data directory or the report
+/// cannot be written.
+pub fn save(report: &str) -> io::Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .map(|dir| dir.join("etherea").join("crashes"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+    fs::create_dir_all(&dir)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis());
+    let path = dir.join(format!("crash-{now}.txt"));
+    fs::write(&path, report)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_state() {
+        let mut intr = Interpreter::new();
+        // V3 = 0x2A, CALL 0x206, (unreachable), 0xFFFF
+        intr.load_rom(&[0x63, 0x2A, 0x22, 0x06, 0x00, 0xE0, 0xFF, 0xFF]);
+        let fault = intr.run_instructions(3).unwrap_err();
+        assert_eq!(
+            fault,
+            Fault::UnknownOpcode {
+                pc: 0x206,
+                opcode: 0xFFFF
+            }
+        );
+
+        let report = report(&intr, &fault);
+        assert!(report.contains("Unknown opcode FFFF at 0x206"));
+        assert!(report.contains("\nCRC-32:  "));
+        assert!(report.contains("V3: 0x2A"));
+        assert!(report.contains("Stack: [0x204]"));
+        assert!(report.contains("   0x200  632A  LD V3, 0x2A\n"));
+        assert!(report.contains("-> 0x206  FFFF  DW 0xFFFF\n"));
+        assert!(report.contains("0xFF0  00 00"));
+    }
+}
@@ -0,0 +1,746 @@
+use crate::{asm, octo, opcode, Interpreter};
+use log::error;
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeSet,
+    fmt, fs,
+    io::{self, BufRead, Write},
+    iter,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// The id of the only thread reported to the client: the interpreter's.
+const THREAD: u64 = 1;
+/// The `variablesReference` of the registers.
+const REGISTERS: u64 = 1;
+
+/// What a paused interpreter is told to do next.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Command {
+    Continue,
+    Step,
+    StepOut,
+}
+
+/// Why the interpreter paused.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Reason {
+    Entry,
+    Step,
+    Breakpoint,
+    Pause,
+}
+
+impl Reason {
+    /// The name of the reason in the protocol.
+    const fn name(self) -> &'static str {
+        match self {
+            Self::Entry => "entry",
+            Self::Step => "step",
+            Self::Breakpoint => "breakpoint",
+            Self::Pause => "pause",
+        }
+    }
+}
+
+/// The interpreter's state when it paused, for the client to inspect.
+#[derive(Clone, Debug)]
+struct Stop {
+    reason: Reason,
+    pc: u16,
+    i: u16,
+    registers: [u8; 16],
+    delay: u8,
+    sound: u8,
+    stack: Vec<u16>,
+    memory: Vec<u8>,
+}
+
+/// What the adapter changes while the interpreter runs.
+#[derive(Debug, Default)]
+struct Shared {
+    breakpoints: Mutex<BTreeSet<u16>>, // Addresses to pause at
+    pause: AtomicBool,                 // Set to pause at the next instruction
+}
+
+/// How a [`Target`] runs until it next pauses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Mode {
+    /// Until a breakpoint, or the client asks to pause.
+    Run,
+    /// Not at all: it pauses before the first instruction.
+    Entry,
+    /// For a single instruction.
+    Step,
+    /// Until the stack is shallower than this.
+    Out(usize),
+}
+
+/// The interpreter's side of a debugging session. Checked before every
+/// instruction like the [`Debugger`](crate::debug::Debugger), it pauses at
+/// breakpoints and after steps, then waits to be told what to do next.
+#[derive(Debug)]
+pub struct Target {
+    shared: Arc<Shared>,
+    mode: Mode,
+    commands: Receiver<Command>, // What to do when paused
+    stops: Sender<Stop>,         // Where to report pauses
+    detached: bool,              // Set once the client is gone
+}
+
+impl Target {
+    /// Pauses before the next instruction if there is a reason to, until
+    /// the client resumes execution. Returns whether execution was paused.
+    pub(crate) fn check(&mut self, intr: &Interpreter) -> bool {
+        if self.detached {
+            return false;
+        }
+        let pc = u16::try_from(intr.pc).unwrap_or(u16::MAX);
+        let reason = match self.mode {
+            Mode::Entry => Reason::Entry,
+            Mode::Step => Reason::Step,
+            Mode::Out(depth) if intr.stack.len() < depth => Reason::Step,
+            _ if self.shared.pause.swap(false, Ordering::Relaxed) => Reason::Pause,
+            _ if lock(&self.shared.breakpoints).contains(&pc) => Reason::Breakpoint,
+            _ => return false,
+        };
+        let stop = Stop {
+            reason,
+            pc,
+            i: intr.i,
+            registers: *intr.registers,
+            delay: intr.timers.delay,
+            sound: intr.timers.sound,
+            stack: intr.stack.clone(),
+            memory: intr.memory.to_vec(),
+        };
+        let command = match self.stops.send(stop) {
+            Ok(()) => self.commands.recv().ok(),
+            Err(_) => None,
+        };
+        self.mode = match command {
+            Some(Command::Continue) => Mode::Run,
+            Some(Command::Step) => Mode::Step,
+            Some(Command::StepOut) => Mode::Out(intr.stack.len()),
+            None => {
+                self.detached = true;
+                Mode::Run
+            }
+        };
+        true
+    }
+}
+
+/// Locks `mutex`, even if a thread panicked while holding it.
+fn lock<T>(mutex: &Mutex<T>) -> std::sync::MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+/// Sends messages to the client, numbering them.
+#[derive(Clone)]
+pub struct Client(Arc<Mutex<(Box<dyn Write + Send>, u64)>>);
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Client")
+    }
+}
+
+impl Client {
+    fn send(&self, mut message: Value) {
+        let mut output = lock(&self.0);
+        output.1 += 1;
+        message["seq"] = json!(output.1);
+        if let Err(e) = write_message(&mut output.0, &message) {
+            error!("Could not write to the debugger client: {}", e);
+        }
+    }
+
+    fn event(&self, event: &str, body: &Value) {
+        self.send(json!({ "type": "event", "event": event, "body": body }));
+    }
+
+    /// Tells the client the program has ended, e.g. as its window was closed.
+    pub fn terminated(&self) {
+        self.event("terminated", &json!({}));
+    }
+}
+
+/// A program launched by the client, ready to run.
+#[derive(Debug)]
+pub struct Launch {
+    /// The path the client launched.
+    pub path: PathBuf,
+    /// The ROM to run, assembled if the path is source.
+    pub rom: Vec<u8>,
+    /// To attach to the interpreter running the ROM.
+    pub target: Target,
+    /// For telling the client when the program ends.
+    pub client: Client,
+    /// Disconnected once the client is done debugging.
+    pub done: Receiver<()>,
+}
+
+/// The signature of the assemblers.
+type Assembler = fn(&str) -> Result<(Vec<u8>, asm::SourceMap), asm::Error>;
+
+/// A program, and the source it was assembled from, if it is known.
+#[derive(Debug)]
+struct Program {
+    path: PathBuf,
+    rom: Vec<u8>,
+    source: Option<asm::SourceMap>,
+}
+
+impl Program {
+    /// Loads the program at `path`: assembly source (`.asm` or `.s`), Octo
+    /// source or a cartridge, or a ROM, whose source is known if it has a
+    /// symbol file.
+    fn load(path: &Path) -> Result<Self, String> {
+        let err = |_| format!("Could not read file: '{}'", path.display());
+        let path = fs::canonicalize(path).map_err(err)?;
+        let file = fs::read(&path).map_err(err)?;
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let (source, assemble): (String, Assembler) = match extension.as_deref() {
+            Some("asm" | "s") => (
+                String::from_utf8(file).map_err(|_| "Assembly source must be UTF-8")?,
+                asm::assemble_with_map,
+            ),
+            Some("8o" | "o8") => (
+                octo::source(&path, &file).unwrap_or_else(|| Err("Not Octo source".into()))?,
+                asm::octo::assemble_with_map,
+            ),
+            _ => {
+                let rom = octo::load(&path, file)?;
+                let symbols = asm::SourceMap::path(&path);
+                let source = symbols
+                    .exists()
+                    .then(|| asm::SourceMap::read(&symbols))
+                    .transpose()
+                    .map_err(|e| format!("{}: {e}", symbols.display()))?;
+                return Ok(Self { path, rom, source });
+            }
+        };
+        let (rom, map) = assemble(&source).map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(Self {
+            source: Some(map.with_source(path.clone(), &source)),
+            path,
+            rom,
+        })
+    }
+
+    /// The program's source, if the client can set breakpoints on it.
+    fn source(&self) -> Option<(&asm::SourceMap, PathBuf)> {
+        let map = self.source.as_ref()?;
+        Some((map, fs::canonicalize(map.source()?).ok()?))
+    }
+}
+
+/// Serves the [Debug Adapter Protocol](https://microsoft.github.io/debug-adapter-protocol/)
+/// to a client, such as an editor, on `input` and `output`. Breakpoints can
+/// be set on lines of assembly source, the program stepped through, and
+/// the registers inspected as variables.
+///
+/// Returns the program the client launches once it has set its
+/// breakpoints, for the caller to run with the returned [`Target`]
+/// attached. The session carries on in the background.
+///
+/// # Errors
+/// This function will error if the client disconnects without launching a
+/// program.
+pub fn start(
+    input: impl BufRead + Send + 'static,
+    output: impl Write + Send + 'static,
+) -> Result<Launch, String> {
+    let client = Client(Arc::new(Mutex::new((Box::new(output), 0))));
+    let shared = Arc::new(Shared::default());
+    let stopped = Arc::new(Mutex::new(None));
+    let (commands, rx) = mpsc::channel();
+    let (stops, reports) = mpsc::channel::<Stop>();
+    let target = Target {
+        shared: Arc::clone(&shared),
+        mode: Mode::Run,
+        commands: rx,
+        stops,
+        detached: false,
+    };
+
+    {
+        let (client, stopped) = (client.clone(), Arc::clone(&stopped));
+        thread::spawn(move || {
+            for stop in reports {
+                let reason = stop.reason.name();
+                *lock(&stopped) = Some(stop);
+                let body =
+                    json!({ "reason": reason, "threadId": THREAD, "allThreadsStopped": true });
+                client.event("stopped", &body);
+            }
+        });
+    }
+
+    let (launches, launch) = mpsc::channel();
+    let (done, done_rx) = mpsc::channel();
+    let adapter = Adapter {
+        client: client.clone(),
+        shared,
+        stopped,
+        commands,
+        program: None,
+        stop_on_entry: false,
+        target: Some(target),
+        launches,
+    };
+    thread::spawn(move || {
+        adapter.serve(input);
+        drop(done);
+    });
+    let (path, rom, target) = launch
+        .recv()
+        .map_err(|_| "The client disconnected without launching a program".to_string())?;
+    Ok(Launch {
+        path,
+        rom,
+        target,
+        client,
+        done: done_rx,
+    })
+}
+
+/// Answers the client's requests.
+struct Adapter {
+    client: Client,
+    shared: Arc<Shared>,
+    stopped: Arc<Mutex<Option<Stop>>>, // The state the interpreter paused in
+    commands: Sender<Command>,
+    program: Option<Program>,
+    stop_on_entry: bool,
+    target: Option<Target>, // Handed over once the client is configured
+    launches: Sender<(PathBuf, Vec<u8>, Target)>,
+}
+
+impl Adapter {
+    /// Answers requests from `input` until the client disconnects.
+    fn serve(mut self, mut input: impl BufRead) {
+        loop {
+            let request = match read_message(&mut input) {
+                Ok(Some(request)) => request,
+                Ok(None) => return,
+                Err(e) => {
+                    error!("Could not read from the debugger client: {}", e);
+                    return;
+                }
+            };
+            let command = request["command"].as_str().unwrap_or_default().to_string();
+            let mut response = json!({
+                "type": "response",
+                "request_seq": request["seq"],
+                "command": command,
+            });
+            match self.handle(&command, &request["arguments"]) {
+                Ok(body) => {
+                    response["success"] = json!(true);
+                    response["body"] = body;
+                }
+                Err(message) => {
+                    response["success"] = json!(false);
+                    response["message"] = json!(message);
+                }
+            }
+            let success = response["success"] == json!(true);
+            self.client.send(response);
+            match command.as_str() {
+                "launch" if success => self.client.event("initialized", &json!({})),
+                "disconnect" => return,
+                _ => {}
+            }
+        }
+    }
+
+    /// Carries out a request, returning the body of the response.
+    fn handle(&mut self, command: &str, args: &Value) -> Result<Value, String> {
+        match command {
+            "initialize" => Ok(json!({ "supportsConfigurationDoneRequest": true })),
+            "launch" => {
+                let path = args["program"].as_str().ok_or("No 'program' to launch")?;
+                self.program = Some(Program::load(Path::new(path))?);
+                self.stop_on_entry = args["stopOnEntry"].as_bool().unwrap_or(false);
+                Ok(json!({}))
+            }
+            "setBreakpoints" => Ok(self.set_breakpoints(args)),
+            "setExceptionBreakpoints" => Ok(json!({ "breakpoints": [] })),
+            "configurationDone" => {
+                let program = self.program.as_ref().ok_or("No program was launched")?;
+                let mut target = self.target.take().ok_or("Already configured")?;
+                if self.stop_on_entry {
+                    target.mode = Mode::Entry;
+                }
+                // The caller may have given up waiting.
+                let _ = self
+                    .launches
+                    .send((program.path.clone(), program.rom.clone(), target));
+                Ok(json!({}))
+            }
+            "threads" => Ok(json!({ "threads": [{ "id": THREAD, "name": "CHIP-8" }] })),
+            "stackTrace" => self.stack_trace(),
+            "scopes" => Ok(json!({ "scopes": [{
+                "name": "Registers",
+                "variablesReference": REGISTERS,
+                "expensive": false,
+            }] })),
+            "variables" => self.variables(args),
+            "continue" => {
+                self.resume(Command::Continue)?;
+                Ok(json!({ "allThreadsContinued": true }))
+            }
+            "next" | "stepIn" => self.resume(Command::Step).map(|()| json!({})),
+            "stepOut" => self.resume(Command::StepOut).map(|()| json!({})),
+            "pause" => {
+                self.shared.pause.store(true, Ordering::Relaxed);
+                Ok(json!({}))
+            }
+            "disconnect" => Ok(json!({})),
+            _ => Err(format!("Unsupported request '{command}'")),
+        }
+    }
+
+    /// Sets breakpoints on lines of the launched program's source. Lines
+    /// without code get a breakpoint on the next line with some.
+    fn set_breakpoints(&mut self, args: &Value) -> Value {
+        let path = args["source"]["path"].as_str().map(fs::canonicalize);
+        let map = self
+            .program
+            .as_ref()
+            .and_then(Program::source)
+            .filter(|(_, source)| matches!(&path, Some(Ok(path)) if path == source))
+            .map(|(map, _)| map);
+        let lines = args["breakpoints"].as_array().into_iter().flatten();
+        let lines = lines.filter_map(|breakpoint| breakpoint["line"].as_u64());
+        let mut addresses = BTreeSet::new();
+        let breakpoints: Vec<Value> = lines
+            .map(|line| {
+                let at = usize::try_from(line)
+                    .ok()
+                    .zip(map)
+                    .and_then(|(line, map)| map.address(line));
+                match at {
+                    Some((address, line)) => {
+                        addresses.insert(address);
+                        json!({ "verified": true, "line": line })
+                    }
+                    None => json!({
+                        "verified": false,
+                        "line": line,
+                        "message": "No code here in the launched program",
+                    }),
+                }
+            })
+            .collect();
+        // The launched program is the only source with breakpoints.
+        if map.is_some() {
+            *lock(&self.shared.breakpoints) = addresses;
+        }
+        json!({ "breakpoints": breakpoints })
+    }
+
+    /// Lists the paused instruction, then the calls it is inside of.
+    fn stack_trace(&self) -> Result<Value, String> {
+        let stopped = lock(&self.stopped);
+        let stop = stopped.as_ref().ok_or("The program isn't paused")?;
+        let calls = stop
+            .stack
+            .iter()
+            .rev()
+            .map(|&address| address.wrapping_sub(2));
+        let frames: Vec<Value> = iter::once(stop.pc)
+            .chain(calls)
+            .enumerate()
+            .map(|(id, address)| self.frame(id, address, &stop.memory))
+            .collect();
+        Ok(json!({ "stackFrames": frames, "totalFrames": frames.len() }))
+    }
+
+    /// Describes the instruction at `address` as a stack frame, with its
+    /// line of source if it has one.
+    fn frame(&self, id: usize, address: u16, memory: &[u8]) -> Value {
+        let byte = |address: u16| {
+            memory
+                .get(usize::from(address))
+                .copied()
+                .unwrap_or_default()
+        };
+        let raw = u16::from_be_bytes([byte(address), byte(address.wrapping_add(1))]);
+        let map = self.program.as_ref().and_then(Program::source);
+        let mut name = opcode::disassemble_word(address, raw);
+        if let Some(label) = map.as_ref().and_then(|(map, _)| map.describe(address)) {
+            name = format!("{name} <{label}>");
+        }
+        let mut frame = json!({
+            "id": id,
+            "name": name,
+            "line": 0,
+            "column": 0,
+            "instructionPointerReference": format!("{address:#05X}"),
+        });
+        if let Some((path, line)) = map.and_then(|(map, path)| Some((path, map.line(address)?))) {
+            frame["source"] = json!({ "path": path });
+            frame["line"] = json!(line);
+            frame["column"] = json!(1);
+        }
+        frame
+    }
+
+    /// Lists the registers and timers.
+    fn variables(&self, args: &Value) -> Result<Value, String> {
+        if args["variablesReference"].as_u64() != Some(REGISTERS) {
+            return Ok(json!({ "variables": [] }));
+        }
+        let stopped = lock(&self.stopped);
+        let stop = stopped.as_ref().ok_or("The program isn't paused")?;
+        let variable = |name: &str, value: String| json!({ "name": name, "value": value, "variablesReference": 0 });
+        let mut variables: Vec<Value> = (0..)
+            .zip(stop.registers)
+            .map(|(x, value): (u8, u8)| variable(&format!("V{x:X}"), format!("{value:#04X}")))
+            .collect();
+        variables.extend([
+            variable("I", format!("{:#05X}", stop.i)),
+            variable("PC", format!("{:#05X}", stop.pc)),
+            variable("DT", format!("{:#04X}", stop.delay)),
+            variable("ST", format!("{:#04X}", stop.sound)),
+            variable("SP", stop.stack.len().to_string()),
+        ]);
+        Ok(json!({ "variables": variables }))
+    }
+
+    /// Tells the paused interpreter to carry on with `command`.
+    fn resume(&self, command: Command) -> Result<(), String> {
+        if lock(&self.stopped).take().is_none() {
+            return Err("The program isn't paused".into());
+        }
+        self.commands
+            .send(command)
+            .map_err(|_| "The program has ended".to_string())
+    }
+}
+
+/// Reads a message: headers, including its `Content-Length`, then the JSON
+/// body. Returns `None` once `input` ends.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Value>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                length = value.trim().parse().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "Missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    serde_json::from_slice(&body)
+        .map(Some)
+        .map_err(io::Error::other)
+}
+
+/// Writes `message` with the header the protocol expects.
+fn write_message(output: &mut impl Write, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(output, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, PipeReader, PipeWriter};
+
+    /// The client's end of a session.
+    struct Editor {
+        output: PipeWriter,
+        input: BufReader<PipeReader>,
+        unread: Vec<Value>, // Messages read while looking for another
+        seq: u64,
+    }
+
+    impl Editor {
+        fn request(&mut self, command: &str, arguments: &Value) {
+            self.seq += 1;
+            let request = json!({
+                "seq": self.seq,
+                "type": "request",
+                "command": command,
+                "arguments": arguments,
+            });
+            write_message(&mut self.output, &request).unwrap();
+        }
+
+        /// Waits for the response to `command`, or the event named `event`.
+        fn expect(&mut self, name: &str) -> Value {
+            let matches = |message: &Value| {
+                message["command"] == json!(name) || message["event"] == json!(name)
+            };
+            if let Some(index) = self.unread.iter().position(matches) {
+                return self.unread.remove(index);
+            }
+            loop {
+                let message = read_message(&mut self.input).unwrap().unwrap();
+                if matches(&message) {
+                    return message;
+                }
+                self.unread.push(message);
+            }
+        }
+
+        /// Makes a request and returns the body of its response.
+        fn call(&mut self, command: &str, arguments: &Value) -> Value {
+            self.request(command, arguments);
+            let response = self.expect(command);
+            assert_eq!(response["success"], json!(true), "{response}");
+            response["body"].clone()
+        }
+
+        /// The lines of the paused call stack.
+        fn lines(&mut self) -> Vec<u64> {
+            let trace = self.call("stackTrace", &json!({ "threadId": THREAD }));
+            let frames = trace["stackFrames"].as_array().unwrap().iter();
+            frames
+                .map(|frame| frame["line"].as_u64().unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({ "seq": 1 })).unwrap();
+        assert_eq!(buffer, b"Content-Length: 9\r\n\r\n{\"seq\":1}");
+        let mut input = &buffer[..];
+        assert_eq!(read_message(&mut input).unwrap(), Some(json!({ "seq": 1 })));
+        assert_eq!(read_message(&mut input).unwrap(), None);
+    }
+
+    #[test]
+    fn session() {
+        let path = std::env::temp_dir().join(format!("etherea-dap-{}.asm", std::process::id()));
+        let source = "start:
+            LD V0, 1
+            ADD V0, 1
+            CALL sub
+            JP start
+        sub:
+            LD V1, 7
+            RET
+        ";
+        fs::write(&path, source).unwrap();
+        let (to_adapter, output) = io::pipe().unwrap();
+        let (input, from_adapter) = io::pipe().unwrap();
+        let mut editor = Editor {
+            output,
+            input: BufReader::new(input),
+            unread: Vec::new(),
+            seq: 0,
+        };
+
+        editor.request("initialize", &json!({ "adapterID": "etherea" }));
+        editor.request("launch", &json!({ "program": path, "stopOnEntry": true }));
+        editor.request(
+            "setBreakpoints",
+            &json!({ "source": { "path": path }, "breakpoints": [{ "line": 6 }, { "line": 9 }] }),
+        );
+        editor.request("configurationDone", &json!({}));
+        let launch = start(BufReader::new(to_adapter), from_adapter).unwrap();
+        assert_eq!(launch.rom[..2], [0x60, 0x01]);
+        assert!(editor.expect("initialized")["body"].is_object());
+        let breakpoints = editor.expect("setBreakpoints")["body"]["breakpoints"].clone();
+        assert_eq!(
+            breakpoints,
+            json!([
+                { "verified": true, "line": 7 },
+                { "verified": false, "line": 9, "message": "No code here in the launched program" },
+            ])
+        );
+
+        let mut target = launch.target;
+        let interpreter = thread::spawn(move || {
+            let mut intr = Interpreter::new();
+            intr.load_rom(&launch.rom);
+            for _ in 0..100 {
+                target.check(&intr);
+                intr.step();
+            }
+        });
+
+        assert_eq!(editor.expect("stopped")["body"]["reason"], json!("entry"));
+        assert_eq!(editor.lines(), [2]);
+        editor.call("next", &json!({ "threadId": THREAD }));
+        assert_eq!(editor.expect("stopped")["body"]["reason"], json!("step"));
+        assert_eq!(editor.lines(), [3]);
+
+        editor.call("continue", &json!({ "threadId": THREAD }));
+        assert_eq!(
+            editor.expect("stopped")["body"]["reason"],
+            json!("breakpoint")
+        );
+        assert_eq!(editor.lines(), [7, 4]);
+        let variables = editor.call("variables", &json!({ "variablesReference": REGISTERS }));
+        assert_eq!(
+            variables["variables"][0],
+            json!({ "name": "V0", "value": "0x02", "variablesReference": 0 })
+        );
+        assert_eq!(
+            variables["variables"][20],
+            json!({ "name": "SP", "value": "1", "variablesReference": 0 })
+        );
+
+        editor.call("stepOut", &json!({ "threadId": THREAD }));
+        assert_eq!(editor.expect("stopped")["body"]["reason"], json!("step"));
+        assert_eq!(editor.lines(), [5]);
+
+        // Once the editor is gone, the interpreter runs freely.
+        drop(editor);
+        let _ = launch.done.recv();
+        interpreter.join().unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn symbol_files() {
+        let dir = std::env::temp_dir().join(format!("etherea-dap-symbols-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let source = "start:\n  LD V0, 1\n  JP start\n";
+        let (rom, map) = asm::assemble_with_map(source).unwrap();
+        let map = map.with_source(PathBuf::from("game.asm"), "");
+        fs::write(dir.join("game.asm"), source).unwrap();
+        fs::write(dir.join("game.ch8"), rom).unwrap();
+        fs::write(dir.join("game.sym"), map.to_string()).unwrap();
+
+        let program = Program::load(&dir.join("game.ch8")).unwrap();
+        let (map, path) = program.source().unwrap();
+        assert_eq!(path, fs::canonicalize(dir.join("game.asm")).unwrap());
+        assert_eq!(
+            (map.line(0x202), map.text(0x202)),
+            (Some(3), Some("JP start"))
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use crate::{
+    config::Profile,
+    debug,
+    hash::Hashes,
+    input::{Bindings, Keymap},
+    opcode::Platform,
+    quirks::Quirks,
+};
+use log::warn;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, fs, io, path::PathBuf};
+
+/// The ROMs known to etherea, written into the binary.
+const BUILT_IN: &str = include_str!("database.toml");
+
+/// What is known about a ROM, and the settings it plays best with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Entry {
+    /// The ROM's title.
+    pub title: String,
+    /// Who wrote the ROM, if known.
+    pub author: Option<String>,
+    /// The platform the ROM was written for, if known.
+    pub platform: Option<Platform>,
+    /// The SHA-1 of a known-good dump of the ROM, to verify others against.
+    pub sha1: Option<String>,
+    /// The number of instructions per second the ROM plays best at.
+    pub ips: Option<u64>,
+    /// The quirks the ROM needs.
+    pub quirks: Option<Quirks>,
+    /// The keymap to play the ROM with instead of the config file's.
+    pub keymap: Option<Keymap>,
+    /// Keys for CHIP-8 keys on top of the keymap.
+    pub keys: Bindings,
+}
+
+/// Formats the entry like `Maze by David Winter`.
+impl fmt::Display for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.title)?;
+        if let Some(author) = &self.author {
+            write!(f, " by {author}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How a ROM compares to the known-good dumps in a [`Database`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Verdict<'a> {
+    /// The ROM is a known-good dump of this entry.
+    Good(&'a Entry),
+    /// The ROM is known, but there's no SHA-1 to check it against.
+    Unchecked(&'a Entry),
+    /// The ROM's hash matches this entry, but not its SHA-1, so it isn't the
+    /// dump the database knows.
+    Bad(&'a Entry),
+    /// The ROM isn't in the database.
+    Unknown,
+}
+
+/// Known ROMs, by their hash: the built-in ones, and any in `roms.toml` in
+/// the user's config directory.
+#[derive(Clone, Debug, Default)]
+pub struct Database(HashMap<u64, Entry>);
+
+impl Database {
+    /// The built-in database.
+    ///
+    /// # Panics
+    /// This function panics if the built-in database is invalid, which its
+    /// tests rule out.
+    #[must_use]
+    pub fn built_in() -> Self {
+        Self::parse(BUILT_IN).expect("the built-in ROM database is valid")
+    }
+
+    /// The built-in database, with the entries of the user's `roms.toml`
+    /// added and replacing any for the same ROMs. A file that cannot be read
+    /// or parsed is skipped.
+    #[must_use]
+    pub fn load() -> Self {
+        let mut database = Self::built_in();
+        let Some(path) = Self::path() else {
+            return database;
+        };
+        let read = match fs::read_to_string(&path) {
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return database,
+            Err(e) => {
+                warn!("Could not read '{}': {}", path.display(), e);
+                return database;
+            }
+        };
+        match Self::parse(&read) {
+            Ok(user) => database.0.extend(user.0),
+            Err(e) => warn!("Could not parse '{}': {}", path.display(), e),
+        }
+        database
+    }
+
+    /// The path of the user's database, inside the user's config directory.
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("etherea").join("roms.toml"))
+    }
+
+    /// Parses a database of tables named by hash, as in `database.toml`.
+    fn parse(s: &str) -> Result<Self, String> {
+        let entries: HashMap<String, Entry> = toml::from_str(s).map_err(|e| e.to_string())?;
+        entries
+            .into_iter()
+            .map(|(hash, entry)| match u64::from_str_radix(&hash, 16) {
+                Ok(hash) => Ok((hash, entry)),
+                Err(_) => Err(format!("Invalid hash '{hash}', expected 16 hex digits")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+
+    /// The entry for `rom`, if it is known.
+    #[must_use]
+    pub fn lookup(&self, rom: &[u8]) -> Option<&Entry> {
+        self.0.get(&debug::hash(rom))
+    }
+
+    /// Checks the ROM with checksums `hashes` against the known-good dump
+    /// of it, if there is one.
+    #[must_use]
+    pub fn verify(&self, hashes: &Hashes) -> Verdict<'_> {
+        let Some(entry) = self.0.get(&hashes.fnv) else {
+            return Verdict::Unknown;
+        };
+        match &entry.sha1 {
+            Some(sha1) if sha1.eq_ignore_ascii_case(&hashes.sha1) => Verdict::Good(entry),
+            Some(_) => Verdict::Bad(entry),
+            None => Verdict::Unchecked(entry),
+        }
+    }
+
+    /// Profiles for the known ROMs that have keys of their own, to be
+    /// tried after the config file's.
+    #[must_use]
+    pub fn profiles(&self) -> Vec<Profile> {
+        let mut profiles: Vec<Profile> = self
+            .0
+            .iter()
+            .filter(|(_, entry)| entry.keymap.is_some() || !entry.keys.is_empty())
+            .map(|(&hash, entry)| Profile {
+                file: None,
+                hash: Some(format!("{hash:016x}")),
+                keymap: entry.keymap.clone(),
+                keys: entry.keys.clone(),
+                save: Vec::new(),
+            })
+            .collect();
+        profiles.sort_by(|a, b| a.hash.cmp(&b.hash));
+        profiles
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn database() {
+        let database = Database::built_in();
+        let maze = database.lookup(include_bytes!("../roms/maze.ch8")).unwrap();
+        assert_eq!(maze.to_string(), "Maze by David Winter");
+        assert_eq!(maze.platform, Some(Platform::Chip8));
+        assert!(database.lookup(&[0x12, 0x00]).is_none());
+
+        let breakout = include_bytes!("../roms/breakout.ch8");
+        let profiles = database.profiles();
+        assert!(profiles
+            .iter()
+            .any(|profile| profile.matches(None, breakout)));
+
+        let user = Database::parse("[00000000000000ff]\ntitle = \"Test\"\nips = 1000").unwrap();
+        assert_eq!(user.0[&0xFF].ips, Some(1000));
+
+        let maze = include_bytes!("../roms/maze.ch8");
+        let hashes = Hashes::of(maze);
+        assert!(matches!(database.verify(&hashes), Verdict::Good(entry) if entry.title == "Maze"));
+        let forged = Hashes {
+            sha1: "0".repeat(40),
+            ..hashes
+        };
+        assert!(matches!(database.verify(&forged), Verdict::Bad(_)));
+        let unchecked = Hashes {
+            fnv: 0xFF,
+            ..forged
+        };
+        assert!(matches!(user.verify(&unchecked), Verdict::Unchecked(_)));
+        assert_eq!(user.verify(&Hashes::of(&[])), Verdict::Unknown);
+
+        // Every ROM in the repository is a known-good dump.
+        for file in fs::read_dir("roms").unwrap() {
+            let path = file.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "ch8") {
+                let hashes = Hashes::of(&fs::read(&path).unwrap());
+                let verdict = database.verify(&hashes);
+                assert!(matches!(verdict, Verdict::Good(_)), "{}", path.display());
+            }
+        }
+        assert!(Database::parse("[pong]\ntitle = \"Pong\"").is_err());
+        assert!(Database::parse("[ff]\nplatform = \"nes\"").is_err());
+    }
+}
@@ -0,0 +1,1248 @@
+use crate::{
+    asm::{SourceMap, ORIGIN},
+    font,
+    opcode::Doc,
+    patch::Patch,
+    record, Interpreter,
+};
+use expr::Expr;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    fmt::{self, Write as _},
+    fs,
+    io::{self, BufRead, Write},
+    ops::Range,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// Expressions over registers and memory, for conditions and watches.
+mod expr;
+
+/// Breakpoints, watchpoints, and comments for a single ROM. Saved to a
+/// project file keyed by the ROM's hash, so they are restored automatically
+/// the next time the same ROM is debugged.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Project {
+    /// Addresses to pause at before executing.
+    #[serde(with = "hex::set")]
+    pub breakpoints: BTreeSet<u16>,
+    /// Memory addresses to pause at when their value changes.
+    #[serde(with = "hex::set")]
+    pub watchpoints: BTreeSet<u16>,
+    /// Free-text comments attached to addresses.
+    #[serde(with = "hex::map")]
+    pub comments: BTreeMap<u16, String>,
+    /// Expressions that breakpoints only pause at when true, like
+    /// `V3 == 0x10`.
+    #[serde(with = "hex::map")]
+    pub conditions: BTreeMap<u16, String>,
+    /// Expressions shown whenever execution pauses.
+    pub displays: Vec<String>,
+}
+
+impl Project {
+    /// The path of the project file for `rom`, inside the user's data directory.
+    #[must_use]
+    pub fn path(rom: &[u8]) -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| {
+            dir.join("etherea")
+                .join("projects")
+                .join(format!("{:016x}.toml", hash(rom)))
+        })
+    }
+
+    /// Loads the project for `rom`, returning an empty project if none has
+    /// been saved yet or it cannot be read.
+    #[must_use]
+    pub fn load(rom: &[u8]) -> Self {
+        let Some(path) = Self::path(rom) else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).unwrap_or_else(|e| {
+                error!("Could not parse project file '{}': {}", path.display(), e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Saves the project for `rom`, creating the data directory if needed.
+    ///
+    /// # Errors
+    /// This function will error if there is no data directory or the file
+    /// cannot be written.
+    pub fn save(&self, rom: &[u8]) -> io::Result<PathBuf> {
+        let path = Self::path(rom)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self).map_err(io::Error::other)?)?;
+        Ok(path)
+    }
+}
+
+/// An interactive, terminal-based debugger. Checked before every
+/// instruction; when paused, it reads commands from standard input.
+#[derive(Debug)]
+pub struct Debugger {
+    rom: Vec<u8>,
+    project: Project,
+    /// The number of instructions left to execute before pausing, or `None`
+    /// if running freely.
+    steps: Option<u64>,
+    /// The last seen value of each watched address.
+    watched: BTreeMap<u16, u8>,
+    /// The results of the last `find` command, narrowed by later ones.
+    search: Option<Search>,
+    /// The pinned values, sampled at the end of every frame.
+    plots: Vec<Plot>,
+    /// Moments to pause at once, given with `--pause-at`.
+    pauses: Vec<PauseAt>,
+    /// The number of frames completed since the debugger was attached.
+    frames: u64,
+    /// The ROM's labels and source lines, from its symbol file.
+    symbols: SourceMap,
+    /// The bytes written with `poke`, by address, to export as a patch.
+    edits: BTreeMap<u16, u8>,
+}
+
+impl Debugger {
+    /// Creates a debugger for `rom`, restoring its saved project. The
+    /// debugger starts out paused at the first instruction.
+    #[must_use]
+    pub fn new(rom: &[u8]) -> Self {
+        let project = Project::load(rom);
+        info!(
+            "Loaded debugger project [breakpoints: {}] [watchpoints: {}]",
+            project.breakpoints.len(),
+            project.watchpoints.len()
+        );
+        Self {
+            rom: rom.to_vec(),
+            project,
+            steps: Some(0),
+            watched: BTreeMap::new(),
+            search: None,
+            plots: Vec::new(),
+            pauses: Vec::new(),
+            frames: 0,
+            symbols: SourceMap::default(),
+            edits: BTreeMap::new(),
+        }
+    }
+
+    /// Shows the labels and source lines of `symbols` where execution
+    /// pauses, and accepts labels wherever an address is expected.
+    pub fn use_symbols(&mut self, symbols: SourceMap) {
+        self.symbols = symbols;
+    }
+
+    /// Runs freely instead of starting out paused.
+    pub fn resume(&mut self) {
+        self.steps = None;
+    }
+
+    /// Pauses once execution reaches `at`.
+    pub fn pause_at(&mut self, at: PauseAt) {
+        self.pauses.push(at);
+    }
+
+    /// Checks breakpoints, watchpoints, and stepping before the next
+    /// instruction executes, prompting for commands if execution should
+    /// pause. Returns whether execution was paused.
+    pub(crate) fn check(&mut self, intr: &mut Interpreter) -> bool {
+        let mut reasons = Vec::new();
+        for &address in &self.project.watchpoints {
+            let value = intr.memory[usize::from(address)];
+            match self.watched.insert(address, value) {
+                Some(last) if last != value => reasons.push(format!(
+                    "Watchpoint {address:#05X} changed: {last:#04X} -> {value:#04X}"
+                )),
+                _ => {}
+            }
+        }
+        let pc = u16::try_from(intr.pc).unwrap_or(u16::MAX);
+        if let Some(reason) = self.breakpoint(intr, pc) {
+            reasons.push(reason);
+        }
+        let frames = self.frames;
+        self.pauses.retain(|&at| {
+            let reached = match at {
+                PauseAt::Frame(frame) => frame <= frames,
+                PauseAt::Pc(address) => address == pc,
+            };
+            if reached {
+                reasons.push(format!("Reached {at}"));
+            }
+            !reached
+        });
+
+        let paused = self.steps == Some(0) || !reasons.is_empty();
+        if paused {
+            for reason in reasons {
+                println!("{reason}");
+            }
+            self.prompt(intr);
+        }
+        if let Some(steps) = self.steps {
+            self.steps = Some(steps.saturating_sub(1));
+        }
+        paused
+    }
+
+    /// Samples the pinned values at the end of a frame.
+    pub(crate) fn end_frame(&mut self, memory: &[u8], registers: &[u8]) {
+        self.frames += 1;
+        for plot in &mut self.plots {
+            plot.record(plot.pin.read(memory, registers));
+        }
+    }
+
+    /// Reads and executes commands until execution is resumed.
+    fn prompt(&mut self, intr: &mut Interpreter) {
+        self.show_displays(intr);
+        println!("{}", self.location(intr));
+        let stdin = io::stdin();
+        loop {
+            print!("(etherea) ");
+            io::stdout().flush().ok();
+            let mut line = String::new();
+            if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+                // Standard input was closed, so there's no way to resume later.
+                self.steps = None;
+                return;
+            }
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words[..] {
+                [] => {}
+                ["c" | "continue"] => {
+                    self.steps = None;
+                    return;
+                }
+                ["s" | "step"] => {
+                    self.steps = Some(1);
+                    return;
+                }
+                ["s" | "step", n] => match n.parse() {
+                    Ok(n) => {
+                        self.steps = Some(n);
+                        return;
+                    }
+                    Err(_) => println!("Invalid step count: '{n}'"),
+                },
+                ["b" | "break", address] => self.edit(address, |project, address| {
+                    project.breakpoints.insert(address);
+                    project.conditions.remove(&address);
+                }),
+                ["b" | "break", address, "when" | "if", ..] => {
+                    self.break_when(address, &rest(&line, 3));
+                }
+                ["d" | "delete", address] => self.edit(address, |project, address| {
+                    project.breakpoints.remove(&address);
+                    project.conditions.remove(&address);
+                }),
+                ["p" | "print", _, ..] => self.print(intr, &rest(&line, 1)),
+                ["display"] => self.show_displays(intr),
+                ["display", ..] => self.display(intr, rest(&line, 1)),
+                ["undisplay", n] => self.undisplay(n),
+                ["w" | "watch", ref range @ ..] if !range.is_empty() => {
+                    self.watch(range, true);
+                }
+                ["unwatch", ref range @ ..] if !range.is_empty() => self.watch(range, false),
+                ["m" | "mem", ref range @ ..] => {
+                    match parse_range(range, self.rom.len(), DUMP_LENGTH) {
+                        Some(range) => print!("{}", hexdump(&intr.memory[..], range)),
+                        None => println!("Invalid range. Type 'help' for usage."),
+                    }
+                }
+                ["poke", address, ref bytes @ ..] if !bytes.is_empty() => {
+                    self.poke(intr, address, bytes);
+                }
+                ["patch", path] => self.export_patch(Path::new(path)),
+                // Everything after the address is the comment's text.
+                ["comment", address, ..] => self.comment(address, rest(&line, 2)),
+                ["find", ref args @ ..] => self.find(intr, args),
+                ["cheats", ref args @ ..] => cheats(intr, args),
+                ["pin", pin] => self.pin(pin, Plot::DEFAULT_FRAMES),
+                ["pin", pin, frames] => match frames.parse() {
+                    Ok(frames) if frames > 0 => self.pin(pin, frames),
+                    _ => println!("Invalid frame count: '{frames}'"),
+                },
+                ["unpin", pin] => match Pin::parse(pin) {
+                    Some(pin) => self.plots.retain(|plot| plot.pin != pin),
+                    None => println!("Invalid address or register: '{pin}'"),
+                },
+                ["plot"] => {
+                    if self.plots.is_empty() {
+                        println!("Nothing is pinned. Type 'help' for usage.");
+                    }
+                    for plot in &self.plots {
+                        println!("{plot}");
+                    }
+                }
+                ["x" | "explain"] => println!("{}", explain(intr, intr.pc)),
+                ["x" | "explain", address] => match self.address(address) {
+                    Some(address) => println!("{}", explain(intr, usize::from(address))),
+                    None => println!("Invalid address: '{address}'"),
+                },
+                ["r" | "regs"] => println!("{}", registers(intr)),
+                ["screenshot", path] => {
+                    match record::screenshot(&intr.framebuffer(), Path::new(path)) {
+                        Ok(()) => println!("Wrote screenshot to {path}"),
+                        Err(e) => println!("Could not write screenshot: {e}"),
+                    }
+                }
+                ["l" | "list"] => self.list(),
+                ["h" | "help"] => println!("{HELP}"),
+                ["q" | "quit"] => std::process::exit(0),
+                _ => println!("Unknown command. Type 'help' for a list of commands."),
+            }
+        }
+    }
+
+    /// Parses a label from the symbol file, or an address. Labels come
+    /// first, since some, like `beef`, are also valid addresses.
+    fn address(&self, address: &str) -> Option<u16> {
+        self.symbols
+            .resolve(address)
+            .or_else(|| parse_address(address))
+    }
+
+    /// Formats `address`, along with its label if it has one, like
+    /// `0x206 <draw+2>`.
+    fn name(&self, address: u16) -> String {
+        match self.symbols.describe(address) {
+            Some(label) => format!("{address:#05X} <{label}>"),
+            None => format!("{address:#05X}"),
+        }
+    }
+
+    /// The reason to pause at a breakpoint at `pc`, if there is one and its
+    /// condition (if any) is true.
+    fn breakpoint(&self, intr: &Interpreter, pc: u16) -> Option<String> {
+        if !self.project.breakpoints.contains(&pc) {
+            return None;
+        }
+        let name = self.name(pc);
+        let Some(condition) = self.project.conditions.get(&pc) else {
+            return Some(format!("Breakpoint at {name}"));
+        };
+        match Expr::parse(condition, &self.symbols) {
+            Ok(expr) if expr.eval(intr) == 0 => None,
+            Ok(_) => Some(format!("Breakpoint at {name} when {condition}")),
+            Err(e) => Some(format!(
+                "Breakpoint at {name}, whose condition is invalid: {e}"
+            )),
+        }
+    }
+
+    /// Sets a breakpoint at `address` that only pauses when `condition` is
+    /// true.
+    fn break_when(&mut self, address: &str, condition: &str) {
+        match Expr::parse(condition, &self.symbols) {
+            Ok(_) => self.edit(address, |project, address| {
+                project.breakpoints.insert(address);
+                project.conditions.insert(address, condition.to_string());
+            }),
+            Err(e) => println!("Invalid condition: {e}"),
+        }
+    }
+
+    /// Prints the value of `expr`.
+    fn print(&self, intr: &Interpreter, expr: &str) {
+        match Expr::parse(expr, &self.symbols) {
+            Ok(expr) => println!("{}", value(expr.eval(intr))),
+            Err(e) => println!("Invalid expression: {e}"),
+        }
+    }
+
+    /// Shows `display` whenever execution pauses, starting now.
+    fn display(&mut self, intr: &Interpreter, display: String) {
+        match Expr::parse(&display, &self.symbols) {
+            Ok(expr) => {
+                let n = self.project.displays.len();
+                println!("{n}: {display} = {}", value(expr.eval(intr)));
+                self.project.displays.push(display);
+                self.save();
+            }
+            Err(e) => println!("Invalid expression: {e}"),
+        }
+    }
+
+    /// Stops showing the expression numbered `n`.
+    fn undisplay(&mut self, n: &str) {
+        match n.parse() {
+            Ok(n) if n < self.project.displays.len() => {
+                self.project.displays.remove(n);
+                self.save();
+            }
+            _ => println!("No such expression: '{n}'"),
+        }
+    }
+
+    /// Applies `edit` to the project with the parsed `address`, then saves it.
+    fn edit(&mut self, address: &str, edit: impl FnOnce(&mut Project, u16)) {
+        let Some(address) = self.address(address) else {
+            println!("Invalid address: '{address}'");
+            return;
+        };
+        edit(&mut self.project, address);
+        self.save();
+    }
+
+    /// Attaches `text` to `address`, or removes its comment if `text` is
+    /// empty.
+    fn comment(&mut self, address: &str, text: String) {
+        self.edit(address, |project, address| {
+            if text.is_empty() {
+                project.comments.remove(&address);
+            } else {
+                project.comments.insert(address, text);
+            }
+        });
+    }
+
+    /// Adds (or removes, if not `watch`) a watchpoint for every address in
+    /// the range parsed from `args`, then saves the project.
+    fn watch(&mut self, args: &[&str], watch: bool) {
+        let Some(range) = parse_range(args, self.rom.len(), 1) else {
+            println!("Invalid range. Type 'help' for usage.");
+            return;
+        };
+        for address in range.filter_map(|address| u16::try_from(address).ok()) {
+            if watch {
+                self.project.watchpoints.insert(address);
+            } else {
+                self.project.watchpoints.remove(&address);
+                self.watched.remove(&address);
+            }
+        }
+        self.save();
+    }
+
+    /// Overwrites memory starting at `address` with `bytes`.
+    fn poke(&mut self, intr: &mut Interpreter, address: &str, bytes: &[&str]) {
+        let Some(start) = parse_address(address).map(usize::from) else {
+            println!("Invalid address: '{address}'");
+            return;
+        };
+        let bytes: Option<Vec<u8>> = bytes
+            .iter()
+            .map(|byte| parse_hex(byte).and_then(|byte| u8::try_from(byte).ok()))
+            .collect();
+        let Some(bytes) = bytes.filter(|bytes| start + bytes.len() <= intr.memory.len()) else {
+            println!("Invalid bytes, or they don't fit in memory. Type 'help' for usage.");
+            return;
+        };
+        intr.memory[start..start + bytes.len()].copy_from_slice(&bytes);
+        intr.forget(start..start + bytes.len());
+        for (address, &value) in (start..).zip(&bytes) {
+            if let Ok(address) = u16::try_from(address) {
+                self.edits.insert(address, value);
+            }
+        }
+        // Don't report our own writes as watchpoint changes.
+        for (address, &value) in (start..).zip(&bytes) {
+            if let Some(last) = u16::try_from(address)
+                .ok()
+                .and_then(|address| self.watched.get_mut(&address))
+            {
+                *last = value;
+            }
+        }
+        print!("{}", hexdump(&intr.memory[..], start..start + bytes.len()));
+    }
+
+    /// The patch that makes the edits made with `poke` to the ROM. Edits
+    /// before the ROM, to the font or the interpreter's memory, can't be
+    /// patched in, so they're left out.
+    fn patch(&self) -> Patch {
+        let mut patched = self.rom.clone();
+        for (&address, &value) in self.edits.range(ORIGIN..) {
+            let offset = usize::from(address - ORIGIN);
+            if patched.len() <= offset {
+                patched.resize(offset + 1, 0);
+            }
+            patched[offset] = value;
+        }
+        Patch::diff(&self.rom, &patched)
+    }
+
+    /// Writes the edits made with `poke` to the ROM to an IPS patch at
+    /// `path`.
+    fn export_patch(&self, path: &Path) {
+        let skipped = self.edits.range(..ORIGIN).count();
+        if skipped > 0 {
+            println!("Leaving out {skipped} byte(s) poked before the ROM, at 0x200");
+        }
+        let edits = self.patch();
+        if edits.is_empty() {
+            println!("Nothing in the ROM has been changed with poke.");
+            return;
+        }
+        match fs::write(path, edits.to_ips()) {
+            Ok(()) => println!("Wrote patch to {}", path.display()),
+            Err(e) => println!("Could not write patch: {e}"),
+        }
+    }
+
+    /// Saves the project, reporting (but otherwise ignoring) failures.
+    fn save(&self) {
+        if let Err(e) = self.project.save(&self.rom) {
+            error!("Could not save debugger project: {}", e);
+        }
+    }
+
+    /// Searches memory for a value, or narrows down the results of the
+    /// previous search, then prints the results.
+    fn find(&mut self, intr: &Interpreter, args: &[&str]) {
+        let memory = &intr.memory[..];
+        let filter = match args {
+            [] => None,
+            ["clear"] => {
+                self.search = None;
+                return;
+            }
+            ["changed"] => Some(Filter::Changed),
+            ["unchanged"] => Some(Filter::Unchanged),
+            ["increased"] => Some(Filter::Increased),
+            ["decreased"] => Some(Filter::Decreased),
+            ["u16", value] => {
+                let Some(value) = parse_hex(value) else {
+                    println!("Invalid value: '{value}'");
+                    return;
+                };
+                Some(Filter::Value(value.to_be_bytes().to_vec()))
+            }
+            bytes => {
+                let pattern: Option<Vec<u8>> = bytes
+                    .iter()
+                    .map(|byte| parse_hex(byte).and_then(|byte| u8::try_from(byte).ok()))
+                    .collect();
+                let Some(pattern) = pattern else {
+                    println!("Invalid byte pattern. Type 'help' for usage.");
+                    return;
+                };
+                Some(Filter::Value(pattern))
+            }
+        };
+        match (&mut self.search, filter) {
+            (Some(search), Some(filter)) => search.narrow(memory, &filter),
+            (None, Some(Filter::Value(pattern))) => {
+                self.search = Some(Search::new(memory, &pattern));
+            }
+            (None, Some(_)) => {
+                // With nothing to narrow down, remember every byte so the
+                // next search can compare against it.
+                self.search = Some(Search::new(memory, &[]));
+                println!("Recorded all of memory; search again once values change.");
+                return;
+            }
+            (_, None) => {}
+        }
+
+        let Some(search) = &self.search else {
+            println!("No search in progress. Type 'help' for usage.");
+            return;
+        };
+        println!("{} result(s)", search.results.len());
+        for (address, value) in search.results.iter().take(Search::LISTED) {
+            let value: Vec<String> = value.iter().map(|byte| format!("{byte:02X}")).collect();
+            println!("  {address:#05X}: {}", value.join(" "));
+        }
+        if search.results.len() > Search::LISTED {
+            println!("  ...");
+        }
+    }
+
+    /// Starts plotting `pin` over the last `frames` frames, replacing any
+    /// existing plot of it.
+    fn pin(&mut self, pin: &str, frames: usize) {
+        let Some(pin) = Pin::parse(pin) else {
+            println!("Invalid address or register: '{pin}'");
+            return;
+        };
+        self.plots.retain(|plot| plot.pin != pin);
+        self.plots.push(Plot::new(pin, frames));
+    }
+
+    /// Prints all breakpoints, watchpoints, comments, and expressions.
+    fn list(&self) {
+        let join = |set: &BTreeSet<u16>| {
+            set.iter()
+                .map(|&address| match self.project.conditions.get(&address) {
+                    Some(condition) => format!("{} when {condition}", self.name(address)),
+                    None => self.name(address),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        println!("Breakpoints: [{}]", join(&self.project.breakpoints));
+        println!("Watchpoints: [{}]", join(&self.project.watchpoints));
+        for (address, comment) in &self.project.comments {
+            println!("{address:#05X}: ; {comment}");
+        }
+        for (n, display) in self.project.displays.iter().enumerate() {
+            println!("{n}: {display}");
+        }
+    }
+
+    /// Prints the value of every expression shown when execution pauses.
+    fn show_displays(&self, intr: &Interpreter) {
+        for (n, display) in self.project.displays.iter().enumerate() {
+            match Expr::parse(display, &self.symbols) {
+                Ok(expr) => println!("{n}: {display} = {}", value(expr.eval(intr))),
+                Err(e) => println!("{n}: {display} is invalid: {e}"),
+            }
+        }
+    }
+
+    /// Describes the instruction about to execute, along with its label,
+    /// comment, and source line.
+    fn location(&self, intr: &Interpreter) -> String {
+        let byte = |address: usize| intr.memory.get(address).copied().unwrap_or_default();
+        let raw = u16::from_be_bytes([byte(intr.pc), byte(intr.pc + 1)]);
+        let pc = u16::try_from(intr.pc).unwrap_or(u16::MAX);
+        let mut s = crate::opcode::disassemble_word(pc, raw);
+        if let Some(label) = self.symbols.describe(pc) {
+            let _ = write!(s, " <{label}>");
+        }
+        if let Some(comment) = self.project.comments.get(&pc) {
+            s += " ; ";
+            s += comment;
+        }
+        if let (Some(line), Some(text)) = (self.symbols.line(pc), self.symbols.text(pc)) {
+            let _ = write!(s, "\n  {line}: {text}");
+        }
+        s
+    }
+}
+
+/// A moment to pause execution at: the start of a frame, or the next time
+/// an address is about to execute.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PauseAt {
+    /// The start of a frame, counted from `0`.
+    Frame(u64),
+    /// An address.
+    Pc(u16),
+}
+
+/// Parses `frame=<n>` or `pc=<addr>`.
+impl FromStr for PauseAt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid pause point '{s}', expected frame=<n> or pc=<addr>");
+        match s.split_once('=').ok_or_else(err)? {
+            ("frame", frame) => frame.parse().map(Self::Frame).map_err(|_| err()),
+            ("pc", address) => parse_address(address).map(Self::Pc).ok_or_else(err),
+            _ => Err(err()),
+        }
+    }
+}
+
+impl fmt::Display for PauseAt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Frame(frame) => write!(f, "frame {frame}"),
+            Self::Pc(address) => write!(f, "{address:#05X}"),
+        }
+    }
+}
+
+/// A condition a search result must meet to remain a result.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Filter {
+    /// The bytes at the address are currently these.
+    Value(Vec<u8>),
+    /// The value differs from the one seen by the last search.
+    Changed,
+    /// The value is the same as the one seen by the last search.
+    Unchanged,
+    /// The value is greater than the one seen by the last search.
+    Increased,
+    /// The value is less than the one seen by the last search.
+    Decreased,
+}
+
+/// A cheat-engine style memory search: the addresses matching the first
+/// search, narrowed down by the values seen there by later ones.
+#[derive(Debug, PartialEq, Eq)]
+struct Search {
+    /// Each matching address and the value seen there by the last search.
+    results: BTreeMap<u16, Vec<u8>>,
+}
+
+impl Search {
+    /// The maximum number of results printed after a search.
+    const LISTED: usize = 16;
+
+    /// Finds every address where `pattern` occurs in `memory`. An empty
+    /// pattern matches every byte.
+    fn new(memory: &[u8], pattern: &[u8]) -> Self {
+        let width = pattern.len().max(1);
+        let results = (0u16..)
+            .zip(memory.windows(width))
+            .filter(|(_, window)| pattern.is_empty() || *window == pattern)
+            .map(|(address, window)| (address, window.to_vec()))
+            .collect();
+        Self { results }
+    }
+
+    /// Keeps only the results that pass `filter`, then records their
+    /// current values.
+    fn narrow(&mut self, memory: &[u8], filter: &Filter) {
+        self.results.retain(|&address, last| {
+            let start = usize::from(address);
+            let width = match filter {
+                Filter::Value(pattern) => pattern.len(),
+                _ => last.len(),
+            };
+            let Some(value) = memory.get(start..start + width) else {
+                return false;
+            };
+            // Values are big-endian, so comparing bytewise compares numerically.
+            let keep = match filter {
+                Filter::Value(pattern) => value == pattern.as_slice(),
+                Filter::Changed => value != last.as_slice(),
+                Filter::Unchanged => value == last.as_slice(),
+                Filter::Increased => value > last.as_slice(),
+                Filter::Decreased => value < last.as_slice(),
+            };
+            *last = value.to_vec();
+            keep
+        });
+    }
+}
+
+/// A memory address or register whose value can be plotted.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Pin {
+    Memory(u16),
+    Register(u8),
+}
+
+impl Pin {
+    /// Parses a register (`V0` through `VF`) or a hexadecimal address.
+    fn parse(s: &str) -> Option<Self> {
+        match s.strip_prefix(['V', 'v']) {
+            Some(n) if n.len() == 1 => u8::from_str_radix(n, 16).ok().map(Self::Register),
+            _ => parse_address(s).map(Self::Memory),
+        }
+    }
+
+    /// The current value of the pinned address or register.
+    fn read(self, memory: &[u8], registers: &[u8]) -> u8 {
+        match self {
+            Self::Memory(address) => memory[usize::from(address)],
+            Self::Register(n) => registers[usize::from(n)],
+        }
+    }
+}
+
+impl fmt::Display for Pin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Memory(address) => write!(f, "{address:#05X}"),
+            Self::Register(n) => write!(f, "V{n:X}"),
+        }
+    }
+}
+
+/// The values of a pinned address or register over the most recent frames.
+#[derive(Debug)]
+struct Plot {
+    pin: Pin,
+    frames: usize,         // the number of frames of history to keep
+    history: VecDeque<u8>, // oldest first
+}
+
+impl Plot {
+    /// The number of frames plotted if none is given: one second.
+    const DEFAULT_FRAMES: usize = 60;
+    /// The maximum width of the plot, in characters.
+    const WIDTH: usize = 60;
+    /// The characters used to draw the plot, from lowest to highest.
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    fn new(pin: Pin, frames: usize) -> Self {
+        Self {
+            pin,
+            frames,
+            history: VecDeque::with_capacity(frames),
+        }
+    }
+
+    /// Records the value at the end of a frame, forgetting the oldest value
+    /// if the history is full.
+    fn record(&mut self, value: u8) {
+        if self.history.len() == self.frames {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+    }
+}
+
+/// Draws the history as a sparkline scaled between its minimum and maximum.
+/// Long histories are squeezed to [`Plot::WIDTH`] by taking the maximum of
+/// neighbouring frames.
+impl fmt::Display for Plot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (Some(&min), Some(&max), Some(&now)) = (
+            self.history.iter().min(),
+            self.history.iter().max(),
+            self.history.back(),
+        ) else {
+            return write!(f, "{:<6} (no frames yet)", self.pin.to_string());
+        };
+        let history: Vec<u8> = self.history.iter().copied().collect();
+        let bucket = history.len().div_ceil(Self::WIDTH);
+        let line: String = history
+            .chunks(bucket)
+            .map(|chunk| {
+                let value = chunk.iter().max().copied().unwrap_or(min);
+                let level = usize::from(value - min) * (Self::BARS.len() - 1)
+                    / usize::from(max - min).max(1);
+                Self::BARS[level]
+            })
+            .collect();
+        write!(
+            f,
+            "{:<6} {line}  min {min:#04X}  max {max:#04X}  now {now:#04X}  ({} frames)",
+            self.pin.to_string(),
+            self.history.len()
+        )
+    }
+}
+
+const HELP: &str = "\
+Commands:
+  c, continue       resume execution
+  s, step [n]       execute n instructions (default 1), then pause
+  b, break <addr>   set a breakpoint
+  b, break <addr> when <expr>
+                    set a breakpoint that only pauses when <expr> is true
+  d, delete <addr>  remove a breakpoint
+  w, watch <range>  pause when any byte in <range> changes
+  unwatch <range>   remove the watchpoints in <range>
+  m, mem [range]    hexdump <range> (default: 64 bytes from 0x000)
+  poke <addr> <byte>...
+                    overwrite memory at <addr> with the bytes
+  patch <path>      write the bytes poked into the ROM to an IPS patch
+  comment <addr> [text]
+                    attach a comment to <addr> (or remove it, without text)
+  find <byte>...    search memory for a sequence of bytes
+  find u16 <value>  search memory for a 16-bit value
+  find changed|unchanged|increased|decreased
+                    narrow down the results (watch them with 'watch')
+  find clear        forget the results and start a new search
+  cheats [n]        list the cheats, or turn cheat n on or off
+  pin <addr|Vx> [n] plot a byte of memory or a register over the last n frames
+  unpin <addr|Vx>   stop plotting it
+  plot              draw the pinned values
+  p, print <expr>   show the value of <expr>
+  display [expr]    show <expr> whenever execution pauses (or show them all now)
+  undisplay <n>     stop showing expression <n>
+  x, explain [addr] describe the instruction at <addr> (default: the next one)
+  r, regs           show registers, timers, and the stack
+  screenshot <path> save the screen as a PNG
+  l, list           list breakpoints, watchpoints, and comments
+  q, quit           exit etherea
+
+A <range> is <addr> [length], or 'font' or 'rom' for the font and the loaded ROM.
+break, delete, comment, and explain also take labels from the ROM's symbol file.
+An <expr> combines numbers (decimal, or with 0x or 0b), V0-VF, I, PC, DT, ST, SP (the
+stack depth), labels, and memory reads like [I+1] with + - & | ^ == != < <= > >= && || !";
+
+/// The text of `line` after its first `words` words, trimmed.
+fn rest(line: &str, words: usize) -> String {
+    let rest = line
+        .trim()
+        .splitn(words + 1, char::is_whitespace)
+        .nth(words);
+    rest.unwrap_or_default().trim().to_string()
+}
+
+/// Formats the value of an expression in decimal, and in hexadecimal if
+/// it isn't negative.
+fn value(value: i64) -> String {
+    if value < 0 {
+        value.to_string()
+    } else {
+        format!("{value} ({value:#X})")
+    }
+}
+
+/// The number of bytes dumped by `mem` if no length is given.
+const DUMP_LENGTH: usize = 64;
+/// The number of bytes on each line of a hexdump.
+const DUMP_WIDTH: usize = 16;
+
+/// Lists the cheats loaded with `--cheats`, or toggles the one numbered
+/// by the first argument.
+fn cheats(intr: &mut Interpreter, args: &[&str]) {
+    let Some(cheats) = intr
+        .cheats
+        .as_mut()
+        .filter(|cheats| !cheats.cheats.is_empty())
+    else {
+        println!("No cheats are loaded. Run with --cheats <file> to load some.");
+        return;
+    };
+    match args {
+        [] => {}
+        [n] => {
+            let Some(cheat) = n.parse().ok().and_then(|n: usize| cheats.cheats.get_mut(n)) else {
+                println!("Invalid cheat: '{n}'");
+                return;
+            };
+            cheat.enabled = !cheat.enabled;
+        }
+        _ => {
+            println!("Type 'help' for usage.");
+            return;
+        }
+    }
+    for (n, cheat) in cheats.cheats.iter().enumerate() {
+        println!("{n}: {cheat}");
+    }
+}
+
+/// Describes the instruction at `address`.
+fn explain(intr: &Interpreter, address: usize) -> String {
+    let (Some(&hi), Some(&lo)) = (intr.memory.get(address), intr.memory.get(address + 1)) else {
+        return format!("{address:#05X} is out of bounds");
+    };
+    let raw = u16::from_be_bytes([hi, lo]);
+    match Doc::lookup(raw) {
+        Some(doc) => format!("{address:#05X}: {raw:04X}\n{doc}"),
+        None => format!("{address:#05X}: {raw:04X} is not an instruction"),
+    }
+}
+
+/// Formats the registers, timers, and stack.
+pub(crate) fn registers(intr: &Interpreter) -> String {
+    let registers: Vec<String> = intr
+        .registers
+        .iter()
+        .enumerate()
+        .map(|(n, value)| format!("V{n:X}: {value:#04X}"))
+        .collect();
+    let stack: Vec<String> = intr.stack.iter().map(|a| format!("{a:#05X}")).collect();
+    format!(
+        "PC: {:#05X}  I: {:#05X}  DT: {}  ST: {}\n{}\n{}\nStack: [{}]",
+        intr.pc,
+        intr.i,
+        intr.timers.delay,
+        intr.timers.sound,
+        registers[..8].join("  "),
+        registers[8..].join("  "),
+        stack.join(", ")
+    )
+}
+
+/// Formats `range` of `memory` as lines of hexadecimal bytes followed by
+/// their printable ASCII characters.
+pub(crate) fn hexdump(memory: &[u8], range: Range<usize>) -> String {
+    let mut s = String::new();
+    for start in range.clone().step_by(DUMP_WIDTH) {
+        let bytes = &memory[start..(start + DUMP_WIDTH).min(range.end)];
+        let hex: Vec<String> = bytes.iter().map(|byte| format!("{byte:02X}")).collect();
+        let ascii: String = bytes
+            .iter()
+            .map(|&byte| {
+                if byte.is_ascii_graphic() || byte == b' ' {
+                    char::from(byte)
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let _ = writeln!(
+            s,
+            "{start:#05X}  {:<width$}  |{ascii}|",
+            hex.join(" "),
+            width = DUMP_WIDTH * 3 - 1
+        );
+    }
+    s
+}
+
+/// Parses a range of memory: `font`, `rom` (a ROM of `rom_len` bytes), or a
+/// hexadecimal address followed by an optional decimal length, which
+/// defaults to `length`. Ranges are cut off at the end of memory.
+fn parse_range(args: &[&str], rom_len: usize, length: usize) -> Option<Range<usize>> {
+    let range = match args {
+        ["font"] => *font::MEMORY_RANGE.start()..*font::MEMORY_RANGE.end() + 1,
+        ["rom"] => Interpreter::MEMORY_OFFSET..Interpreter::MEMORY_OFFSET + rom_len,
+        [] => 0..length,
+        [address] => {
+            let start = usize::from(parse_address(address)?);
+            start..start + length
+        }
+        [address, length] => {
+            let start = usize::from(parse_address(address)?);
+            start..start + length.parse::<usize>().ok()?
+        }
+        _ => return None,
+    };
+    let range = range.start..range.end.min(Interpreter::MEMORY_SIZE);
+    (!range.is_empty()).then_some(range)
+}
+
+/// Parses an address given in hexadecimal, with or without a `0x` prefix.
+pub(crate) fn parse_address(s: &str) -> Option<u16> {
+    parse_hex(s).filter(|&address| address < 0x1000)
+}
+
+/// Parses a value given in hexadecimal, with or without a `0x` prefix.
+fn parse_hex(s: &str) -> Option<u16> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    u16::from_str_radix(digits, 16).ok()
+}
+
+/// Hashes a ROM with 64-bit FNV-1a, which is stable across platforms and
+/// releases (unlike [`std::hash::DefaultHasher`]).
+#[must_use]
+pub fn hash(rom: &[u8]) -> u64 {
+    rom.iter().fold(0xCBF2_9CE4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01B3)
+    })
+}
+
+/// Serializes addresses as hexadecimal strings, which TOML requires for map
+/// keys and which are easier to read than decimal numbers.
+mod hex {
+    fn format(address: u16) -> String {
+        format!("{address:#05X}")
+    }
+
+    fn parse<E: serde::de::Error>(s: &str) -> Result<u16, E> {
+        super::parse_address(s).ok_or_else(|| E::custom(format!("invalid address '{s}'")))
+    }
+
+    pub mod set {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::collections::BTreeSet;
+
+        pub fn serialize<S: Serializer>(set: &BTreeSet<u16>, s: S) -> Result<S::Ok, S::Error> {
+            s.collect_seq(set.iter().map(|&address| super::format(address)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<BTreeSet<u16>, D::Error> {
+            Vec::<String>::deserialize(d)?
+                .iter()
+                .map(|s| super::parse(s))
+                .collect()
+        }
+    }
+
+    pub mod map {
+        use serde::{Deserialize, Deserializer, Serializer};
+        use std::collections::BTreeMap;
+
+        pub fn serialize<S: Serializer>(
+            map: &BTreeMap<u16, String>,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            s.collect_map(map.iter().map(|(&address, v)| (super::format(address), v)))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            d: D,
+        ) -> Result<BTreeMap<u16, String>, D::Error> {
+            BTreeMap::<String, String>::deserialize(d)?
+                .into_iter()
+                .map(|(k, v)| Ok((super::parse(&k)?, v)))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_round_trip() {
+        let project = Project {
+            breakpoints: BTreeSet::from([0x200, 0x2A4]),
+            watchpoints: BTreeSet::from([0xF00]),
+            comments: BTreeMap::from([(0x202, "main loop".to_string())]),
+            conditions: BTreeMap::from([(0x2A4, "V3 == 0x10".to_string())]),
+            displays: vec!["[I] + 1".to_string()],
+        };
+        let s = toml::to_string(&project).unwrap();
+        assert!(s.contains("\"0x2A4\""));
+        assert_eq!(toml::from_str::<Project>(&s).unwrap(), project);
+        assert_eq!(toml::from_str::<Project>("").unwrap(), Project::default());
+    }
+
+    #[test]
+    fn addresses() {
+        assert_eq!(parse_address("0x2a4"), Some(0x2A4));
+        assert_eq!(parse_address("2A4"), Some(0x2A4));
+        assert_eq!(parse_address("0x1000"), None);
+        assert_eq!(parse_address("zz"), None);
+    }
+
+    #[test]
+    fn symbols() {
+        let source = "start:\n  LD V0, 1\n  JP start\nbeef: RET\n";
+        let (rom, map) = crate::asm::assemble_with_map(source).unwrap();
+        let mut debugger = Debugger::new(&rom);
+        debugger.use_symbols(map.with_source(PathBuf::from("game.asm"), source));
+        assert_eq!(debugger.address("start"), Some(0x200));
+        assert_eq!(debugger.address("beef"), Some(0x204));
+        assert_eq!(debugger.address("0x202"), Some(0x202));
+        assert_eq!(debugger.name(0x202), "0x202 <start+2>");
+
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.pc = 0x202;
+        let location = debugger.location(&intr);
+        assert!(location.contains(" <start+2>"));
+        assert!(location.ends_with("\n  3: JP start"));
+    }
+
+    #[test]
+    fn conditional_breakpoints() {
+        let mut debugger = Debugger::new(&[0x00, 0xE0]);
+        debugger.project.breakpoints.insert(0x200);
+        debugger
+            .project
+            .conditions
+            .insert(0x200, "V3 == 0x10".into());
+        let mut intr = Interpreter::new();
+        assert_eq!(debugger.breakpoint(&intr, 0x200), None);
+        intr.registers[3] = 0x10;
+        assert_eq!(
+            debugger.breakpoint(&intr, 0x200).as_deref(),
+            Some("Breakpoint at 0x200 when V3 == 0x10")
+        );
+        assert_eq!(debugger.breakpoint(&intr, 0x202), None);
+
+        assert_eq!(rest("b 0x200 when V3 == 1\n", 3), "V3 == 1");
+        assert_eq!(value(16), "16 (0x10)");
+        assert_eq!(value(-1), "-1");
+    }
+
+    #[test]
+    fn patches_pokes() {
+        let rom = [0x60, 0x01, 0x12, 0x00];
+        let mut debugger = Debugger::new(&rom);
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        debugger.poke(&mut intr, "0x201", &["05"]);
+        debugger.poke(&mut intr, "0x205", &["AA"]);
+        debugger.poke(&mut intr, "0x100", &["FF"]);
+        // Poking a byte back to what the ROM has undoes the edit.
+        debugger.poke(&mut intr, "0x202", &["13", "12"]);
+        debugger.poke(&mut intr, "0x202", &["12"]);
+        let mut patched = rom.to_vec();
+        debugger.patch().apply(&mut patched);
+        assert_eq!(patched, [0x60, 0x05, 0x12, 0x12, 0x00, 0xAA]);
+        assert_eq!(patched[..], intr.memory[0x200..0x206]);
+    }
+
+    #[test]
+    fn search() {
+        let mut memory = [0u8; 8];
+        memory[2..4].copy_from_slice(&[0xDE, 0xAD]);
+        memory[5] = 3;
+        assert_eq!(
+            Search::new(&memory, &[0xDE, 0xAD]).results,
+            BTreeMap::from([(2, vec![0xDE, 0xAD])])
+        );
+
+        let mut search = Search::new(&memory, &[]);
+        assert_eq!(search.results.len(), 8);
+        memory[5] = 2;
+        memory[6] = 1;
+        search.narrow(&memory, &Filter::Changed);
+        assert_eq!(search.results.keys().copied().collect::<Vec<_>>(), [5, 6]);
+        search.narrow(&memory, &Filter::Unchanged);
+        assert_eq!(search.results.len(), 2);
+        memory[5] = 1;
+        memory[6] = 2;
+        search.narrow(&memory, &Filter::Decreased);
+        assert_eq!(search.results, BTreeMap::from([(5, vec![1])]));
+        search.narrow(&memory, &Filter::Value(vec![2]));
+        assert!(search.results.is_empty());
+    }
+
+    #[test]
+    fn plot() {
+        assert_eq!(Pin::parse("vA"), Some(Pin::Register(0xA)));
+        assert_eq!(Pin::parse("0x2F0"), Some(Pin::Memory(0x2F0)));
+        assert_eq!(Pin::parse("VG"), None);
+
+        let mut plot = Plot::new(Pin::Register(3), 4);
+        assert_eq!(plot.to_string(), "V3     (no frames yet)");
+        for value in [9, 0, 7, 14, 14] {
+            plot.record(value);
+        }
+        assert_eq!(
+            plot.to_string(),
+            "V3     ▁▄██  min 0x00  max 0x0E  now 0x0E  (4 frames)"
+        );
+    }
+
+    #[test]
+    fn memory_ranges() {
+        assert_eq!(parse_range(&["font"], 0, 1), Some(0x50..0xA0));
+        assert_eq!(parse_range(&["rom"], 0x84, 1), Some(0x200..0x284));
+        assert_eq!(parse_range(&[], 0, 64), Some(0..64));
+        assert_eq!(parse_range(&["0x300"], 0, 1), Some(0x300..0x301));
+        assert_eq!(parse_range(&["0x300", "32"], 0, 1), Some(0x300..0x320));
+        assert_eq!(parse_range(&["0xFF0", "32"], 0, 1), Some(0xFF0..0x1000));
+        assert_eq!(parse_range(&["0x300", "0"], 0, 1), None);
+        assert_eq!(parse_range(&["rom"], 0, 1), None);
+
+        let mut memory = [0u8; 0x20];
+        memory[0x10..0x14].copy_from_slice(b"HI!\x00");
+        assert_eq!(
+            hexdump(&memory, 0x0E..0x14),
+            format!("0x00E  {:<47}  |..HI!.|\n", "00 00 48 49 21 00")
+        );
+        assert_eq!(hexdump(&memory, 0x00..0x20).lines().count(), 2);
+    }
+
+    #[test]
+    fn pause_points() {
+        assert_eq!("frame=3600".parse(), Ok(PauseAt::Frame(3600)));
+        assert_eq!("pc=0x2F0".parse(), Ok(PauseAt::Pc(0x2F0)));
+        assert!("pc=0x1000".parse::<PauseAt>().is_err());
+        assert!("frame".parse::<PauseAt>().is_err());
+        assert!("cycle=3".parse::<PauseAt>().is_err());
+
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[0x12, 0x00]); // JP 0x200
+        let mut debugger = Debugger::new(&[]);
+        debugger.resume();
+        debugger.pause_at(PauseAt::Frame(2));
+        for _ in 0..2 {
+            assert!(!debugger.check(&mut intr));
+            debugger.end_frame(&intr.memory[..], &intr.registers[..]);
+        }
+        // The next check pauses, which would prompt on standard input.
+        assert_eq!(debugger.pauses, [PauseAt::Frame(2)]);
+        assert_eq!(debugger.frames, 2);
+    }
+
+    #[test]
+    fn stable_hash() {
+        assert_eq!(hash(b""), 0xCBF2_9CE4_8422_2325);
+        assert_eq!(hash(b"a"), 0xAF63_DC4C_8601_EC8C);
+    }
+}
@@ -0,0 +1,295 @@
+use crate::{
+    asm::{self, SourceMap},
+    Interpreter,
+};
+
+/// Something in the interpreter an expression can read.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Source {
+    /// A general purpose register, `V0` through `VF`.
+    V(u8),
+    /// The index register, `I`.
+    I,
+    /// The program counter, `PC`.
+    Pc,
+    /// The delay timer, `DT`.
+    Dt,
+    /// The sound timer, `ST`.
+    St,
+    /// The depth of the stack, `SP`.
+    Sp,
+}
+
+/// An operator between two expressions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Or,
+    And,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+impl Op {
+    /// The operators, longest first so `<=` isn't read as `<`.
+    const ALL: [(&'static str, Self); 13] = [
+        ("||", Self::Or),
+        ("&&", Self::And),
+        ("==", Self::Eq),
+        ("!=", Self::Ne),
+        ("<=", Self::Le),
+        (">=", Self::Ge),
+        ("<", Self::Lt),
+        (">", Self::Gt),
+        ("|", Self::BitOr),
+        ("^", Self::BitXor),
+        ("&", Self::BitAnd),
+        ("+", Self::Add),
+        ("-", Self::Sub),
+    ];
+
+    /// How tightly the operator binds: higher binds tighter.
+    const fn precedence(self) -> u8 {
+        match self {
+            Self::Or => 1,
+            Self::And => 2,
+            Self::Eq | Self::Ne | Self::Lt | Self::Le | Self::Gt | Self::Ge => 3,
+            Self::BitOr | Self::BitXor | Self::BitAnd => 4,
+            Self::Add | Self::Sub => 5,
+        }
+    }
+
+    fn apply(self, a: i64, b: i64) -> i64 {
+        match self {
+            Self::Or => i64::from(a != 0 || b != 0),
+            Self::And => i64::from(a != 0 && b != 0),
+            Self::Eq => i64::from(a == b),
+            Self::Ne => i64::from(a != b),
+            Self::Lt => i64::from(a < b),
+            Self::Le => i64::from(a <= b),
+            Self::Gt => i64::from(a > b),
+            Self::Ge => i64::from(a >= b),
+            Self::BitOr => a | b,
+            Self::BitXor => a ^ b,
+            Self::BitAnd => a & b,
+            Self::Add => a.wrapping_add(b),
+            Self::Sub => a.wrapping_sub(b),
+        }
+    }
+}
+
+/// An expression over the interpreter's state, like `V3 == 0x10 && [I] > 2`,
+/// used by conditional breakpoints and watch expressions.
+///
+/// Numbers are decimal unless prefixed with `0x` or `0b`. Names are
+/// registers (`V0`-`VF`, `I`, `PC`, `DT`, `ST`, and `SP` for the depth of
+/// the stack) or labels from the ROM's symbol file, and `[address]` reads
+/// a byte of memory. Comparisons and `!` give `1` for true and `0` for
+/// false.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Expr(Node);
+
+/// A node of an expression's syntax tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Node {
+    Number(i64),
+    Read(Source),
+    Memory(Box<Node>),
+    Not(Box<Node>),
+    Binary(Op, Box<Node>, Box<Node>),
+}
+
+impl Expr {
+    /// Parses `s`, looking up labels in `symbols`.
+    pub(crate) fn parse(s: &str, symbols: &SourceMap) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(s)?,
+            position: 0,
+            symbols,
+        };
+        let node = parser.binary(0)?;
+        match parser.tokens.get(parser.position) {
+            None => Ok(Self(node)),
+            Some(token) => Err(format!("Unexpected '{token}'")),
+        }
+    }
+
+    /// The value of the expression for the current state of `intr`.
+    pub(crate) fn eval(&self, intr: &Interpreter) -> i64 {
+        self.0.eval(intr)
+    }
+}
+
+impl Node {
+    fn eval(&self, intr: &Interpreter) -> i64 {
+        match self {
+            Self::Number(n) => *n,
+            Self::Read(source) => match *source {
+                Source::V(x) => intr.registers[usize::from(x)].into(),
+                Source::I => intr.i.into(),
+                Source::Pc => i64::try_from(intr.pc).unwrap_or_default(),
+                Source::Dt => intr.timers.delay.into(),
+                Source::St => intr.timers.sound.into(),
+                Source::Sp => i64::try_from(intr.stack.len()).unwrap_or_default(),
+            },
+            Self::Memory(address) => usize::try_from(address.eval(intr))
+                .ok()
+                .and_then(|address| intr.memory.get(address))
+                .copied()
+                .unwrap_or_default()
+                .into(),
+            Self::Not(expr) => i64::from(expr.eval(intr) == 0),
+            Self::Binary(op, a, b) => op.apply(a.eval(intr), b.eval(intr)),
+        }
+    }
+}
+
+/// Splits `s` into numbers, names, operators, and brackets.
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = s.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_alphanumeric() || c == '_' {
+            rest.find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len())
+        } else if let Some((op, _)) = Op::ALL.iter().find(|(op, _)| rest.starts_with(op)) {
+            op.len()
+        } else if "!()[]".contains(c) {
+            1
+        } else {
+            return Err(format!("Unexpected '{c}'"));
+        };
+        tokens.push(rest[..length].to_string());
+        rest = rest[length..].trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Parses tokens into [`Node`]s by precedence climbing.
+struct Parser<'a> {
+    tokens: Vec<String>,
+    position: usize,
+    symbols: &'a SourceMap,
+}
+
+impl Parser<'_> {
+    /// The next token, consuming it.
+    fn next(&mut self) -> Result<&str, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or("Unexpected end of expression")?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token, which must be `expected`.
+    fn expect(&mut self, expected: &str) -> Result<(), String> {
+        match self.next()? {
+            token if token == expected => Ok(()),
+            token => Err(format!("Expected '{expected}', found '{token}'")),
+        }
+    }
+
+    /// Parses operands joined by operators that bind at least as tightly
+    /// as `precedence`.
+    fn binary(&mut self, precedence: u8) -> Result<Node, String> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.tokens.get(self.position).and_then(|token| {
+            Op::ALL
+                .iter()
+                .find(|(op, _)| op == token)
+                .map(|&(_, op)| op)
+                .filter(|op| op.precedence() >= precedence)
+        }) {
+            self.position += 1;
+            let rhs = self.binary(op.precedence() + 1)?;
+            expr = Node::Binary(op, Box::new(expr), Box::new(rhs));
+        }
+        Ok(expr)
+    }
+
+    /// Parses a single operand: a number, name, memory read, negation, or
+    /// an expression in parentheses.
+    fn unary(&mut self) -> Result<Node, String> {
+        let symbols = self.symbols;
+        let token = self.next()?.to_string();
+        match token.as_str() {
+            "!" => Ok(Node::Not(Box::new(self.unary()?))),
+            "(" => {
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            "[" => {
+                let expr = self.binary(0)?;
+                self.expect("]")?;
+                Ok(Node::Memory(Box::new(expr)))
+            }
+            number if number.starts_with(|c: char| c.is_ascii_digit()) => asm::parse_number(number)
+                .map(|n| Node::Number(n.into()))
+                .ok_or_else(|| format!("Invalid number '{number}'")),
+            name if name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') => {
+                let upper = name.to_ascii_uppercase();
+                let source = match upper.as_str() {
+                    "I" => Some(Source::I),
+                    "PC" => Some(Source::Pc),
+                    "DT" => Some(Source::Dt),
+                    "ST" => Some(Source::St),
+                    "SP" => Some(Source::Sp),
+                    _ => upper
+                        .strip_prefix('V')
+                        .filter(|n| n.len() == 1)
+                        .and_then(|n| u8::from_str_radix(n, 16).ok())
+                        .map(Source::V),
+                };
+                match (source, symbols.resolve(name)) {
+                    (Some(source), _) => Ok(Node::Read(source)),
+                    (None, Some(address)) => Ok(Node::Number(address.into())),
+                    (None, None) => Err(format!("Unknown name '{name}'")),
+                }
+            }
+            token => Err(format!("Unexpected '{token}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expressions() {
+        let source = "start: CLS\ndata: db 7\n";
+        let (rom, symbols) = asm::assemble_with_map(source).unwrap();
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.registers[3] = 0x10;
+        intr.i = 0x202;
+        let eval = |s: &str| Expr::parse(s, &symbols).map(|expr| expr.eval(&intr));
+
+        assert_eq!(eval("V3 == 0x10"), Ok(1));
+        assert_eq!(eval("v3 != 16"), Ok(0));
+        assert_eq!(eval("[I] + 1"), Ok(8));
+        assert_eq!(eval("[data] == 7 && PC == start"), Ok(1));
+        assert_eq!(eval("1 + 2 == 3 || 0"), Ok(1));
+        assert_eq!(eval("!(V3 & 0b10000) | SP"), Ok(0));
+        assert_eq!(eval("2 - 3 < 0"), Ok(1));
+        assert_eq!(eval("[0x1000]"), Ok(0));
+
+        assert_eq!(eval("V3 =="), Err("Unexpected end of expression".into()));
+        assert_eq!(eval("V3 V4"), Err("Unexpected 'V4'".into()));
+        assert_eq!(eval("[I"), Err("Unexpected end of expression".into()));
+        assert_eq!(eval("nowhere"), Err("Unknown name 'nowhere'".into()));
+        assert_eq!(eval("V3 = 1"), Err("Unexpected '='".into()));
+        assert_eq!(eval("== 1"), Err("Unexpected '=='".into()));
+    }
+}
@@ -0,0 +1,220 @@
+use crate::{
+    analyze::Analysis, database::Entry, opcode::Platform, quirks::JumpOffset, timing::Speed,
+    Interpreter,
+};
+use log::warn;
+use serde::Deserialize;
+use std::{fmt, fs, io, path::Path};
+
+/// Where a ROM's platform was worked out from, most reliable first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// Chosen with `--platform`.
+    Flag,
+    /// A `.toml` file next to the ROM, with the same name.
+    Sidecar,
+    /// The ROM database, which knows the ROM by its hash.
+    Database,
+    /// The ROM's file extension, e.g. `.sc8` for SUPER-CHIP.
+    Extension,
+    /// The `1260` HIRES CHIP-8 header.
+    Header,
+    /// A ROM too big for anything but XO-CHIP's 64K of memory.
+    Size,
+    /// The instructions reachable from the start of the ROM.
+    Instructions,
+    /// Nothing suggested anything else.
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Flag => write!(f, "--platform"),
+            Self::Sidecar => write!(f, "metadata file"),
+            Self::Database => write!(f, "ROM database"),
+            Self::Extension => write!(f, "file extension"),
+            Self::Header => write!(f, "header"),
+            Self::Size => write!(f, "size"),
+            Self::Instructions => write!(f, "instructions"),
+            Self::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// The platform a ROM was written for, and how that was worked out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Detection {
+    pub platform: Platform,
+    pub source: Source,
+}
+
+/// Formats the detection like `SUPER-CHIP (from file extension)`.
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (from {})", self.platform, self.source)
+    }
+}
+
+impl Detection {
+    /// The speed the platform's ROMs expect, if it isn't the configured one.
+    #[must_use]
+    pub const fn speed(self) -> Option<Speed> {
+        match self.platform {
+            Platform::SuperChip => Some(Speed::Schip),
+            Platform::XoChip => Some(Speed::Xochip),
+            Platform::Chip8 | Platform::Chip8X | Platform::Hires => None,
+        }
+    }
+
+    /// The register `BNNN` adds to on the platform, if it isn't the
+    /// configured one.
+    #[must_use]
+    pub const fn jump_offset(self) -> Option<JumpOffset> {
+        match self.platform {
+            Platform::SuperChip => Some(JumpOffset::Vx),
+            _ => None,
+        }
+    }
+}
+
+/// The contents of a sidecar metadata file, e.g. `platform = "schip"` and
+/// `title = "Ant"`.
+#[derive(Debug, Deserialize)]
+struct Sidecar {
+    platform: Option<Platform>,
+    title: Option<String>,
+}
+
+/// Works out which platform the ROM `rom`, read from `path`, was written
+/// for. In order, this looks for a sidecar metadata file, the ROM's entry
+/// in the ROM database (`known`), a telling file extension, the HIRES
+/// CHIP-8 header, a size only XO-CHIP has room for, and extension
+/// instructions, falling back to CHIP-8.
+#[must_use]
+pub fn detect(path: &Path, rom: &[u8], known: Option<&Entry>) -> Detection {
+    let found = |platform, source| Detection { platform, source };
+    if let Some(platform) = sidecar(path).and_then(|sidecar| sidecar.platform) {
+        return found(platform, Source::Sidecar);
+    }
+    if let Some(platform) = known.and_then(|entry| entry.platform) {
+        return found(platform, Source::Database);
+    }
+    if let Some(platform) = extension(path) {
+        return found(platform, Source::Extension);
+    }
+    if rom.starts_with(&Interpreter::HIRES_HEADER) {
+        return found(Platform::Hires, Source::Header);
+    }
+    if rom.len() > Interpreter::ROM_CAPACITY {
+        return found(Platform::XoChip, Source::Size);
+    }
+    match Analysis::new(rom).requirements().last() {
+        Some(&platform) => found(platform, Source::Instructions),
+        None => found(Platform::Chip8, Source::Default),
+    }
+}
+
+/// The title of the ROM read from `path`, if it's known: from its sidecar
+/// metadata file, or else its entry in the ROM database (`known`).
+#[must_use]
+pub fn title(path: Option<&Path>, known: Option<&Entry>) -> Option<String> {
+    path.and_then(sidecar)
+        .and_then(|sidecar| sidecar.title)
+        .or_else(|| known.map(|entry| entry.title.clone()))
+        .filter(|title| !title.is_empty())
+}
+
+/// The `.toml` file next to `path`, if there is one.
+fn sidecar(path: &Path) -> Option<Sidecar> {
+    let sidecar = path.with_extension("toml");
+    let read = match fs::read_to_string(&sidecar) {
+        Ok(read) => read,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+        Err(e) => {
+            warn!("Could not read '{}': {}", sidecar.display(), e);
+            return None;
+        }
+    };
+    toml::from_str(&read)
+        .map_err(|e| warn!("Could not parse '{}': {}", sidecar.display(), e))
+        .ok()
+}
+
+/// The platform implied by the extension of `path`. `.ch8` is used for
+/// every platform, so it implies nothing.
+fn extension(path: &Path) -> Option<Platform> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "c8x" => Some(Platform::Chip8X),
+        "sc8" | "sch" => Some(Platform::SuperChip),
+        "xo8" => Some(Platform::XoChip),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_platforms() {
+        let detect = |path: &str, rom: &[u8]| {
+            let Detection { platform, source } = detect(Path::new(path), rom, None);
+            (platform, source)
+        };
+        let cls = [0x00, 0xE0];
+        assert_eq!(detect("a.ch8", &cls), (Platform::Chip8, Source::Default));
+        assert_eq!(
+            detect("a.SC8", &cls),
+            (Platform::SuperChip, Source::Extension)
+        );
+        assert_eq!(
+            detect("a.ch8", &[0x12, 0x60]),
+            (Platform::Hires, Source::Header)
+        );
+        assert_eq!(
+            detect("a.ch8", &[0; 4000]),
+            (Platform::XoChip, Source::Size)
+        );
+        // 00FF switches SUPER-CHIP to its high resolution mode.
+        assert_eq!(
+            detect("a.ch8", &[0x00, 0xFF, 0x12, 0x02]),
+            (Platform::SuperChip, Source::Instructions)
+        );
+
+        let dir = std::env::temp_dir().join(format!("etherea-detect-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let rom = dir.join("rom.sc8");
+        fs::write(dir.join("rom.toml"), "platform = \"chip8x\"\n").unwrap();
+        let detected = detect(rom.to_str().unwrap(), &cls);
+        assert_eq!(detected, (Platform::Chip8X, Source::Sidecar));
+        fs::write(dir.join("rom.toml"), "platform = \"nes\"\n").unwrap();
+        let detected = detect(rom.to_str().unwrap(), &cls);
+        assert_eq!(detected, (Platform::SuperChip, Source::Extension));
+        fs::write(dir.join("rom.toml"), "title = \"Ant\"\n").unwrap();
+        let detected = detect(rom.to_str().unwrap(), &cls);
+        assert_eq!(detected, (Platform::SuperChip, Source::Extension));
+        let known = Entry {
+            title: "Maze".into(),
+            ..Entry::default()
+        };
+        assert_eq!(title(Some(&rom), Some(&known)).as_deref(), Some("Ant"));
+        assert_eq!(title(None, Some(&known)).as_deref(), Some("Maze"));
+        assert_eq!(title(None, Some(&Entry::default())), None);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let known = Entry {
+            platform: Some(Platform::Chip8X),
+            ..Entry::default()
+        };
+        let detected = super::detect(Path::new("a.sc8"), &cls, Some(&known));
+        assert_eq!(
+            detected,
+            Detection {
+                platform: Platform::Chip8X,
+                source: Source::Database,
+            }
+        );
+    }
+}
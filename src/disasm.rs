@@ -0,0 +1,634 @@
+use crate::{
+    asm::{self, octo, SourceMap, ORIGIN},
+    opcode::Opcode,
+};
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+};
+
+/// The maximum number of data bytes emitted on a single `db` line.
+const BYTES_PER_LINE: usize = 4;
+
+/// The width of the code column, after which the annotation comment starts.
+const CODE_WIDTH: usize = 28;
+
+/// Names Octo can't use for labels in every statement: `main`, which makes
+/// the program start with a jump, and the words `i :=` takes instead of one.
+const OCTO_RESERVED: [&str; 4] = ["main", "hex", "bighex", "long"];
+
+/// The addresses reached by following control flow from the entry point.
+#[derive(Debug, Default)]
+pub(crate) struct Flow {
+    pub code: BTreeSet<usize>,    // addresses of reachable instructions
+    pub targets: BTreeSet<usize>, // addresses jumped to or called
+    pub unknown: BTreeSet<usize>, // reachable addresses that don't decode
+    pub data: BTreeSet<usize>,    // addresses `I` is pointed at
+}
+
+/// Follows jumps, calls, and skips from `0x200` to work out which words of
+/// `rom` are reachable instructions. Anything else is treated as data.
+///
+/// `JP V0, addr` is followed as if `V0` were zero, since its real target
+/// depends on runtime state.
+pub(crate) fn trace(rom: &[u8]) -> Flow {
+    let mut flow = Flow::default();
+    let mut pending = vec![usize::from(ORIGIN)];
+    while let Some(address) = pending.pop() {
+        if flow.code.contains(&address) {
+            continue;
+        }
+        let Some(raw) = word(rom, address) else {
+            continue;
+        };
+        let Ok(opcode) = Opcode::try_from(raw) else {
+            flow.unknown.insert(address);
+            continue;
+        };
+        flow.code.insert(address);
+        if let Opcode::Jump(target) | Opcode::Call(target) | Opcode::JumpOffset(target) = opcode {
+            flow.targets.insert(usize::from(target));
+        }
+        if let Opcode::LoadIndex(target) = opcode {
+            flow.data.insert(usize::from(target));
+        }
+        pending.extend(
+            successors(address, opcode)
+                .into_iter()
+                .map(|(next, _)| next),
+        );
+    }
+    flow
+}
+
+/// The addresses that can execute after `opcode` at `address`, each paired
+/// with whether it is reached by a call.
+pub(crate) fn successors(address: usize, opcode: Opcode) -> Vec<(usize, bool)> {
+    let next = address + 2;
+    match opcode {
+        Opcode::Return => vec![],
+        Opcode::Jump(target) | Opcode::JumpOffset(target) => vec![(usize::from(target), false)],
+        Opcode::Call(target) => vec![(usize::from(target), true), (next, false)],
+        Opcode::SkipEq { .. }
+        | Opcode::SkipNe { .. }
+        | Opcode::SkipEqReg { .. }
+        | Opcode::SkipNeReg { .. }
+        | Opcode::SkipKey { .. }
+        | Opcode::SkipNotKey { .. } => vec![(next, false), (next + 2, false)],
+        _ => vec![(next, false)],
+    }
+}
+
+/// The big-endian word at `address`, if it lies entirely within `rom`.
+pub(crate) fn word(rom: &[u8], address: usize) -> Option<u16> {
+    let offset = address.checked_sub(usize::from(ORIGIN))?;
+    match rom.get(offset..offset + 2)? {
+        &[hi, lo] => Some(u16::from_be_bytes([hi, lo])),
+        _ => None,
+    }
+}
+
+/// The name of the label at `address`.
+fn label(address: usize) -> String {
+    format!("L_{address:#05X}")
+}
+
+/// A single line of disassembly: either an instruction or a run of data.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Line {
+    /// The address of the first byte.
+    pub address: u16,
+    /// The raw bytes.
+    pub bytes: Vec<u8>,
+    /// The decoded instruction, or `None` if the bytes are data.
+    pub opcode: Option<Opcode>,
+    /// The label defined at this address, if it is a jump or call target
+    /// or has a symbol.
+    pub label: Option<String>,
+}
+
+impl Line {
+    /// The mnemonic and operands, with jump and call targets replaced by
+    /// their labels where `labels` has one.
+    fn code(&self, labels: &BTreeMap<u16, &str>) -> String {
+        let operand = |target: u16| match labels.get(&target) {
+            Some(label) => (*label).to_string(),
+            None => format!("{target:#05X}"),
+        };
+        match self.opcode {
+            Some(Opcode::Jump(target)) => format!("JP {}", operand(target)),
+            Some(Opcode::Call(target)) => format!("CALL {}", operand(target)),
+            Some(Opcode::JumpOffset(target)) => format!("JP V0, {}", operand(target)),
+            Some(opcode) => opcode.to_string(),
+            None => {
+                let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{b:#04X}")).collect();
+                format!("db {}", bytes.join(", "))
+            }
+        }
+    }
+}
+
+impl Line {
+    /// The statement in [Octo](https://johnearnest.github.io/Octo/)'s
+    /// syntax, with addresses replaced by their labels where `labels` has
+    /// one. Skips become `if ... then`, with the condition inverted, and
+    /// data a run of bytes.
+    fn octo(&self, labels: &BTreeMap<u16, &str>) -> String {
+        let operand = |target: u16| match labels.get(&target) {
+            Some(label) => (*label).to_string(),
+            None => format!("{target:#05X}"),
+        };
+        let Some(opcode) = self.opcode else {
+            let bytes: Vec<String> = self.bytes.iter().map(|b| format!("{b:#04X}")).collect();
+            return bytes.join(" ");
+        };
+        match opcode {
+            Opcode::Sys(addr) => format!("native {}", operand(addr)),
+            Opcode::ClearScreen => "clear".to_string(),
+            Opcode::Return => "return".to_string(),
+            Opcode::Jump(addr) => format!("jump {}", operand(addr)),
+            Opcode::Call(addr) => format!(":call {}", operand(addr)),
+            Opcode::SkipEq { x, nn } => format!("if v{x:x} != {nn:#04X} then"),
+            Opcode::SkipNe { x, nn } => format!("if v{x:x} == {nn:#04X} then"),
+            Opcode::SkipEqReg { x, y } => format!("if v{x:x} != v{y:x} then"),
+            Opcode::SkipNeReg { x, y } => format!("if v{x:x} == v{y:x} then"),
+            Opcode::SkipKey { x } => format!("if v{x:x} -key then"),
+            Opcode::SkipNotKey { x } => format!("if v{x:x} key then"),
+            Opcode::Load { x, nn } => format!("v{x:x} := {nn:#04X}"),
+            Opcode::AddImm { x, nn } => format!("v{x:x} += {nn:#04X}"),
+            Opcode::Move { x, y } => format!("v{x:x} := v{y:x}"),
+            Opcode::Or { x, y } => format!("v{x:x} |= v{y:x}"),
+            Opcode::And { x, y } => format!("v{x:x} &= v{y:x}"),
+            Opcode::Xor { x, y } => format!("v{x:x} ^= v{y:x}"),
+            Opcode::Add { x, y } => format!("v{x:x} += v{y:x}"),
+            Opcode::Sub { x, y } => format!("v{x:x} -= v{y:x}"),
+            Opcode::ShiftRight { x, y } => format!("v{x:x} >>= v{y:x}"),
+            Opcode::SubN { x, y } => format!("v{x:x} =- v{y:x}"),
+            Opcode::ShiftLeft { x, y } => format!("v{x:x} <<= v{y:x}"),
+            Opcode::LoadIndex(addr) => format!("i := {}", operand(addr)),
+            Opcode::JumpOffset(addr) => format!("jump0 {}", operand(addr)),
+            Opcode::Random { x, nn } => format!("v{x:x} := random {nn:#04X}"),
+            Opcode::Draw { x, y, n } => format!("sprite v{x:x} v{y:x} {n}"),
+            Opcode::LoadDelay { x } => format!("v{x:x} := delay"),
+            Opcode::WaitKey { x } => format!("v{x:x} := key"),
+            Opcode::SetDelay { x } => format!("delay := v{x:x}"),
+            Opcode::SetSound { x } => format!("buzzer := v{x:x}"),
+            Opcode::AddIndex { x } => format!("i += v{x:x}"),
+            Opcode::Font { x } => format!("i := hex v{x:x}"),
+            Opcode::Bcd { x } => format!("bcd v{x:x}"),
+            Opcode::Store { x } => format!("save v{x:x}"),
+            Opcode::LoadMemory { x } => format!("load v{x:x}"),
+            Opcode::SaveFlags { x } => format!("saveflags v{x:x}"),
+            Opcode::LoadFlags { x } => format!("loadflags v{x:x}"),
+            Opcode::ScrollDown { n } => format!("scroll-down {n}"),
+            Opcode::ScrollUp { n } => format!("scroll-up {n}"),
+            Opcode::ScrollRight => "scroll-right".to_string(),
+            Opcode::ScrollLeft => "scroll-left".to_string(),
+        }
+    }
+}
+
+/// Splits `rom` into lines of disassembly.
+///
+/// Control flow is followed from `0x200` to separate code from data: jump
+/// and call targets get labels (`L_0x2A4`), and bytes that are never
+/// executed become data. Labels in `symbols` are used where they are valid
+/// names, and their addresses get labels even if nothing jumps to them.
+/// Data is split into runs of at most [`BYTES_PER_LINE`] bytes, breaking
+/// wherever `breaks` returns `true`.
+fn split(rom: &[u8], symbols: &SourceMap, breaks: impl Fn(usize) -> bool) -> Vec<Line> {
+    let flow = trace(rom);
+    let start = usize::from(ORIGIN);
+    let end = start + rom.len();
+
+    // Instructions can overlap if control flow reaches both halves of a
+    // word, so only emit those that don't start inside an earlier one.
+    let mut instructions = BTreeSet::new();
+    let mut address = start;
+    while address < end {
+        if flow.code.contains(&address) {
+            instructions.insert(address);
+            address += 2;
+        } else {
+            address += 1;
+        }
+    }
+    let name = |address: usize| {
+        u16::try_from(address)
+            .ok()
+            .and_then(|address| symbols.label(address))
+            .filter(|name| asm::is_identifier(name))
+    };
+    let labels: BTreeSet<usize> = flow
+        .targets
+        .iter()
+        .copied()
+        .chain((start..end).filter(|&address| name(address).is_some()))
+        .filter(|&target| (start..end).contains(&target) && !instructions.contains(&(target - 1)))
+        .collect();
+
+    let mut lines = Vec::new();
+    let mut address = start;
+    while address < end {
+        let mut next = address + 1;
+        let opcode = if instructions.contains(&address) {
+            next += 1;
+            word(rom, address).and_then(|raw| Opcode::try_from(raw).ok())
+        } else {
+            // Gather data up to the next instruction or label.
+            while next < end
+                && next - address < BYTES_PER_LINE
+                && !instructions.contains(&next)
+                && !labels.contains(&next)
+                && !breaks(next)
+            {
+                next += 1;
+            }
+            None
+        };
+        lines.push(Line {
+            address: u16::try_from(address).unwrap_or(u16::MAX),
+            bytes: rom[address - start..next - start].to_vec(),
+            opcode,
+            label: labels
+                .contains(&address)
+                .then(|| name(address).map_or_else(|| label(address), str::to_string)),
+        });
+        address = next;
+    }
+    lines
+}
+
+/// Disassembles `rom` into lines, as used by the structured output formats,
+/// with labels from `symbols`.
+#[must_use]
+pub fn lines(rom: &[u8], symbols: &SourceMap) -> Vec<Line> {
+    split(rom, symbols, |_| false)
+}
+
+/// Disassembles `rom` into source that [`assemble`](crate::asm::assemble)
+/// turns back into the same bytes.
+///
+/// Every line is annotated with its address and, for instructions, the raw
+/// opcode, followed by the source line it was assembled from if `symbols`
+/// has one, and the matching entry of `comments` if there is one.
+#[must_use]
+pub fn disassemble(rom: &[u8], comments: &BTreeMap<u16, String>, symbols: &SourceMap) -> String {
+    let has_comment = |address: usize| {
+        u16::try_from(address).is_ok_and(|address| comments.contains_key(&address))
+    };
+    let lines = split(rom, symbols, has_comment);
+    let labels: BTreeMap<u16, &str> = lines
+        .iter()
+        .filter_map(|line| Some((line.address, line.label.as_deref()?)))
+        .collect();
+
+    let mut source = String::new();
+    for line in &lines {
+        if let Some(label) = &line.label {
+            let _ = writeln!(source, "{label}:");
+        }
+        let code = line.code(&labels);
+        let kind = match line.opcode {
+            Some(_) => hex(&line.bytes),
+            None => "data".to_string(),
+        };
+        let _ = write!(
+            source,
+            "    {code:<CODE_WIDTH$}; {:#05X}  {kind}",
+            line.address
+        );
+        if let (Some(number), Some(text)) = (symbols.line(line.address), symbols.text(line.address))
+        {
+            let _ = write!(source, "  {number}: {text}");
+        }
+        if let Some(comment) = comments.get(&line.address) {
+            let _ = write!(source, "  {comment}");
+        }
+        source.push('\n');
+    }
+    source
+}
+
+/// Decompiles `rom` into source in [Octo](https://johnearnest.github.io/Octo/)'s
+/// syntax, checking that [`asm::octo::assemble`] turns it back into the
+/// same bytes.
+///
+/// Code and data are told apart and labelled as in [`disassemble`], and the
+/// data `I` is pointed at is labelled too. That data is written a byte to a
+/// line, with the sprite row it draws as a comment, since most of it is
+/// sprites. Every line is annotated with its address and, for instructions,
+/// the raw opcode, followed by the matching entry of `comments` if there is
+/// one. Labels in `symbols` are used where Octo allows the name.
+///
+/// # Errors
+/// This function will error if the source doesn't assemble back into
+/// `rom`, such as when the ROM is too big for memory.
+pub fn octo(
+    rom: &[u8],
+    comments: &BTreeMap<u16, String>,
+    symbols: &SourceMap,
+) -> Result<String, String> {
+    let data = trace(rom).data;
+    let breaks = |address: usize| {
+        data.contains(&address)
+            || u16::try_from(address).is_ok_and(|address| comments.contains_key(&address))
+    };
+    let mut lines = split(rom, symbols, breaks);
+    for line in &mut lines {
+        let address = usize::from(line.address);
+        line.label = match line.label.take() {
+            Some(name) if octo::is_name(&name) && !OCTO_RESERVED.contains(&name.as_str()) => {
+                Some(name)
+            }
+            Some(_) => Some(label(address)),
+            None => data.contains(&address).then(|| label(address)),
+        };
+    }
+    let labels: BTreeMap<u16, &str> = lines
+        .iter()
+        .filter_map(|line| Some((line.address, line.label.as_deref()?)))
+        .collect();
+
+    let annotated = |code: &str, address: u16, kind: &str| {
+        let mut line = format!("    {code:<CODE_WIDTH$}# {address:#05X}  {kind}");
+        if let Some(comment) = comments.get(&address) {
+            let _ = write!(line, "  {comment}");
+        }
+        line.push('\n');
+        line
+    };
+    let mut source = String::new();
+    let mut sprite = false;
+    for line in &lines {
+        if let Some(label) = &line.label {
+            let _ = writeln!(source, ": {label}");
+            sprite = data.contains(&usize::from(line.address));
+        }
+        match line.opcode {
+            Some(_) => {
+                sprite = false;
+                source.push_str(&annotated(
+                    &line.octo(&labels),
+                    line.address,
+                    &hex(&line.bytes),
+                ));
+            }
+            None if sprite => {
+                for (address, byte) in (line.address..).zip(&line.bytes) {
+                    let row: String = (0..8)
+                        .map(|bit| if byte << bit & 0x80 == 0 { '.' } else { '#' })
+                        .collect();
+                    source.push_str(&annotated(&format!("{byte:#04X}"), address, &row));
+                }
+            }
+            None => source.push_str(&annotated(&line.octo(&labels), line.address, "data")),
+        }
+    }
+
+    match octo::assemble(&source) {
+        Ok(assembled) if assembled == rom => Ok(source),
+        Ok(_) => Err("The decompiled source doesn't assemble back into the ROM".to_string()),
+        Err(e) => Err(format!("The decompiled source doesn't assemble: {e}")),
+    }
+}
+
+/// A line of disassembly as written by [`json`] and [`csv`].
+#[derive(Debug, Serialize)]
+struct Record<'a> {
+    address: String,
+    opcode: String,
+    mnemonic: String,
+    operands: Vec<String>,
+    label: Option<&'a str>,
+    comment: Option<&'a str>,
+}
+
+impl<'a> Record<'a> {
+    fn new(line: &'a Line, comments: &'a BTreeMap<u16, String>) -> Self {
+        let code = line.code(&BTreeMap::new());
+        let (mnemonic, operands) = code.split_once(' ').unwrap_or((&code, ""));
+        Record {
+            address: format!("{:#05X}", line.address),
+            opcode: hex(&line.bytes),
+            mnemonic: mnemonic.to_ascii_uppercase(),
+            operands: operands
+                .split(", ")
+                .filter(|operand| !operand.is_empty())
+                .map(str::to_string)
+                .collect(),
+            label: line.label.as_deref(),
+            comment: comments.get(&line.address).map(String::as_str),
+        }
+    }
+}
+
+/// Disassembles `rom` into a JSON array with one object per line, holding
+/// its `address`, raw `opcode` bytes, `mnemonic`, `operands`, `label`, and
+/// `comment`. Operands are numeric rather than labels, which come from
+/// `symbols` where it has them.
+#[must_use]
+pub fn json(rom: &[u8], comments: &BTreeMap<u16, String>, symbols: &SourceMap) -> String {
+    let lines = lines(rom, symbols);
+    let records: Vec<Record> = lines
+        .iter()
+        .map(|line| Record::new(line, comments))
+        .collect();
+    let mut json = serde_json::to_string_pretty(&records).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// Disassembles `rom` into CSV with the same columns as [`json`], with
+/// operands joined by `", "`.
+///
+/// # Errors
+/// This function will error if the CSV cannot be written.
+pub fn csv(
+    rom: &[u8],
+    comments: &BTreeMap<u16, String>,
+    symbols: &SourceMap,
+) -> csv::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "address", "opcode", "mnemonic", "operands", "label", "comment",
+    ])?;
+    for line in lines(rom, symbols) {
+        let record = Record::new(&line, comments);
+        writer.write_record([
+            record.address.as_str(),
+            &record.opcode,
+            &record.mnemonic,
+            &record.operands.join(", "),
+            record.label.unwrap_or_default(),
+            record.comment.unwrap_or_default(),
+        ])?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(csv::IntoInnerError::into_error)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Formats `bytes` as contiguous uppercase hexadecimal.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, byte| {
+        let _ = write!(s, "{byte:02X}");
+        s
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::{assemble, assemble_with_map};
+    use std::path::PathBuf;
+
+    #[test]
+    fn follows_control_flow() {
+        let rom = [
+            0x22, 0x06, // CALL 0x206
+            0x12, 0x02, // JP 0x202
+            0xF0, 0x90, // sprite data
+            0xA2, 0x04, // LD I, 0x204
+            0x00, 0xEE, // RET
+            0xFF, // trailing byte
+        ];
+        let source = disassemble(
+            &rom,
+            &BTreeMap::from([(0x206, "draw".to_string())]),
+            &SourceMap::default(),
+        );
+        assert_eq!(
+            source,
+            "    CALL L_0x206                ; 0x200  2206
+L_0x202:
+    JP L_0x202                  ; 0x202  1202
+    db 0xF0, 0x90               ; 0x204  data
+L_0x206:
+    LD I, 0x204                 ; 0x206  A204  draw
+    RET                         ; 0x208  00EE
+    db 0xFF                     ; 0x20A  data
+"
+        );
+        assert_eq!(assemble(&source).unwrap(), rom);
+    }
+
+    #[test]
+    fn structured_formats() {
+        let rom = [0x22, 0x04, 0x00, 0xEE, 0xF0];
+        let comments = BTreeMap::from([(0x204, "sprite, \"ball\"".to_string())]);
+        let json: serde_json::Value =
+            serde_json::from_str(&json(&rom, &comments, &SourceMap::default())).unwrap();
+        assert_eq!(
+            json[0],
+            serde_json::json!({
+                "address": "0x200",
+                "opcode": "2204",
+                "mnemonic": "CALL",
+                "operands": ["0x204"],
+                "label": null,
+                "comment": null,
+            })
+        );
+        assert_eq!(json[2]["label"], "L_0x204");
+        assert_eq!(json[2]["mnemonic"], "DB");
+        assert_eq!(
+            csv(&rom, &comments, &SourceMap::default()).unwrap(),
+            "address,opcode,mnemonic,operands,label,comment
+0x200,2204,CALL,0x204,,
+0x202,00EE,RET,,,
+0x204,F0,DB,0xF0,L_0x204,\"sprite, \"\"ball\"\"\"
+"
+        );
+    }
+
+    #[test]
+    fn round_trips_overlapping_and_out_of_range_targets() {
+        let rom = [
+            0x32, 0x01, // SE V2, 0x01
+            0x12, 0x03, // JP 0x203, into the middle of this instruction
+            0x13, 0x00, // JP 0x300, past the end of the ROM
+            0x51, 0x21, // not an instruction
+        ];
+        let source = disassemble(&rom, &BTreeMap::new(), &SourceMap::default());
+        assert!(source.contains("JP 0x203"));
+        assert!(source.contains("JP 0x300"));
+        assert!(source.contains("db 0x51, 0x21"));
+        assert_eq!(assemble(&source).unwrap(), rom);
+    }
+
+    #[test]
+    fn decompiles_to_octo() {
+        let rom = [
+            0xA2, 0x0A, // LD I, 0x20A
+            0x30, 0x01, // SE V0, 0x01
+            0xD0, 0x12, // DRW V0, V1, 2
+            0x22, 0x0C, // CALL 0x20C
+            0x12, 0x00, // JP 0x200
+            0xF0, 0x90, // sprite
+            0x00, 0xFB, // SCR
+            0x00, 0xEE, // RET
+            0x51, 0x21, // not an instruction
+        ];
+        let comments = BTreeMap::from([(0x20B, "bottom".to_string())]);
+        let source = octo(&rom, &comments, &SourceMap::default()).unwrap();
+        assert_eq!(
+            source,
+            ": L_0x200
+    i := L_0x20A                # 0x200  A20A
+    if v0 != 0x01 then          # 0x202  3001
+    sprite v0 v1 2              # 0x204  D012
+    :call L_0x20C               # 0x206  220C
+    jump L_0x200                # 0x208  1200
+: L_0x20A
+    0xF0                        # 0x20A  ####....
+    0x90                        # 0x20B  #..#....  bottom
+: L_0x20C
+    scroll-right                # 0x20C  00FB
+    return                      # 0x20E  00EE
+    0x51 0x21                   # 0x210  data
+"
+        );
+        assert_eq!(octo::assemble(&source).unwrap(), rom);
+
+        for entry in std::fs::read_dir("roms").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|extension| extension == "ch8") {
+                let rom = std::fs::read(&path).unwrap();
+                let source = octo(&rom, &BTreeMap::new(), &SourceMap::default());
+                assert!(source.is_ok(), "{}: {source:?}", path.display());
+            }
+        }
+
+        // Labels Octo can't use are renamed.
+        let (rom, symbols) = assemble_with_map("main:\n  JP main\n").unwrap();
+        assert!(octo(&rom, &BTreeMap::new(), &symbols)
+            .unwrap()
+            .starts_with(": L_0x200\n"));
+    }
+
+    #[test]
+    fn uses_symbols() {
+        let source =
+            "start:\n  CALL draw\n  JP start\ndraw:\n  LD I, sprite\n  RET\nsprite: db 0xF0\n";
+        let (rom, symbols) = assemble_with_map(source).unwrap();
+        let symbols = symbols.with_source(PathBuf::from("game.asm"), source);
+        let disassembly = disassemble(&rom, &BTreeMap::new(), &symbols);
+        assert_eq!(
+            disassembly,
+            "start:
+    CALL draw                   ; 0x200  2204  2: CALL draw
+    JP start                    ; 0x202  1200  3: JP start
+draw:
+    LD I, 0x208                 ; 0x204  A208  5: LD I, sprite
+    RET                         ; 0x206  00EE  6: RET
+sprite:
+    db 0xF0                     ; 0x208  data  7: sprite: db 0xF0
+"
+        );
+        assert_eq!(assemble(&disassembly).unwrap(), rom);
+        assert_eq!(lines(&rom, &symbols)[4].label.as_deref(), Some("sprite"));
+    }
+}
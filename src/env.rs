@@ -0,0 +1,329 @@
+use crate::{
+    frame::Frame,
+    input::KeyEvent,
+    screen::{self, Resolution},
+    Fault, Interpreter,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+
+/// How much an agent earned in a step, worked out from memory, where
+/// CHIP-8 games keep their scores and lives. Attached to an [`Env`] with
+/// [`Env::with_reward`]; every attached reward is added up.
+pub trait Reward: Send {
+    /// The reward for a step that changed memory from `before` to `after`.
+    fn reward(&mut self, before: &[u8], after: &[u8]) -> f64;
+
+    /// Whether the game is over, e.g. with no lives left. Never, unless
+    /// overridden.
+    fn terminated(&self, _memory: &[u8]) -> bool {
+        false
+    }
+}
+
+impl<F: FnMut(&[u8], &[u8]) -> f64 + Send> Reward for F {
+    fn reward(&mut self, before: &[u8], after: &[u8]) -> f64 {
+        self(before, after)
+    }
+}
+
+/// Watches a byte of memory, such as a game's score or lives, rewarding
+/// every change to it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Observer {
+    /// The address of the byte.
+    pub address: usize,
+    /// The reward for each unit the byte rises by. Negative to punish a
+    /// rise, or to reward a fall, e.g. for a byte counting lives lost.
+    pub scale: f64,
+    /// The value the byte ends the game at, if any, e.g. `0` lives.
+    pub end: Option<u8>,
+}
+
+impl Observer {
+    /// An observer of the byte at `address`, rewarding each unit it rises
+    /// by with `scale`, that never ends the game.
+    #[must_use]
+    pub const fn new(address: usize, scale: f64) -> Self {
+        Self {
+            address,
+            scale,
+            end: None,
+        }
+    }
+
+    /// The byte at the address in `memory`, or `0` if it's out of range.
+    fn value(&self, memory: &[u8]) -> u8 {
+        memory.get(self.address).copied().unwrap_or_default()
+    }
+}
+
+impl Reward for Observer {
+    fn reward(&mut self, before: &[u8], after: &[u8]) -> f64 {
+        let change = i16::from(self.value(after)) - i16::from(self.value(before));
+        f64::from(change) * self.scale
+    }
+
+    fn terminated(&self, memory: &[u8]) -> bool {
+        self.end == Some(self.value(memory))
+    }
+}
+
+/// The screen seen by an agent: a bit a pixel, packed into
+/// [`Resolution::stride`] `u64`s a row, with the leftmost pixel in the
+/// highest bit and lit pixels set, so a row of the usual 64-pixel-wide
+/// screen is one `u64`. 32 rows, or 64 for HIRES CHIP-8 ROMs.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Observation {
+    resolution: Resolution,
+    rows: Vec<u64>,
+}
+
+impl Observation {
+    /// The size of the screen.
+    #[must_use]
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// The rows of the screen, top to bottom.
+    #[must_use]
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    /// Whether the pixel at (`x`, `y`) is lit. Pixels off the screen never
+    /// are.
+    #[must_use]
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        screen::lit(&self.rows, self.resolution, x, y)
+    }
+}
+
+impl From<&Frame> for Observation {
+    fn from(frame: &Frame) -> Self {
+        let rows = frame
+            .pixels()
+            .chunks(frame.width())
+            .flat_map(|row| row.chunks(64))
+            .map(|word| {
+                let bits = word.iter().fold(0, |bits, &on| bits << 1 | u64::from(on));
+                bits << (64 - word.len())
+            })
+            .collect();
+        Self {
+            resolution: frame.resolution(),
+            rows,
+        }
+    }
+}
+
+/// What happened in a call to [`Env::step`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    /// The screen after the step.
+    pub observation: Observation,
+    /// The sum of the attached rewards for the step.
+    pub reward: f64,
+    /// Whether the game is over, because a reward says so or the ROM
+    /// faulted. The environment has to be [reset](Env::reset) to carry on.
+    pub terminated: bool,
+    /// Whether the episode ran out of frames, as set with
+    /// [`Env::with_frame_limit`], before the game was over.
+    pub truncated: bool,
+    /// The fault that stopped the ROM, if one did.
+    pub fault: Option<Fault>,
+}
+
+/// A CHIP-8 game as an environment for reinforcement learning, in the style
+/// of Gymnasium: [`reset`](Self::reset) starts an episode, and each
+/// [`step`](Self::step) holds down the keys the agent picked, runs a few
+/// frames, and reports the screen and the reward earned.
+pub struct Env {
+    intr: Interpreter,             // The game
+    rom: Vec<u8>,                  // Loaded again on every reset
+    rewards: Vec<Box<dyn Reward>>, // Added up after every step
+    frame_skip: u64,               // Frames run by each step
+    frame_limit: Option<u64>,      // Frames an episode is truncated after
+    frames: u64,                   // Frames run in this episode
+    keys: u16,                     // The keys held down, a bit each
+    over: bool,                    // Whether the episode has terminated
+}
+
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Env")
+            .field("rewards", &self.rewards.len())
+            .field("frame_skip", &self.frame_skip)
+            .field("frame_limit", &self.frame_limit)
+            .field("frames", &self.frames)
+            .field("keys", &self.keys)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Env {
+    /// An environment playing `rom` on `intr`, which keeps the quirks,
+    /// timing, and so on it was set up with. Each step runs one frame.
+    #[must_use]
+    pub fn new(intr: Interpreter, rom: &[u8]) -> Self {
+        Self {
+            intr,
+            rom: rom.to_vec(),
+            rewards: Vec::new(),
+            frame_skip: 1,
+            frame_limit: None,
+            frames: 0,
+            keys: 0,
+            over: true,
+        }
+    }
+
+    /// Adds `reward` to those earned after every step, e.g. an
+    /// [`Observer`] of the game's score.
+    pub fn with_reward(&mut self, reward: impl Reward + 'static) {
+        self.rewards.push(Box::new(reward));
+    }
+
+    /// Runs `frames` frames in each step, holding the same keys, as agents
+    /// playing Atari games usually do. At least one.
+    pub fn with_frame_skip(&mut self, frames: u64) {
+        self.frame_skip = frames.max(1);
+    }
+
+    /// Truncates episodes after `frames` frames.
+    pub fn with_frame_limit(&mut self, frames: u64) {
+        self.frame_limit = Some(frames);
+    }
+
+    /// Starts a new episode, loading the ROM again with the random number
+    /// generator seeded with `seed`, and returns the screen.
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.intr.with_seed(seed);
+        self.intr.load_rom(&self.rom);
+        self.frames = 0;
+        self.keys = 0;
+        self.over = false;
+        self.observation()
+    }
+
+    /// Holds down the keys set in `keys`, bit `k` for key `k`, lets go of the
+    /// rest, and runs the frames of a step.
+    ///
+    /// # Panics
+    /// This function panics if the episode is over, or hasn't been
+    /// [reset](Self::reset) yet.
+    pub fn step(&mut self, keys: u16) -> Step {
+        assert!(
+            !self.over,
+            "the episode is over; reset the environment first"
+        );
+        for key in 0..16 {
+            let pressed = keys >> key & 1 == 1;
+            if pressed != (self.keys >> key & 1 == 1) {
+                self.intr.handle_key(KeyEvent { key, pressed });
+            }
+        }
+        self.keys = keys;
+
+        let before = self.intr.memory().to_vec();
+        let fault = self.intr.run_frames(self.frame_skip).err();
+        self.frames += self.frame_skip;
+        let after = self.intr.memory();
+        let reward = self
+            .rewards
+            .iter_mut()
+            .map(|reward| reward.reward(&before, after))
+            .sum();
+        let terminated =
+            fault.is_some() || self.rewards.iter().any(|reward| reward.terminated(after));
+        let truncated = !terminated && self.frame_limit.is_some_and(|limit| self.frames >= limit);
+        self.over = terminated || truncated;
+        Step {
+            observation: self.observation(),
+            reward,
+            terminated,
+            truncated,
+            fault,
+        }
+    }
+
+    /// The screen, as an agent sees it.
+    #[must_use]
+    pub fn observation(&self) -> Observation {
+        Observation::from(&self.intr.framebuffer())
+    }
+
+    /// The interpreter playing the game, e.g. to look at its memory.
+    #[must_use]
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.intr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Draws the font's 0 at the top left, then adds one to the byte at
+    /// `0x300` for every instruction key 5 is held.
+    const ROM: [u8; 22] = [
+        0xF0, 0x29, 0xD0, 0x05, 0x61, 0x05, 0xA3, 0x00, 0xE1, 0x9E, 0x12, 0x08, 0xF0, 0x65, 0x70,
+        0x01, 0xF0, 0x55, 0xA3, 0x00, 0x12, 0x08,
+    ];
+
+    #[test]
+    fn observes_the_screen() {
+        let mut env = Env::new(Interpreter::new(), &ROM);
+        assert_eq!(env.reset(0).rows(), [0; 32]);
+        let step = env.step(0);
+        assert_eq!(step.observation.rows()[0], 0xF000_0000_0000_0000);
+        assert!(step.observation.get(3, 0) && !step.observation.get(4, 0));
+        assert!(step.observation.get(0, 1) && !step.observation.get(1, 1));
+        assert!(!step.observation.get(64, 0) && !step.observation.get(0, 32));
+        assert_eq!(
+            step.observation,
+            Observation::from(&env.interpreter().framebuffer())
+        );
+    }
+
+    #[test]
+    fn rewards() {
+        let mut env = Env::new(Interpreter::new(), &ROM);
+        env.with_reward(Observer::new(0x300, 0.5));
+        env.with_reward(|_: &[u8], _: &[u8]| 1.0);
+        env.with_frame_skip(2);
+        env.reset(0);
+        let idle = env.step(0);
+        assert!((idle.reward - 1.0).abs() < f64::EPSILON);
+        let held = env.step(1 << 5);
+        let score = env.interpreter().memory()[0x300];
+        assert!(score > 0);
+        assert!((held.reward - (f64::from(score) * 0.5 + 1.0)).abs() < f64::EPSILON);
+        assert!(!held.terminated && !held.truncated);
+    }
+
+    #[test]
+    fn episodes_end() {
+        let mut env = Env::new(Interpreter::new(), &ROM);
+        env.with_frame_limit(3);
+        env.with_frame_skip(2);
+        env.reset(0);
+        assert!(!env.step(0).truncated);
+        let last = env.step(0);
+        assert!(last.truncated && !last.terminated);
+
+        env.reset(0);
+        env.with_reward(Observer {
+            end: Some(0),
+            ..Observer::new(0x300, 1.0)
+        });
+        assert!(env.step(0).terminated);
+
+        let mut env = Env::new(Interpreter::new(), &[0x00, 0xEE]);
+        env.reset(0);
+        let step = env.step(0);
+        assert!(step.terminated);
+        assert_eq!(step.fault, Some(Fault::StackUnderflow { pc: 0x200 }));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{input::KeyEvent, screen::Resolution, Interpreter};
+use std::{
+    ffi::{c_char, CString},
+    ptr, slice,
+};
+
+/// An interpreter, and the fault that stopped it, if one did. Opaque to C.
+#[derive(Debug, Default)]
+pub struct Machine {
+    interpreter: Interpreter,
+    fault: Option<CString>, // The fault that stopped the ROM, as text
+    rgba: Vec<u8>,          // The screen, expanded when asked for
+}
+
+impl Machine {
+    /// Keeps the fault `result` ended with, if any. Returns whether the
+    /// ROM can keep running.
+    fn record(&mut self, result: &Result<(), crate::Fault>) -> bool {
+        self.fault = result
+            .as_ref()
+            .err()
+            .map(|fault| CString::new(fault.to_string()).unwrap_or_default());
+        self.fault.is_none()
+    }
+}
+
+/// Creates an interpreter with nothing loaded. Free it with
+/// [`etherea_destroy`].
+#[no_mangle]
+#[must_use]
+pub extern "C" fn etherea_create() -> Box<Machine> {
+    Box::default()
+}
+
+/// Frees an interpreter made with [`etherea_create`]. Does nothing if
+/// `machine` is null.
+#[no_mangle]
+pub extern "C" fn etherea_destroy(machine: Option<Box<Machine>>) {
+    drop(machine);
+}
+
+/// Loads the `len` bytes at `rom`, resetting the interpreter.
+///
+/// # Safety
+/// `rom` must point to `len` readable bytes, or be null if `len` is `0`.
+#[no_mangle]
+pub unsafe extern "C" fn etherea_load_rom(
+    machine: Option<&mut Machine>,
+    rom: *const u8,
+    len: usize,
+) {
+    let Some(machine) = machine else {
+        return;
+    };
+    let rom = if rom.is_null() {
+        &[]
+    } else {
+        // SAFETY: the caller promises `len` bytes can be read from `rom`.
+        unsafe { slice::from_raw_parts(rom, len) }
+    };
+    machine.interpreter.load_rom(rom);
+    machine.fault = None;
+}
+
+/// Executes `instructions` instructions, without ticking the timers.
+/// Returns `false` if a fault stopped the ROM, as [`etherea_fault`]
+/// describes.
+#[no_mangle]
+pub extern "C" fn etherea_step(machine: Option<&mut Machine>, instructions: u64) -> bool {
+    machine.is_some_and(|machine| {
+        let result = machine.interpreter.run_instructions(instructions);
+        machine.record(&result)
+    })
+}
+
+/// Runs `frames` frames as fast as possible, each executing its share of
+/// instructions and ticking the timers once. Returns `false` if a fault
+/// stopped the ROM, as [`etherea_fault`] describes.
+#[no_mangle]
+pub extern "C" fn etherea_run_frames(machine: Option<&mut Machine>, frames: u64) -> bool {
+    machine.is_some_and(|machine| {
+        let result = machine.interpreter.run_frames(frames);
+        machine.record(&result)
+    })
+}
+
+/// The fault that stopped the ROM, as text, or null if it hasn't faulted.
+/// Valid until the interpreter next runs or is destroyed.
+#[no_mangle]
+#[must_use]
+pub extern "C" fn etherea_fault(machine: Option<&Machine>) -> *const c_char {
+    machine
+        .and_then(|machine| machine.fault.as_ref())
+        .map_or(ptr::null(), |fault| fault.as_ptr())
+}
+
+/// The width of the screen, in pixels: 64, unless the ROM switched to a
+/// wider resolution.
+#[no_mangle]
+#[must_use]
+pub extern "C" fn etherea_width(machine: Option<&Machine>) -> u16 {
+    machine.map_or(0, |machine| machine.interpreter.resolution().width)
+}
+
+/// The screen's RGBA pixels, row by row, [`etherea_width`] wide and
+/// `*height` tall: 64 for HIRES CHIP-8 ROMs, and 32 otherwise, unless the
+/// ROM switched resolution. Lit pixels are white, and unlit ones
+/// transparent black. Valid until the interpreter next runs or is
+/// destroyed.
+///
+/// # Safety
+/// `height` must be null, or point to a writable byte.
+#[no_mangle]
+pub unsafe extern "C" fn etherea_framebuffer(
+    machine: Option<&mut Machine>,
+    height: *mut u8,
+) -> *const u8 {
+    let Some(machine) = machine else {
+        return ptr::null();
+    };
+    let Resolution {
+        width,
+        height: rows,
+    } = machine.interpreter.resolution();
+    if !height.is_null() {
+        // SAFETY: the caller promises `height` can be written.
+        unsafe { height.write(u8::try_from(rows).unwrap_or(u8::MAX)) };
+    }
+    machine.rgba.clear();
+    machine.rgba.extend(
+        (0..rows)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .flat_map(|(x, y)| [u8::from(machine.interpreter.screen.get_at(x, y)) * 0xFF; 4]),
+    );
+    machine.rgba.as_ptr()
+}
+
+/// Presses (`pressed` true) or lets go of CHIP-8 key `key`, from `0x0` to
+/// `0xF`.
+#[no_mangle]
+pub extern "C" fn etherea_set_key(machine: Option<&mut Machine>, key: u8, pressed: bool) {
+    if let Some(machine) = machine {
+        machine.interpreter.handle_key(KeyEvent { key, pressed });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn drives_a_rom() {
+        let mut machine = etherea_create();
+        // Wait for a key in V0, then draw its digit.
+        let rom = [0xF0, 0x0A, 0xF0, 0x29, 0xD0, 0x05, 0x12, 0x06];
+        unsafe { etherea_load_rom(Some(&mut machine), rom.as_ptr(), rom.len()) };
+        assert!(etherea_run_frames(Some(&mut machine), 2));
+        etherea_set_key(Some(&mut machine), 0x8, true);
+        etherea_set_key(Some(&mut machine), 0x8, false);
+        assert!(etherea_step(Some(&mut machine), 3));
+
+        let mut height = 0;
+        let pixels = unsafe { etherea_framebuffer(Some(&mut machine), ptr::from_mut(&mut height)) };
+        assert_eq!((etherea_width(Some(&machine)), height), (64, 32));
+        let pixels = unsafe { slice::from_raw_parts(pixels, 64 * 32 * 4) };
+        let lit = pixels.chunks(4).filter(|pixel| pixel[0] != 0).count();
+        // The font's 8 has 16 pixels lit.
+        assert_eq!(lit, 16);
+        assert!(etherea_fault(Some(&machine)).is_null());
+        etherea_destroy(Some(machine));
+    }
+
+    #[test]
+    fn faults() {
+        let mut machine = etherea_create();
+        let rom = [0x00, 0xEE];
+        unsafe { etherea_load_rom(Some(&mut machine), rom.as_ptr(), rom.len()) };
+        assert!(!etherea_step(Some(&mut machine), 1));
+        let fault = unsafe { CStr::from_ptr(etherea_fault(Some(&machine))) };
+        assert!(fault.to_str().unwrap().contains("0x200"));
+
+        unsafe { etherea_load_rom(Some(&mut machine), ptr::null(), 0) };
+        assert!(etherea_fault(Some(&machine)).is_null());
+        assert!(!etherea_step(None, 1));
+        etherea_destroy(None);
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{debug::hash, Interpreter};
+use log::error;
+use std::{fs, io, path::PathBuf};
+
+/// Where `FX75` saves the RPL user flags of each ROM, in a file keyed by the
+/// ROM's hash, so games that keep high scores in them still have them the
+/// next time they're played, like a cartridge's battery-backed save.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Battery {
+    dir: PathBuf,
+}
+
+impl Battery {
+    /// Saves flags to files in `dir`, which is created on the first save.
+    #[must_use]
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Saves flags inside the user's data directory, if there is one.
+    #[must_use]
+    pub fn open() -> Option<Self> {
+        dirs::data_dir().map(|dir| Self::new(dir.join("etherea").join("flags")))
+    }
+
+    /// The path of the file the flags of `rom` are saved to.
+    #[must_use]
+    pub fn path(&self, rom: &[u8]) -> PathBuf {
+        self.dir.join(format!("{:016x}.bin", hash(rom)))
+    }
+
+    /// The flags last saved for `rom`, or all zeroes if none have been saved
+    /// or they cannot be read.
+    #[must_use]
+    pub fn load(&self, rom: &[u8]) -> [u8; Interpreter::FLAG_COUNT] {
+        let mut flags = [0; Interpreter::FLAG_COUNT];
+        let path = self.path(rom);
+        match fs::read(&path) {
+            Ok(saved) => {
+                let len = saved.len().min(Interpreter::FLAG_COUNT);
+                flags[..len].copy_from_slice(&saved[..len]);
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => error!("Could not read flags from '{}': {}", path.display(), e),
+        }
+        flags
+    }
+
+    /// Saves `flags` for `rom`.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be written.
+    pub fn save(&self, rom: &[u8], flags: &[u8; Interpreter::FLAG_COUNT]) -> io::Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.path(rom), flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saves_per_rom() {
+        let dir = std::env::temp_dir().join(format!("etherea-flags-{}", std::process::id()));
+        let battery = Battery::new(dir.clone());
+        let mut flags = [0; Interpreter::FLAG_COUNT];
+        flags[..3].copy_from_slice(&[1, 2, 3]);
+        assert_eq!(battery.load(b"game"), [0; Interpreter::FLAG_COUNT]);
+        battery.save(b"game", &flags).unwrap();
+        assert_eq!(battery.load(b"game"), flags);
+        assert_eq!(battery.load(b"other game"), [0; Interpreter::FLAG_COUNT]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
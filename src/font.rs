@@ -1,4 +1,4 @@
-use std::ops::RangeInclusive;
+use core::ops::RangeInclusive;
 
 /// A conventional memory chunk where font data is stored.
 pub const MEMORY_RANGE: RangeInclusive<usize> = 0x50..=0x9F;
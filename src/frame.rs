@@ -0,0 +1,105 @@
+use crate::screen::Resolution;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A snapshot of the CHIP-8 screen, independent of any window, taken with
+/// [`Interpreter::framebuffer`](crate::Interpreter::framebuffer).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    resolution: Resolution,
+    pixels: Vec<bool>, // row-major, `true` for lit pixels
+}
+
+impl Frame {
+    /// Creates a frame of `resolution` from row-major pixels.
+    ///
+    /// # Panics
+    /// This function will panic if there isn't exactly one pixel for every
+    /// position on the screen.
+    #[must_use]
+    pub fn new(resolution: Resolution, pixels: Vec<bool>) -> Self {
+        assert!(
+            pixels.len() == resolution.pixels(),
+            "a {resolution} frame can't have {} pixels",
+            pixels.len()
+        );
+        Self { resolution, pixels }
+    }
+
+    /// The size of the frame.
+    #[must_use]
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    /// The width of the frame, in pixels.
+    #[must_use]
+    pub fn width(&self) -> usize {
+        usize::from(self.resolution.width)
+    }
+
+    /// The height of the frame, in pixels.
+    #[must_use]
+    pub fn height(&self) -> usize {
+        usize::from(self.resolution.height)
+    }
+
+    /// Whether the pixel at (`x`, `y`) is lit. Out-of-range coordinates are
+    /// never lit.
+    #[must_use]
+    pub fn get(&self, x: usize, y: usize) -> bool {
+        x < self.width() && y < self.height() && self.pixels[y * self.width() + x]
+    }
+
+    /// All pixels, row by row.
+    #[must_use]
+    pub fn pixels(&self) -> &[bool] {
+        &self.pixels
+    }
+
+    /// The number of lit pixels.
+    #[must_use]
+    pub fn lit(&self) -> usize {
+        self.pixels.iter().filter(|&&on| on).count()
+    }
+}
+
+/// Draws the frame as ASCII art, with `#` for lit pixels and `.` for unlit
+/// ones, ending every row with a newline.
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.pixels.chunks(self.width()) {
+            for &on in row {
+                write!(f, "{}", if on { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii() {
+        let mut pixels = vec![false; Resolution::LORES.pixels()];
+        pixels[1] = true;
+        pixels[64] = true;
+        let frame = Frame::new(Resolution::LORES, pixels);
+        assert!(frame.get(1, 0) && frame.get(0, 1));
+        assert!(!frame.get(0, 0) && !frame.get(64, 0));
+        assert_eq!(frame.lit(), 2);
+
+        let ascii = frame.to_string();
+        let rows: Vec<&str> = ascii.lines().collect();
+        assert_eq!(rows.len(), 32);
+        assert_eq!(&rows[0][..3], ".#.");
+        assert_eq!(&rows[1][..3], "#..");
+
+        let frame = Frame::new(Resolution::SUPER, vec![true; 128 * 64]);
+        assert_eq!((frame.width(), frame.height()), (128, 64));
+        assert!(frame.get(127, 63) && !frame.get(128, 0));
+    }
+}
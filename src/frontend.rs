@@ -0,0 +1,1027 @@
+#[cfg(feature = "audio")]
+use crate::audio;
+use crate::{
+    cheats, config, control, dap, database, debug, detect, flags, input, keypad, kiosk, library,
+    netplay, octo, opcode, quirks, record, replay, save,
+    screen::{self, Resolution, Scaling},
+    settings, stats, targets, text, timing, trace, vip, watch, Counters, Event, Fault, Interpreter,
+    SwapMode,
+};
+use log::{debug, error, info, warn};
+use pixels::{Pixels, SurfaceTexture};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    ops::Range,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use winit::{
+    dpi::{LogicalSize, PhysicalPosition},
+    event::{Touch, TouchPhase, VirtualKeyCode, WindowEvent},
+    event_loop::{ControlFlow, EventLoop},
+    platform::run_return::EventLoopExtRunReturn,
+    window::{Fullscreen, Window, WindowBuilder},
+};
+use winit_input_helper::WinitInputHelper;
+
+/// Options controlling how [`run`] runs a ROM.
+#[derive(Debug, Default)]
+pub struct Options {
+    /// How many instructions to execute per frame, and frames per second.
+    pub timing: timing::Timing,
+    /// Seeds the random number generator. Seeded from entropy if not provided.
+    pub seed: Option<u64>,
+    /// Starts a recording immediately, written to this path when it is
+    /// stopped (with `F9`) or the window is closed.
+    pub record: Option<PathBuf>,
+    /// Journals every key event so the session can be replayed later.
+    pub journal: Option<replay::Journal>,
+    /// Writes the state of the machine before every instruction, to compare
+    /// runs with [`trace::diff`].
+    pub trace: Option<trace::Writer>,
+    /// Plays back recorded key events instead of taking keyboard input.
+    pub replay: Option<VecDeque<replay::Entry>>,
+    /// Pauses execution at breakpoints and watchpoints.
+    pub debugger: Option<debug::Debugger>,
+    /// Behavior that differs between interpreters.
+    pub quirks: quirks::Quirks,
+    /// Times instructions by what they cost on the COSMAC VIP, rather than
+    /// running a flat number of them each frame.
+    pub cycle_costs: Option<vip::Costs>,
+    /// User settings, such as the window scale and palette.
+    pub config: config::Config,
+    /// The file the ROM was read from, to find its profile by name.
+    pub path: Option<PathBuf>,
+    /// Added to the local usage statistics when the window is closed.
+    pub session: Option<stats::Session>,
+    /// Maps 2K banks of a ROM too big for memory, with
+    /// [`Banks`](crate::bank::Banks).
+    pub banking: bool,
+    /// The platform the ROM was written for. CHIP-8X ROMs run in color, and
+    /// HIRES CHIP-8 ROMs are recognized by their header whatever this is.
+    pub platform: opcode::Platform,
+    /// Shows a menu of ROMs to switch to when `Escape` is pressed.
+    pub library: Option<library::Library>,
+    /// Reloads the ROM from this path whenever the file changes.
+    pub watch: Option<PathBuf>,
+    /// Keeps registers and memory at set values, e.g. to never lose a life.
+    pub cheats: Option<cheats::Cheats>,
+    /// Hooks into the interpreter as it runs, e.g. to cheat, split, or play.
+    #[cfg(feature = "scripting")]
+    pub script: Option<crate::script::Rhai>,
+    /// Runs this ROM in a second window beside the first, with its own
+    /// state, e.g. to race or compare quirks. Keys go to the focused window,
+    /// and `Tab` switches between them.
+    pub twin: Option<Vec<u8>>,
+    /// Plays with someone else over the network: key events are exchanged
+    /// every frame, and applied on both sides at the same point.
+    pub netplay: Option<netplay::Session>,
+    /// Serves an HTTP API on this local port, for other programs to load
+    /// ROMs, pause, take screenshots, read state, and press keys.
+    pub control_port: Option<u16>,
+    /// Pauses at breakpoints set from an editor, and steps as it says.
+    pub dap: Option<dap::Target>,
+    /// Shows the ROMs of a playlist one after another, in a borderless
+    /// fullscreen window, switching whenever the kiosk says.
+    pub kiosk: Option<kiosk::Kiosk>,
+    /// Kept up to date with what the ROM's interpreter has done, at the end
+    /// of every frame, for statistics shown once it stops.
+    pub counters: Option<Arc<Mutex<Counters>>>,
+}
+
+/// The entrypoint for the CHIP-8 interpreter. Creates a new interpreter and
+/// starts a thread for the frame-based fetch/decode/execute loop, which also
+/// ticks the 60Hz timers. Runs the window event loop in the calling thread,
+/// and returns once the window is closed and the interpreter has stopped.
+///
+/// # Errors
+/// This function will error with the fault that stopped the ROM, which
+/// closes the window. The fault has already been logged, along with where
+/// its crash report was written.
+pub fn run(rom: &[u8], options: Options) -> Result<(), Fault> {
+    let Options {
+        timing,
+        seed,
+        record,
+        journal,
+        trace,
+        replay,
+        debugger,
+        quirks,
+        cycle_costs,
+        config,
+        path,
+        session,
+        banking,
+        platform,
+        library,
+        watch,
+        cheats,
+        #[cfg(feature = "scripting")]
+        script,
+        twin,
+        netplay,
+        control_port,
+        dap,
+        kiosk,
+        counters,
+    } = options;
+    let el = EventLoop::new();
+
+    let recorder = recorder(record, timing);
+
+    // Settings shared by both instances, when there are two.
+    let configure = |intr: &mut Interpreter| {
+        intr.with_timing(timing);
+        intr.with_quirks(quirks);
+        if banking {
+            intr.with_banking();
+        }
+        if platform == opcode::Platform::Chip8X {
+            intr.with_chip8x();
+        }
+        if let Some(seed) = seed {
+            intr.with_seed(seed);
+        }
+        if let Some(costs) = &cycle_costs {
+            intr.with_cycle_costs(costs.clone());
+        }
+        if let Some(battery) = config.save_flags.then(flags::Battery::open).flatten() {
+            intr.with_battery(battery);
+        }
+        #[cfg(feature = "audio")]
+        audio::Speaker::attach(intr, config.beep);
+    };
+    let mut intr = Interpreter::new();
+    configure(&mut intr);
+    hook_frames(&mut intr, &recorder, counters);
+    intr.journal = journal;
+    intr.trace = trace;
+    intr.replay = replay;
+    if let Some(debugger) = debugger {
+        intr.attach_debugger(debugger);
+    }
+    intr.cheats = cheats;
+    #[cfg(feature = "scripting")]
+    if let Some(script) = script {
+        intr.attach_script(script);
+    }
+    intr.netplay = netplay;
+    intr.dap = dap;
+    let main = Instance::start(&el, intr, (path.as_deref(), rom), &config, platform);
+    if kiosk.is_some() {
+        main.window
+            .set_fullscreen(Some(Fullscreen::Borderless(None)));
+        main.window.set_cursor_visible(false);
+    }
+    let mut instances = vec![main];
+    if let Some(twin) = twin {
+        let mut intr = Interpreter::new();
+        configure(&mut intr);
+        let instance = Instance::start(&el, intr, (None, &twin), &config, platform);
+        instance.place_beside(&instances[0]);
+        instances.push(instance);
+    }
+
+    // Kept alive for as long as the window is open.
+    let _watcher = watch.and_then(|path| {
+        watch::watch(&path, rom, instances[0].tx.clone())
+            .map_err(|e| error!("Could not watch '{}': {}", path.display(), e))
+            .ok()
+    });
+    if let Some(Err(e)) = control_port.map(|port| control::serve(port, instances[0].tx.clone())) {
+        error!("Could not serve the control API: {}", e);
+    }
+
+    Interpreter::ui(el, &mut instances, recorder, session, library, kiosk);
+    shut_down(instances)
+}
+
+/// Captures every frame of `intr` for `recorder`, and copies its counters
+/// to `counters` if they're wanted.
+fn hook_frames(
+    intr: &mut Interpreter,
+    recorder: &Arc<Mutex<record::Recorder>>,
+    counters: Option<Arc<Mutex<Counters>>>,
+) {
+    let hook = Arc::clone(recorder);
+    intr.on_frame(move |info| {
+        if let Ok(mut recorder) = hook.lock() {
+            recorder.capture(info.frame, info.resolution, info.framebuffer);
+        }
+    });
+    if let Some(counters) = counters {
+        intr.on_frame(move |info| {
+            if let Ok(mut counters) = counters.lock() {
+                *counters = info.counters;
+            }
+        });
+    }
+}
+
+/// A recorder of frames at the rate of `timing`, which has started if
+/// there's a path to `record` to.
+fn recorder(record: Option<PathBuf>, timing: timing::Timing) -> Arc<Mutex<record::Recorder>> {
+    let start = record.is_some();
+    let mut recorder = record::Recorder::new(
+        usize::from(Resolution::LORES.width),
+        usize::from(Resolution::LORES.height),
+        timing.frames_per_second,
+        record,
+    );
+    if start {
+        recorder.start();
+    }
+    Arc::new(Mutex::new(recorder))
+}
+
+/// Stops the interpreters of `instances`, once their windows are closed,
+/// and waits for them, so their traces and journals are written out.
+/// Returns the fault that stopped one, if any did, and carries on the panic
+/// of one that panicked.
+fn shut_down(instances: Vec<Instance>) -> Result<(), Fault> {
+    for instance in &instances {
+        let _ = instance.tx.send(Event::Shutdown);
+    }
+    // A debugger waiting for a command can't see the event until it's told
+    // to carry on, so say why etherea hasn't exited.
+    let deadline = Instant::now() + SHUTDOWN_TIMEOUT;
+    while instances
+        .iter()
+        .any(|instance| !instance.handle.is_finished())
+        && Instant::now() < deadline
+    {
+        thread::sleep(Duration::from_millis(10));
+    }
+    if instances
+        .iter()
+        .any(|instance| !instance.handle.is_finished())
+    {
+        warn!("Waiting for the interpreter to stop; continue or quit the debugger if it's paused");
+    }
+    let mut result = Ok(());
+    for instance in instances {
+        match instance.handle.join() {
+            Ok(stopped) => result = result.and(stopped),
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+    result
+}
+
+/// How long [`run`] waits for the interpreter to stop once the window
+/// closes before saying it's still waiting.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
+
+impl Interpreter {
+    /// Runs the window event loop until a window is closed, then saves the
+    /// recording and usage statistics. Input goes to the focused instance.
+    fn ui(
+        mut el: EventLoop<()>,
+        instances: &mut [Instance],
+        recorder: Arc<Mutex<record::Recorder>>,
+        mut session: Option<stats::Session>,
+        library: Option<library::Library>,
+        mut kiosk: Option<kiosk::Kiosk>,
+    ) {
+        let mut input = WinitInputHelper::new();
+        let mut focused = 0;
+        // ROMs chosen from the library, for the instance focused at the time.
+        let (picks, picked) = mpsc::channel::<(usize, PathBuf, Vec<u8>)>();
+        for instance in instances.iter_mut() {
+            instance.overlay.show();
+        }
+        el.run_return(move |event, _, cf| {
+            *cf = ControlFlow::Poll;
+            for instance in instances.iter_mut() {
+                instance.overlay.update();
+            }
+            if let Ok((n, path, rom)) = picked.try_recv() {
+                instances[n].load(Some(&path), rom);
+            }
+            if let Some(kiosk) = kiosk.as_mut() {
+                instances[0].show(kiosk, &event);
+            }
+            match &event {
+                winit::event::Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::Focused(gained),
+                } => {
+                    if let Some(n) = instances.iter().position(|i| i.window.id() == *window_id) {
+                        if *gained {
+                            focused = n;
+                        }
+                        if instances[n].pause_unfocused {
+                            let _ = instances[n].tx.send(Event::Focus(*gained));
+                        }
+                    }
+                }
+                // Redraw at the new size, even if the screen hasn't changed.
+                winit::event::Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::Resized(_),
+                } => {
+                    if let Some(i) = instances.iter().find(|i| i.window.id() == *window_id) {
+                        let _ = i.tx.send(Event::Rescale(i.scaling));
+                    }
+                }
+                // Touches go to the window touched, focused or not.
+                winit::event::Event::WindowEvent {
+                    window_id,
+                    event: WindowEvent::Touch(touch),
+                } => {
+                    if let Some(i) = instances.iter_mut().find(|i| i.window.id() == *window_id) {
+                        i.touch(touch);
+                    }
+                }
+                _ => {}
+            }
+
+            let updated = input.update(&event);
+            // An interpreter only stops by itself when its ROM faults.
+            let stopped = instances.iter().any(|i| i.closed || i.handle.is_finished());
+            if stopped || (updated && input.quit()) {
+                let mut recorder = recorder.lock().unwrap();
+                if recorder.is_active() {
+                    save_recording(&mut recorder);
+                }
+                if let Some(Err(e)) = session.take().map(stats::Session::finish) {
+                    error!("Could not save usage statistics: {}", e);
+                }
+                *cf = ControlFlow::Exit;
+                return;
+            }
+
+            if updated {
+                if input.key_pressed(VirtualKeyCode::Tab) && instances.len() > 1 {
+                    focused = (focused + 1) % instances.len();
+                    instances[focused].window.focus_window();
+                }
+
+                if let Some(library) = library.as_ref() {
+                    if input.key_pressed(VirtualKeyCode::Escape) {
+                        // The menu reads from the terminal, so it's shown on
+                        // another thread to keep the window responsive.
+                        let (library, picks, n) = (library.clone(), picks.clone(), focused);
+                        thread::spawn(move || {
+                            if let Some((path, rom)) = library.pick() {
+                                let _ = picks.send((n, path, rom));
+                            }
+                        });
+                    }
+                }
+
+                if input.key_pressed(VirtualKeyCode::F9) {
+                    let mut recorder = recorder.lock().unwrap();
+                    if recorder.is_active() {
+                        save_recording(&mut recorder);
+                    } else {
+                        recorder.start();
+                    }
+                }
+
+                instances[focused].update(&input);
+            }
+        });
+    }
+}
+
+/// Stops the recording, writing it out and reporting where it was written.
+fn save_recording(recorder: &mut record::Recorder) {
+    match recorder.stop() {
+        Ok(Some(path)) => println!("Wrote recording to {}", path.display()),
+        Ok(None) => {}
+        Err(e) => error!("Could not write recording: {}", e),
+    }
+}
+
+/// The CHIP-8 display: a window showing the screen.
+pub struct Display {
+    /// The window, shared with the [`Overlay`].
+    window: Arc<Window>,
+    /// A pixel buffer of the pixels currently being displayed.
+    pixels: Pixels,
+    /// The colors pixels are drawn in.
+    palette: config::Palette,
+    /// How many pixels of the buffer make up a CHIP-8 pixel, so that text
+    /// can be drawn at a finer resolution.
+    detail: usize,
+    /// How many times bigger than the screen the window is.
+    scale: u32,
+    /// The size of the screen being shown, in CHIP-8 pixels, shared with
+    /// the [`Instance`] to find where clicks land.
+    resolution: Arc<Mutex<Resolution>>,
+    /// How the screen is scaled to fill the window.
+    scaling: Scaling,
+    /// The screen and overlays, before they're scaled to fill the window.
+    image: Vec<u8>,
+    /// The size of the window's surface, and of the pixel buffer, which
+    /// match so the window's pixels are filled as [`Scaling`] says.
+    size: (u32, u32),
+}
+
+impl Display {
+    const TITLE: &'static str = "etherea";
+
+    /// The window title for a ROM with the title `rom`, if it's known.
+    #[must_use]
+    pub fn title(rom: Option<&str>) -> String {
+        match rom {
+            Some(rom) => format!("{} \u{2014} {rom}", Self::TITLE),
+            None => Self::TITLE.to_string(),
+        }
+    }
+
+    /// Creates a new Window and pixel buffer attached to the given [`EventLoop`](winit::event_loop::EventLoop),
+    /// titled `title`, and scaled and colored according to `config`.
+    ///
+    /// # Panics
+    /// This function will panic if the window fails to be created.
+    #[must_use]
+    pub fn new(el: &EventLoop<()>, config: &config::Config, title: &str) -> Self {
+        let window = {
+            let size = LogicalSize::new(
+                u32::from(Resolution::LORES.width),
+                u32::from(Resolution::LORES.height),
+            );
+            WindowBuilder::new()
+                .with_title(title)
+                .with_resizable(true)
+                .with_inner_size(Self::scaled(Resolution::LORES, config.scale))
+                .with_min_inner_size(size)
+                .build(el)
+                .unwrap()
+        };
+
+        let detail = text::detail(config.scale);
+        let size = window.inner_size();
+        let pixels = {
+            let texture = SurfaceTexture::new(size.width, size.height, &window);
+            Pixels::new(size.width, size.height, texture).unwrap()
+        };
+
+        Self {
+            window: Arc::new(window),
+            pixels,
+            palette: config.palette,
+            detail: detail as usize,
+            scale: config.scale,
+            resolution: Arc::new(Mutex::new(Resolution::LORES)),
+            scaling: config.scaling,
+            image: Vec::new(),
+            size: (size.width, size.height),
+        }
+    }
+
+    /// The window the display renders to.
+    fn window(&self) -> Arc<Window> {
+        Arc::clone(&self.window)
+    }
+
+    /// The size of the screen being shown, in CHIP-8 pixels.
+    fn resolution(&self) -> Arc<Mutex<Resolution>> {
+        Arc::clone(&self.resolution)
+    }
+
+    /// The size of the window for a screen of `resolution`, `scale` times
+    /// bigger than the screen if it's 64 pixels wide. Wider screens have
+    /// smaller pixels, so the window stays as wide whatever the resolution.
+    fn scaled(resolution: Resolution, scale: u32) -> LogicalSize<f64> {
+        let scale = f64::from(scale) * 64.0 / f64::from(resolution.width.max(1));
+        LogicalSize::new(
+            f64::from(resolution.width) * scale,
+            f64::from(resolution.height) * scale,
+        )
+    }
+}
+
+/// Composites the planes of the rows of the screen that changed in the
+/// display's palette, draws the debug overlay and the on-screen keypad over
+/// them, and renders them to the window, resizing the window first if the
+/// resolution changed.
+impl screen::Screen for Display {
+    fn render(&mut self, view: &screen::View<'_>) {
+        let resized = view.resolution != *self.resolution.lock().unwrap();
+        if resized {
+            self.resize(view.resolution);
+        }
+        let dirty = |y| resized || view.is_dirty(y);
+        let (columns, lines) = (
+            usize::from(view.resolution.width),
+            usize::from(view.resolution.height),
+        );
+        let width = columns * self.detail;
+        let height = lines * self.detail;
+        self.image.resize(width * height * 4, 0);
+        for y in (0..lines).filter(|&y| dirty(y)) {
+            let rows =
+                &mut self.image[y * self.detail * width * 4..(y + 1) * self.detail * width * 4];
+            let (row, copies) = rows.split_at_mut(width * 4);
+            for (x, out) in row.chunks_exact_mut(4 * self.detail).enumerate() {
+                let rgba = view.rgba(&self.palette, x, y);
+                for out in out.chunks_exact_mut(4) {
+                    out.copy_from_slice(&rgba);
+                }
+            }
+            for copy in copies.chunks_exact_mut(width * 4) {
+                copy.copy_from_slice(row);
+            }
+        }
+        let frame = &mut self.image[..];
+        let (foreground, background) = (self.palette.rgba(true), self.palette.rgba(false));
+        if let Some(lines) = view.hud {
+            text::draw(frame, width, lines, foreground, background);
+        }
+        if let Some((pad, keypad)) = view.keypad {
+            pad.draw(frame, width, keypad, foreground, background);
+        }
+        // Only the rows from the first dirty one to the last changed.
+        let detail = u32::try_from(self.detail).unwrap_or(1);
+        let first = (0..lines).find(|&y| dirty(y));
+        let last = (0..lines).rfind(|&y| dirty(y));
+        let rows = match (first, last) {
+            (Some(first), Some(last)) => {
+                let row = |y: usize| u32::try_from(y).unwrap_or(u32::MAX) * detail;
+                row(first)..row(last + 1)
+            }
+            _ => 0..0,
+        };
+        self.present((width, height), rows);
+    }
+
+    fn set_palette(&mut self, palette: config::Palette) {
+        self.palette = palette;
+    }
+
+    fn set_scaling(&mut self, scaling: Scaling) {
+        self.scaling = scaling;
+    }
+}
+
+impl Display {
+    /// Resizes the window for a screen of `resolution`.
+    fn resize(&mut self, resolution: Resolution) {
+        self.window
+            .set_inner_size(Self::scaled(resolution, self.scale));
+        *self.resolution.lock().unwrap() = resolution;
+    }
+
+    /// Scales `rows` of the image, of `size` (width, height), to fill the
+    /// window and renders it, first resizing the surface and pixel buffer to
+    /// match the window if it was resized, which rescales all of it.
+    fn present(&mut self, size: (usize, usize), mut rows: Range<u32>) {
+        let window = self.window.inner_size();
+        let window = (window.width, window.height);
+        if window.0 == 0 || window.1 == 0 {
+            return; // minimized
+        }
+        if window != self.size {
+            let resized = self
+                .pixels
+                .resize_surface(window.0, window.1)
+                .and_then(|()| self.pixels.resize_buffer(window.0, window.1));
+            if let Err(e) = resized {
+                error!(target: targets::DISPLAY, "Could not resize the display: {}", e);
+                return;
+            }
+            self.size = window;
+            rows = 0..u32::MAX;
+        }
+        let size = (
+            u32::try_from(size.0).unwrap_or_default(),
+            u32::try_from(size.1).unwrap_or_default(),
+        );
+        let frame = self.pixels.get_frame_mut();
+        self.scaling
+            .scale_rows(&self.image, size, rows, frame, window);
+        self.pixels.render().unwrap();
+    }
+}
+
+impl fmt::Debug for Display {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Display")
+    }
+}
+
+/// An interpreter running on its own thread, shown in its own window.
+struct Instance {
+    window: Arc<Window>,                 // The window the instance is shown in
+    tx: Sender<Event>,                   // Events for the interpreter
+    overlay: Overlay,                    // The machine configuration in the title
+    panel: settings::Panel,              // The settings panel toggled with `F2`
+    keymap: HashMap<VirtualKeyCode, u8>, // The keyboard keys for the keypad
+    keys: input::Bindings,               // Keys on top of the keymap, from a ROM's profile
+    default: input::Keymap,              // The keymap for ROMs without a profile
+    profiles: Vec<config::Profile>,      // Keys for particular ROMs
+    pad: bool,                           // Whether the on-screen keypad is shown
+    pointers: HashMap<u64, u8>,          // Pads held by the mouse or touches
+    detail: u32,                         // Image pixels per CHIP-8 pixel, to find pads
+    resolution: Arc<Mutex<Resolution>>,  // The screen's size in CHIP-8 pixels, likewise
+    scaling: Scaling,                    // How the screen is scaled, switched with `F7`
+    pause_unfocused: bool,               // Whether to pause while the window lacks focus
+    paddle: Option<input::Paddle>,       // Keys held by moving the mouse, if enabled
+    paddled: Option<u8>,                 // The key the mouse is holding, if any
+    turbo: Option<input::Turbo>,         // Auto-fire, while its modifier is held
+    closed: bool,                        // Whether the interpreter has stopped taking events
+    handle: thread::JoinHandle<Result<(), Fault>>, // The interpreter's thread
+}
+
+impl Instance {
+    /// Opens a window for `intr`, loads `rom` (written for `platform`, and
+    /// read from `path` if it was read from a file) into it, and starts it
+    /// running with the keys of its profile.
+    fn start(
+        el: &EventLoop<()>,
+        mut intr: Interpreter,
+        (path, rom): (Option<&Path>, &[u8]),
+        config: &config::Config,
+        platform: opcode::Platform,
+    ) -> Self {
+        let title = title(path, rom);
+        let display = Display::new(el, config, &title);
+        let window = display.window();
+        let resolution = display.resolution();
+        intr.attach_display(Box::new(display));
+        intr.load_rom(rom);
+        if let Some(save) = save(&config.profiles, path, rom) {
+            intr.with_save(save);
+        }
+        let (timing, quirks) = (intr.timing, intr.quirks);
+        let overlay = Overlay::new(
+            Arc::clone(&window),
+            title,
+            format!(
+                "{}, {} IPS, {}",
+                if intr.hires {
+                    opcode::Platform::Hires
+                } else {
+                    platform
+                },
+                timing.cycles_before(u64::from(timing.frames_per_second)),
+                quirks
+            ),
+        );
+        let mut panel = settings::Panel::new(config, timing, quirks);
+        if platform == opcode::Platform::Chip8X {
+            panel.with_second_keypad();
+        }
+        let (tx, rx) = mpsc::channel();
+        let mut instance = Self {
+            window,
+            tx,
+            overlay,
+            keymap: panel.codes(),
+            keys: input::Bindings::default(),
+            default: config.keymap.clone(),
+            profiles: config.profiles.clone(),
+            panel,
+            pad: false,
+            pointers: HashMap::new(),
+            detail: text::detail(config.scale),
+            resolution,
+            scaling: config.scaling,
+            pause_unfocused: config.pause_unfocused,
+            paddle: config.paddle,
+            paddled: None,
+            turbo: config.turbo.clone(),
+            closed: false,
+            handle: intr.spawn(rx),
+        };
+        instance.profile(path, rom);
+        instance
+    }
+
+    /// Sends `event` to the interpreter, closing the instance if it has
+    /// stopped, as it does when its ROM faults.
+    fn send(&mut self, event: Event) {
+        if self.tx.send(event).is_err() && !self.closed {
+            error!("The interpreter has stopped");
+            self.closed = true;
+        }
+    }
+
+    /// Loads `rom`, read from `path` if it was read from a file, in place of
+    /// the ROM running, with the keys of its profile.
+    fn load(&mut self, path: Option<&Path>, rom: Vec<u8>) {
+        self.profile(path, &rom);
+        self.overlay.retitle(title(path, &rom));
+        let save = save(&self.profiles, path, &rom);
+        let mode = SwapMode::Reset;
+        self.send(Event::SwapRom { rom, mode });
+        if let Some(save) = save {
+            self.send(Event::Save(save));
+        }
+    }
+
+    /// Notes any input in `event` for `kiosk`, and loads the next ROM of
+    /// its playlist once it's due.
+    fn show(&mut self, kiosk: &mut kiosk::Kiosk, event: &winit::event::Event<()>) {
+        let now = Instant::now();
+        if let winit::event::Event::WindowEvent {
+            event:
+                WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::Touch(_),
+            ..
+        } = event
+        {
+            kiosk.input(now);
+        }
+        if kiosk.due(now) {
+            if let Some((path, rom)) = kiosk.advance(now) {
+                info!("Showing '{}'", path.display());
+                self.load(Some(&path), rom);
+            }
+        }
+    }
+
+    /// Switches to the keys of the first profile matching `rom`, read from
+    /// `path` if it was read from a file, or to the config file's keymap if
+    /// none does.
+    fn profile(&mut self, path: Option<&Path>, rom: &[u8]) {
+        debug!(target: targets::INPUT, "ROM hash: {:016x}", debug::hash(rom));
+        let profile = self
+            .profiles
+            .iter()
+            .find(|profile| profile.matches(path, rom));
+        let (keymap, keys) = match profile {
+            Some(profile) => {
+                let name = profile.file.as_deref().or(profile.hash.as_deref());
+                info!(
+                    target: targets::INPUT,
+                    "Using the keys of the profile for '{}'",
+                    name.unwrap_or_default()
+                );
+                (profile.keymap.clone(), profile.keys.clone())
+            }
+            None => (None, input::Bindings::default()),
+        };
+        self.keys = keys;
+        self.panel
+            .with_keymap(keymap.unwrap_or_else(|| self.default.clone()));
+        self.rebind();
+    }
+
+    /// Maps keyboard keys to the keypad as the settings panel and the
+    /// profile say, relabeling the on-screen keypad if it is shown.
+    fn rebind(&mut self) {
+        self.keymap = self.panel.codes();
+        self.keymap.extend(self.keys.codes());
+        if self.pad {
+            let pad = keypad::Overlay::new(&self.panel.keymap());
+            self.send(Event::Keypad(Some(pad)));
+        }
+    }
+
+    /// Moves the window to the right of `other`'s.
+    fn place_beside(&self, other: &Self) {
+        let position = other.window.outer_position().unwrap_or_default();
+        let width = i32::try_from(other.window.outer_size().width).unwrap_or_default();
+        self.window
+            .set_outer_position(PhysicalPosition::new(position.x + width, position.y));
+    }
+
+    /// The pointer ID of the mouse, which touches never have.
+    const MOUSE: u64 = u64::MAX;
+
+    /// Shows the on-screen keypad, or hides it and lets go of the pads held.
+    fn toggle_pad(&mut self) {
+        self.pad = !self.pad;
+        let pad = self.pad.then(|| keypad::Overlay::new(&self.panel.keymap()));
+        self.send(Event::Keypad(pad));
+        for pointer in self.pointers.keys().copied().collect::<Vec<_>>() {
+            self.release(pointer);
+        }
+    }
+
+    /// Presses the pad of the on-screen keypad, if it is shown, at `(x, y)`
+    /// in the window's physical pixels, held until `pointer` is released.
+    fn press(&mut self, pointer: u64, x: f64, y: f64) {
+        let size = self.window.inner_size();
+        let resolution = *self.resolution.lock().unwrap();
+        let width = u32::from(resolution.width) * self.detail;
+        let height = u32::from(resolution.height) * self.detail;
+        let (left, top, w, h) = self.scaling.fit((width, height), (size.width, size.height));
+        if !self.pad || w == 0 || h == 0 {
+            return;
+        }
+        let x = (x - f64::from(left)) * f64::from(width) / f64::from(w);
+        let y = (y - f64::from(top)) * f64::from(height) / f64::from(h);
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let (x, y) = (x as usize, y as usize);
+        if let Some(key) = keypad::Overlay::key_at(width as usize, x, y) {
+            self.pointers.insert(pointer, key);
+            let event = input::KeyEvent { key, pressed: true };
+            self.send(Event::Key(event));
+        }
+    }
+
+    /// Lets go of the pad held by `pointer`, if there is one.
+    fn release(&mut self, pointer: u64) {
+        if let Some(key) = self.pointers.remove(&pointer) {
+            let event = input::KeyEvent {
+                key,
+                pressed: false,
+            };
+            self.send(Event::Key(event));
+        }
+    }
+
+    /// Holds the key for where the mouse is across the window, letting go of
+    /// the one held before, if playing with the mouse as a paddle.
+    fn steer(&mut self, input: &WinitInputHelper) {
+        let Some(paddle) = self.paddle else {
+            return;
+        };
+        let width = f64::from(self.window.inner_size().width);
+        let key = input
+            .mouse()
+            .and_then(|(x, _)| paddle.key(f64::from(x), width));
+        if key == self.paddled {
+            return;
+        }
+        for (key, pressed) in [(self.paddled, false), (key, true)] {
+            if let Some(key) = key {
+                let event = input::KeyEvent { key, pressed };
+                self.send(Event::Key(event));
+            }
+        }
+        self.paddled = key;
+    }
+
+    /// Presses and lets go of pads as `touch` says.
+    fn touch(&mut self, touch: &Touch) {
+        match touch.phase {
+            TouchPhase::Started => self.press(touch.id, touch.location.x, touch.location.y),
+            TouchPhase::Ended | TouchPhase::Cancelled => self.release(touch.id),
+            TouchPhase::Moved => {}
+        }
+    }
+
+    /// Handles the keys and dropped files for this instance, while its
+    /// window has focus.
+    fn update(&mut self, input: &WinitInputHelper) {
+        if input.key_pressed(VirtualKeyCode::F4) {
+            self.toggle_pad();
+        }
+        if input.mouse_pressed(0) {
+            if let Some((x, y)) = input.mouse() {
+                self.press(Self::MOUSE, f64::from(x), f64::from(y));
+            }
+        } else if input.mouse_released(0) {
+            self.release(Self::MOUSE);
+        }
+        self.steer(input);
+        if let Some(turbo) = &self.turbo {
+            let modifier = turbo.modifier.code();
+            if input.key_pressed(modifier) {
+                self.send(Event::Turbo(Some(turbo.clone())));
+            } else if input.key_released(modifier) {
+                self.send(Event::Turbo(None));
+            }
+        }
+
+        if input.key_pressed(VirtualKeyCode::F1) {
+            self.overlay.show();
+        }
+
+        if input.key_pressed(VirtualKeyCode::F7) {
+            self.scaling = self.scaling.next();
+            info!(target: targets::DISPLAY, "Scaling: {:?}", self.scaling);
+            self.send(Event::Rescale(self.scaling));
+        }
+
+        if let Some(path) = input.dropped_file() {
+            let rom = std::fs::read(&path).map_err(|e| e.to_string());
+            match rom.and_then(|file| octo::load(&path, file)) {
+                Ok(rom) => {
+                    info!("Loading dropped ROM '{}'", path.display());
+                    self.load(Some(&path), rom);
+                }
+                Err(e) => error!("Could not read '{}': {}", path.display(), e),
+            }
+        }
+
+        if input.key_pressed(VirtualKeyCode::F3) {
+            self.send(Event::ToggleHud);
+        }
+
+        if input.key_pressed(VirtualKeyCode::F5) {
+            self.send(Event::ToggleFrameAdvance);
+        }
+        if input.key_pressed(VirtualKeyCode::F6) {
+            self.send(Event::AdvanceFrame);
+        }
+        if input.key_pressed(VirtualKeyCode::F8) {
+            self.send(Event::Reset);
+        }
+
+        if input.key_pressed(VirtualKeyCode::F2) {
+            self.panel.toggle();
+            let lines = self.panel.is_open().then(|| self.panel.lines());
+            self.send(Event::Panel(lines));
+        } else if let Some(changed) = self.panel.update(input) {
+            if changed {
+                self.send(Event::Settings(self.panel.settings()));
+                self.rebind();
+            }
+            self.send(Event::Panel(Some(self.panel.lines())));
+        }
+
+        // Keys pressed for the settings panel don't reach the ROM.
+        let open = self.panel.is_open();
+        let keys: Vec<_> = (self.keymap.iter())
+            .filter_map(|(&code, &key)| {
+                if input.key_pressed(code) && !open {
+                    Some(input::KeyEvent { key, pressed: true })
+                } else if input.key_released(code) {
+                    Some(input::KeyEvent {
+                        key,
+                        pressed: false,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+        for event in keys {
+            self.send(Event::Key(event));
+        }
+    }
+}
+
+/// The title of the window for `rom`, read from `path` if it was read from a
+/// file, naming the ROM if its sidecar file or the ROM database does.
+fn title(path: Option<&Path>, rom: &[u8]) -> String {
+    let database = database::Database::load();
+    Display::title(detect::title(path, database.lookup(rom)).as_deref())
+}
+
+/// What to save of the memory of `rom`, read from `path` if it was read
+/// from a file, if the first profile matching it saves any.
+fn save(profiles: &[config::Profile], path: Option<&Path>, rom: &[u8]) -> Option<save::Save> {
+    let profile = profiles.iter().find(|profile| profile.matches(path, rom))?;
+    if profile.save.is_empty() {
+        return None;
+    }
+    save::Save::open(rom, profile.save.clone())
+}
+
+/// The machine configuration a ROM is running with, shown in the window
+/// title for a few seconds when the ROM starts and whenever `F1` is pressed.
+struct Overlay {
+    window: Arc<Window>,
+    title: String, // the window's title while the overlay is hidden
+    text: String,
+    shown: Option<Instant>, // when the overlay was last shown, if it still is
+}
+
+impl Overlay {
+    /// How long the overlay stays up.
+    const DURATION: Duration = Duration::from_secs(3);
+
+    fn new(window: Arc<Window>, title: String, text: String) -> Self {
+        Self {
+            window,
+            title,
+            text,
+            shown: None,
+        }
+    }
+
+    /// Shows the overlay, restarting its timer if it is already up.
+    fn show(&mut self) {
+        self.window
+            .set_title(&format!("{} - {}", self.title, self.text));
+        self.shown = Some(Instant::now());
+        info!("Running as {}", self.text);
+    }
+
+    /// Hides the overlay once it has been up for [`DURATION`](Self::DURATION).
+    fn update(&mut self) {
+        if self
+            .shown
+            .is_some_and(|shown| shown.elapsed() >= Self::DURATION)
+        {
+            self.window.set_title(&self.title);
+            self.shown = None;
+        }
+    }
+
+    /// Changes the window's title while the overlay is hidden, e.g. for a
+    /// newly loaded ROM, and shows the overlay under it.
+    fn retitle(&mut self, title: String) {
+        self.title = title;
+        self.show();
+    }
+}
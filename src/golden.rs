@@ -0,0 +1,337 @@
+use crate::{
+    font,
+    quirks::{IndexOverflow, JumpOffset, Quirks, Shift},
+    Fault,
+};
+
+/// The size of memory, in bytes.
+const MEMORY_SIZE: usize = 0x1000;
+/// Where ROMs are loaded, and execution starts.
+const START: usize = 0x200;
+/// The width of the screen, in pixels.
+pub const WIDTH: usize = 64;
+/// The height of the screen, in pixels.
+pub const HEIGHT: usize = 32;
+
+/// A CHIP-8 interpreter written as plainly as possible, straight from
+/// [Cowgod's reference](http://devernay.free.fr/hacks/chip8/C8TECH10.HTM),
+/// to check the real one against with [`compare`](crate::compare). It
+/// fetches and decodes every instruction afresh, and knows nothing of
+/// extensions, banks, or the display.
+///
+/// Where interpreters disagree, it follows the [`Quirks`] it is given.
+/// Arithmetic writes `VF` after its result, so the flag wins when `VF` is
+/// the destination.
+#[derive(Clone, Debug)]
+pub struct Core {
+    /// The general purpose registers, `V0` through `VF`.
+    pub v: [u8; 16],
+    /// The index register.
+    pub i: u16,
+    /// The address of the next instruction.
+    pub pc: usize,
+    /// Return addresses pushed by `2NNN`.
+    pub stack: Vec<u16>,
+    /// All of memory, with the font at the start.
+    pub memory: [u8; MEMORY_SIZE],
+    /// The delay timer.
+    pub delay: u8,
+    /// The sound timer.
+    pub sound: u8,
+    /// The pixels of the screen, by row.
+    pub screen: [[bool; WIDTH]; HEIGHT],
+    quirks: Quirks,
+    keys: [bool; 16],     // Keys held down
+    waiting: bool,        // Whether `FX0A` is waiting for a key
+    released: Option<u8>, // The key released while `FX0A` waited
+}
+
+impl Core {
+    /// A core with `rom` loaded, following `quirks`.
+    #[must_use]
+    pub fn new(rom: &[u8], quirks: Quirks) -> Self {
+        let mut memory = [0; MEMORY_SIZE];
+        memory[font::MEMORY_RANGE].copy_from_slice(font::FONT);
+        let loaded = &rom[..rom.len().min(MEMORY_SIZE - START)];
+        memory[START..START + loaded.len()].copy_from_slice(loaded);
+        Self {
+            v: [0; 16],
+            i: 0,
+            pc: START,
+            stack: Vec::new(),
+            memory,
+            delay: 0,
+            sound: 0,
+            screen: [[false; WIDTH]; HEIGHT],
+            quirks,
+            keys: [false; 16],
+            waiting: false,
+            released: None,
+        }
+    }
+
+    /// The instruction at the PC, or `None` if the PC is out of bounds.
+    #[must_use]
+    pub fn opcode(&self) -> Option<u16> {
+        let pc = self.quirks.pc_overflow.resolve(self.pc, MEMORY_SIZE)?;
+        let next = (pc + 1) % MEMORY_SIZE;
+        Some(u16::from_be_bytes([self.memory[pc], self.memory[next]]))
+    }
+
+    /// Presses or releases keypad key `key`. Releasing a key while `FX0A`
+    /// waits gives it that key.
+    pub fn key(&mut self, key: u8, pressed: bool) {
+        let Some(held) = self.keys.get_mut(usize::from(key)) else {
+            return;
+        };
+        *held = pressed;
+        if !pressed && self.waiting && self.released.is_none() {
+            self.released = Some(key);
+        }
+    }
+
+    /// Counts both timers down by one, as happens 60 times a second.
+    pub fn tick(&mut self) {
+        self.delay = self.delay.saturating_sub(1);
+        self.sound = self.sound.saturating_sub(1);
+    }
+
+    /// Executes one instruction. `CXNN` masks `random` with `NN`, so that
+    /// the caller decides what's random.
+    ///
+    /// # Errors
+    /// This function will error with the fault that stops the core, such as
+    /// an unknown instruction.
+    pub fn step(&mut self, random: u8) -> Result<(), Fault> {
+        let Some(opcode) = self.opcode() else {
+            return Err(Fault::PcOutOfBounds { pc: self.pc });
+        };
+        let at = self.quirks.pc_overflow.resolve(self.pc, MEMORY_SIZE);
+        self.pc = at.unwrap_or(self.pc) + 2;
+        let pc = self.pc - 2;
+
+        let x = usize::from(opcode >> 8 & 0xF);
+        let y = usize::from(opcode >> 4 & 0xF);
+        let n = opcode & 0xF;
+        let [_, nn] = opcode.to_be_bytes();
+        let nnn = opcode & 0xFFF;
+        let (vx, vy) = (self.v[x], self.v[y]);
+        match (opcode >> 12, n) {
+            _ if opcode == 0x00E0 => self.screen = [[false; WIDTH]; HEIGHT],
+            _ if opcode == 0x00EE => {
+                let addr = self.stack.pop().ok_or(Fault::StackUnderflow { pc })?;
+                self.pc = usize::from(addr);
+            }
+            (0x0, _) => {}
+            (0x1, _) => self.pc = usize::from(nnn),
+            (0x2, _) => {
+                if self.stack.len() >= self.quirks.stack_depth {
+                    let depth = self.stack.len();
+                    return Err(Fault::StackOverflow { pc, depth });
+                }
+                self.stack.push(u16::try_from(self.pc).unwrap_or(u16::MAX));
+                self.pc = usize::from(nnn);
+            }
+            (0x3, _) => self.skip(vx == nn),
+            (0x4, _) => self.skip(vx != nn),
+            (0x5, 0) => self.skip(vx == vy),
+            (0x6, _) => self.v[x] = nn,
+            (0x7, _) => self.v[x] = vx.wrapping_add(nn),
+            (0x8, 0) => self.v[x] = vy,
+            (0x8, 1) => self.logic(x, vx | vy),
+            (0x8, 2) => self.logic(x, vx & vy),
+            (0x8, 3) => self.logic(x, vx ^ vy),
+            (0x8, 4) => {
+                let (sum, carry) = vx.overflowing_add(vy);
+                self.arithmetic(x, sum, carry);
+            }
+            (0x8, 5) => self.arithmetic(x, vx.wrapping_sub(vy), vx >= vy),
+            (0x8, 6) => {
+                let shifted = self.shifted(vx, vy);
+                self.arithmetic(x, shifted >> 1, shifted & 1 == 1);
+            }
+            (0x8, 7) => self.arithmetic(x, vy.wrapping_sub(vx), vy >= vx),
+            (0x8, 0xE) => {
+                let shifted = self.shifted(vx, vy);
+                self.arithmetic(x, shifted << 1, shifted >> 7 == 1);
+            }
+            (0x9, 0) => self.skip(vx != vy),
+            (0xA, _) => self.i = nnn,
+            (0xB, _) => {
+                let offset = match self.quirks.jump_offset {
+                    JumpOffset::V0 => self.v[0],
+                    JumpOffset::Vx => vx,
+                };
+                self.pc = usize::from(nnn) + usize::from(offset);
+            }
+            (0xC, _) => self.v[x] = random & nn,
+            (0xD, _) => self.draw(vx, vy, n, pc)?,
+            (0xE, _) if nn == 0x9E => self.skip(self.keys[usize::from(vx & 0xF)]),
+            (0xE, _) if nn == 0xA1 => self.skip(!self.keys[usize::from(vx & 0xF)]),
+            (0xF, _) => self.misc(opcode, pc)?,
+            _ => return Err(Fault::UnknownOpcode { pc, opcode }),
+        }
+        Ok(())
+    }
+
+    /// The `FXNN` instructions, which deal with timers, keys, `I`, and
+    /// memory.
+    fn misc(&mut self, opcode: u16, pc: usize) -> Result<(), Fault> {
+        let [_, nn] = opcode.to_be_bytes();
+        let x = usize::from(opcode >> 8 & 0xF);
+        let vx = self.v[x];
+        match nn {
+            0x07 => self.v[x] = self.delay,
+            0x0A => {
+                if let Some(key) = self.released.take() {
+                    self.v[x] = key;
+                    self.waiting = false;
+                } else {
+                    self.waiting = true;
+                    self.pc -= 2;
+                }
+            }
+            0x15 => self.delay = vx,
+            0x18 => self.sound = vx,
+            0x1E => {
+                let i = self.i.wrapping_add(u16::from(vx));
+                if self.quirks.index_carry {
+                    self.v[0xF] = u8::from(usize::from(i) >= MEMORY_SIZE);
+                }
+                self.i = match self.quirks.index_overflow {
+                    IndexOverflow::Mask => i & 0xFFF,
+                    IndexOverflow::Fault => i,
+                };
+            }
+            0x29 => {
+                let font = u16::try_from(*font::MEMORY_RANGE.start()).unwrap_or_default();
+                self.i = font + u16::from(vx & 0xF) * 5;
+            }
+            0x33 => {
+                for (offset, digit) in [vx / 100, vx / 10 % 10, vx % 10].into_iter().enumerate() {
+                    let address = self.address(offset, pc)?;
+                    self.memory[address] = digit;
+                }
+            }
+            0x55 => {
+                for register in 0..=x {
+                    let address = self.address(register, pc)?;
+                    self.memory[address] = self.v[register];
+                }
+            }
+            0x65 => {
+                for register in 0..=x {
+                    let address = self.address(register, pc)?;
+                    self.v[register] = self.memory[address];
+                }
+            }
+            _ => return Err(Fault::UnknownOpcode { pc, opcode }),
+        }
+        Ok(())
+    }
+
+    /// Skips the next instruction if `condition` holds.
+    fn skip(&mut self, condition: bool) {
+        if condition {
+            self.pc += 2;
+        }
+    }
+
+    /// Sets `VX` to `result`, then resets `VF` if the quirks say to.
+    fn logic(&mut self, x: usize, result: u8) {
+        self.v[x] = result;
+        if self.quirks.vf_reset {
+            self.v[0xF] = 0;
+        }
+    }
+
+    /// `VX` or `VY`, whichever the [`Shift`] quirk says shifts shift.
+    fn shifted(&self, vx: u8, vy: u8) -> u8 {
+        match self.quirks.shift {
+            Shift::Vx => vx,
+            Shift::Vy => vy,
+        }
+    }
+
+    /// Sets `VX` to `result`, then `VF` to `flag`.
+    fn arithmetic(&mut self, x: usize, result: u8, flag: bool) {
+        self.v[x] = result;
+        self.v[0xF] = u8::from(flag);
+    }
+
+    /// The address `offset` bytes past `I`, as the
+    /// [`IndexOverflow`] quirk resolves it.
+    fn address(&self, offset: usize, pc: usize) -> Result<usize, Fault> {
+        let address = usize::from(self.i) + offset;
+        self.quirks
+            .index_overflow
+            .resolve(address, MEMORY_SIZE)
+            .ok_or(Fault::IndexOutOfBounds {
+                pc,
+                i: self.i,
+                address,
+            })
+    }
+
+    /// `DXYN`: draws the `n` rows of the sprite at `I` at (`vx`, `vy`),
+    /// wrapping the position onto the screen and clipping the sprite at its
+    /// edges. `VF` is set if any pixel was turned off.
+    fn draw(&mut self, vx: u8, vy: u8, n: u16, pc: usize) -> Result<(), Fault> {
+        let (left, top) = (usize::from(vx) % WIDTH, usize::from(vy) % HEIGHT);
+        let mut collision = false;
+        for row in 0..usize::from(n) {
+            let y = top + row;
+            if y >= HEIGHT {
+                break;
+            }
+            let sprite = self.memory[self.address(row, pc)?];
+            for column in 0..8 {
+                let x = left + column;
+                if x < WIDTH && sprite & (0x80 >> column) != 0 {
+                    collision |= self.screen[y][x];
+                    self.screen[y][x] = !self.screen[y][x];
+                }
+            }
+        }
+        self.v[0xF] = u8::from(collision);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm;
+
+    #[test]
+    fn core() {
+        let source = "LD V0, 0xFF\nADD V0, 2\nLD V1, 3\nSUB V1, V0\nLD VF, 1\nADD VF, V0\n\
+                      LD I, 0x300\nLD B, V1\nLD V2, [I]\nLD F, V2\nDRW V3, V3, 5\nDRW V3, V3, 5\n";
+        let rom = asm::assemble(source).unwrap();
+        let mut core = Core::new(&rom, Quirks::default());
+        let run = |core: &mut Core, steps| (0..steps).try_for_each(|_| core.step(0));
+        run(&mut core, 6).unwrap();
+        assert_eq!(core.v[..2], [1, 2]);
+        // VF is written after the sum.
+        assert_eq!(core.v[0xF], 0);
+        run(&mut core, 4).unwrap();
+        assert_eq!(core.memory[0x300..0x303], [0, 0, 2]);
+        assert_eq!(core.v[..3], [0, 0, 2]);
+        assert_eq!(core.i, 0x5A);
+        run(&mut core, 1).unwrap();
+        assert!(core.screen[0][..4].iter().all(|&pixel| pixel));
+        assert!(core.screen[1][3] && !core.screen[1][0]);
+        run(&mut core, 1).unwrap();
+        assert_eq!(core.v[0xF], 1);
+        assert!(core.screen.iter().flatten().all(|&pixel| !pixel));
+
+        let mut core = Core::new(&[0xF0, 0x0A, 0x00, 0xEE], Quirks::default());
+        core.step(0).unwrap();
+        assert_eq!(core.pc, 0x200);
+        core.key(5, true);
+        core.key(5, false);
+        core.step(0).unwrap();
+        assert_eq!((core.v[0], core.pc), (5, 0x202));
+        assert_eq!(core.step(0), Err(Fault::StackUnderflow { pc: 0x202 }));
+    }
+}
@@ -0,0 +1,54 @@
+use crate::debug;
+use std::fmt;
+
+/// Checksums of a ROM, to tell exactly which version of it is which, e.g.
+/// in a bug report.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hashes {
+    /// The 64-bit FNV-1a hash that keys the ROM database and debugger
+    /// projects.
+    pub fnv: u64,
+    /// The CRC-32, as used by ZIP files and many ROM lists.
+    pub crc32: u32,
+    /// The SHA-1, as 40 hex digits.
+    pub sha1: String,
+}
+
+impl Hashes {
+    /// The checksums of `rom`.
+    #[must_use]
+    pub fn of(rom: &[u8]) -> Self {
+        Self {
+            fnv: debug::hash(rom),
+            crc32: crc32fast::hash(rom),
+            sha1: sha1_smol::Sha1::from(rom).digest().to_string(),
+        }
+    }
+}
+
+/// Formats the checksums a line each, like `CRC-32:  7b4c5e3d`.
+impl fmt::Display for Hashes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "FNV-1a:  {:016x}", self.fnv)?;
+        writeln!(f, "CRC-32:  {:08x}", self.crc32)?;
+        write!(f, "SHA-1:   {}", self.sha1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hashes() {
+        let hashes = Hashes::of(b"abc");
+        assert_eq!(hashes.crc32, 0x3524_41C2);
+        assert_eq!(hashes.sha1, "a9993e364706816aba3e25717850c26c9cd0d89d");
+        assert_eq!(
+            hashes.to_string(),
+            "FNV-1a:  e71fa2190541574b\n\
+             CRC-32:  352441c2\n\
+             SHA-1:   a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+    }
+}
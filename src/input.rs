@@ -1,38 +1,528 @@
+use alloc::{
+    collections::{BTreeMap, BTreeSet},
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{de, Deserialize, Deserializer, Serialize};
+#[cfg(feature = "gui")]
 use std::collections::HashMap;
+#[cfg(feature = "gui")]
 use winit::event::VirtualKeyCode;
 
-/// Generates a keymap from a mapping of QWERTY keys to CHIP-8 key codes,
-/// represented as a [`HashMap`](std::collections::HashMap).
-macro_rules! keymap {
-    ($($keycode:ident => $mapping:literal),*) => {
-        lazy_static::lazy_static! {
-            /// A mapping of QWERTY key codes to the CHIP-8 key it represents.
-            pub static ref KEYMAP: HashMap<VirtualKeyCode, u8> = {
-                let mut m = HashMap::new();
-                $(
-                  m.insert(VirtualKeyCode::$keycode, $mapping);
-                )*
-                m
-            };
-        }
-    };
-}
-
-keymap! {
-    Key1 => 0x1,
-    Key2 => 0x2,
-    Key3 => 0x3,
-    Key4 => 0xC,
-    Q => 0x4,
-    W => 0x5,
-    E => 0x6,
-    R => 0xD,
-    A => 0x7,
-    S => 0x8,
-    D => 0x9,
-    F => 0xE,
-    Z => 0xA,
-    X => 0x0,
-    C => 0xB,
-    V => 0xF
+/// A CHIP-8 key being pressed or released.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    /// The CHIP-8 key, from `0x0` to `0xF`, or the same key on the CHIP-8X
+    /// second keypad plus [`KEYPAD_2`].
+    pub key: u8,
+    /// Whether the key was pressed (`true`) or released (`false`).
+    pub pressed: bool,
+}
+
+/// Added to the keys of the second keypad of CHIP-8X, which is mapped to
+/// the numeric keypad (see [`second_keypad`]).
+pub const KEYPAD_2: u8 = 0x10;
+
+/// The keypad as laid out on a QWERTY keyboard, row by row: each key
+/// alongside the CHIP-8 key it represents.
+pub const LAYOUT: [[(char, u8); 4]; 4] = [
+    [('1', 0x1), ('2', 0x2), ('3', 0x3), ('4', 0xC)],
+    [('Q', 0x4), ('W', 0x5), ('E', 0x6), ('R', 0xD)],
+    [('A', 0x7), ('S', 0x8), ('D', 0x9), ('F', 0xE)],
+    [('Z', 0xA), ('X', 0x0), ('C', 0xB), ('V', 0xF)],
+];
+
+/// The keyboard keys that stand for the CHIP-8 keypad, as four rows of four
+/// keys in the positions of [`LAYOUT`], like `["1234", "QWER", "ASDF",
+/// "ZXCV"]`. Only letters and digits can be used.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "Vec<String>", into = "Vec<String>")
+)]
+pub struct Keymap([[char; 4]; 4]);
+
+impl Keymap {
+    /// The keyboard keys, row by row, in the positions of [`LAYOUT`].
+    #[must_use]
+    pub const fn keys(&self) -> [[char; 4]; 4] {
+        self.0
+    }
+
+    /// A mapping of key codes to the CHIP-8 key each represents.
+    #[cfg(feature = "gui")]
+    #[must_use]
+    pub fn codes(&self) -> HashMap<VirtualKeyCode, u8> {
+        let mut codes = HashMap::new();
+        for (row, layout) in self.0.iter().zip(LAYOUT) {
+            for (&key, (_, chip8)) in row.iter().zip(layout) {
+                if let Some(code) = key_code(key) {
+                    codes.insert(code, chip8);
+                }
+            }
+        }
+        codes
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self(LAYOUT.map(|row| row.map(|(key, _)| key)))
+    }
+}
+
+impl TryFrom<Vec<String>> for Keymap {
+    type Error = String;
+
+    fn try_from(rows: Vec<String>) -> Result<Self, Self::Error> {
+        let err =
+            || format!("Invalid keymap {rows:?}, expected four rows of four letters or digits");
+        let keys: Vec<[char; 4]> = rows
+            .iter()
+            .map(|row| {
+                let keys: Vec<char> = row.chars().map(|c| c.to_ascii_uppercase()).collect();
+                keys.try_into()
+                    .ok()
+                    .filter(|keys: &[char; 4]| keys.iter().all(char::is_ascii_alphanumeric))
+            })
+            .collect::<Option<_>>()
+            .ok_or_else(err)?;
+        let keymap = Self(keys.try_into().map_err(|_| err())?);
+        if keymap.0.iter().flatten().collect::<BTreeSet<_>>().len() < 16 {
+            return Err(format!("Invalid keymap {rows:?}, a key is used twice"));
+        }
+        Ok(keymap)
+    }
+}
+
+impl From<Keymap> for Vec<String> {
+    fn from(keymap: Keymap) -> Self {
+        keymap.0.iter().map(|row| row.iter().collect()).collect()
+    }
+}
+
+/// Two keys held by moving the mouse across the window, written `4,6`, so
+/// paddle games can be played with the mouse: left of the middle holds the
+/// `left` key, and right of it the `right` key. Around the middle, neither
+/// is held.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct Paddle {
+    /// The key held with the cursor in the left of the window.
+    pub left: u8,
+    /// The key held with the cursor in the right of the window.
+    pub right: u8,
+}
+
+impl Paddle {
+    /// The share of the window's width, around the middle, where neither
+    /// key is held.
+    pub const DEAD_ZONE: f64 = 0.1;
+
+    /// The key to hold with the cursor `x` pixels across a window `width`
+    /// pixels wide, if any.
+    #[must_use]
+    pub fn key(self, x: f64, width: f64) -> Option<u8> {
+        let offset = x / width - 0.5;
+        if offset < -Self::DEAD_ZONE / 2.0 {
+            Some(self.left)
+        } else if offset > Self::DEAD_ZONE / 2.0 {
+            Some(self.right)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for Paddle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let key = |key: &str| {
+            u8::from_str_radix(key.trim(), 16)
+                .ok()
+                .filter(|&key| key < 0x10)
+        };
+        s.split_once(',')
+            .and_then(|(left, right)| Some((key(left)?, key(right)?)))
+            .map(|(left, right)| Self { left, right })
+            .ok_or_else(|| format!("Invalid paddle keys '{s}', expected two keys like 4,6"))
+    }
+}
+
+impl TryFrom<String> for Paddle {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Paddle> for String {
+    fn from(paddle: Paddle) -> Self {
+        paddle.to_string()
+    }
+}
+
+impl fmt::Display for Paddle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X},{:X}", self.left, self.right)
+    }
+}
+
+/// Auto-fire: while the `modifier` key is held, the CHIP-8 `key` is
+/// pressed and released `rate` times a second, for games that need rapid
+/// presses.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Turbo {
+    /// The CHIP-8 key pressed over and over.
+    #[cfg_attr(feature = "serde", serde(deserialize_with = "hex_key"))]
+    pub key: u8,
+    /// The keyboard key held for turbo.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub modifier: KeyName,
+    /// How many times a second the key is pressed.
+    #[cfg_attr(feature = "serde", serde(default = "Turbo::default_rate"))]
+    pub rate: u32,
+}
+
+impl Turbo {
+    /// The default number of presses a second.
+    pub const RATE: u32 = 10;
+
+    /// Turbo for `key`, held with `Space`, at the default rate.
+    #[must_use]
+    pub fn new(key: u8) -> Self {
+        Self {
+            key,
+            modifier: KeyName::default(),
+            rate: Self::RATE,
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    const fn default_rate() -> u32 {
+        Self::RATE
+    }
+
+    /// Whether the key is down `frame` frames after turbo started, at
+    /// `frames_per_second`: each press lasts half of its period, which is
+    /// at least two frames.
+    #[must_use]
+    pub fn pressed(&self, frame: u64, frames_per_second: u32) -> bool {
+        let period = u64::from((frames_per_second / self.rate.max(1)).max(2));
+        frame % period < period / 2
+    }
+}
+
+/// Parses a CHIP-8 key written as a number, like `0xC`, rejecting any past
+/// `0xF`.
+#[cfg(feature = "serde")]
+fn hex_key<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u8, D::Error> {
+    let key = u8::deserialize(deserializer)?;
+    (key < 0x10)
+        .then_some(key)
+        .ok_or_else(|| de::Error::custom(format!("invalid key {key}, expected 0x0 to 0xF")))
+}
+
+/// A keyboard key, by name: a letter, a digit, `Space`, `Shift`, `Ctrl`,
+/// `Alt`, or an arrow key (`Up`, `Down`, `Left`, or `Right`).
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "String", into = "String")
+)]
+pub struct KeyName(String);
+
+impl KeyName {
+    /// The keys named by a word.
+    const WORDS: [&'static str; 8] = [
+        "Space", "Shift", "Ctrl", "Alt", "Up", "Down", "Left", "Right",
+    ];
+    /// The codes of the keys named by [`WORDS`](Self::WORDS), in the same
+    /// order.
+    #[cfg(feature = "gui")]
+    const CODES: [VirtualKeyCode; 8] = [
+        VirtualKeyCode::Space,
+        VirtualKeyCode::LShift,
+        VirtualKeyCode::LControl,
+        VirtualKeyCode::LAlt,
+        VirtualKeyCode::Up,
+        VirtualKeyCode::Down,
+        VirtualKeyCode::Left,
+        VirtualKeyCode::Right,
+    ];
+
+    /// The key's code.
+    #[cfg(feature = "gui")]
+    #[must_use]
+    pub(crate) fn code(&self) -> VirtualKeyCode {
+        let mut chars = self.0.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => key_code(c),
+            _ => Self::word(&self.0).map(|word| Self::CODES[word]),
+        };
+        code.expect("key names are checked when parsed")
+    }
+
+    /// Whether `name` names a key.
+    fn valid(name: &str) -> bool {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => c.is_ascii_alphanumeric(),
+            _ => Self::word(name).is_some(),
+        }
+    }
+
+    /// The position of `name` in [`WORDS`](Self::WORDS), if it is one.
+    fn word(name: &str) -> Option<usize> {
+        Self::WORDS
+            .iter()
+            .position(|word| word.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Default for KeyName {
+    fn default() -> Self {
+        Self(Self::WORDS[0].to_string())
+    }
+}
+
+impl FromStr for KeyName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if Self::valid(s) {
+            Ok(Self(s.to_string()))
+        } else {
+            Err(format!(
+                "Invalid key '{s}', expected a letter, digit, Space, Shift, Ctrl, Alt, or arrow"
+            ))
+        }
+    }
+}
+
+impl TryFrom<String> for KeyName {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<KeyName> for String {
+    fn from(name: KeyName) -> Self {
+        name.0
+    }
+}
+
+/// Keyboard keys for CHIP-8 keys on top of a [`Keymap`], by name, like
+/// `{ Up = 0x5, Left = 0x7 }`. Any [`KeyName`] can be bound, so arrows
+/// can stand for keys too.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "BTreeMap<KeyName, u8>", into = "BTreeMap<KeyName, u8>")
+)]
+pub struct Bindings(BTreeMap<KeyName, u8>);
+
+impl Bindings {
+    /// Whether no keys are bound.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// A mapping of key codes to the CHIP-8 key each represents.
+    #[cfg(feature = "gui")]
+    #[must_use]
+    pub fn codes(&self) -> HashMap<VirtualKeyCode, u8> {
+        self.0
+            .iter()
+            .map(|(name, &key)| (name.code(), key))
+            .collect()
+    }
+}
+
+impl TryFrom<BTreeMap<KeyName, u8>> for Bindings {
+    type Error = String;
+
+    fn try_from(keys: BTreeMap<KeyName, u8>) -> Result<Self, Self::Error> {
+        match keys.iter().find(|(_, &key)| key >= 0x10) {
+            Some((name, key)) => Err(format!(
+                "Invalid key {key} for '{}', expected 0x0 to 0xF",
+                name.0
+            )),
+            None => Ok(Self(keys)),
+        }
+    }
+}
+
+/// Formats the keys like `Left = 0x5, Right = 0x6`.
+impl fmt::Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (name, key)) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{} = {key:#X}", name.0)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<Bindings> for BTreeMap<KeyName, u8> {
+    fn from(bindings: Bindings) -> Self {
+        bindings.0
+    }
+}
+
+/// A mapping of the numeric keypad's key codes to keys of the CHIP-8X
+/// second keypad: its digits to `0x0`..`0x9`, then `/`, `*`, `-`, `+`,
+/// `Enter`, and `.` to `0xA`..`0xF`.
+#[cfg(feature = "gui")]
+#[must_use]
+pub fn second_keypad() -> HashMap<VirtualKeyCode, u8> {
+    use VirtualKeyCode as K;
+    [
+        K::Numpad0,
+        K::Numpad1,
+        K::Numpad2,
+        K::Numpad3,
+        K::Numpad4,
+        K::Numpad5,
+        K::Numpad6,
+        K::Numpad7,
+        K::Numpad8,
+        K::Numpad9,
+        K::NumpadDivide,
+        K::NumpadMultiply,
+        K::NumpadSubtract,
+        K::NumpadAdd,
+        K::NumpadEnter,
+        K::NumpadDecimal,
+    ]
+    .into_iter()
+    .zip(KEYPAD_2..)
+    .collect()
+}
+
+/// The key code of the letter or digit `key`.
+#[cfg(feature = "gui")]
+fn key_code(key: char) -> Option<VirtualKeyCode> {
+    use VirtualKeyCode as K;
+    const DIGITS: [VirtualKeyCode; 10] = [
+        K::Key0,
+        K::Key1,
+        K::Key2,
+        K::Key3,
+        K::Key4,
+        K::Key5,
+        K::Key6,
+        K::Key7,
+        K::Key8,
+        K::Key9,
+    ];
+    const LETTERS: [VirtualKeyCode; 26] = [
+        K::A,
+        K::B,
+        K::C,
+        K::D,
+        K::E,
+        K::F,
+        K::G,
+        K::H,
+        K::I,
+        K::J,
+        K::K,
+        K::L,
+        K::M,
+        K::N,
+        K::O,
+        K::P,
+        K::Q,
+        K::R,
+        K::S,
+        K::T,
+        K::U,
+        K::V,
+        K::W,
+        K::X,
+        K::Y,
+        K::Z,
+    ];
+    match key.to_ascii_uppercase() {
+        key @ '0'..='9' => Some(DIGITS[key as usize - '0' as usize]),
+        key @ 'A'..='Z' => Some(LETTERS[key as usize - 'A' as usize]),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keymaps() {
+        #[cfg(feature = "gui")]
+        {
+            let codes = Keymap::default().codes();
+            assert_eq!(codes.len(), 16);
+            assert_eq!(codes[&VirtualKeyCode::Key4], 0xC);
+            assert_eq!(codes[&VirtualKeyCode::X], 0x0);
+        }
+
+        let rows = ["1234", "azer", "qsdf", "wxcv"].map(String::from).to_vec();
+        let keymap = Keymap::try_from(rows).unwrap();
+        #[cfg(feature = "gui")]
+        assert_eq!(keymap.codes()[&VirtualKeyCode::A], 0x4);
+        assert_eq!(Vec::from(keymap)[1], "AZER");
+
+        let bad = |rows: [&str; 4]| Keymap::try_from(rows.map(String::from).to_vec()).is_err();
+        assert!(bad(["1234", "QWER", "ASDF", "ZXC"]));
+        assert!(bad(["1234", "QWER", "ASDF", "ZXC;"]));
+        assert!(bad(["1234", "QWER", "ASDF", "ZXCQ"]));
+    }
+
+    #[cfg(feature = "gui")]
+    #[test]
+    fn turbo() {
+        let turbo: Turbo = toml::from_str("key = 0xC\nmodifier = \"shift\"").unwrap();
+        assert_eq!(turbo.key, 0xC);
+        assert_eq!(turbo.modifier.code(), VirtualKeyCode::LShift);
+        assert_eq!(turbo.rate, Turbo::RATE);
+        assert!(toml::from_str::<Turbo>("key = 0x10").is_err());
+        assert!(toml::from_str::<Turbo>("key = 1\nmodifier = \"F1\"").is_err());
+
+        let presses: Vec<bool> = (0..8).map(|frame| turbo.pressed(frame, 60)).collect();
+        assert_eq!(presses, [true, true, true, false, false, false, true, true]);
+        let fastest = Turbo { rate: 60, ..turbo };
+        assert!(fastest.pressed(0, 60) && !fastest.pressed(1, 60));
+    }
+
+    #[test]
+    fn paddles() {
+        let paddle: Paddle = "4, 6".parse().unwrap();
+        assert_eq!((paddle.left, paddle.right), (0x4, 0x6));
+        assert_eq!(paddle.to_string(), "4,6");
+        assert!("4".parse::<Paddle>().is_err());
+        assert!("4,10".parse::<Paddle>().is_err());
+
+        assert_eq!(paddle.key(10.0, 640.0), Some(0x4));
+        assert_eq!(paddle.key(280.0, 640.0), Some(0x4));
+        assert_eq!(paddle.key(320.0, 640.0), None);
+        assert_eq!(paddle.key(345.0, 640.0), None);
+        assert_eq!(paddle.key(640.0, 640.0), Some(0x6));
+    }
 }
@@ -0,0 +1,116 @@
+use crate::{
+    input::{Keymap, LAYOUT},
+    text,
+};
+use alloc::format;
+
+/// The width of a pad, in pixels of the display's buffer: room for a label
+/// like `C:4` and a pixel of padding.
+const PAD_WIDTH: usize = 3 * text::ADVANCE + 1;
+/// The height of a pad, in pixels of the display's buffer.
+const PAD_HEIGHT: usize = text::LINE_HEIGHT + 1;
+/// The space between pads.
+const GAP: usize = 1;
+
+/// The on-screen keypad toggled with `F4`: the CHIP-8 keypad in the top
+/// right corner of the screen, each pad labeled with its key and the
+/// keyboard key for it, like `C:4`, and lit while the key is held. Pads can
+/// be clicked or touched to press them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Overlay {
+    labels: [[char; 4]; 4], // The keyboard key for each pad, in the positions of `LAYOUT`
+}
+
+impl Overlay {
+    /// An on-screen keypad labeled with the keys of `keymap`.
+    #[must_use]
+    pub fn new(keymap: &Keymap) -> Self {
+        Self {
+            labels: keymap.keys(),
+        }
+    }
+
+    /// Draws the keypad into `frame`, an RGBA buffer `width` pixels wide, in
+    /// `foreground` on `background`, with the colors swapped on the pads
+    /// whose keys are held in `keypad`.
+    pub fn draw(
+        &self,
+        frame: &mut [u8],
+        width: usize,
+        keypad: &[bool],
+        foreground: [u8; 4],
+        background: [u8; 4],
+    ) {
+        let height = frame.len() / 4 / width;
+        for (row, (labels, layout)) in self.labels.iter().zip(LAYOUT).enumerate() {
+            for (column, (label, (_, key))) in labels.iter().zip(layout).enumerate() {
+                let held = keypad.get(usize::from(key)).copied().unwrap_or_default();
+                let (ink, fill) = if held {
+                    (background, foreground)
+                } else {
+                    (foreground, background)
+                };
+                let (left, top) = origin(width, row, column);
+                for y in top..(top + PAD_HEIGHT).min(height) {
+                    for x in left..(left + PAD_WIDTH).min(width) {
+                        let pixel = (y * width + x) * 4;
+                        frame[pixel..pixel + 4].copy_from_slice(&fill);
+                    }
+                }
+                let line = format!("{key:X}:{label}");
+                text::draw_at(frame, width, (left + 1, top + 1), &[line], ink, fill);
+            }
+        }
+    }
+
+    /// The key of the pad at `(x, y)` in a buffer `width` pixels wide, if
+    /// there is one there.
+    #[must_use]
+    pub fn key_at(width: usize, x: usize, y: usize) -> Option<u8> {
+        let (left, _) = origin(width, 0, 0);
+        let (column, dx) = (
+            x.checked_sub(left)? / (PAD_WIDTH + GAP),
+            x.checked_sub(left)? % (PAD_WIDTH + GAP),
+        );
+        let (row, dy) = (y / (PAD_HEIGHT + GAP), y % (PAD_HEIGHT + GAP));
+        let (_, key) = *LAYOUT.get(row)?.get(column)?;
+        (dx < PAD_WIDTH && dy < PAD_HEIGHT).then_some(key)
+    }
+}
+
+/// The top left corner of the pad in `row` and `column`, in a buffer
+/// `width` pixels wide.
+fn origin(width: usize, row: usize, column: usize) -> (usize, usize) {
+    let left = width.saturating_sub(4 * PAD_WIDTH + 3 * GAP);
+    (left + column * (PAD_WIDTH + GAP), row * (PAD_HEIGHT + GAP))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypad() {
+        const ON: [u8; 4] = [1; 4];
+        const OFF: [u8; 4] = [2; 4];
+        let (width, height) = (64, 32);
+        let mut frame = vec![0; width * height * 4];
+        let mut keypad = [false; 16];
+        keypad[0xC] = true;
+        Overlay::new(&Keymap::default()).draw(&mut frame, width, &keypad, ON, OFF);
+        let pixel = |x: usize, y: usize| frame[(y * width + x) * 4];
+
+        // The keypad is 55 pixels wide, from x = 9, and 31 high.
+        assert_eq!((pixel(8, 0), pixel(9, 0), pixel(9, 31)), (0, 2, 0));
+        // `C:4` is the top right pad, which is held.
+        assert_eq!((pixel(51, 0), pixel(63, 6)), (1, 1));
+        assert_eq!((pixel(53, 1), pixel(51, 7)), (2, 0));
+
+        assert_eq!(Overlay::key_at(width, 10, 1), Some(0x1));
+        assert_eq!(Overlay::key_at(width, 63, 0), Some(0xC));
+        assert_eq!(Overlay::key_at(width, 30, 25), Some(0x0));
+        assert_eq!(Overlay::key_at(width, 22, 1), None); // Between pads
+        assert_eq!(Overlay::key_at(width, 8, 1), None);
+        assert_eq!(Overlay::key_at(width, 10, 31), None);
+    }
+}
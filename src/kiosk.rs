@@ -0,0 +1,170 @@
+use crate::library::Library;
+use log::error;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// A playlist of ROMs shown one after another, for demo installations: each
+/// runs for a set time, then the next takes over, unless someone is playing.
+#[derive(Clone, Debug)]
+pub struct Kiosk {
+    roms: Vec<PathBuf>,
+    duration: Duration,
+    idle: Duration,
+    current: Option<usize>, // the index of the ROM running, once one is
+    started: Instant,       // when it started
+    input: Option<Instant>, // when someone last pressed a key or clicked, if they have
+}
+
+impl Kiosk {
+    /// A kiosk cycling through `roms`, giving each `duration`, and switching
+    /// only once nobody has played for `idle`. The first ROM is the first
+    /// one [`advance`](Self::advance) returns.
+    #[must_use]
+    pub fn new(roms: Vec<PathBuf>, duration: Duration, idle: Duration) -> Self {
+        Self {
+            roms,
+            duration,
+            idle,
+            current: None,
+            started: Instant::now(),
+            input: None,
+        }
+    }
+
+    /// The ROMs in the playlist, in the order they're shown.
+    #[must_use]
+    pub fn roms(&self) -> &[PathBuf] {
+        &self.roms
+    }
+
+    /// Notes that someone pressed a key or clicked at `now`, which holds the
+    /// ROM running until they've been idle for long enough.
+    pub fn input(&mut self, now: Instant) {
+        self.input = Some(now);
+    }
+
+    /// Whether it's time to switch to the next ROM at `now`: the one running
+    /// has had its time, and nobody is playing it.
+    #[must_use]
+    pub fn due(&self, now: Instant) -> bool {
+        let idle = self
+            .input
+            .is_none_or(|input| now.saturating_duration_since(input) >= self.idle);
+        self.roms.len() > 1 && now.saturating_duration_since(self.started) >= self.duration && idle
+    }
+
+    /// Moves on to the next ROM that can be read, starting `now` and
+    /// wrapping around, and returns its path and contents. Unreadable ROMs
+    /// are logged and skipped; if none can be read, this returns `None` and
+    /// the ROM running keeps its place.
+    pub fn advance(&mut self, now: Instant) -> Option<(PathBuf, Vec<u8>)> {
+        self.started = now;
+        let first = self.current.map_or(0, |current| current + 1);
+        for offset in 0..self.roms.len() {
+            let n = (first + offset) % self.roms.len();
+            let path = &self.roms[n];
+            let rom =
+                fs::read(path).map_err(|e| format!("Could not read '{}': {e}", path.display()));
+            match rom.and_then(|file| crate::octo::load(path, file)) {
+                Ok(rom) => {
+                    self.current = Some(n);
+                    return Some((path.clone(), rom));
+                }
+                Err(e) => error!("{}", e),
+            }
+        }
+        None
+    }
+}
+
+/// The ROMs named by `paths`, in order: a ROM stands for itself, a
+/// directory for the ROMs in it (as the library finds them), and a `.txt` or
+/// `.m3u` playlist for the paths listed in it, one per line and relative to
+/// the playlist. Blank lines and lines starting with `#` are skipped.
+///
+/// # Errors
+/// This function will error if a directory or playlist cannot be read.
+pub fn playlist(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut roms = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            roms.extend_from_slice(Library::scan(path)?.roms());
+        } else if path.extension().is_some_and(|ext| {
+            ["txt", "m3u"]
+                .iter()
+                .any(|list| ext.eq_ignore_ascii_case(list))
+        }) {
+            let dir = path.parent().unwrap_or(Path::new(""));
+            roms.extend(
+                fs::read_to_string(path)?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(|line| dir.join(line)),
+            );
+        } else {
+            roms.push(path.clone());
+        }
+    }
+    Ok(roms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles() {
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let roms = ["roms/maze.ch8", "roms/missing.ch8", "roms/ibm-logo.ch8"].map(PathBuf::from);
+        let mut kiosk = Kiosk::new(
+            roms.to_vec(),
+            Duration::from_secs(90),
+            Duration::from_secs(20),
+        );
+        assert_eq!(kiosk.advance(start).unwrap().0, roms[0]);
+        assert!(!kiosk.due(at(89)));
+        assert!(kiosk.due(at(90)));
+        // Someone is playing, so the ROM stays until they stop.
+        kiosk.input(at(80));
+        assert!(!kiosk.due(at(99)));
+        assert!(kiosk.due(at(100)));
+
+        // The missing ROM is skipped.
+        let (path, rom) = kiosk.advance(at(100)).unwrap();
+        assert_eq!(path, roms[2]);
+        assert_eq!(rom, fs::read(&roms[2]).unwrap());
+        assert!(!kiosk.due(at(189)));
+        assert!(kiosk.due(at(190)));
+        assert_eq!(kiosk.advance(at(190)).unwrap().0, roms[0]);
+
+        let mut alone = Kiosk::new(vec![roms[1].clone()], Duration::ZERO, Duration::ZERO);
+        assert!(!alone.due(at(100)));
+        assert_eq!(alone.advance(start), None);
+    }
+
+    #[test]
+    fn playlists() {
+        let dir = std::env::temp_dir().join(format!("etherea-kiosk-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let list = dir.join("demo.m3u");
+        fs::write(&list, "# Demo\nmaze.ch8\n\n  games/pong.ch8\n").unwrap();
+        let roms = playlist(&[list, PathBuf::from("a.ch8")]).unwrap();
+        assert_eq!(
+            roms,
+            [
+                dir.join("maze.ch8"),
+                dir.join("games/pong.ch8"),
+                PathBuf::from("a.ch8")
+            ]
+        );
+        fs::remove_dir_all(&dir).unwrap();
+
+        let roms = playlist(&[PathBuf::from("roms")]).unwrap();
+        assert!(roms.contains(&PathBuf::from("roms/ibm-logo.ch8")));
+    }
+}
@@ -0,0 +1,185 @@
+use log::error;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+/// The ROMs in a directory, chosen from a menu in the terminal. The menu is
+/// shown before the first ROM starts, and again whenever `Escape` is pressed
+/// in the window.
+#[derive(Clone, Debug)]
+pub struct Library {
+    dir: PathBuf,
+    roms: Vec<PathBuf>,
+    open: Arc<AtomicBool>, // whether the menu is being shown
+}
+
+impl Library {
+    /// Finds every `.ch8` file and Octo source file (`.8o` or `.o8`) in
+    /// `dir` and its subdirectories, sorted by path. Octo's `.gif` cartridges
+    /// aren't included, since they can't be told apart from other images.
+    ///
+    /// # Errors
+    /// This function will error if `dir` or one of its subdirectories
+    /// cannot be read.
+    pub fn scan(dir: &Path) -> io::Result<Self> {
+        let mut roms = Vec::new();
+        let mut dirs = vec![dir.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir)? {
+                let path = entry?.path();
+                if path.is_dir() {
+                    dirs.push(path);
+                } else if path.extension().is_some_and(|ext| {
+                    ["ch8", "8o", "o8"]
+                        .iter()
+                        .any(|rom| ext.eq_ignore_ascii_case(rom))
+                }) {
+                    roms.push(path);
+                }
+            }
+        }
+        roms.sort();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            roms,
+            open: Arc::default(),
+        })
+    }
+
+    /// The paths of the ROMs found.
+    #[must_use]
+    pub fn roms(&self) -> &[PathBuf] {
+        &self.roms
+    }
+
+    /// Lists the ROMs in `output` and asks which to play, by number or by
+    /// (part of) its name. Returns `None` if the answer is empty or `input`
+    /// ends.
+    ///
+    /// # Errors
+    /// This function will error if `input` cannot be read or `output` cannot
+    /// be written.
+    pub fn choose(
+        &self,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+    ) -> io::Result<Option<&Path>> {
+        writeln!(output, "ROMs in {}:", self.dir.display())?;
+        for (n, rom) in self.roms.iter().enumerate() {
+            writeln!(output, "  {:>3}. {}", n + 1, self.name(rom))?;
+        }
+        loop {
+            write!(output, "Play which ROM? (Enter to quit): ")?;
+            output.flush()?;
+            let mut answer = String::new();
+            input.read_line(&mut answer)?;
+            let answer = answer.trim();
+            if answer.is_empty() {
+                return Ok(None);
+            }
+            let rom = answer
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.roms.get(n.wrapping_sub(1)))
+                .or_else(|| {
+                    let answer = answer.to_lowercase();
+                    let mut matches = self
+                        .roms
+                        .iter()
+                        .filter(|rom| self.name(rom).to_lowercase().contains(&answer));
+                    matches.next().filter(|_| matches.next().is_none())
+                });
+            match rom {
+                Some(rom) => return Ok(Some(rom)),
+                None => writeln!(output, "Please enter one of the numbers above.")?,
+            }
+        }
+    }
+
+    /// Shows the menu on the terminal and reads the chosen ROM, returning
+    /// its path along with it. Returns `None` if nothing was chosen, or if
+    /// the menu is already being shown.
+    #[must_use]
+    pub fn pick(&self) -> Option<(PathBuf, Vec<u8>)> {
+        if self.open.swap(true, Ordering::SeqCst) {
+            return None;
+        }
+        let rom = loop {
+            let chosen = self.choose(&mut io::stdin().lock(), &mut io::stdout());
+            let path = match chosen {
+                Ok(Some(path)) => path,
+                Ok(None) => break None,
+                Err(e) => {
+                    error!("Could not show the ROM library: {}", e);
+                    break None;
+                }
+            };
+            let rom =
+                fs::read(path).map_err(|e| format!("Could not read '{}': {e}", path.display()));
+            match rom.and_then(|file| crate::octo::load(path, file)) {
+                Ok(rom) => break Some((path.to_path_buf(), rom)),
+                Err(e) => println!("{e}"),
+            }
+        };
+        self.open.store(false, Ordering::SeqCst);
+        rom
+    }
+
+    /// The path of `rom`, relative to the library's directory.
+    fn name(&self, rom: &Path) -> String {
+        rom.strip_prefix(&self.dir)
+            .unwrap_or(rom)
+            .display()
+            .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn chooses_roms() {
+        let library = Library {
+            dir: PathBuf::from("roms"),
+            roms: ["roms/pong.ch8", "roms/pong2.ch8", "roms/games/tetris.ch8"]
+                .map(PathBuf::from)
+                .to_vec(),
+            open: Arc::default(),
+        };
+        let choose = |answers: &str| {
+            let mut output = Vec::new();
+            let rom = library
+                .choose(&mut Cursor::new(answers), &mut output)
+                .unwrap()
+                .map(Path::to_path_buf);
+            (rom, String::from_utf8(output).unwrap())
+        };
+
+        let (rom, output) = choose("2\n");
+        assert_eq!(rom, Some(PathBuf::from("roms/pong2.ch8")));
+        assert!(output.contains("    3. games/tetris.ch8\n"));
+        // "pong" matches two ROMs, so it's asked again.
+        let (rom, output) = choose("pong\n7\nTETRIS\n");
+        assert_eq!(rom, Some(PathBuf::from("roms/games/tetris.ch8")));
+        assert_eq!(output.matches("Please enter").count(), 2);
+        assert_eq!(choose("").0, None);
+    }
+
+    #[test]
+    fn scans_for_roms() {
+        let library = Library::scan(Path::new("roms")).unwrap();
+        assert!(library.roms().contains(&PathBuf::from("roms/ibm-logo.ch8")));
+        assert!(library
+            .roms()
+            .iter()
+            .all(|rom| rom.extension().unwrap() == "ch8"));
+    }
+}
@@ -4,11 +4,175 @@ use log::error;
 fn main() {
     let cli = cli::init();
     match cli.command {
-        cli::Commands::Run { path, ips } => cli::run(&path, ips),
-        cli::Commands::Disassemble { path, output_file } => cli::disassemble(&path, output_file)
-            .unwrap_or_else(|e| {
+        cli::Commands::Run(args) => cli::run(*args),
+        cli::Commands::Replay {
+            path,
+            replay,
+            record,
+            trace,
+        } => cli::replay(&path, &replay, record, trace.as_deref()),
+        cli::Commands::Assemble {
+            input,
+            output,
+            symbols,
+        } => cli::assemble(&input, output, symbols).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Analyze {
+            path,
+            map,
+            instructions,
+            ips,
+            replay,
+        } => cli::analyze(
+            &path,
+            map.as_ref().map(Option::as_deref),
+            instructions,
+            ips,
+            replay.as_deref(),
+        )
+        .unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Info { path } => cli::info(&path).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Hash { path } => cli::hash(&path).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Verify { path } => match cli::verify(&path) {
+            Ok(good) => std::process::exit(i32::from(!good)),
+            Err(e) => {
                 error!("{}", e);
                 std::process::exit(1);
-            }),
+            }
+        },
+        cli::Commands::DiffTrace { first, second } => match cli::diff_trace(&first, &second) {
+            Ok(same) => std::process::exit(i32::from(!same)),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        cli::Commands::Batch {
+            dir,
+            cycles,
+            ips,
+            out,
+        } => cli::batch(&dir, cycles, ips, out.as_deref()).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Bench {
+            path,
+            instructions,
+            ips,
+        } => cli::bench(&path, instructions, ips).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Profile {
+            path,
+            instructions,
+            ips,
+            replay,
+            heatmap,
+        } => cli::profile(
+            &path,
+            instructions,
+            ips,
+            replay.as_deref(),
+            heatmap.as_deref(),
+        )
+        .unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Compare {
+            path,
+            instructions,
+            ips,
+            replay,
+        } => match cli::compare(&path, instructions, ips, replay.as_deref()) {
+            Ok(agreed) => std::process::exit(i32::from(!agreed)),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        cli::Commands::Library { dir } => cli::library(dir).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Kiosk {
+            playlist,
+            duration,
+            idle,
+        } => cli::kiosk(&playlist, duration, idle).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Setup => {
+            cli::setup().unwrap_or_else(|e| {
+                error!("{}", e);
+                std::process::exit(1);
+            });
+        }
+        cli::Commands::Stats { command } => match command {
+            cli::StatsCommand::History => cli::stats_history(),
+            cli::StatsCommand::Wipe => cli::stats_wipe(),
+        }
+        .unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Update { check } => cli::update(check).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Test { filter, screens } => {
+            std::process::exit(cli::test(filter.as_deref(), screens))
+        }
+        cli::Commands::Conformance {
+            dir,
+            download,
+            bless,
+            screens,
+        } => std::process::exit(cli::conformance(&dir, download, bless, screens)),
+        cli::Commands::Explain { query } => cli::explain(&query).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Dap => cli::dap().unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Disassemble {
+            path,
+            output_file,
+            format,
+        } => cli::disassemble(&path, output_file, format).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
+        cli::Commands::Decompile { path, output } => {
+            cli::decompile(&path, output).unwrap_or_else(|e| {
+                error!("{}", e);
+                std::process::exit(1);
+            });
+        }
+        cli::Commands::Patch {
+            path,
+            edits,
+            apply,
+            output,
+        } => cli::patch(&path, &edits, &apply, &output).unwrap_or_else(|e| {
+            error!("{}", e);
+            std::process::exit(1);
+        }),
     }
 }
@@ -0,0 +1,233 @@
+use crate::{font, profile::Profile, Interpreter};
+use std::{fmt, io};
+
+/// The number of addresses on each row of a map.
+const COLUMNS: usize = 64;
+/// The size, in pixels, of each address in a map drawn as an image.
+const CELL: usize = 8;
+
+/// What an address in memory was used for during a profiling run. Where an
+/// address was used for more than one thing, the latest in this list wins.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Region {
+    /// Nothing was loaded there, and it was never touched.
+    Untouched,
+    /// The built-in font, which was never drawn from.
+    Font,
+    /// Part of the ROM that was never executed, read, or written.
+    Rom,
+    /// Read as data, by a sprite or `FX65`.
+    Read,
+    /// Written, by `FX33` or `FX55`.
+    Written,
+    /// Executed as code.
+    Code,
+}
+
+impl Region {
+    /// Every region, in the order of the legend.
+    pub const ALL: [Self; 6] = [
+        Self::Code,
+        Self::Read,
+        Self::Written,
+        Self::Rom,
+        Self::Font,
+        Self::Untouched,
+    ];
+
+    /// The character an address in the region is shown as in text.
+    #[must_use]
+    pub const fn symbol(self) -> char {
+        match self {
+            Self::Untouched => '.',
+            Self::Font => 'f',
+            Self::Rom => 'o',
+            Self::Read => 'r',
+            Self::Written => 'w',
+            Self::Code => 'X',
+        }
+    }
+
+    /// The color an address in the region is drawn in, as RGB.
+    #[must_use]
+    pub const fn color(self) -> [u8; 3] {
+        match self {
+            Self::Untouched => [0x20, 0x20, 0x20],
+            Self::Font => [0x80, 0x40, 0xC0],
+            Self::Rom => [0x50, 0x50, 0x60],
+            Self::Read => [0x30, 0xA0, 0xE0],
+            Self::Written => [0xE0, 0xA0, 0x30],
+            Self::Code => [0xD0, 0x30, 0x30],
+        }
+    }
+
+    /// What the region is, for the legend.
+    #[must_use]
+    pub const fn describe(self) -> &'static str {
+        match self {
+            Self::Untouched => "untouched",
+            Self::Font => "font",
+            Self::Rom => "ROM, unused",
+            Self::Read => "data read",
+            Self::Written => "data written",
+            Self::Code => "executed code",
+        }
+    }
+}
+
+/// What every address in memory was used for during a profiling run, made
+/// with [`profile::Report::map`](crate::profile::Report::map).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryMap {
+    regions: Vec<Region>,
+}
+
+impl MemoryMap {
+    /// The map of a run that loaded a ROM of `rom_size` bytes and used
+    /// memory as recorded in `profile`.
+    #[must_use]
+    pub fn new(profile: &Profile, rom_size: usize) -> Self {
+        let rom = Interpreter::MEMORY_OFFSET..Interpreter::MEMORY_OFFSET + rom_size;
+        let regions = (0..Interpreter::MEMORY_SIZE)
+            .map(|address| {
+                let word = u16::try_from(address).unwrap_or(u16::MAX);
+                // Instructions are two bytes long.
+                if profile.executions(word) > 0 || address > 0 && profile.executions(word - 1) > 0 {
+                    Region::Code
+                } else if profile.written(word) {
+                    Region::Written
+                } else if profile.read(word) {
+                    Region::Read
+                } else if rom.contains(&address) {
+                    Region::Rom
+                } else if font::MEMORY_RANGE.contains(&address) {
+                    Region::Font
+                } else {
+                    Region::Untouched
+                }
+            })
+            .collect();
+        Self { regions }
+    }
+
+    /// What the address `address` was used for. Addresses past the end of
+    /// memory were never touched.
+    #[must_use]
+    pub fn region(&self, address: usize) -> Region {
+        self.regions
+            .get(address)
+            .copied()
+            .unwrap_or(Region::Untouched)
+    }
+
+    /// Writes the map to `out` as rows of colored blocks, for a terminal
+    /// that understands 24-bit ANSI colors, followed by the legend.
+    ///
+    /// # Errors
+    /// This function will error if `out` cannot be written to.
+    pub fn write_blocks(&self, mut out: impl io::Write) -> io::Result<()> {
+        let block = |region: Region| {
+            let [r, g, b] = region.color();
+            format!("\x1b[38;2;{r};{g};{b}m█\x1b[0m")
+        };
+        for (row, regions) in self.regions.chunks(COLUMNS).enumerate() {
+            let line: String = regions.iter().map(|&region| block(region)).collect();
+            writeln!(out, "{:03X} {line}", row * COLUMNS)?;
+        }
+        for region in Region::ALL {
+            writeln!(out, "  {} {}", block(region), region.describe())?;
+        }
+        Ok(())
+    }
+
+    /// Renders the map as a PNG written to `out`, with a square for each
+    /// address, [`COLUMNS`] to a row.
+    ///
+    /// # Errors
+    /// This function will error if `out` cannot be written to.
+    pub fn png(&self, out: impl io::Write) -> io::Result<()> {
+        let width = COLUMNS * CELL;
+        let mut pixels = Vec::with_capacity(self.regions.len() * CELL * CELL * 4);
+        for regions in self.regions.chunks(COLUMNS) {
+            let row: Vec<u8> = regions
+                .iter()
+                .flat_map(|region| {
+                    let [r, g, b] = region.color();
+                    [r, g, b, 0xFF].repeat(CELL)
+                })
+                .collect();
+            for _ in 0..CELL {
+                pixels.extend_from_slice(&row);
+            }
+        }
+        let height = pixels.len() / 4 / width;
+
+        let mut encoder = png::Encoder::new(
+            out,
+            u32::try_from(width).map_err(io::Error::other)?,
+            u32::try_from(height).map_err(io::Error::other)?,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&pixels).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+impl fmt::Display for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (row, regions) in self.regions.chunks(COLUMNS).enumerate() {
+            let line: String = regions.iter().map(|region| region.symbol()).collect();
+            writeln!(f, "{:03X} {line}", row * COLUMNS)?;
+        }
+        let legend: Vec<String> = Region::ALL
+            .iter()
+            .map(|region| format!("{} {}", region.symbol(), region.describe()))
+            .collect();
+        write!(f, "{}", legend.join("  "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{asm::assemble, profile, quirks::Quirks, timing::Timing};
+
+    #[test]
+    fn maps() {
+        // Draw the sprite at `sprite` and the font's 0, save the registers
+        // past the end of the ROM, and loop.
+        let source = "  LD I, sprite\n  DRW V0, V0, 2\n  LD F, V0\n  DRW V0, V0, 5\n  \
+                      LD I, 0x400\n  LD [I], V1\nloop:\n  JP loop\nsprite:\n  \
+                      DB 0xFF, 0xFF, 0x00\n";
+        let rom = assemble(source).unwrap();
+        let report =
+            profile::run(&rom, 100, Timing::from_ips(600), Quirks::default(), None).unwrap();
+        let map = report.map();
+        assert_eq!(map.region(0x000), Region::Untouched);
+        assert_eq!(map.region(0x050), Region::Read);
+        assert_eq!(map.region(0x055), Region::Font);
+        assert_eq!(map.region(0x200), Region::Code);
+        assert_eq!(map.region(0x20D), Region::Code);
+        assert_eq!(map.region(0x20E), Region::Read);
+        assert_eq!(map.region(0x20F), Region::Read);
+        assert_eq!(map.region(0x210), Region::Rom);
+        assert_eq!(map.region(0x400), Region::Written);
+        assert_eq!(map.region(0x401), Region::Written);
+        assert_eq!(map.region(0x402), Region::Untouched);
+        assert_eq!(map.region(0x1000), Region::Untouched);
+
+        let text = map.to_string();
+        assert!(text.starts_with(&format!("000 {}\n", ".".repeat(COLUMNS))));
+        assert!(text.contains("\n200 XXXXXXXXXXXXXXrro."));
+        assert!(text.ends_with(
+            "X executed code  r data read  w data written  o ROM, unused  f font  . untouched"
+        ));
+
+        let mut png = Vec::new();
+        map.png(&mut png).unwrap();
+        let reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        assert_eq!((reader.info().width, reader.info().height), (512, 512));
+    }
+}
@@ -0,0 +1,285 @@
+use crate::{
+    debug,
+    input::{KeyEvent, KEYPAD_2},
+    quirks::Quirks,
+    timing::Timing,
+    vip,
+};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+/// The first line the host sends.
+const HEADER: &str = "# etherea netplay v2";
+
+/// How many frames after being pressed a key is applied, on both sides, so
+/// the other side's keys have time to arrive.
+pub const DELAY: u64 = 3;
+
+/// What the host decides for both sides, so they run in lockstep: the
+/// same ROM with the same seed, timing, and quirks, and the same
+/// instruction costs if the host is cycle-accurate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Terms {
+    pub seed: u64,
+    pub timing: Timing,
+    pub quirks: Quirks,
+    pub cycle_costs: Option<vip::Costs>,
+}
+
+/// A connection to the other player. Every frame, each side sends the keys
+/// pressed and released during it, and waits for the other side's keys for
+/// the same frame before running it. Both sides apply the same keys at the
+/// start of the same frame, so their interpreters never diverge.
+///
+/// Messages are lines of text: the frame number, then key events like
+/// those of a replay file:
+///
+/// ```text
+/// 1520 5 down A up
+/// ```
+#[derive(Debug)]
+pub struct Session {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    host: bool,                       // whether this side's keys are applied first
+    queued: Vec<KeyEvent>,            // this side's keys during the current frame
+    pending: VecDeque<Vec<KeyEvent>>, // this side's keys sent, but not yet applied
+}
+
+impl Session {
+    /// Waits for a player to connect to `addr`, then tells them `terms` and
+    /// the hash of `rom`.
+    ///
+    /// # Errors
+    /// This function will error if `addr` can't be listened on, or the
+    /// connection fails.
+    pub fn host(addr: impl ToSocketAddrs, rom: &[u8], terms: &Terms) -> io::Result<Self> {
+        let (stream, _) = TcpListener::bind(addr)?.accept()?;
+        let mut session = Self::new(stream, true)?;
+        let quirks = serde_json::to_string(&terms.quirks).map_err(io::Error::other)?;
+        let costs = serde_json::to_string(&terms.cycle_costs).map_err(io::Error::other)?;
+        let writer = &mut session.writer;
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "rom {:016x}", debug::hash(rom))?;
+        writeln!(writer, "seed {}", terms.seed)?;
+        writeln!(writer, "timing {}", terms.timing)?;
+        writeln!(writer, "quirks {quirks}")?;
+        writeln!(writer, "costs {costs}")?;
+        session.start()?;
+        Ok(session)
+    }
+
+    /// Connects to the host at `addr`, returning the session and the terms
+    /// the host decided.
+    ///
+    /// # Errors
+    /// This function will error if the connection fails, or the host is
+    /// running a different ROM.
+    pub fn join(addr: impl ToSocketAddrs, rom: &[u8]) -> io::Result<(Self, Terms)> {
+        let mut session = Self::new(TcpStream::connect(addr)?, false)?;
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        if session.line()? != HEADER {
+            return Err(invalid("Not an etherea netplay host".into()));
+        }
+        let mut field = |name: &str| {
+            let line = session.line()?;
+            line.strip_prefix(name)
+                .and_then(|rest| rest.strip_prefix(' '))
+                .map(str::to_string)
+                .ok_or_else(|| invalid(format!("Expected '{name}' from the host, got '{line}'")))
+        };
+        if field("rom")? != format!("{:016x}", debug::hash(rom)) {
+            return Err(invalid("The host is running a different ROM".into()));
+        }
+        let seed = field("seed")?;
+        let seed = seed
+            .parse()
+            .map_err(|_| invalid(format!("Invalid seed '{seed}'")))?;
+        let timing = field("timing")?.parse().map_err(invalid)?;
+        let quirks = serde_json::from_str(&field("quirks")?).map_err(io::Error::other)?;
+        let cycle_costs = serde_json::from_str(&field("costs")?).map_err(io::Error::other)?;
+        session.start()?;
+        Ok((
+            session,
+            Terms {
+                seed,
+                timing,
+                quirks,
+                cycle_costs,
+            },
+        ))
+    }
+
+    fn new(stream: TcpStream, host: bool) -> io::Result<Self> {
+        // Messages are tiny, and each one holds the other side up.
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            host,
+            queued: Vec::new(),
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Sends no keys for the frames before the first keys can arrive.
+    fn start(&mut self) -> io::Result<()> {
+        for frame in 0..DELAY {
+            self.pending.push_back(Vec::new());
+            writeln!(self.writer, "{frame}")?;
+        }
+        Ok(())
+    }
+
+    /// Reads a line from the other side, without its line ending.
+    fn line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(line.trim_end().to_string())
+    }
+
+    /// Queues a key event from this side, to be sent at the end of the
+    /// current frame.
+    pub(crate) fn queue(&mut self, event: KeyEvent) {
+        self.queued.push(event);
+    }
+
+    /// Sends the keys queued during the frame before `frame`, to be applied
+    /// [`DELAY`] frames later, then waits for the other side's keys for
+    /// `frame`. Returns the keys of both sides to apply at the start of
+    /// `frame`, the host's first.
+    ///
+    /// # Errors
+    /// This function will error if the other side disconnects, or sends
+    /// keys for another frame.
+    pub(crate) fn exchange(&mut self, frame: u64) -> io::Result<Vec<KeyEvent>> {
+        let queued = std::mem::take(&mut self.queued);
+        writeln!(self.writer, "{}", message(frame + DELAY, &queued))?;
+        self.pending.push_back(queued);
+
+        let line = self.line()?;
+        let remote = parse(&line)
+            .filter(|&(at, _)| at == frame)
+            .map(|(_, events)| events)
+            .ok_or_else(|| {
+                let message = format!("Expected keys for frame {frame}, got '{line}'");
+                io::Error::new(io::ErrorKind::InvalidData, message)
+            })?;
+        let local = self.pending.pop_front().unwrap_or_default();
+        Ok(if self.host {
+            [local, remote].concat()
+        } else {
+            [remote, local].concat()
+        })
+    }
+}
+
+/// Formats the keys for `frame` as a message.
+fn message(frame: u64, events: &[KeyEvent]) -> String {
+    let events = events.iter().map(|event| {
+        let state = if event.pressed { "down" } else { "up" };
+        format!("{:X} {state}", event.key)
+    });
+    std::iter::once(frame.to_string())
+        .chain(events)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses a message into its frame number and keys.
+fn parse(message: &str) -> Option<(u64, Vec<KeyEvent>)> {
+    let mut words = message.split_whitespace();
+    let frame = words.next()?.parse().ok()?;
+    let words: Vec<&str> = words.collect();
+    let events = words
+        .chunks(2)
+        .map(|event| match *event {
+            [key, state] => Some(KeyEvent {
+                key: u8::from_str_radix(key, 16)
+                    .ok()
+                    .filter(|&key| key < KEYPAD_2 + 0x10)?,
+                pressed: match state {
+                    "down" => true,
+                    "up" => false,
+                    _ => return None,
+                },
+            }),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    Some((frame, events))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn messages() {
+        let events = [
+            KeyEvent {
+                key: 0x5,
+                pressed: true,
+            },
+            KeyEvent {
+                key: 0xA,
+                pressed: false,
+            },
+        ];
+        assert_eq!(message(1520, &events), "1520 5 down A up");
+        assert_eq!(parse("1520 5 down A up"), Some((1520, events.to_vec())));
+        assert_eq!(parse("7"), Some((7, Vec::new())));
+        assert_eq!(parse("7 5"), None);
+        assert_eq!(parse("7 20 down"), None);
+    }
+
+    #[test]
+    fn lockstep() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        let terms = Terms {
+            seed: 42,
+            timing: Timing::from_ips(700),
+            quirks: Quirks {
+                display_wait: true,
+                ..Quirks::default()
+            },
+            cycle_costs: Some("DXYN=30".parse().unwrap()),
+        };
+        let rom = [0x00, 0xE0];
+        let host = {
+            let terms = terms.clone();
+            thread::spawn(move || Session::host(addr, &rom, &terms).unwrap())
+        };
+        let (mut guest, joined) = loop {
+            match Session::join(addr, &rom) {
+                Ok(joined) => break joined,
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => thread::yield_now(),
+                Err(e) => panic!("{e}"),
+            }
+        };
+        let mut host = host.join().unwrap();
+        assert_eq!(joined, terms);
+
+        let press = |key| KeyEvent { key, pressed: true };
+        host.queue(press(1));
+        guest.queue(press(2));
+        for frame in 0..DELAY {
+            assert_eq!(host.exchange(frame).unwrap(), []);
+            assert_eq!(guest.exchange(frame).unwrap(), []);
+        }
+        // Both sides apply the same keys, in the same order.
+        assert_eq!(host.exchange(DELAY).unwrap(), [press(1), press(2)]);
+        assert_eq!(guest.exchange(DELAY).unwrap(), [press(1), press(2)]);
+
+        drop(host);
+        assert!(guest.exchange(DELAY + 1).is_err());
+    }
+}
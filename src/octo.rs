@@ -0,0 +1,165 @@
+use crate::asm;
+use serde::Deserialize;
+use std::path::Path;
+
+/// What an Octo cartridge holds: the source of a program, and the settings
+/// it was written with.
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct Cartridge {
+    /// The program's source, in Octo's assembly syntax.
+    pub program: String,
+    /// Settings for running the program, which only some cartridges have.
+    #[serde(default)]
+    pub options: Options,
+}
+
+/// The settings saved in an Octo cartridge that etherea understands.
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+pub struct Options {
+    /// The number of instructions to execute per frame.
+    pub tickrate: Option<u32>,
+}
+
+/// Turns the contents of the file at `path` into a ROM. Octo cartridges
+/// and source are assembled, and anything else is taken to already be a
+/// ROM.
+///
+/// # Errors
+/// This function will error if the cartridge can't be read, or the program
+/// fails to assemble.
+pub fn load(path: &Path, file: Vec<u8>) -> Result<Vec<u8>, String> {
+    match source(path, &file) {
+        Some(source) => {
+            asm::octo::assemble(&source?).map_err(|e| format!("{}: {e}", path.display()))
+        }
+        None => Ok(file),
+    }
+}
+
+/// The program in `file`, read from `path`, if it is an Octo cartridge
+/// (`.gif` or `.html`) or source file (`.8o` or `.o8`).
+///
+/// # Errors
+/// This function will error if the cartridge can't be read, or the source
+/// isn't UTF-8.
+pub fn source(path: &Path, file: &[u8]) -> Option<Result<String, String>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    let source = match extension.as_deref()? {
+        "gif" => gif(file).map(|cartridge| cartridge.program),
+        "html" | "htm" => html(&String::from_utf8_lossy(file)).map(|cartridge| cartridge.program),
+        "8o" | "o8" => {
+            String::from_utf8(file.to_vec()).map_err(|_| "Octo source must be UTF-8".to_string())
+        }
+        _ => return None,
+    };
+    Some(source)
+}
+
+/// Reads the cartridge hidden in the pixels of an Octo `.gif` cartridge.
+/// The low nibble of each pixel's palette index holds half a byte, high
+/// half first, through every frame. The first four bytes are the length of
+/// the rest, which is the cartridge as JSON.
+///
+/// # Errors
+/// This function will error if `file` isn't a GIF, or doesn't hold a
+/// cartridge.
+pub fn gif(file: &[u8]) -> Result<Cartridge, String> {
+    let err = |e: gif::DecodingError| format!("Could not read the cartridge: {e}");
+    let mut options = gif::DecodeOptions::new();
+    options.set_color_output(gif::ColorOutput::Indexed);
+    let mut decoder = options.read_info(file).map_err(err)?;
+    let mut nibbles = Vec::new();
+    while let Some(frame) = decoder.read_next_frame().map_err(err)? {
+        nibbles.extend(frame.buffer.iter().map(|index| index & 0xF));
+    }
+    let bytes: Vec<u8> = nibbles
+        .chunks_exact(2)
+        .map(|pair| (pair[0] << 4) | pair[1])
+        .collect();
+
+    let missing = || "The GIF doesn't hold an Octo cartridge".to_string();
+    let (size, rest) = bytes.split_first_chunk::<4>().ok_or_else(missing)?;
+    let size = usize::try_from(u32::from_be_bytes(*size)).map_err(|_| missing())?;
+    let json = rest.get(..size).ok_or_else(missing)?;
+    serde_json::from_slice(json).map_err(|_| missing())
+}
+
+/// Reads the cartridge embedded in an Octo `.html` export, as the JSON
+/// object holding its `"program"`.
+///
+/// # Errors
+/// This function will error if the page doesn't embed a cartridge.
+pub fn html(page: &str) -> Result<Cartridge, String> {
+    let missing = || "The page doesn't hold an Octo cartridge".to_string();
+    let key = page.find("\"program\"").ok_or_else(missing)?;
+    let start = page[..key].rfind('{').ok_or_else(missing)?;
+    serde_json::Deserializer::from_str(&page[start..])
+        .into_iter::<Cartridge>()
+        .next()
+        .and_then(Result::ok)
+        .ok_or_else(missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JSON: &str = r#"{"program":"clear\njump 0x200","options":{"tickrate":20}}"#;
+
+    fn cartridge() -> Cartridge {
+        Cartridge {
+            program: "clear\njump 0x200".into(),
+            options: Options { tickrate: Some(20) },
+        }
+    }
+
+    #[test]
+    fn reads_gifs() {
+        let mut payload = u32::try_from(JSON.len()).unwrap().to_be_bytes().to_vec();
+        payload.extend(JSON.bytes());
+        // Every palette entry of a label color looks the same, whatever the
+        // data in its low nibble.
+        let label: Vec<u8> = (0..=255u8).flat_map(|i| [i & 0xF0; 3]).collect();
+        let mut pixels: Vec<u8> = payload
+            .iter()
+            .flat_map(|byte| [0x30 | byte >> 4, 0x30 | byte & 0xF])
+            .collect();
+        pixels.resize(32 * 32, 0x30);
+
+        let mut file = Vec::new();
+        {
+            let mut encoder = gif::Encoder::new(&mut file, 32, 32, &label).unwrap();
+            let frame = gif::Frame::from_indexed_pixels(32, 32, &pixels, None);
+            encoder.write_frame(&frame).unwrap();
+        }
+        assert_eq!(gif(&file), Ok(cartridge()));
+        assert!(gif(&[0x47, 0x49, 0x46]).is_err());
+        assert_eq!(
+            load(Path::new("cart.gif"), file),
+            Ok(vec![0x00, 0xE0, 0x12, 0x00])
+        );
+    }
+
+    #[test]
+    fn reads_html() {
+        let page = format!("<script>\nconst cart = {JSON};\nrun(cart);\n</script>");
+        assert_eq!(html(&page), Ok(cartridge()));
+        assert!(html("<p>\"program\"</p>").is_err());
+    }
+
+    #[test]
+    fn loads_source() {
+        let source = b": main\n  clear\n".to_vec();
+        assert_eq!(
+            load(Path::new("a.8o"), source),
+            Ok(vec![0x12, 0x02, 0x00, 0xE0])
+        );
+        assert_eq!(load(Path::new("a.ch8"), vec![1, 2]), Ok(vec![1, 2]));
+        assert!(load(Path::new("a.o8"), b"jump".to_vec())
+            .unwrap_err()
+            .starts_with("a.o8: line 1"));
+    }
+}
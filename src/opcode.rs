@@ -0,0 +1,920 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::Deserialize;
+
+/// A decoded CHIP-8 instruction. `x` and `y` are register indices, `nn` an
+/// 8-bit immediate, `n` a 4-bit immediate, and bare `u16`s 12-bit addresses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Opcode {
+    /// `0NNN`: call a machine code routine (ignored).
+    Sys(u16),
+    /// `00E0`: clear the display.
+    ClearScreen,
+    /// `00EE`: return from a subroutine.
+    Return,
+    /// `1NNN`: jump to an address.
+    Jump(u16),
+    /// `2NNN`: call a subroutine.
+    Call(u16),
+    /// `3XNN`: skip the next instruction if `VX == NN`.
+    SkipEq { x: u8, nn: u8 },
+    /// `4XNN`: skip the next instruction if `VX != NN`.
+    SkipNe { x: u8, nn: u8 },
+    /// `5XY0`: skip the next instruction if `VX == VY`.
+    SkipEqReg { x: u8, y: u8 },
+    /// `6XNN`: set `VX` to `NN`.
+    Load { x: u8, nn: u8 },
+    /// `7XNN`: add `NN` to `VX`, without carry.
+    AddImm { x: u8, nn: u8 },
+    /// `8XY0`: set `VX` to `VY`.
+    Move { x: u8, y: u8 },
+    /// `8XY1`: set `VX` to `VX | VY`.
+    Or { x: u8, y: u8 },
+    /// `8XY2`: set `VX` to `VX & VY`.
+    And { x: u8, y: u8 },
+    /// `8XY3`: set `VX` to `VX ^ VY`.
+    Xor { x: u8, y: u8 },
+    /// `8XY4`: add `VY` to `VX`, setting `VF` on carry.
+    Add { x: u8, y: u8 },
+    /// `8XY5`: subtract `VY` from `VX`, setting `VF` if there is no borrow.
+    Sub { x: u8, y: u8 },
+    /// `8XY6`: shift `VX` right by one, setting `VF` to the shifted out bit.
+    ShiftRight { x: u8, y: u8 },
+    /// `8XY7`: set `VX` to `VY - VX`, setting `VF` if there is no borrow.
+    SubN { x: u8, y: u8 },
+    /// `8XYE`: shift `VX` left by one, setting `VF` to the shifted out bit.
+    ShiftLeft { x: u8, y: u8 },
+    /// `9XY0`: skip the next instruction if `VX != VY`.
+    SkipNeReg { x: u8, y: u8 },
+    /// `ANNN`: set `I` to an address.
+    LoadIndex(u16),
+    /// `BNNN`: jump to an address plus `V0`.
+    JumpOffset(u16),
+    /// `CXNN`: set `VX` to a random number masked by `NN`.
+    Random { x: u8, nn: u8 },
+    /// `DXYN`: draw an `N`-byte sprite at (`VX`, `VY`).
+    Draw { x: u8, y: u8, n: u8 },
+    /// `EX9E`: skip the next instruction if the key in `VX` is pressed.
+    SkipKey { x: u8 },
+    /// `EXA1`: skip the next instruction if the key in `VX` is not pressed.
+    SkipNotKey { x: u8 },
+    /// `FX07`: set `VX` to the delay timer.
+    LoadDelay { x: u8 },
+    /// `FX0A`: wait for a key and store it in `VX`.
+    WaitKey { x: u8 },
+    /// `FX15`: set the delay timer to `VX`.
+    SetDelay { x: u8 },
+    /// `FX18`: set the sound timer to `VX`.
+    SetSound { x: u8 },
+    /// `FX1E`: add `VX` to `I`.
+    AddIndex { x: u8 },
+    /// `FX29`: point `I` at the font character for `VX`.
+    Font { x: u8 },
+    /// `FX33`: store the binary-coded decimal of `VX` at `I`.
+    Bcd { x: u8 },
+    /// `FX55`: store `V0..=VX` to memory at `I`.
+    Store { x: u8 },
+    /// `FX65`: load `V0..=VX` from memory at `I`.
+    LoadMemory { x: u8 },
+    /// `FX75`: store `V0..=VX` in the RPL user flags (SUPER-CHIP).
+    SaveFlags { x: u8 },
+    /// `FX85`: load `V0..=VX` from the RPL user flags (SUPER-CHIP).
+    LoadFlags { x: u8 },
+    /// `00CN`: scroll the display down by `N` pixels (SUPER-CHIP).
+    ScrollDown { n: u8 },
+    /// `00DN`: scroll the display up by `N` pixels (XO-CHIP).
+    ScrollUp { n: u8 },
+    /// `00FB`: scroll the display right by 4 pixels (SUPER-CHIP).
+    ScrollRight,
+    /// `00FC`: scroll the display left by 4 pixels (SUPER-CHIP).
+    ScrollLeft,
+}
+
+/// The error returned when a 16-bit word isn't a known instruction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct UnknownOpcode(pub u16);
+
+impl fmt::Display for UnknownOpcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown opcode: {:04X}", self.0)
+    }
+}
+
+impl core::error::Error for UnknownOpcode {}
+
+impl TryFrom<u16> for Opcode {
+    type Error = UnknownOpcode;
+
+    fn try_from(raw: u16) -> Result<Self, Self::Error> {
+        let [hi, lo] = raw.to_be_bytes();
+        let nibbles = [hi >> 4, hi & 0xF, lo >> 4, lo & 0xF];
+        let addr = raw & 0xFFF;
+        let (x, y, nn) = (nibbles[1], nibbles[2], lo);
+        Ok(match nibbles {
+            [0, 0, 0xE, 0] => Self::ClearScreen,
+            [0, 0, 0xE, 0xE] => Self::Return,
+            [0, 0, 0xC, n] => Self::ScrollDown { n },
+            [0, 0, 0xD, n] => Self::ScrollUp { n },
+            [0, 0, 0xF, 0xB] => Self::ScrollRight,
+            [0, 0, 0xF, 0xC] => Self::ScrollLeft,
+            [0, ..] => Self::Sys(addr),
+            [1, ..] => Self::Jump(addr),
+            [2, ..] => Self::Call(addr),
+            [3, ..] => Self::SkipEq { x, nn },
+            [4, ..] => Self::SkipNe { x, nn },
+            [5, _, _, 0] => Self::SkipEqReg { x, y },
+            [6, ..] => Self::Load { x, nn },
+            [7, ..] => Self::AddImm { x, nn },
+            [8, _, _, 0] => Self::Move { x, y },
+            [8, _, _, 1] => Self::Or { x, y },
+            [8, _, _, 2] => Self::And { x, y },
+            [8, _, _, 3] => Self::Xor { x, y },
+            [8, _, _, 4] => Self::Add { x, y },
+            [8, _, _, 5] => Self::Sub { x, y },
+            [8, _, _, 6] => Self::ShiftRight { x, y },
+            [8, _, _, 7] => Self::SubN { x, y },
+            [8, _, _, 0xE] => Self::ShiftLeft { x, y },
+            [9, _, _, 0] => Self::SkipNeReg { x, y },
+            [0xA, ..] => Self::LoadIndex(addr),
+            [0xB, ..] => Self::JumpOffset(addr),
+            [0xC, ..] => Self::Random { x, nn },
+            [0xD, _, _, n] => Self::Draw { x, y, n },
+            [0xE, _, 9, 0xE] => Self::SkipKey { x },
+            [0xE, _, 0xA, 1] => Self::SkipNotKey { x },
+            [0xF, _, 0, 7] => Self::LoadDelay { x },
+            [0xF, _, 0, 0xA] => Self::WaitKey { x },
+            [0xF, _, 1, 5] => Self::SetDelay { x },
+            [0xF, _, 1, 8] => Self::SetSound { x },
+            [0xF, _, 1, 0xE] => Self::AddIndex { x },
+            [0xF, _, 2, 9] => Self::Font { x },
+            [0xF, _, 3, 3] => Self::Bcd { x },
+            [0xF, _, 5, 5] => Self::Store { x },
+            [0xF, _, 6, 5] => Self::LoadMemory { x },
+            [0xF, _, 7, 5] => Self::SaveFlags { x },
+            [0xF, _, 8, 5] => Self::LoadFlags { x },
+            _ => return Err(UnknownOpcode(raw)),
+        })
+    }
+}
+
+impl Opcode {
+    /// The documentation for this instruction.
+    ///
+    /// # Panics
+    /// This function will panic if [`DOCS`] is missing an entry for this
+    /// instruction, which the tests check for.
+    #[must_use]
+    pub fn doc(&self) -> &'static Doc {
+        DOCS.iter()
+            .find(|doc| doc.pattern == self.pattern())
+            .expect("every instruction is documented")
+    }
+
+    /// The pattern this instruction is decoded from, e.g. `8XY4`.
+    #[must_use]
+    pub const fn pattern(&self) -> &'static str {
+        match self {
+            Self::Sys(_) => "0NNN",
+            Self::ClearScreen => "00E0",
+            Self::Return => "00EE",
+            Self::Jump(_) => "1NNN",
+            Self::Call(_) => "2NNN",
+            Self::SkipEq { .. } => "3XNN",
+            Self::SkipNe { .. } => "4XNN",
+            Self::SkipEqReg { .. } => "5XY0",
+            Self::Load { .. } => "6XNN",
+            Self::AddImm { .. } => "7XNN",
+            Self::Move { .. } => "8XY0",
+            Self::Or { .. } => "8XY1",
+            Self::And { .. } => "8XY2",
+            Self::Xor { .. } => "8XY3",
+            Self::Add { .. } => "8XY4",
+            Self::Sub { .. } => "8XY5",
+            Self::ShiftRight { .. } => "8XY6",
+            Self::SubN { .. } => "8XY7",
+            Self::ShiftLeft { .. } => "8XYE",
+            Self::SkipNeReg { .. } => "9XY0",
+            Self::LoadIndex(_) => "ANNN",
+            Self::JumpOffset(_) => "BNNN",
+            Self::Random { .. } => "CXNN",
+            Self::Draw { .. } => "DXYN",
+            Self::SkipKey { .. } => "EX9E",
+            Self::SkipNotKey { .. } => "EXA1",
+            Self::LoadDelay { .. } => "FX07",
+            Self::WaitKey { .. } => "FX0A",
+            Self::SetDelay { .. } => "FX15",
+            Self::SetSound { .. } => "FX18",
+            Self::AddIndex { .. } => "FX1E",
+            Self::Font { .. } => "FX29",
+            Self::Bcd { .. } => "FX33",
+            Self::Store { .. } => "FX55",
+            Self::LoadMemory { .. } => "FX65",
+            Self::SaveFlags { .. } => "FX75",
+            Self::LoadFlags { .. } => "FX85",
+            Self::ScrollDown { .. } => "00CN",
+            Self::ScrollUp { .. } => "00DN",
+            Self::ScrollRight => "00FB",
+            Self::ScrollLeft => "00FC",
+        }
+    }
+}
+
+/// Formats the instruction using the conventional mnemonics, e.g.
+/// `LD V3, 0x2A` or `DRW V1, V2, 5`.
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Sys(addr) => write!(f, "SYS {addr:#05X}"),
+            Self::ClearScreen => write!(f, "CLS"),
+            Self::Return => write!(f, "RET"),
+            Self::Jump(addr) => write!(f, "JP {addr:#05X}"),
+            Self::Call(addr) => write!(f, "CALL {addr:#05X}"),
+            Self::SkipEq { x, nn } => write!(f, "SE V{x:X}, {nn:#04X}"),
+            Self::SkipNe { x, nn } => write!(f, "SNE V{x:X}, {nn:#04X}"),
+            Self::SkipEqReg { x, y } => write!(f, "SE V{x:X}, V{y:X}"),
+            Self::Load { x, nn } => write!(f, "LD V{x:X}, {nn:#04X}"),
+            Self::AddImm { x, nn } => write!(f, "ADD V{x:X}, {nn:#04X}"),
+            Self::Move { x, y } => write!(f, "LD V{x:X}, V{y:X}"),
+            Self::Or { x, y } => write!(f, "OR V{x:X}, V{y:X}"),
+            Self::And { x, y } => write!(f, "AND V{x:X}, V{y:X}"),
+            Self::Xor { x, y } => write!(f, "XOR V{x:X}, V{y:X}"),
+            Self::Add { x, y } => write!(f, "ADD V{x:X}, V{y:X}"),
+            Self::Sub { x, y } => write!(f, "SUB V{x:X}, V{y:X}"),
+            Self::ShiftRight { x, y } => write!(f, "SHR V{x:X}, V{y:X}"),
+            Self::SubN { x, y } => write!(f, "SUBN V{x:X}, V{y:X}"),
+            Self::ShiftLeft { x, y } => write!(f, "SHL V{x:X}, V{y:X}"),
+            Self::SkipNeReg { x, y } => write!(f, "SNE V{x:X}, V{y:X}"),
+            Self::LoadIndex(addr) => write!(f, "LD I, {addr:#05X}"),
+            Self::JumpOffset(addr) => write!(f, "JP V0, {addr:#05X}"),
+            Self::Random { x, nn } => write!(f, "RND V{x:X}, {nn:#04X}"),
+            Self::Draw { x, y, n } => write!(f, "DRW V{x:X}, V{y:X}, {n}"),
+            Self::SkipKey { x } => write!(f, "SKP V{x:X}"),
+            Self::SkipNotKey { x } => write!(f, "SKNP V{x:X}"),
+            Self::LoadDelay { x } => write!(f, "LD V{x:X}, DT"),
+            Self::WaitKey { x } => write!(f, "LD V{x:X}, K"),
+            Self::SetDelay { x } => write!(f, "LD DT, V{x:X}"),
+            Self::SetSound { x } => write!(f, "LD ST, V{x:X}"),
+            Self::AddIndex { x } => write!(f, "ADD I, V{x:X}"),
+            Self::Font { x } => write!(f, "LD F, V{x:X}"),
+            Self::Bcd { x } => write!(f, "LD B, V{x:X}"),
+            Self::Store { x } => write!(f, "LD [I], V{x:X}"),
+            Self::LoadMemory { x } => write!(f, "LD V{x:X}, [I]"),
+            Self::SaveFlags { x } => write!(f, "LD R, V{x:X}"),
+            Self::LoadFlags { x } => write!(f, "LD V{x:X}, R"),
+            Self::ScrollDown { n } => write!(f, "SCD {n}"),
+            Self::ScrollUp { n } => write!(f, "SCU {n}"),
+            Self::ScrollRight => write!(f, "SCR"),
+            Self::ScrollLeft => write!(f, "SCL"),
+        }
+    }
+}
+
+/// A variant of CHIP-8, and so a set of supported instructions.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Platform {
+    /// CHIP-8 as it ran on the COSMAC VIP
+    #[default]
+    Chip8,
+    /// CHIP-8X, with the VP-590 color board and a second keypad
+    #[cfg_attr(feature = "cli", value(name = "chip8x"))]
+    Chip8X,
+    /// HIRES CHIP-8, with a 64x64 screen
+    Hires,
+    /// SUPER-CHIP on the HP-48
+    #[cfg_attr(feature = "cli", value(name = "schip"))]
+    #[cfg_attr(feature = "serde", serde(rename = "schip"))]
+    SuperChip,
+    /// XO-CHIP, as in Octo
+    #[cfg_attr(feature = "cli", value(name = "xochip"))]
+    XoChip,
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Chip8 => write!(f, "CHIP-8"),
+            Self::Chip8X => write!(f, "CHIP-8X"),
+            Self::Hires => write!(f, "HIRES CHIP-8"),
+            Self::SuperChip => write!(f, "SUPER-CHIP"),
+            Self::XoChip => write!(f, "XO-CHIP"),
+        }
+    }
+}
+
+/// Documentation for a single instruction. [`DOCS`] holds an entry for
+/// every instruction of every [`Platform`], and is shared by the
+/// assembler, the debugger, and the `explain` command.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Doc {
+    /// The pattern the instruction is decoded from, e.g. `8XY6`. `X` and `Y`
+    /// stand for register indices, and `N` for a digit of an immediate value.
+    pub pattern: &'static str,
+    /// The assembly syntax, e.g. `SHR Vx, Vy`.
+    pub syntax: &'static str,
+    /// What the instruction does.
+    pub description: &'static str,
+    /// Behavior that differs between interpreters, as pairs of the name of
+    /// the quirk and a description of how it differs.
+    pub quirks: &'static [(&'static str, &'static str)],
+    /// The platforms the instruction is available on.
+    pub platforms: &'static [Platform],
+}
+
+impl Doc {
+    /// The mnemonic, e.g. `SHR`.
+    #[must_use]
+    pub fn mnemonic(&self) -> &'static str {
+        self.syntax.split(' ').next().unwrap_or_default()
+    }
+
+    /// Whether `raw` matches the pattern.
+    #[must_use]
+    pub fn matches(&self, raw: u16) -> bool {
+        self.pattern
+            .chars()
+            .zip(
+                raw.to_be_bytes()
+                    .iter()
+                    .flat_map(|byte| [byte >> 4, byte & 0xF]),
+            )
+            .all(|(c, nibble)| {
+                c.to_digit(16)
+                    .is_none_or(|digit| digit == u32::from(nibble))
+            })
+    }
+
+    /// Whether the instruction is decoded to an [`Opcode`], and so can be
+    /// run and assembled.
+    #[must_use]
+    pub fn supported(&self) -> bool {
+        let raw = self.pattern.chars().fold(0, |raw, c| {
+            raw << 4
+                | c.to_digit(16)
+                    .and_then(|digit| u16::try_from(digit).ok())
+                    .unwrap_or(0)
+        });
+        Opcode::try_from(raw).is_ok_and(|opcode| opcode.pattern() == self.pattern)
+    }
+
+    /// The entry for the instruction `raw`. If several patterns match, e.g.
+    /// `DXY0` and `DXYN`, the most specific one is returned.
+    #[must_use]
+    pub fn lookup(raw: u16) -> Option<&'static Self> {
+        DOCS.iter()
+            .filter(|doc| doc.matches(raw))
+            .max_by_key(|doc| doc.pattern.chars().filter(char::is_ascii_hexdigit).count())
+    }
+
+    /// The entries for every form of `mnemonic`, ignoring case.
+    pub fn with_mnemonic(mnemonic: &str) -> impl Iterator<Item = &'static Self> + '_ {
+        DOCS.iter()
+            .filter(move |doc| doc.mnemonic().eq_ignore_ascii_case(mnemonic))
+    }
+
+    /// Finds the entries for `query`, which is either a raw instruction
+    /// (`D125`), a pattern (`8XY6`), or a mnemonic (`DRW`).
+    #[must_use]
+    pub fn search(query: &str) -> Vec<&'static Self> {
+        if let Some(doc) = DOCS
+            .iter()
+            .find(|doc| doc.pattern.eq_ignore_ascii_case(query))
+        {
+            return vec![doc];
+        }
+        if query.len() == 4 {
+            if let Ok(raw) = u16::from_str_radix(query, 16) {
+                return Self::lookup(raw).into_iter().collect();
+            }
+        }
+        Self::with_mnemonic(query).collect()
+    }
+}
+
+/// Formats the entry as a short help text.
+impl fmt::Display for Doc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let platforms: Vec<String> = self.platforms.iter().map(ToString::to_string).collect();
+        writeln!(f, "{}  {}", self.pattern, self.syntax)?;
+        writeln!(f, "  {}", self.description)?;
+        write!(f, "  Available on: {}", platforms.join(", "))?;
+        for (quirk, description) in self.quirks {
+            write!(f, "\n  {quirk} quirk: {description}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The documentation for every instruction.
+pub static DOCS: &[Doc] = &[
+    Doc {
+        pattern: "0NNN",
+        syntax: "SYS addr",
+        description: "Calls a machine code routine on the original hardware. Ignored by modern interpreters, except that with `--banking`, `0BNN` maps memory bank NN.",
+        quirks: &[],
+        platforms: &[Platform::Chip8],
+    },
+    Doc {
+        pattern: "00E0",
+        syntax: "CLS",
+        description: "Clears the display.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00EE",
+        syntax: "RET",
+        description: "Returns from a subroutine, popping the address to return to off the stack.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "1NNN",
+        syntax: "JP addr",
+        description: "Jumps to addr.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "2NNN",
+        syntax: "CALL addr",
+        description: "Calls the subroutine at addr, pushing the address of the next instruction onto the stack.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "3XNN",
+        syntax: "SE Vx, byte",
+        description: "Skips the next instruction if Vx equals byte.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "4XNN",
+        syntax: "SNE Vx, byte",
+        description: "Skips the next instruction if Vx does not equal byte.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "5XY0",
+        syntax: "SE Vx, Vy",
+        description: "Skips the next instruction if Vx equals Vy.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "6XNN",
+        syntax: "LD Vx, byte",
+        description: "Sets Vx to byte.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "7XNN",
+        syntax: "ADD Vx, byte",
+        description: "Adds byte to Vx. VF is not affected.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY0",
+        syntax: "LD Vx, Vy",
+        description: "Sets Vx to Vy.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY1",
+        syntax: "OR Vx, Vy",
+        description: "Sets Vx to Vx OR Vy.",
+        quirks: &[("vf reset", "the COSMAC VIP also resets VF to 0")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY2",
+        syntax: "AND Vx, Vy",
+        description: "Sets Vx to Vx AND Vy.",
+        quirks: &[("vf reset", "the COSMAC VIP also resets VF to 0")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY3",
+        syntax: "XOR Vx, Vy",
+        description: "Sets Vx to Vx XOR Vy.",
+        quirks: &[("vf reset", "the COSMAC VIP also resets VF to 0")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY4",
+        syntax: "ADD Vx, Vy",
+        description: "Adds Vy to Vx. VF is set to 1 on carry, and 0 otherwise.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY5",
+        syntax: "SUB Vx, Vy",
+        description: "Subtracts Vy from Vx. VF is set to 0 on borrow, and 1 otherwise.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY6",
+        syntax: "SHR Vx, Vy",
+        description: "Shifts Vx right by one. VF is set to the bit shifted out.",
+        quirks: &[("shift", "the COSMAC VIP shifts Vy and stores the result in Vx")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XY7",
+        syntax: "SUBN Vx, Vy",
+        description: "Sets Vx to Vy minus Vx. VF is set to 0 on borrow, and 1 otherwise.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "8XYE",
+        syntax: "SHL Vx, Vy",
+        description: "Shifts Vx left by one. VF is set to the bit shifted out.",
+        quirks: &[("shift", "the COSMAC VIP shifts Vy and stores the result in Vx")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "9XY0",
+        syntax: "SNE Vx, Vy",
+        description: "Skips the next instruction if Vx does not equal Vy.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "ANNN",
+        syntax: "LD I, addr",
+        description: "Sets I to addr.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "BNNN",
+        syntax: "JP V0, addr",
+        description: "Jumps to addr plus V0.",
+        quirks: &[
+            ("jump", "CHIP-48 and SUPER-CHIP jump to XNN plus Vx instead (`--jump-offset vx`)"),
+            ("CHIP-8X", "BXYN sets the foreground color of zones at (Vx, Vx+1) to Vy instead, N rows high, or in 4-row zones if N is 0 (`--platform chip8x`)"),
+        ],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "CXNN",
+        syntax: "RND Vx, byte",
+        description: "Sets Vx to a random number AND byte.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "DXYN",
+        syntax: "DRW Vx, Vy, nibble",
+        description: "Draws the nibble-byte sprite at I at (Vx, Vy), XORing it onto the display. VF is set to 1 if any pixel is turned off, and 0 otherwise.",
+        quirks: &[
+            ("clipping", "sprites are clipped at the edges of the screen by most interpreters, but wrap around on some"),
+            ("display wait", "the COSMAC VIP waits for the vertical blank before drawing"),
+        ],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "EX9E",
+        syntax: "SKP Vx",
+        description: "Skips the next instruction if the key Vx is pressed.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "EXA1",
+        syntax: "SKNP Vx",
+        description: "Skips the next instruction if the key Vx is not pressed.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX07",
+        syntax: "LD Vx, DT",
+        description: "Sets Vx to the delay timer.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX0A",
+        syntax: "LD Vx, K",
+        description: "Waits for a key to be pressed and released, then stores it in Vx.",
+        quirks: &[("key wait", "some interpreters resume when the key is pressed rather than released")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX15",
+        syntax: "LD DT, Vx",
+        description: "Sets the delay timer to Vx.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX18",
+        syntax: "LD ST, Vx",
+        description: "Sets the sound timer to Vx.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX1E",
+        syntax: "ADD I, Vx",
+        description: "Adds Vx to I.",
+        quirks: &[("overflow", "some interpreters set VF when I passes 0xFFF")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX29",
+        syntax: "LD F, Vx",
+        description: "Sets I to the location of the font sprite for the digit Vx.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX33",
+        syntax: "LD B, Vx",
+        description: "Stores the binary-coded decimal digits of Vx at I, I + 1, and I + 2.",
+        quirks: &[],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX55",
+        syntax: "LD [I], Vx",
+        description: "Stores V0 through Vx in memory starting at I.",
+        quirks: &[("memory", "the COSMAC VIP increments I past the stored registers")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX65",
+        syntax: "LD Vx, [I]",
+        description: "Loads V0 through Vx from memory starting at I.",
+        quirks: &[("memory", "the COSMAC VIP increments I past the loaded registers")],
+        platforms: &[Platform::Chip8, Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "0230",
+        syntax: "CLS",
+        description: "Clears the 64x64 screen of HIRES CHIP-8.",
+        quirks: &[],
+        platforms: &[Platform::Hires],
+    },
+    Doc {
+        pattern: "02A0",
+        syntax: "CLR",
+        description: "Switches the background to the next of blue, black, green, and red.",
+        quirks: &[],
+        platforms: &[Platform::Chip8X],
+    },
+    Doc {
+        pattern: "5XY1",
+        syntax: "ADD Vx, Vy, octal",
+        description: "Adds Vy to Vx nibble by nibble, each nibble wrapping around at 8.",
+        quirks: &[],
+        platforms: &[Platform::Chip8X],
+    },
+    Doc {
+        pattern: "EXF2",
+        syntax: "SKP2 Vx",
+        description: "Skips the next instruction if the key Vx is pressed on the second keypad.",
+        quirks: &[],
+        platforms: &[Platform::Chip8X],
+    },
+    Doc {
+        pattern: "EXF5",
+        syntax: "SKNP2 Vx",
+        description: "Skips the next instruction if the key Vx is not pressed on the second keypad.",
+        quirks: &[],
+        platforms: &[Platform::Chip8X],
+    },
+    Doc {
+        pattern: "00CN",
+        syntax: "SCD nibble",
+        description: "Scrolls the display down by nibble pixels.",
+        quirks: &[("half scroll", "SUPER-CHIP 1.1 scrolls half as far in low resolution")],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00FB",
+        syntax: "SCR",
+        description: "Scrolls the display right by 4 pixels.",
+        quirks: &[("half scroll", "SUPER-CHIP 1.1 scrolls half as far in low resolution")],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00FC",
+        syntax: "SCL",
+        description: "Scrolls the display left by 4 pixels.",
+        quirks: &[("half scroll", "SUPER-CHIP 1.1 scrolls half as far in low resolution")],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00FD",
+        syntax: "EXIT",
+        description: "Exits the interpreter.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00FE",
+        syntax: "LOW",
+        description: "Switches to the 64x32 low resolution mode.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00FF",
+        syntax: "HIGH",
+        description: "Switches to the 128x64 high resolution mode.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "DXY0",
+        syntax: "DRW Vx, Vy, 0",
+        description: "Draws the 16x16 sprite at I at (Vx, Vy).",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX30",
+        syntax: "LD HF, Vx",
+        description: "Sets I to the location of the large font sprite for the digit Vx.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX75",
+        syntax: "LD R, Vx",
+        description: "Stores V0 through Vx in the persistent flag registers.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX85",
+        syntax: "LD Vx, R",
+        description: "Loads V0 through Vx from the persistent flag registers.",
+        quirks: &[],
+        platforms: &[Platform::SuperChip, Platform::XoChip],
+    },
+    Doc {
+        pattern: "00DN",
+        syntax: "SCU nibble",
+        description: "Scrolls the display up by nibble pixels.",
+        quirks: &[("half scroll", "scrolls half as far in low resolution, like 00CN")],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "5XY2",
+        syntax: "SAVE Vx, Vy",
+        description: "Stores Vx through Vy in memory starting at I, without changing I.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "5XY3",
+        syntax: "LOAD Vx, Vy",
+        description: "Loads Vx through Vy from memory starting at I, without changing I.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "F000",
+        syntax: "LD I, long addr",
+        description: "Sets I to the 16-bit address in the following word.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "FN01",
+        syntax: "PLANE n",
+        description: "Selects the bit planes drawn to by later instructions.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "F002",
+        syntax: "AUDIO",
+        description: "Loads the 16-byte audio pattern buffer from I.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+    Doc {
+        pattern: "FX3A",
+        syntax: "PITCH Vx",
+        description: "Sets the audio playback pitch to Vx.",
+        quirks: &[],
+        platforms: &[Platform::XoChip],
+    },
+];
+
+/// Formats the word at `address` as a line of disassembly: the address, the
+/// raw word, and its mnemonic (or a `DW` directive if it isn't an instruction).
+#[must_use]
+pub fn disassemble_word(address: u16, raw: u16) -> String {
+    match Opcode::try_from(raw) {
+        Ok(opcode) => format!("{address:#05X}  {raw:04X}  {opcode}"),
+        Err(_) => format!("{address:#05X}  {raw:04X}  DW {raw:#06X}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        assert_eq!(Opcode::try_from(0x00E0), Ok(Opcode::ClearScreen));
+        assert_eq!(Opcode::try_from(0x1234), Ok(Opcode::Jump(0x234)));
+        assert_eq!(
+            Opcode::try_from(0xD125),
+            Ok(Opcode::Draw { x: 1, y: 2, n: 5 })
+        );
+        assert_eq!(Opcode::try_from(0x5121), Err(UnknownOpcode(0x5121)));
+        assert_eq!(Opcode::try_from(0xFFFF), Err(UnknownOpcode(0xFFFF)));
+    }
+
+    #[test]
+    fn mnemonics() {
+        let mnemonic = |raw| Opcode::try_from(raw).unwrap().to_string();
+        assert_eq!(mnemonic(0x632A), "LD V3, 0x2A");
+        assert_eq!(mnemonic(0xD125), "DRW V1, V2, 5");
+        assert_eq!(mnemonic(0xA2F0), "LD I, 0x2F0");
+        assert_eq!(mnemonic(0xFA55), "LD [I], VA");
+        assert_eq!(mnemonic(0xB300), "JP V0, 0x300");
+        assert_eq!(mnemonic(0xF775), "LD R, V7");
+        assert_eq!(mnemonic(0x00C3), "SCD 3");
+        assert_eq!(mnemonic(0x00D1), "SCU 1");
+        assert_eq!(mnemonic(0x00FB), "SCR");
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn round_trips_through_assembler() {
+        for raw in 0..=u16::MAX {
+            if let Ok(opcode) = Opcode::try_from(raw) {
+                let rom = crate::asm::assemble(&opcode.to_string()).unwrap();
+                assert_eq!(rom, raw.to_be_bytes(), "{opcode}");
+            }
+        }
+    }
+
+    #[test]
+    fn docs() {
+        for raw in 0..=u16::MAX {
+            if let Ok(opcode) = Opcode::try_from(raw) {
+                let doc = opcode.doc();
+                // Some extension instructions, like 00FF, are more specific
+                // forms of CHIP-8 ones.
+                let found = Doc::lookup(raw).unwrap();
+                assert!(found == doc || !found.platforms.contains(&Platform::Chip8));
+                assert!(doc.supported());
+                assert_eq!(
+                    doc.mnemonic(),
+                    opcode.to_string().split(' ').next().unwrap()
+                );
+            }
+        }
+        assert_eq!(Doc::lookup(0xD120).unwrap().pattern, "DXY0");
+        assert_eq!(
+            Doc::lookup(0x00FF).unwrap().platforms[0],
+            Platform::SuperChip
+        );
+        assert_eq!(Doc::search("8xy6")[0].syntax, "SHR Vx, Vy");
+        assert_eq!(Doc::search("D125")[0].pattern, "DXYN");
+        assert_eq!(Doc::search("drw").len(), 2);
+        assert!(Doc::search("nope").is_empty());
+        // The RPL user flags and scrolling are the only extensions decoded.
+        let supported: Vec<&str> = DOCS
+            .iter()
+            .filter(|doc| doc.supported() && !doc.platforms.contains(&Platform::Chip8))
+            .map(|doc| doc.pattern)
+            .collect();
+        assert_eq!(supported, ["00CN", "00FB", "00FC", "FX75", "FX85", "00DN"]);
+    }
+
+    #[test]
+    fn disassembly_line() {
+        assert_eq!(disassemble_word(0x200, 0x00E0), "0x200  00E0  CLS");
+        assert_eq!(disassemble_word(0x202, 0xFFFF), "0x202  FFFF  DW 0xFFFF");
+    }
+}
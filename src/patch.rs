@@ -0,0 +1,248 @@
+use crate::{asm::ORIGIN, debug};
+use std::{fmt, str::FromStr};
+
+/// The bytes every IPS patch starts with.
+const HEADER: &[u8] = b"PATCH";
+/// The bytes every IPS patch ends with, optionally followed by the length
+/// to truncate the file to.
+const FOOTER: &[u8] = b"EOF";
+/// The most bytes a single IPS record can hold.
+const RECORD_MAX: usize = 0xFFFF;
+
+/// An edit to a ROM from the command line, written `0x200:6A02`: the
+/// bytes, in hexadecimal, to write at an address in memory. The ROM is
+/// loaded at `0x200`, so that's its first byte, as in the debugger.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edit {
+    address: u16,
+    bytes: Vec<u8>,
+}
+
+impl FromStr for Edit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid edit '{s}', expected e.g. 0x200:6A02");
+        let (address, hex) = s.split_once(':').ok_or_else(invalid)?;
+        let address = debug::parse_address(address.trim())
+            .filter(|&address| address >= ORIGIN)
+            .ok_or_else(invalid)?;
+        let hex = hex.trim();
+        if hex.is_empty() || hex.len() % 2 != 0 {
+            return Err(invalid());
+        }
+        let bytes = (0..hex.len())
+            .step_by(2)
+            .map(|n| {
+                hex.get(n..n + 2)
+                    .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+        if usize::from(address) + bytes.len() > 0x1000 {
+            return Err(format!("The edit '{s}' doesn't fit in memory"));
+        }
+        Ok(Self { address, bytes })
+    }
+}
+
+impl fmt::Display for Edit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#05X}:", self.address)?;
+        self.bytes
+            .iter()
+            .try_for_each(|byte| write!(f, "{byte:02X}"))
+    }
+}
+
+/// Changes to a ROM file, as runs of bytes to write at offsets into it,
+/// which can be read from and written to [IPS](https://zerosoft.zophar.net/ips.php)
+/// patches, the format ROM hacks are usually shared in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Patch {
+    records: Vec<(usize, Vec<u8>)>, // Offsets into the file, and the bytes written there
+    truncate: Option<usize>,        // The length to cut the file to, after writing
+}
+
+impl Patch {
+    /// Reads an IPS patch.
+    ///
+    /// # Errors
+    /// This function will error if `ips` isn't an IPS patch, or ends early.
+    pub fn ips(ips: &[u8]) -> Result<Self, String> {
+        let mut rest = ips
+            .strip_prefix(HEADER)
+            .ok_or_else(|| "Not an IPS patch".to_string())?;
+        let truncated = || "The IPS patch ends early".to_string();
+        let mut take = |n: usize| -> Result<&[u8], String> {
+            let (taken, left) = rest.split_at_checked(n).ok_or_else(truncated)?;
+            rest = left;
+            Ok(taken)
+        };
+        let number = |bytes: &[u8]| bytes.iter().fold(0, |n, &byte| n << 8 | usize::from(byte));
+        let mut patch = Self::default();
+        loop {
+            let offset = take(3)?;
+            if offset == FOOTER {
+                break;
+            }
+            let offset = number(offset);
+            let bytes = match number(take(2)?) {
+                // A size of 0 means a run of one byte repeated.
+                0 => {
+                    let count = number(take(2)?);
+                    vec![take(1)?[0]; count]
+                }
+                size => take(size)?.to_vec(),
+            };
+            patch.records.push((offset, bytes));
+        }
+        // Some tools add the length to truncate the file to after the footer.
+        if let Ok(length) = take(3) {
+            patch.truncate = Some(number(length));
+        }
+        Ok(patch)
+    }
+
+    /// The patch that turns `original` into `patched`, writing each run of
+    /// bytes that differs between them. Bytes past the end of `original`
+    /// are added, and the file is cut short if `patched` is shorter.
+    #[must_use]
+    pub fn diff(original: &[u8], patched: &[u8]) -> Self {
+        let mut patch = Self::default();
+        let mut offset = 0;
+        while offset < patched.len() {
+            if original.get(offset) == Some(&patched[offset]) {
+                offset += 1;
+                continue;
+            }
+            let start = offset;
+            while offset < patched.len() && original.get(offset) != Some(&patched[offset]) {
+                offset += 1;
+            }
+            patch.records.push((start, patched[start..offset].to_vec()));
+        }
+        if patched.len() < original.len() {
+            patch.truncate = Some(patched.len());
+        }
+        patch
+    }
+
+    /// Adds `edit` after everything else in the patch.
+    pub fn edit(&mut self, edit: &Edit) {
+        let offset = usize::from(edit.address - ORIGIN);
+        self.records.push((offset, edit.bytes.clone()));
+    }
+
+    /// Whether the patch changes nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty() && self.truncate.is_none()
+    }
+
+    /// Applies the patch to `rom`, in order, growing it with zeroes where
+    /// bytes are written past its end.
+    pub fn apply(&self, rom: &mut Vec<u8>) {
+        for (offset, bytes) in &self.records {
+            let end = offset + bytes.len();
+            if rom.len() < end {
+                rom.resize(end, 0);
+            }
+            rom[*offset..end].copy_from_slice(bytes);
+        }
+        if let Some(length) = self.truncate {
+            rom.truncate(length);
+        }
+    }
+
+    /// Writes the patch as an IPS patch. Records longer than IPS allows
+    /// are split up.
+    #[must_use]
+    pub fn to_ips(&self) -> Vec<u8> {
+        let mut ips = HEADER.to_vec();
+        for (offset, bytes) in &self.records {
+            for (n, chunk) in bytes.chunks(RECORD_MAX).enumerate() {
+                let offset = offset + n * RECORD_MAX;
+                ips.extend_from_slice(&offset.to_be_bytes()[size_of::<usize>() - 3..]);
+                ips.extend_from_slice(&chunk.len().to_be_bytes()[size_of::<usize>() - 2..]);
+                ips.extend_from_slice(chunk);
+            }
+        }
+        ips.extend_from_slice(FOOTER);
+        if let Some(length) = self.truncate {
+            ips.extend_from_slice(&length.to_be_bytes()[size_of::<usize>() - 3..]);
+        }
+        ips
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits() {
+        let edit: Edit = "0x200:6A02".parse().unwrap();
+        assert_eq!(edit.to_string(), "0x200:6A02");
+        assert_eq!("2FF:aa".parse::<Edit>().unwrap().bytes, [0xAA]);
+        assert!("0x1FF:00".parse::<Edit>().is_err());
+        assert!("0x200:6A0".parse::<Edit>().is_err());
+        assert!("0x200:6G".parse::<Edit>().is_err());
+        assert!("0x200".parse::<Edit>().is_err());
+        assert!("0xFFF:0000"
+            .parse::<Edit>()
+            .unwrap_err()
+            .contains("doesn't fit"));
+
+        let mut patch = Patch::default();
+        patch.edit(&"0x201:FF".parse().unwrap());
+        patch.edit(&"0x204:1234".parse().unwrap());
+        let mut rom = vec![0x60, 0x01, 0x12, 0x00];
+        patch.apply(&mut rom);
+        assert_eq!(rom, [0x60, 0xFF, 0x12, 0x00, 0x12, 0x34]);
+    }
+
+    #[test]
+    fn ips() {
+        let ips = [
+            b"PATCH".as_slice(),
+            &[0x00, 0x00, 0x01, 0x00, 0x02, 0xAB, 0xCD], // 2 bytes at 1
+            &[0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x03, 0xEE], // 3 0xEEs at 5
+            b"EOF",
+        ]
+        .concat();
+        let patch = Patch::ips(&ips).unwrap();
+        let mut rom = vec![0; 4];
+        patch.apply(&mut rom);
+        assert_eq!(rom, [0, 0xAB, 0xCD, 0, 0, 0xEE, 0xEE, 0xEE]);
+        // Runs are written out in full.
+        assert_eq!(Patch::ips(&patch.to_ips()).unwrap(), patch);
+
+        assert!(Patch::ips(b"PATCH\x00\x00\x01\x00\x05\xAB").is_err());
+        assert!(Patch::ips(b"PATCH").is_err());
+        assert!(Patch::ips(b"NOPE").is_err());
+
+        // Truncation survives a round trip.
+        let truncate = Patch::ips(b"PATCHEOF\x00\x00\x02").unwrap();
+        assert_eq!(truncate.to_ips(), b"PATCHEOF\x00\x00\x02");
+        let mut rom = vec![1, 2, 3];
+        truncate.apply(&mut rom);
+        assert_eq!(rom, [1, 2]);
+    }
+
+    #[test]
+    fn diffs() {
+        let original = [0x60, 0x01, 0x61, 0x02, 0x12, 0x00];
+        let patched = [0x60, 0x05, 0x61, 0x02, 0x13, 0x10, 0xFF];
+        let patch = Patch::diff(&original, &patched);
+        assert_eq!(
+            patch.records,
+            [(1, vec![0x05]), (4, vec![0x13, 0x10, 0xFF])]
+        );
+        let mut rom = original.to_vec();
+        Patch::ips(&patch.to_ips()).unwrap().apply(&mut rom);
+        assert_eq!(rom, patched);
+        assert!(Patch::diff(&original, &original).is_empty());
+        assert_eq!(Patch::diff(&patched, &original).truncate, Some(6));
+    }
+}
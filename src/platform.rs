@@ -0,0 +1,75 @@
+use alloc::boxed::Box;
+use core::{fmt, time::Duration};
+#[cfg(feature = "std")]
+use std::{thread, time::Instant};
+
+/// A clock that never goes backwards, which paces frames in real time and
+/// measures the rates the debug overlay shows. With `std`, [`StdClock`] is
+/// used unless another is attached with
+/// [`Interpreter::with_clock`](crate::Interpreter::with_clock); without it,
+/// time stands still until one is, e.g. a microcontroller's timer.
+pub trait Clock: Send {
+    /// The time since some fixed point, such as the clock being created or
+    /// the device starting.
+    fn now(&self) -> Duration;
+
+    /// Waits for `duration`. Clocks that can't wait return at once, leaving
+    /// the caller to run frames as fast as it can.
+    fn sleep(&self, duration: Duration) {
+        let _ = duration;
+    }
+}
+
+/// The system's monotonic clock, counting from when it was created.
+#[cfg(feature = "std")]
+#[derive(Copy, Clone, Debug)]
+pub struct StdClock(Instant);
+
+#[cfg(feature = "std")]
+impl Default for StdClock {
+    fn default() -> Self {
+        Self(Instant::now())
+    }
+}
+
+#[cfg(feature = "std")]
+impl Clock for StdClock {
+    fn now(&self) -> Duration {
+        self.0.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// The clock used without `std` until one is attached, at which no time
+/// ever passes.
+#[cfg(not(feature = "std"))]
+struct Stopped;
+
+#[cfg(not(feature = "std"))]
+impl Clock for Stopped {
+    fn now(&self) -> Duration {
+        Duration::ZERO
+    }
+}
+
+/// The clock attached to an interpreter.
+pub(crate) struct Attached(pub(crate) Box<dyn Clock>);
+
+impl Default for Attached {
+    fn default() -> Self {
+        #[cfg(feature = "std")]
+        let clock = Box::new(StdClock::default());
+        #[cfg(not(feature = "std"))]
+        let clock = Box::new(Stopped);
+        Self(clock)
+    }
+}
+
+impl fmt::Debug for Attached {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Clock")
+    }
+}
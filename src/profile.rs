@@ -0,0 +1,320 @@
+use crate::{
+    asm::SourceMap, disasm, map::MemoryMap, opcode::Opcode, quirks::Quirks, replay::Replay, text,
+    timing::Timing, Fault, Interpreter,
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, io,
+    time::Duration,
+};
+
+/// The number of addresses listed in a report, hottest first.
+const HOT_SPOTS: usize = 20;
+/// The number of characters on each row of a heatmap.
+const HEATMAP_COLUMNS: usize = 40;
+/// How much a heatmap is scaled up, so its text is readable.
+const HEATMAP_SCALE: usize = 3;
+/// The color of heatmap rows that never executed.
+const COLD: [u8; 4] = [0x20, 0x20, 0x20, 0xFF];
+
+/// How often each address executed, and the time spent executing each kind
+/// of instruction.
+#[derive(Debug, Default)]
+pub struct Profile {
+    addresses: BTreeMap<u16, u64>, // Executions of each address
+    opcodes: BTreeMap<&'static str, (u64, Duration)>, // Executions of, and time on, each pattern
+    reads: BTreeSet<u16>,          // Addresses read as data, through I
+    writes: BTreeSet<u16>,         // Addresses written, through I
+}
+
+impl Profile {
+    /// Adds an execution of `opcode` at `address` that took `elapsed`.
+    pub fn record(&mut self, address: u16, opcode: Opcode, elapsed: Duration) {
+        *self.addresses.entry(address).or_default() += 1;
+        let (count, total) = self.opcodes.entry(opcode.pattern()).or_default();
+        *count += 1;
+        *total += elapsed;
+    }
+
+    /// Adds the memory `opcode` read or wrote through `I`, which was `i`
+    /// when it executed. Sprites and `FX65` read memory; `FX33` and `FX55`
+    /// write it.
+    pub fn access(&mut self, opcode: Opcode, i: u16) {
+        let (accessed, len) = match opcode {
+            Opcode::Draw { n, .. } => (&mut self.reads, u16::from(n)),
+            Opcode::LoadMemory { x } => (&mut self.reads, u16::from(x) + 1),
+            Opcode::Store { x } => (&mut self.writes, u16::from(x) + 1),
+            Opcode::Bcd { .. } => (&mut self.writes, 3),
+            _ => return,
+        };
+        let size = u16::try_from(Interpreter::MEMORY_SIZE).unwrap_or(u16::MAX);
+        accessed.extend((0..len).map(|offset| i.wrapping_add(offset) % size));
+    }
+
+    /// Whether `address` was read as data, by a sprite or `FX65`.
+    #[must_use]
+    pub fn read(&self, address: u16) -> bool {
+        self.reads.contains(&address)
+    }
+
+    /// Whether `address` was written, by `FX33` or `FX55`.
+    #[must_use]
+    pub fn written(&self, address: u16) -> bool {
+        self.writes.contains(&address)
+    }
+
+    /// The number of times the instruction at `address` executed.
+    #[must_use]
+    pub fn executions(&self, address: u16) -> u64 {
+        self.addresses.get(&address).copied().unwrap_or_default()
+    }
+
+    /// The number of executions of, and the time spent on, instructions
+    /// matching `pattern`, like `7XNN`.
+    #[must_use]
+    pub fn opcode(&self, pattern: &str) -> (u64, Duration) {
+        self.opcodes.get(pattern).copied().unwrap_or_default()
+    }
+
+    /// Writes a table of the time spent on each kind of instruction, the
+    /// slowest first.
+    pub(crate) fn write_opcodes(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut opcodes: Vec<(&str, u64, Duration)> = self
+            .opcodes
+            .iter()
+            .map(|(&pattern, &(count, total))| (pattern, count, total))
+            .collect();
+        opcodes.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+        let total: Duration = opcodes.iter().map(|&(_, _, total)| total).sum();
+        write!(f, "Opcodes:")?;
+        for (pattern, count, time) in opcodes {
+            #[allow(clippy::cast_precision_loss)]
+            let (share, per) = (
+                time.as_secs_f64() / total.as_secs_f64().max(f64::EPSILON) * 100.0,
+                time.as_secs_f64() * 1e9 / count as f64,
+            );
+            write!(
+                f,
+                "\n  {pattern}  {count:>12}  {share:>5.1}%  {per:>8.1} ns"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The results of profiling a ROM with [`run`].
+#[derive(Debug)]
+pub struct Report {
+    /// The number of instructions executed.
+    pub instructions: u64,
+    /// The number of frames those instructions took, at the timing used.
+    pub frames: u64,
+    /// How often each address executed, and the time spent on each kind of
+    /// instruction.
+    pub profile: Profile,
+    /// Labels to describe addresses with, from the ROM's symbol file.
+    pub symbols: SourceMap,
+    rom: Vec<u8>,    // The ROM profiled
+    memory: Vec<u8>, // Memory once the run ended, to disassemble hot spots
+}
+
+impl Report {
+    /// What every address in memory was used for during the run.
+    #[must_use]
+    pub fn map(&self) -> MemoryMap {
+        MemoryMap::new(&self.profile, self.rom.len())
+    }
+
+    /// Renders a heatmap of the ROM's disassembly as a PNG written to `out`:
+    /// a row per instruction, with its address, mnemonic, and executions,
+    /// colored from blue for the coldest to red for the hottest. Rows that
+    /// never executed are gray, and data is left out.
+    ///
+    /// # Errors
+    /// This function will error if `out` cannot be written to.
+    pub fn heatmap(&self, out: impl io::Write) -> io::Result<()> {
+        let hottest = self.profile.addresses.values().copied().max().unwrap_or(0);
+        let width = HEATMAP_COLUMNS * text::ADVANCE;
+        let mut rows = Vec::new();
+        for line in disasm::lines(&self.rom, &self.symbols) {
+            let Some(opcode) = line.opcode else {
+                continue;
+            };
+            if let Some(label) = &line.label {
+                rows.push((format!("{label}:"), COLD));
+            }
+            let executions = self.profile.executions(line.address);
+            let code = opcode.to_string();
+            rows.push((
+                format!("{:03X} {code:<24} {executions:>10}", line.address),
+                heat(executions, hottest),
+            ));
+        }
+
+        let mut pixels = Vec::with_capacity(rows.len() * text::LINE_HEIGHT * width * 4);
+        for (row, background) in rows {
+            let mut band: Vec<u8> = background.repeat(text::LINE_HEIGHT * width);
+            text::draw(&mut band, width, &[row], [0xFF; 4], background);
+            pixels.extend(band);
+        }
+        let height = pixels.len() / 4 / width;
+        let pixels = scale(&pixels, width);
+
+        let mut encoder = png::Encoder::new(
+            out,
+            u32::try_from(width * HEATMAP_SCALE).map_err(io::Error::other)?,
+            u32::try_from(height * HEATMAP_SCALE).map_err(io::Error::other)?,
+        );
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer.write_image_data(&pixels).map_err(io::Error::other)?;
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions:  {}", self.instructions)?;
+        writeln!(f, "Frames:        {}", self.frames)?;
+
+        let mut hot: Vec<(u16, u64)> = self
+            .profile
+            .addresses
+            .iter()
+            .map(|(&address, &count)| (address, count))
+            .collect();
+        hot.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        writeln!(f, "Hot spots:")?;
+        for &(address, count) in hot.iter().take(HOT_SPOTS) {
+            let byte = |address: u16| {
+                self.memory
+                    .get(usize::from(address))
+                    .copied()
+                    .unwrap_or_default()
+            };
+            let raw = u16::from_be_bytes([byte(address), byte(address.wrapping_add(1))]);
+            #[allow(clippy::cast_precision_loss)]
+            let share = count as f64 / self.instructions.max(1) as f64 * 100.0;
+            let code = crate::opcode::disassemble_word(address, raw);
+            write!(f, "  {code:<30}  {count:>12}  {share:>5.1}%")?;
+            if let Some(label) = self.symbols.describe(address) {
+                write!(f, "  <{label}>")?;
+            }
+            writeln!(f)?;
+        }
+        if hot.len() > HOT_SPOTS {
+            writeln!(f, "  ...")?;
+        }
+        self.profile.write_opcodes(f)
+    }
+}
+
+/// The color of a heatmap row for an instruction executed `executions`
+/// times, when the hottest was executed `hottest` times. The scale is
+/// logarithmic, so warm code stands out next to the hottest loop.
+fn heat(executions: u64, hottest: u64) -> [u8; 4] {
+    if executions == 0 {
+        return COLD;
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let t = (executions as f64).ln_1p() / (hottest as f64).ln_1p().max(f64::EPSILON);
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let mix = |cold: u8, hot: u8| (f64::from(cold) + (f64::from(hot) - f64::from(cold)) * t) as u8;
+    [mix(0x20, 0xC0), mix(0x30, 0x30), mix(0xA0, 0x20), 0xFF]
+}
+
+/// Scales RGBA `pixels`, in rows of `width`, up by [`HEATMAP_SCALE`].
+fn scale(pixels: &[u8], width: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(pixels.len() * HEATMAP_SCALE * HEATMAP_SCALE);
+    for row in pixels.chunks_exact(width * 4) {
+        let row: Vec<u8> = row
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel.repeat(HEATMAP_SCALE))
+            .collect();
+        for _ in 0..HEATMAP_SCALE {
+            out.extend_from_slice(&row);
+        }
+    }
+    out
+}
+
+/// Runs `rom` headlessly, as fast as possible, until at least `instructions`
+/// instructions have executed, counting how often each address executes and
+/// timing each instruction. Keys are pressed as in `replay`, which also
+/// decides the seed and timing, if there is one.
+///
+/// # Errors
+/// This function will error with the fault that stopped the run.
+pub fn run(
+    rom: &[u8],
+    instructions: u64,
+    timing: Timing,
+    quirks: Quirks,
+    replay: Option<Replay>,
+) -> Result<Report, Fault> {
+    let mut intr = Interpreter::new();
+    intr.with_quirks(quirks);
+    if let Some(replay) = replay {
+        intr.with_seed(replay.seed);
+        intr.with_timing(replay.timing);
+        intr.replay = Some(replay.entries);
+    } else {
+        intr.with_seed(0);
+        intr.with_timing(timing);
+    }
+    intr.load_rom(rom);
+    intr.profile = Some(Profile::default());
+
+    let mut frames = 0;
+    while intr.cycles < instructions {
+        intr.frame(frames)?;
+        frames += 1;
+    }
+
+    Ok(Report {
+        instructions: intr.cycles,
+        frames,
+        profile: intr.profile.take().unwrap_or_default(),
+        symbols: SourceMap::default(),
+        rom: rom.to_vec(),
+        memory: intr.memory.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asm::assemble_with_map;
+
+    #[test]
+    fn profiles() {
+        let source = "start:\n  CLS\nloop:\n  ADD V0, 1\n  SE V0, 0\n  JP loop\n  JP start\n";
+        let (rom, symbols) = assemble_with_map(source).unwrap();
+        let mut report = run(&rom, 1000, Timing::from_ips(600), Quirks::default(), None).unwrap();
+        report.symbols = symbols;
+        assert_eq!(report.instructions, 1000);
+        assert_eq!(report.profile.executions(0x200), 2);
+        assert_eq!(report.profile.executions(0x202), 333);
+        assert_eq!(report.profile.executions(0x208), 1);
+        assert_eq!(report.profile.opcode("7XNN").0, 333);
+
+        let text = report.to_string();
+        assert!(text.contains(
+            "Hot spots:\n  0x202  7001  ADD V0, 0x01                333   33.3%  <loop>\n"
+        ));
+        assert!(text.contains("\nOpcodes:\n"));
+
+        let mut png = Vec::new();
+        report.heatmap(&mut png).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let reader = decoder.read_info().unwrap();
+        // Two labels and five instructions.
+        assert_eq!(
+            reader.info().height as usize,
+            7 * text::LINE_HEIGHT * HEATMAP_SCALE
+        );
+        assert_eq!(heat(0, 10), COLD);
+        assert_eq!(heat(10, 10), [0xC0, 0x30, 0x20, 0xFF]);
+    }
+}
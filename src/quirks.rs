@@ -0,0 +1,257 @@
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use core::fmt;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Settings for behavior that differs between CHIP-8 interpreters, which
+/// some ROMs depend on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(default))]
+#[allow(clippy::struct_excessive_bools)] // they're independent quirks
+pub struct Quirks {
+    /// What happens when `FX1E` moves `I` past the end of memory.
+    pub index_overflow: IndexOverflow,
+    /// What happens when the PC moves past the end of memory.
+    pub pc_overflow: PcOverflow,
+    /// Which register `BNNN` adds to its address.
+    pub jump_offset: JumpOffset,
+    /// Which register `8XY6` and `8XYE` shift into `VX`.
+    pub shift: Shift,
+    /// Whether `8XY1`, `8XY2`, and `8XY3` reset `VF` to `0`, as the COSMAC
+    /// VIP's did, since it used `VF` to work them out.
+    pub vf_reset: bool,
+    /// Whether `FX1E` sets `VF` to `1` when it moves `I` past `0xFFF` and to
+    /// `0` when it doesn't, as the Amiga interpreter did. Otherwise it leaves
+    /// `VF` alone.
+    pub index_carry: bool,
+    /// Whether `DXYN` waits for the next frame, ending the current one, as
+    /// the COSMAC VIP waited for the display's vertical blank.
+    pub display_wait: bool,
+    /// Whether `00CN`, `00DN`, `00FB`, and `00FC` scroll half as far in low
+    /// resolution, as SUPER-CHIP 1.1 did, which drew low resolution at
+    /// double size on its 128x64 screen and scrolled that. Half a pixel
+    /// can't be shown, so odd distances round down.
+    pub half_scroll: bool,
+    /// How many subroutine calls can be nested before `2NNN` faults.
+    pub stack_depth: usize,
+}
+
+impl Quirks {
+    /// The stack depth of most interpreters since the HP-48 ones.
+    pub const STACK_DEPTH: usize = 16;
+}
+
+impl Default for Quirks {
+    fn default() -> Self {
+        Self {
+            index_overflow: IndexOverflow::default(),
+            pc_overflow: PcOverflow::default(),
+            jump_offset: JumpOffset::default(),
+            shift: Shift::default(),
+            vf_reset: false,
+            index_carry: false,
+            display_wait: false,
+            half_scroll: false,
+            stack_depth: Self::STACK_DEPTH,
+        }
+    }
+}
+
+/// How `I` behaves when `FX1E` adds enough to it to pass `0xFFF`. Whether
+/// `VF` is set is up to [`Quirks::index_carry`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum IndexOverflow {
+    /// `I` wraps around to the start of memory, keeping only its lower 12 bits
+    #[default]
+    Mask,
+    /// `I` keeps counting past the end of memory, and reading or writing
+    /// memory through it stops the interpreter with a fault
+    Fault,
+}
+
+/// How the PC behaves when a jump, skip, or the end of the last
+/// instruction takes it past `0xFFF`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum PcOverflow {
+    /// Instructions are fetched from the start of memory, as on hardware with
+    /// a 12-bit address bus
+    #[default]
+    Wrap,
+    /// Fetching an instruction past the end of memory stops the interpreter
+    /// with a fault
+    Fault,
+}
+
+/// The register `BNNN` adds to its address when it jumps.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum JumpOffset {
+    /// `BNNN` jumps to `NNN` plus `V0`, as on the COSMAC VIP
+    #[default]
+    V0,
+    /// `BXNN` jumps to `XNN` plus `VX`, as on CHIP-48 and SUPER-CHIP
+    Vx,
+}
+
+/// The register `8XY6` and `8XYE` shift, putting the result in `VX`.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Shift {
+    /// `VX` is shifted in place, ignoring `VY`, as on CHIP-48 and SUPER-CHIP
+    #[default]
+    Vx,
+    /// `VY` is shifted into `VX`, as on the COSMAC VIP
+    Vy,
+}
+
+/// A one-line summary, like `I overflow: mask, PC overflow: wrap, jump: V0,
+/// stack depth: 16`, followed by the quirks that are off by default which
+/// are on, e.g. `, shift: VY, VF reset, display wait, half scroll`.
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "I overflow: {}, PC overflow: {}, jump: {}, stack depth: {}",
+            self.index_overflow, self.pc_overflow, self.jump_offset, self.stack_depth
+        )?;
+        if self.shift != Shift::default() {
+            write!(f, ", shift: {}", self.shift)?;
+        }
+        if self.vf_reset {
+            write!(f, ", VF reset")?;
+        }
+        if self.index_carry {
+            write!(f, ", index carry")?;
+        }
+        if self.display_wait {
+            write!(f, ", display wait")?;
+        }
+        if self.half_scroll {
+            write!(f, ", half scroll")?;
+        }
+        Ok(())
+    }
+}
+
+impl IndexOverflow {
+    /// Resolves `address`, the result of offsetting `I`, to an index into
+    /// memory of `size` bytes, or `None` if it is out of bounds.
+    #[must_use]
+    pub fn resolve(self, address: usize, size: usize) -> Option<usize> {
+        match self {
+            Self::Mask => Some(address % size),
+            Self::Fault => (address < size).then_some(address),
+        }
+    }
+}
+
+impl PcOverflow {
+    /// Resolves the PC to the index of the instruction it points to in
+    /// memory of `size` bytes, or `None` if any of it is out of bounds.
+    #[must_use]
+    pub fn resolve(self, pc: usize, size: usize) -> Option<usize> {
+        match self {
+            Self::Wrap => Some(pc % size),
+            Self::Fault => (pc + 1 < size).then_some(pc),
+        }
+    }
+}
+
+impl fmt::Display for PcOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Wrap => write!(f, "wrap"),
+            Self::Fault => write!(f, "fault"),
+        }
+    }
+}
+
+impl fmt::Display for JumpOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V0 => write!(f, "V0"),
+            Self::Vx => write!(f, "VX"),
+        }
+    }
+}
+
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Vx => write!(f, "VX"),
+            Self::Vy => write!(f, "VY"),
+        }
+    }
+}
+
+impl fmt::Display for IndexOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Mask => write!(f, "mask"),
+            Self::Fault => write!(f, "fault"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve() {
+        assert_eq!(IndexOverflow::Mask.resolve(0xFFF, 0x1000), Some(0xFFF));
+        assert_eq!(IndexOverflow::Mask.resolve(0x1002, 0x1000), Some(0x002));
+        assert_eq!(IndexOverflow::Fault.resolve(0xFFF, 0x1000), Some(0xFFF));
+        assert_eq!(IndexOverflow::Fault.resolve(0x1002, 0x1000), None);
+        assert_eq!(PcOverflow::Wrap.resolve(0x1002, 0x1000), Some(0x002));
+        assert_eq!(PcOverflow::Fault.resolve(0xFFE, 0x1000), Some(0xFFE));
+        assert_eq!(PcOverflow::Fault.resolve(0xFFF, 0x1000), None);
+    }
+
+    #[test]
+    fn summary() {
+        assert_eq!(
+            Quirks::default().to_string(),
+            "I overflow: mask, PC overflow: wrap, jump: V0, stack depth: 16"
+        );
+        let quirks = Quirks {
+            index_overflow: IndexOverflow::Fault,
+            pc_overflow: PcOverflow::Fault,
+            jump_offset: JumpOffset::Vx,
+            shift: Shift::Vy,
+            vf_reset: true,
+            index_carry: true,
+            display_wait: true,
+            half_scroll: true,
+            stack_depth: 12,
+        };
+        assert_eq!(
+            quirks.to_string(),
+            "I overflow: fault, PC overflow: fault, jump: VX, stack depth: 12, shift: VY, \
+             VF reset, index carry, display wait, half scroll"
+        );
+    }
+}
@@ -0,0 +1,304 @@
+use crate::screen::{self, Resolution};
+use log::info;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// The factor each CHIP-8 pixel is scaled by in the written image.
+const SCALE: usize = 8;
+
+/// The animated image formats a recording can be written as.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    Gif,
+    Apng,
+}
+
+impl Format {
+    /// Determines the format from the extension of `path`. Paths ending in
+    /// `.png` or `.apng` are written as APNG, everything else as GIF.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("png") || ext.eq_ignore_ascii_case("apng") => {
+                Self::Apng
+            }
+            _ => Self::Gif,
+        }
+    }
+}
+
+/// Writes `frame` to `path` as a PNG, scaled up like recordings are.
+///
+/// # Errors
+/// This function will error if the file cannot be written.
+pub fn screenshot(frame: &crate::frame::Frame, path: &Path) -> io::Result<()> {
+    encode_png(frame, io::BufWriter::new(fs::File::create(path)?))
+}
+
+/// Encodes `frame` as a PNG written to `out`, scaled up like recordings are.
+///
+/// # Errors
+/// This function will error if `out` cannot be written to.
+pub fn encode_png(frame: &crate::frame::Frame, out: impl io::Write) -> io::Result<()> {
+    let (width, height) = (frame.width(), frame.height());
+    let pixels: Vec<u8> = frame.pixels().iter().map(|&on| u8::from(on)).collect();
+    let data: Vec<u8> = scale(&pixels, width).iter().map(|&p| p * 0xFF).collect();
+    let mut encoder = png::Encoder::new(
+        out,
+        u32::try_from(width * SCALE).map_err(io::Error::other)?,
+        u32::try_from(height * SCALE).map_err(io::Error::other)?,
+    );
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(io::Error::other)?;
+    writer.write_image_data(&data).map_err(io::Error::other)?;
+    writer.finish().map_err(io::Error::other)
+}
+
+/// Scales `pixels`, in rows of `width`, up by [`SCALE`], producing one byte
+/// per output pixel.
+fn scale(pixels: &[u8], width: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(pixels.len() * SCALE * SCALE);
+    for row in pixels.chunks_exact(width) {
+        let row: Vec<u8> = row
+            .iter()
+            .flat_map(|&p| std::iter::repeat_n(p, SCALE))
+            .collect();
+        for _ in 0..SCALE {
+            out.extend_from_slice(&row);
+        }
+    }
+    out
+}
+
+/// A single captured frame.
+#[derive(Debug)]
+struct Frame {
+    /// One byte per CHIP-8 pixel: `1` if on, `0` if off.
+    pixels: Vec<u8>,
+    /// The number of interpreter frames this frame stays on screen for.
+    frames: u64,
+}
+
+/// Captures display frames whenever the display changes and writes them
+/// out as an animated GIF or APNG once the recording is stopped. Frames are
+/// timed by interpreter frame rather than wall-clock time, so a recording
+/// plays back at emulated speed even if the host fell behind.
+#[derive(Debug)]
+pub struct Recorder {
+    width: usize,
+    height: usize,
+    frames_per_second: u32,
+    /// Where the next recording is written to. Falls back to a timestamped
+    /// file name in the working directory once used.
+    path: Option<PathBuf>,
+    frames: Vec<Frame>,
+    /// The interpreter frame number of the last capture.
+    last: Option<u64>,
+    active: bool,
+}
+
+impl Recorder {
+    /// Creates a new, inactive recorder for a display of `width` x `height`
+    /// pixels, updated `frames_per_second` times per second.
+    #[must_use]
+    pub fn new(width: usize, height: usize, frames_per_second: u32, path: Option<PathBuf>) -> Self {
+        Self {
+            width,
+            height,
+            frames_per_second,
+            path,
+            frames: Vec::new(),
+            last: None,
+            active: false,
+        }
+    }
+
+    /// Whether frames are currently being captured.
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Starts capturing frames, discarding any previously captured ones.
+    pub fn start(&mut self) {
+        self.frames.clear();
+        self.last = None;
+        self.active = true;
+        info!("Started recording");
+    }
+
+    /// Captures `rows`, the screen of `resolution` at the end of interpreter
+    /// frame number `frame`, if recording, packed as
+    /// [`View::rows`](crate::screen::View::rows) are. Frames identical to the
+    /// previous one extend its duration rather than being stored again. A
+    /// recording can only have one size, so if the screen changes size (when
+    /// a HIRES ROM is swapped in, or a ROM switches resolution), it starts
+    /// over at the new size.
+    pub fn capture(&mut self, frame: u64, resolution: Resolution, rows: &[u64]) {
+        if !self.active {
+            return;
+        }
+        let (width, height) = (
+            usize::from(resolution.width),
+            usize::from(resolution.height),
+        );
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| u8::from(screen::lit(rows, resolution, x, y)))
+            .collect();
+        if (width, height) != (self.width, self.height) {
+            (self.width, self.height) = (width, height);
+            info!("The screen changed size; restarting the recording");
+            self.start();
+        }
+        self.settle(frame);
+        if self.frames.last().is_some_and(|last| last.pixels == pixels) {
+            return;
+        }
+        self.frames.push(Frame { pixels, frames: 0 });
+    }
+
+    /// Stops recording and writes the captured frames, returning the path
+    /// written to. Returns `Ok(None)` if nothing was captured.
+    ///
+    /// # Errors
+    /// This function will error if the output file cannot be created or encoded.
+    pub fn stop(&mut self) -> io::Result<Option<PathBuf>> {
+        if let Some(last) = self.last {
+            self.settle(last + 1);
+        }
+        self.active = false;
+        if self.frames.is_empty() {
+            return Ok(None);
+        }
+
+        let path = self.path.take().unwrap_or_else(|| {
+            let secs = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs());
+            PathBuf::from(format!("etherea-{secs}.gif"))
+        });
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = io::BufWriter::new(fs::File::create(&path)?);
+        match Format::from_path(&path) {
+            Format::Gif => self.write_gif(file)?,
+            Format::Apng => self.write_apng(file)?,
+        }
+        info!("Wrote recording [frames: {}]", self.frames.len());
+        self.frames.clear();
+
+        Ok(Some(path))
+    }
+
+    /// Extends the last captured frame up until interpreter frame `now`.
+    fn settle(&mut self, now: u64) {
+        if let (Some(last), Some(frame)) = (self.last, self.frames.last_mut()) {
+            frame.frames += now.saturating_sub(last);
+        }
+        self.last = Some(now);
+    }
+
+    /// The on-screen duration of `frame` in milliseconds.
+    fn millis(&self, frame: &Frame) -> u64 {
+        frame.frames * 1000 / u64::from(self.frames_per_second.max(1))
+    }
+
+    /// Scales `pixels` up by [`SCALE`], producing one byte per output pixel.
+    fn scale(&self, pixels: &[u8]) -> Vec<u8> {
+        scale(pixels, self.width)
+    }
+
+    fn write_gif<W: io::Write>(&self, w: W) -> io::Result<()> {
+        let width = u16::try_from(self.width * SCALE).map_err(io::Error::other)?;
+        let height = u16::try_from(self.height * SCALE).map_err(io::Error::other)?;
+        let palette = [0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF];
+        let mut encoder =
+            gif::Encoder::new(w, width, height, &palette).map_err(io::Error::other)?;
+        encoder
+            .set_repeat(gif::Repeat::Infinite)
+            .map_err(io::Error::other)?;
+        for frame in &self.frames {
+            let mut out =
+                gif::Frame::from_indexed_pixels(width, height, &self.scale(&frame.pixels), None);
+            // GIF delays are measured in hundredths of a second.
+            out.delay = u16::try_from(self.millis(frame) / 10)
+                .unwrap_or(u16::MAX)
+                .max(2);
+            encoder.write_frame(&out).map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn write_apng<W: io::Write>(&self, w: W) -> io::Result<()> {
+        let width = u32::try_from(self.width * SCALE).map_err(io::Error::other)?;
+        let height = u32::try_from(self.height * SCALE).map_err(io::Error::other)?;
+        let frames = u32::try_from(self.frames.len()).map_err(io::Error::other)?;
+        let mut encoder = png::Encoder::new(w, width, height);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(frames, 0).map_err(io::Error::other)?;
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        for frame in &self.frames {
+            let delay = u16::try_from(self.millis(frame)).unwrap_or(u16::MAX).max(1);
+            writer
+                .set_frame_delay(delay, 1000)
+                .map_err(io::Error::other)?;
+            let data: Vec<u8> = self
+                .scale(&frame.pixels)
+                .iter()
+                .map(|&p| p * 0xFF)
+                .collect();
+            writer.write_image_data(&data).map_err(io::Error::other)?;
+        }
+        writer.finish().map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_from_extension() {
+        assert_eq!(Format::from_path(Path::new("out.gif")), Format::Gif);
+        assert_eq!(Format::from_path(Path::new("out.PNG")), Format::Apng);
+        assert_eq!(Format::from_path(Path::new("out.apng")), Format::Apng);
+        assert_eq!(Format::from_path(Path::new("out")), Format::Gif);
+    }
+
+    #[test]
+    fn captures_only_changes() {
+        let mut recorder = Recorder::new(2, 1, 60, None);
+        let (size, lit, left) = (Resolution::new(2, 1), u64::MAX, 1 << 63);
+        recorder.capture(0, size, &[lit]);
+        assert!(recorder.frames.is_empty());
+
+        recorder.start();
+        recorder.capture(1, size, &[lit]);
+        recorder.capture(2, size, &[lit]);
+        recorder.capture(3, size, &[lit]);
+        recorder.capture(4, size, &[left]);
+        recorder.capture(5, size, &[left]);
+        assert_eq!(recorder.frames.len(), 2);
+        assert_eq!(recorder.frames[0].frames, 3);
+        assert_eq!(recorder.frames[1].pixels, vec![1, 0]);
+        assert_eq!(recorder.millis(&recorder.frames[0]), 50);
+    }
+
+    #[test]
+    fn restarts_on_resize() {
+        let mut recorder = Recorder::new(2, 1, 60, None);
+        recorder.start();
+        recorder.capture(0, Resolution::new(2, 1), &[u64::MAX]);
+        recorder.capture(1, Resolution::new(2, 2), &[0; 2]);
+        assert_eq!(recorder.height, 2);
+        assert_eq!(recorder.frames.len(), 1);
+        assert_eq!(recorder.frames[0].pixels, vec![0; 4]);
+    }
+}
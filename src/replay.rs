@@ -0,0 +1,235 @@
+use crate::{
+    input::{KeyEvent, KEYPAD_2},
+    timing::Timing,
+    vip,
+};
+use alloc::{collections::VecDeque, format, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+/// The first line of every replay file.
+const HEADER: &str = "# etherea replay v1";
+
+/// A key event stamped with the number of instructions that had been
+/// executed when it was applied.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub cycle: u64,
+    pub event: KeyEvent,
+}
+
+#[cfg(feature = "std")]
+impl Entry {
+    /// Formats the entry as a single line of a replay file.
+    fn line(self) -> String {
+        let state = if self.event.pressed { "down" } else { "up" };
+        format!("{} {:X} {state}", self.cycle, self.event.key)
+    }
+}
+
+/// A recorded play session: the seed and timing model needed to reproduce
+/// the run, plus every key event in the order it was applied.
+///
+/// Replay files are plain text:
+///
+/// ```text
+/// # etherea replay v1
+/// seed 42
+/// timing 764587/60
+/// 1520 5 down
+/// 1610 5 up
+/// ```
+///
+/// Runs recorded in cycle-accurate mode add a `costs` line after the
+/// timing, with any instruction costs changed from the defaults, like
+/// `costs DXYN=30`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    pub seed: u64,
+    pub timing: Timing,
+    /// The instruction costs of a cycle-accurate run, if it was one.
+    pub cycle_costs: Option<vip::Costs>,
+    pub entries: VecDeque<Entry>,
+}
+
+#[cfg(feature = "std")]
+impl Replay {
+    /// Reads the replay file at `path`.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be read or is malformed.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|_| format!("Could not read file: '{}'", path.display()))?;
+        contents.parse()
+    }
+}
+
+impl core::str::FromStr for Replay {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines().enumerate().map(|(n, line)| (n + 1, line.trim()));
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err("Not an etherea replay file".into());
+        }
+
+        let mut seed = None;
+        let mut timing = None;
+        let mut cycle_costs = None;
+        let mut entries = VecDeque::new();
+        for (n, line) in lines.filter(|(_, line)| !line.is_empty()) {
+            let err = || format!("Malformed replay file at line {n}: '{line}'");
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts[..] {
+                ["seed", value] => seed = Some(value.parse().map_err(|_| err())?),
+                ["timing", value] => timing = Some(value.parse()?),
+                ["costs", ref overrides @ ..] => cycle_costs = Some(overrides.join(" ").parse()?),
+                [cycle, key, state] => {
+                    let key = u8::from_str_radix(key, 16)
+                        .ok()
+                        .filter(|&key| key < KEYPAD_2 + 0x10)
+                        .ok_or_else(err)?;
+                    let pressed = match state {
+                        "down" => true,
+                        "up" => false,
+                        _ => return Err(err()),
+                    };
+                    entries.push_back(Entry {
+                        cycle: cycle.parse().map_err(|_| err())?,
+                        event: KeyEvent { key, pressed },
+                    });
+                }
+                _ => return Err(err()),
+            }
+        }
+
+        Ok(Self {
+            seed: seed.ok_or("Replay file is missing a seed")?,
+            timing: timing.ok_or("Replay file is missing a timing model")?,
+            cycle_costs,
+            entries,
+        })
+    }
+}
+
+/// Writes key events to a replay file as they are applied, so the file is
+/// complete even if the process exits abruptly.
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct Journal {
+    writer: io::BufWriter<fs::File>,
+}
+
+#[cfg(feature = "std")]
+impl Journal {
+    /// Creates the replay file at `path` for a run with the given `seed`
+    /// and `timing`, and `cycle_costs` if it's cycle-accurate.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be created or written.
+    pub fn create(
+        path: &Path,
+        seed: u64,
+        timing: Timing,
+        cycle_costs: Option<&vip::Costs>,
+    ) -> io::Result<Self> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "seed {seed}")?;
+        writeln!(writer, "timing {timing}")?;
+        if let Some(costs) = cycle_costs {
+            writeln!(writer, "costs {costs}")?;
+        }
+        writer.flush()?;
+        Ok(Self { writer })
+    }
+
+    /// Appends `entry` to the replay file.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be written.
+    pub fn record(&mut self, entry: Entry) -> io::Result<()> {
+        writeln!(self.writer, "{}", entry.line())?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let replay: Replay =
+            "# etherea replay v1\nseed 42\ntiming 764587/60\n\n1520 5 down\n1610 A up\n"
+                .parse()
+                .unwrap();
+        assert_eq!(replay.seed, 42);
+        assert_eq!(replay.timing, Timing::from_ips(700));
+        assert_eq!(replay.cycle_costs, None);
+        assert_eq!(
+            Vec::from(replay.entries),
+            vec![
+                Entry {
+                    cycle: 1520,
+                    event: KeyEvent {
+                        key: 0x5,
+                        pressed: true
+                    }
+                },
+                Entry {
+                    cycle: 1610,
+                    event: KeyEvent {
+                        key: 0xA,
+                        pressed: false
+                    }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_cycle_costs() {
+        let replay: Replay = "# etherea replay v1\nseed 1\ntiming 1/60\ncosts DXYN=30 8XY4=50\n"
+            .parse()
+            .unwrap();
+        let costs = replay.cycle_costs.unwrap();
+        assert_eq!(costs.to_string(), "8XY4=50 DXYN=30");
+        let replay: Replay = "# etherea replay v1\nseed 1\ntiming 1/60\ncosts\n"
+            .parse()
+            .unwrap();
+        assert_eq!(replay.cycle_costs, Some(vip::Costs::default()));
+        assert!("# etherea replay v1\nseed 1\ntiming 1/60\ncosts DXYN"
+            .parse::<Replay>()
+            .is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!("seed 42".parse::<Replay>().is_err());
+        assert!("# etherea replay v1\ntiming 1/60"
+            .parse::<Replay>()
+            .is_err());
+        assert!("# etherea replay v1\nseed 1\ntiming 1/60\n5 20 down"
+            .parse::<Replay>()
+            .is_err());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn entry_line() {
+        let entry = Entry {
+            cycle: 7,
+            event: KeyEvent {
+                key: 0xC,
+                pressed: false,
+            },
+        };
+        assert_eq!(entry.line(), "7 C up");
+    }
+}
@@ -0,0 +1,175 @@
+use crate::debug::{self, hash};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// A range of memory a ROM keeps something worth saving in, like its score
+/// table, written `"0x3A0-0x3AF"` (both ends included) or as one address.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Span {
+    start: u16,
+    end: u16,
+}
+
+impl Span {
+    /// The addresses in the span.
+    #[must_use]
+    pub fn range(self) -> RangeInclusive<usize> {
+        usize::from(self.start)..=usize::from(self.end)
+    }
+}
+
+impl FromStr for Span {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s.split_once('-').unwrap_or((s, s));
+        match (
+            debug::parse_address(start.trim()),
+            debug::parse_address(end.trim()),
+        ) {
+            (Some(start), Some(end)) if start <= end => Ok(Self { start, end }),
+            _ => Err(format!(
+                "Invalid memory range '{s}', expected e.g. 0x3A0-0x3AF"
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for Span {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Span> for String {
+    fn from(span: Span) -> Self {
+        span.to_string()
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#05X}-{:#05X}", self.start, self.end)
+    }
+}
+
+/// The parts of a ROM's memory saved when it stops and restored when it is
+/// next loaded, in a file keyed by the ROM's hash, for games that keep high
+/// scores in memory with no way of their own to save them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Save {
+    path: PathBuf,
+    spans: Vec<Span>,
+}
+
+impl Save {
+    /// Saves `spans` of the memory of `rom` to a file in `dir`, which is
+    /// created when first saved to.
+    #[must_use]
+    pub fn new(dir: &Path, rom: &[u8], spans: Vec<Span>) -> Self {
+        let path = dir.join(format!("{:016x}.bin", hash(rom)));
+        Self { path, spans }
+    }
+
+    /// Saves `spans` of the memory of `rom` inside the user's data
+    /// directory, if there is one.
+    #[must_use]
+    pub fn open(rom: &[u8], spans: Vec<Span>) -> Option<Self> {
+        dirs::data_dir().map(|dir| Self::new(&dir.join("etherea").join("saves"), rom, spans))
+    }
+
+    /// The number of bytes saved.
+    fn len(&self) -> usize {
+        self.spans.iter().map(|span| span.range().count()).sum()
+    }
+
+    /// Copies the saved bytes into `memory`, if they have been saved.
+    /// Returns whether they were. Saves of other spans are ignored, with a
+    /// warning.
+    ///
+    /// # Errors
+    /// This function will error if the file exists but cannot be read.
+    pub fn restore(&self, memory: &mut [u8]) -> io::Result<bool> {
+        let saved = match fs::read(&self.path) {
+            Ok(saved) => saved,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(e) => return Err(e),
+        };
+        if saved.len() != self.len() {
+            warn!(
+                "Ignoring '{}', which was saved for other memory ranges",
+                self.path.display()
+            );
+            return Ok(false);
+        }
+        let mut bytes = saved.as_slice();
+        for span in &self.spans {
+            let (span_bytes, rest) = bytes.split_at(span.range().count());
+            memory[span.range()].copy_from_slice(span_bytes);
+            bytes = rest;
+        }
+        info!("Restored saved memory from '{}'", self.path.display());
+        Ok(true)
+    }
+
+    /// Saves the spans of `memory`.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be written.
+    pub fn write(&self, memory: &[u8]) -> io::Result<()> {
+        let bytes: Vec<u8> = self
+            .spans
+            .iter()
+            .flat_map(|span| memory[span.range()].iter().copied())
+            .collect();
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&self.path, bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spans() {
+        let span: Span = "0x3A0-0x3AF".parse().unwrap();
+        assert_eq!(span.range(), 0x3A0..=0x3AF);
+        assert_eq!(span.to_string(), "0x3A0-0x3AF");
+        assert_eq!("3A0".parse::<Span>().unwrap().range(), 0x3A0..=0x3A0);
+        assert!("0x3AF-0x3A0".parse::<Span>().is_err());
+        assert!("0x3A0-0x1000".parse::<Span>().is_err());
+    }
+
+    #[test]
+    fn saves() {
+        let dir = std::env::temp_dir().join(format!("etherea-saves-{}", std::process::id()));
+        let spans = vec!["0x300-0x301".parse().unwrap(), "0x310".parse().unwrap()];
+        let save = Save::new(&dir, b"game", spans);
+        let mut memory = [0; 0x1000];
+        assert!(!save.restore(&mut memory).unwrap());
+
+        memory[0x300..0x302].copy_from_slice(&[1, 2]);
+        memory[0x310] = 3;
+        save.write(&memory).unwrap();
+        let mut restored = [0; 0x1000];
+        assert!(save.restore(&mut restored).unwrap());
+        assert_eq!(restored, memory);
+
+        // A save of other ranges isn't restored.
+        let other = Save::new(&dir, b"game", vec!["0x300".parse().unwrap()]);
+        assert!(!other.restore(&mut [0; 0x1000]).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,512 @@
+use crate::{chip8x::ColorBoard, config::Palette, frame::Frame, keypad};
+use alloc::string::String;
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use core::{fmt, ops::Range};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::sync::{Arc, Mutex};
+
+/// Where the interpreter shows its screen: a window, or anything else
+/// attached with [`Interpreter::attach_display`](crate::Interpreter::attach_display).
+pub trait Screen: Send {
+    /// Shows `view`. Called once an instruction or frame has changed the
+    /// screen, and whenever the overlays on top of it change. Only the rows
+    /// in [`View::dirty`] changed since the last call, so screens that keep
+    /// what they showed can redraw just those.
+    fn render(&mut self, view: &View<'_>);
+
+    /// Changes the colors pixels are shown in, as the settings panel does.
+    /// Screens that don't color pixels can ignore it.
+    fn set_palette(&mut self, _palette: Palette) {}
+
+    /// Changes how the screen is scaled to fill the window. Screens without
+    /// a window can ignore it.
+    fn set_scaling(&mut self, _scaling: Scaling) {}
+}
+
+impl fmt::Debug for dyn Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Screen")
+    }
+}
+
+/// How the screen is scaled to fill a window of a different size.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum Scaling {
+    /// The biggest whole multiple of the screen's size that fits, so every pixel is the same
+    /// size, with black bars around it
+    #[default]
+    Integer,
+    /// As big as fits without changing the screen's shape, with black bars on two sides; pixels
+    /// may differ in size by a window pixel
+    Nearest,
+    /// Filling the whole window, smoothly interpolated
+    Stretch,
+}
+
+impl Scaling {
+    /// The mode after this one, wrapping around, for switching with a key.
+    #[must_use]
+    pub const fn next(self) -> Self {
+        match self {
+            Self::Integer => Self::Nearest,
+            Self::Nearest => Self::Stretch,
+            Self::Stretch => Self::Integer,
+        }
+    }
+
+    /// Where an image of `size` (width, height) is drawn in a window of
+    /// `window`, as `(x, y, width, height)`.
+    #[must_use]
+    pub fn fit(self, size: (u32, u32), window: (u32, u32)) -> (u32, u32, u32, u32) {
+        let ((width, height), (window_width, window_height)) = (size, window);
+        let (w, h) = match self {
+            _ if width == 0 || height == 0 => (0, 0),
+            Self::Integer => {
+                let scale = (window_width / width).min(window_height / height).max(1);
+                (width * scale, height * scale)
+            }
+            Self::Nearest
+                if u64::from(window_width) * u64::from(height)
+                    <= u64::from(window_height) * u64::from(width) =>
+            {
+                let h = u64::from(window_width) * u64::from(height) / u64::from(width);
+                (window_width, u32::try_from(h).unwrap_or(u32::MAX))
+            }
+            Self::Nearest => {
+                let w = u64::from(window_height) * u64::from(width) / u64::from(height);
+                (u32::try_from(w).unwrap_or(u32::MAX), window_height)
+            }
+            Self::Stretch => window,
+        };
+        (
+            window_width.saturating_sub(w) / 2,
+            window_height.saturating_sub(h) / 2,
+            w,
+            h,
+        )
+    }
+
+    /// Scales the RGBA `image`, of `size` (width, height), into `out`, the
+    /// RGBA pixels of a window of `window`, where [`fit`](Self::fit) places
+    /// it. The rest of the window is black.
+    pub fn scale(self, image: &[u8], size: (u32, u32), out: &mut [u8], window: (u32, u32)) {
+        self.scale_rows(image, size, 0..size.1, out, window);
+    }
+
+    /// Scales `rows` of the RGBA `image` as [`scale`](Self::scale) does,
+    /// writing only the pixels of `out` they're drawn to (and those near
+    /// them, when smoothly interpolated), so a window can be updated where
+    /// the screen changed. Scaling every row fills the rest of the window
+    /// with black, as [`scale`](Self::scale) does.
+    pub fn scale_rows(
+        self,
+        image: &[u8],
+        size: (u32, u32),
+        rows: Range<u32>,
+        out: &mut [u8],
+        window: (u32, u32),
+    ) {
+        const BLACK: [u8; 4] = [0, 0, 0, 0xFF];
+        let (left, top, w, h) = self.fit(size, window);
+        let (width, height) = (size.0 as usize, size.1 as usize);
+        let pixel = |x: usize, y: usize| {
+            let i = (y * width + x) * 4;
+            image.get(i..i + 4).unwrap_or(&BLACK)
+        };
+        let span = self.span(rows, size.1, (top, h), window.1);
+        let start = span.start as usize * window.0 as usize;
+        let end = (span.end as usize * window.0 as usize * 4).min(out.len());
+        let out = out.get_mut(start * 4..end).unwrap_or_default();
+        for (n, out) in out.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (
+                (start + n) % window.0 as usize,
+                (start + n) / window.0 as usize,
+            );
+            let (x, y) = (x.wrapping_sub(left as usize), y.wrapping_sub(top as usize));
+            if x >= w as usize || y >= h as usize {
+                out.copy_from_slice(&BLACK);
+                continue;
+            }
+            if self != Self::Stretch {
+                out.copy_from_slice(pixel(x * width / w as usize, y * height / h as usize));
+                continue;
+            }
+            // The point sampled, in 256ths of an image pixel, measured
+            // from pixel centers, and the weight of the next pixel over.
+            let sample = |at: usize, to: usize, of: usize| {
+                let at = ((2 * at + 1) * of * 256 / (2 * to)).saturating_sub(128);
+                let near = (at >> 8).min(of - 1);
+                (near, (near + 1).min(of - 1), at & 0xFF)
+            };
+            let (x0, x1, fx) = sample(x, w as usize, width);
+            let (y0, y1, fy) = sample(y, h as usize, height);
+            for (c, out) in out.iter_mut().enumerate() {
+                let mix = |a: &[u8], b: &[u8], f: usize| {
+                    (usize::from(a[c]) * (256 - f) + usize::from(b[c]) * f) >> 8
+                };
+                let top = mix(pixel(x0, y0), pixel(x1, y0), fx);
+                let bottom = mix(pixel(x0, y1), pixel(x1, y1), fx);
+                *out = u8::try_from((top * (256 - fy) + bottom * fy) >> 8).unwrap_or(u8::MAX);
+            }
+        }
+    }
+
+    /// The rows of a window `window_height` tall that `rows` of an image
+    /// `height` tall are drawn to, when it's placed at `top` and scaled to
+    /// `h` tall. Smooth interpolation blends each row with its neighbours,
+    /// so their rows are included too. All of them, if `rows` is the whole
+    /// image.
+    fn span(
+        self,
+        rows: Range<u32>,
+        height: u32,
+        (top, h): (u32, u32),
+        window_height: u32,
+    ) -> Range<u32> {
+        if rows.start == 0 && rows.end >= height || height == 0 {
+            return 0..window_height;
+        }
+        let grow = u32::from(self == Self::Stretch);
+        let at = |row: u32| {
+            let y = (u64::from(row) * u64::from(h)).div_ceil(u64::from(height));
+            top + u32::try_from(y).unwrap_or(u32::MAX)
+        };
+        let start = at(rows.start.saturating_sub(grow)).saturating_sub(grow);
+        let end = (at((rows.end + grow).min(height)) + grow).min(window_height);
+        start..end.max(start)
+    }
+}
+
+/// The size of a screen, in pixels. ROMs can switch between them as they
+/// run, as SUPER-CHIP ROMs do between its low and high resolution modes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Resolution {
+    /// The width of the screen, in pixels.
+    pub width: u16,
+    /// The height of the screen, in pixels.
+    pub height: u16,
+}
+
+impl Resolution {
+    /// The CHIP-8 screen.
+    pub const LORES: Self = Self::new(64, 32);
+    /// The screen of HIRES CHIP-8 ROMs.
+    pub const HIRES: Self = Self::new(64, 64);
+    /// The high resolution mode of SUPER-CHIP.
+    pub const SUPER: Self = Self::new(128, 64);
+    /// The screen of Mega-Chip ROMs.
+    pub const MEGA: Self = Self::new(256, 192);
+
+    /// A screen `width` by `height` pixels.
+    #[must_use]
+    pub const fn new(width: u16, height: u16) -> Self {
+        Self { width, height }
+    }
+
+    /// The number of pixels on the screen.
+    #[must_use]
+    pub const fn pixels(self) -> usize {
+        self.width as usize * self.height as usize
+    }
+
+    /// The number of `u64`s each row is packed into, a bit a pixel.
+    #[must_use]
+    pub const fn stride(self) -> usize {
+        (self.width as usize).div_ceil(64)
+    }
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self::LORES
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// The number of bit planes a screen has: two, as XO-CHIP's does, so each
+/// pixel is one of four colors.
+pub const PLANES: usize = 2;
+
+/// The color of the pixel at (`x`, `y`) in `rows`, a screen of `resolution`
+/// packed as [`View::rows`] are: a bit a plane, set if the pixel is lit in
+/// that plane, with the first plane in the lowest bit. Pixels off the screen
+/// are never lit.
+pub(crate) fn pixel(rows: &[u64], resolution: Resolution, x: usize, y: usize) -> u8 {
+    let len = resolution.stride() * usize::from(resolution.height);
+    if x >= usize::from(resolution.width) || len == 0 {
+        return 0;
+    }
+    let at = y * resolution.stride() + x / 64;
+    rows.chunks(len)
+        .enumerate()
+        .filter(|(_, plane)| {
+            plane
+                .get(at)
+                .is_some_and(|word| word >> (63 - x % 64) & 1 == 1)
+        })
+        .fold(0, |pixel, (plane, _)| pixel | 1 << plane)
+}
+
+/// Whether the pixel at (`x`, `y`) is lit in any plane of `rows`, as in
+/// [`pixel`].
+pub(crate) fn lit(rows: &[u64], resolution: Resolution, x: usize, y: usize) -> bool {
+    pixel(rows, resolution, x, y) != 0
+}
+
+/// What the interpreter shows each time it renders to a [`Screen`].
+#[derive(Copy, Clone, Debug)]
+pub struct View<'a> {
+    /// The size of the screen, which can change from one render to the
+    /// next.
+    pub resolution: Resolution,
+    /// The rows of the screen, top to bottom, a bit a pixel packed into
+    /// [`Resolution::stride`] `u64`s a row, with the leftmost pixel in the
+    /// highest bit of the first and lit pixels set. Each of the [`PLANES`]
+    /// planes follows the one before.
+    pub rows: &'a [u64],
+    /// The rows changed since the screen was last rendered, a bit a row with
+    /// the top row in the lowest bit of the first `u64`. Every row is dirty
+    /// when the resolution or the overlays change.
+    pub dirty: &'a [u64],
+    /// The lines of the debug overlay, if it is shown.
+    pub hud: Option<&'a [String]>,
+    /// The on-screen keypad, with the keys held, if it is shown.
+    pub keypad: Option<(&'a keypad::Overlay, &'a [bool])>,
+    /// The colors of a CHIP-8X ROM's screen.
+    pub colors: Option<&'a ColorBoard>,
+}
+
+impl View<'_> {
+    /// Whether the pixel at (`x`, `y`) is lit, in any plane. Pixels off the
+    /// screen never are.
+    #[must_use]
+    pub fn lit(&self, x: usize, y: usize) -> bool {
+        lit(self.rows, self.resolution, x, y)
+    }
+
+    /// The planes the pixel at (`x`, `y`) is lit in, a bit a plane with the
+    /// first in the lowest bit: `0` for unlit pixels, `1` for those lit in
+    /// only the first plane, and so on.
+    #[must_use]
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        pixel(self.rows, self.resolution, x, y)
+    }
+
+    /// The color of the pixel at (`x`, `y`), as RGBA: the CHIP-8X colors of
+    /// the ROM if it has them, and otherwise the color of `palette` for
+    /// the planes it's lit in.
+    #[must_use]
+    pub fn rgba(&self, palette: &Palette, x: usize, y: usize) -> [u8; 4] {
+        let pixel = self.pixel(x, y);
+        match self.colors {
+            Some(colors) if pixel != 0 => colors.foreground(x, y).rgba(),
+            Some(colors) => colors.background().rgba(),
+            None => palette.color(pixel).rgba(),
+        }
+    }
+
+    /// Whether row `y` changed since the screen was last rendered.
+    #[must_use]
+    pub fn is_dirty(&self, y: usize) -> bool {
+        self.dirty
+            .get(y / 64)
+            .is_some_and(|word| word >> (y % 64) & 1 == 1)
+    }
+
+    /// The screen, without the overlays.
+    #[must_use]
+    pub fn frame(&self) -> Frame {
+        let (width, height) = (
+            usize::from(self.resolution.width),
+            usize::from(self.resolution.height),
+        );
+        Frame::new(
+            self.resolution,
+            (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| self.lit(x, y))
+                .collect(),
+        )
+    }
+}
+
+/// A [`Screen`] that shows nothing, but keeps every frame rendered to it,
+/// for tests and programs without a window. Clones share what they keep,
+/// so one can be kept to look at it after attaching another.
+#[cfg(feature = "std")]
+#[derive(Clone, Debug, Default)]
+pub struct MockScreen {
+    frames: Arc<Mutex<Vec<Frame>>>,
+    palette: Arc<Mutex<Option<Palette>>>,
+}
+
+#[cfg(feature = "std")]
+impl MockScreen {
+    /// Creates a screen that hasn't been rendered to.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every frame rendered, oldest first.
+    ///
+    /// # Panics
+    /// This function panics if the interpreter panicked while rendering.
+    #[must_use]
+    pub fn frames(&self) -> Vec<Frame> {
+        self.frames.lock().unwrap().clone()
+    }
+
+    /// The frame rendered last, if any was.
+    ///
+    /// # Panics
+    /// This function panics if the interpreter panicked while rendering.
+    #[must_use]
+    pub fn last(&self) -> Option<Frame> {
+        self.frames.lock().unwrap().last().cloned()
+    }
+
+    /// The palette set last, if any was.
+    ///
+    /// # Panics
+    /// This function panics if the interpreter panicked while setting it.
+    #[must_use]
+    pub fn palette(&self) -> Option<Palette> {
+        *self.palette.lock().unwrap()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Screen for MockScreen {
+    fn render(&mut self, view: &View<'_>) {
+        self.frames.lock().unwrap().push(view.frame());
+    }
+
+    fn set_palette(&mut self, palette: Palette) {
+        *self.palette.lock().unwrap() = Some(palette);
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{quirks::Quirks, settings::Settings, Interpreter};
+
+    #[test]
+    fn mock_screen() {
+        let screen = MockScreen::new();
+        let mut intr = Interpreter::new();
+        intr.attach_display(Box::new(screen.clone()));
+        // I = font character 0, draw it, clear the screen.
+        intr.load_rom(&[0xF0, 0x29, 0xD0, 0x05, 0x00, 0xE0]);
+        let rendered = screen.frames().len();
+        intr.run_instructions(2).unwrap();
+        assert_eq!(screen.last(), Some(intr.framebuffer()));
+        assert_eq!(intr.framebuffer().lit(), 14);
+
+        intr.run_instructions(1).unwrap();
+        let frames = screen.frames();
+        assert_eq!(frames.len(), rendered + 2);
+        assert_eq!(frames[rendered].lit(), 14);
+        assert_eq!(frames[rendered + 1].lit(), 0);
+
+        let palette = Palette::PRESETS[1].1;
+        intr.apply(Settings {
+            palette,
+            ips: 700,
+            quirks: Quirks::default(),
+            volume: 0.2,
+        });
+        assert_eq!(screen.palette(), Some(palette));
+    }
+
+    #[test]
+    fn batches_draws() {
+        /// Keeps the rows dirty in each render.
+        struct Dirty(Arc<Mutex<alloc::vec::Vec<u64>>>);
+        impl Screen for Dirty {
+            fn render(&mut self, view: &View<'_>) {
+                self.0.lock().unwrap().push(view.dirty[0]);
+            }
+        }
+        let dirty = Arc::new(Mutex::new(alloc::vec::Vec::new()));
+        let mut intr = Interpreter::new();
+        intr.attach_display(Box::new(Dirty(Arc::clone(&dirty))));
+        // Draw font character 0 at rows 0 and 10, and loop.
+        intr.load_rom(&[0xF0, 0x29, 0x61, 0x0A, 0xD0, 0x05, 0xD0, 0x15, 0x12, 0x08]);
+        assert_eq!(dirty.lock().unwrap().split_off(0), [u64::from(u32::MAX)]);
+        // Both sprites are shown at once, with only the rows they drew
+        // dirty, and nothing is shown while the ROM loops.
+        intr.run_frames(2).unwrap();
+        let rows = 0b1_1111 | 0b1_1111 << 10;
+        assert_eq!(dirty.lock().unwrap().split_off(0), [rows]);
+        // Clearing the screen redraws only the rows that were lit.
+        intr.execute_opcode(0x00E0).unwrap();
+        intr.execute_opcode(0x00E0).unwrap();
+        assert_eq!(dirty.lock().unwrap().split_off(0), [rows]);
+    }
+
+    #[test]
+    fn scaling() {
+        let (size, window) = ((64, 32), (200, 120));
+        assert_eq!(Scaling::Integer.fit(size, window), (4, 12, 192, 96));
+        assert_eq!(Scaling::Nearest.fit(size, window), (0, 10, 200, 100));
+        assert_eq!(Scaling::Nearest.fit(size, (100, 20)), (30, 0, 40, 20));
+        assert_eq!(Scaling::Stretch.fit(size, window), (0, 0, 200, 120));
+        // Windows smaller than the screen still show all of it.
+        assert_eq!(Scaling::Integer.fit(size, (32, 16)), (0, 0, 64, 32));
+        assert_eq!(Scaling::Stretch.next().next(), Scaling::Nearest);
+
+        // A white pixel to the left of a black one.
+        let image = [[0xFF; 4], [0, 0, 0, 0xFF]].concat();
+        let scaled = |scaling: Scaling, window: (u32, u32)| {
+            let mut out = alloc::vec![0; (window.0 * window.1 * 4) as usize];
+            scaling.scale(&image, (2, 1), &mut out, window);
+            out.chunks_exact(4)
+                .map(|pixel| pixel[0])
+                .collect::<alloc::vec::Vec<_>>()
+        };
+        assert_eq!(scaled(Scaling::Integer, (5, 1)), [0, 0xFF, 0, 0, 0]);
+        assert_eq!(
+            scaled(Scaling::Nearest, (5, 3)),
+            [0xFF, 0xFF, 0xFF, 0, 0, 0xFF, 0xFF, 0xFF, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(scaled(Scaling::Stretch, (4, 1)), [0xFF, 0xBF, 0x3F, 0]);
+
+        // Scaling some rows over the whole image changes just the window
+        // pixels they're drawn to.
+        let size = (3, 8);
+        let image: alloc::vec::Vec<u8> = (0..3 * 8 * 4_u8).map(|n| n.wrapping_mul(37)).collect();
+        for scaling in [Scaling::Integer, Scaling::Nearest, Scaling::Stretch] {
+            for window in [(7, 29), (5, 5), (3, 8), (11, 3)] {
+                let mut full = alloc::vec![0; (window.0 * window.1 * 4) as usize];
+                scaling.scale(&image, size, &mut full, window);
+                for rows in [0..1, 2..5, 7..8] {
+                    let mut changed = image.clone();
+                    for pixel in &mut changed[rows.start as usize * 12..rows.end as usize * 12] {
+                        *pixel = !*pixel;
+                    }
+                    let mut partial = full.clone();
+                    scaling.scale_rows(&changed, size, rows, &mut partial, window);
+                    let mut expected = full.clone();
+                    scaling.scale(&changed, size, &mut expected, window);
+                    assert_eq!(partial, expected, "{scaling:?} in {window:?}");
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,517 @@
+use crate::{input::KeyEvent, opcode::Opcode, Interpreter};
+use alloc::{boxed::Box, vec::Vec};
+use core::fmt;
+#[cfg(feature = "scripting")]
+use log::error;
+#[cfg(feature = "scripting")]
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+/// Code that watches the interpreter as it runs, and may change its
+/// registers and memory: cheats, auto-splitters, bots, or instrumentation
+/// for experiments. Every hook does nothing unless overridden.
+pub trait Script: Send {
+    /// Called before each instruction is executed, once the program counter
+    /// has moved past it.
+    fn on_instruction(&mut self, _machine: &mut Machine<'_>, _opcode: Opcode) {}
+
+    /// Called at the end of every frame, after the timers are updated.
+    fn on_frame(&mut self, _machine: &mut Machine<'_>, _frame: u64) {}
+
+    /// Called after a key is pressed or released.
+    fn on_key(&mut self, _machine: &mut Machine<'_>, _event: KeyEvent) {}
+
+    /// Called after a sprite is drawn.
+    fn on_draw(&mut self, _machine: &mut Machine<'_>) {}
+}
+
+/// The parts of the interpreter a [`Script`] can see and change.
+pub struct Machine<'a> {
+    intr: &'a mut Interpreter,
+}
+
+impl Machine<'_> {
+    /// The address of the next instruction.
+    #[must_use]
+    pub fn pc(&self) -> usize {
+        self.intr.pc
+    }
+
+    /// The index register, `I`.
+    #[must_use]
+    pub fn i(&self) -> u16 {
+        self.intr.i
+    }
+
+    /// Sets the index register, `I`.
+    pub fn set_i(&mut self, i: u16) {
+        self.intr.i = i;
+    }
+
+    /// The variable registers, `V0` to `VF`.
+    #[must_use]
+    pub fn registers(&self) -> &[u8] {
+        &self.intr.registers[..]
+    }
+
+    /// Sets register `Vx` to `value`.
+    ///
+    /// # Panics
+    /// This function panics if `x` isn't a register, i.e. is over `0xF`.
+    pub fn set_register(&mut self, x: usize, value: u8) {
+        self.intr.registers[x] = value;
+    }
+
+    /// The whole of memory.
+    #[must_use]
+    pub fn memory(&self) -> &[u8] {
+        &self.intr.memory[..]
+    }
+
+    /// Writes `bytes` to memory, starting at `address`.
+    ///
+    /// # Panics
+    /// This function panics if the bytes don't fit in memory.
+    pub fn write(&mut self, address: usize, bytes: &[u8]) {
+        let range = address..address + bytes.len();
+        self.intr.memory[range.clone()].copy_from_slice(bytes);
+        self.intr.forget(range);
+    }
+
+    /// The number of instructions executed since the ROM was loaded.
+    #[must_use]
+    pub fn cycles(&self) -> u64 {
+        self.intr.cycles
+    }
+}
+
+/// The scripts attached to an interpreter.
+#[derive(Default)]
+pub(crate) struct Scripts(Vec<Box<dyn Script>>);
+
+impl fmt::Debug for Scripts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Scripts({})", self.0.len())
+    }
+}
+
+impl Scripts {
+    /// Adds `script` to those run by `intr`.
+    pub(crate) fn attach(&mut self, script: Box<dyn Script>) {
+        self.0.push(script);
+    }
+
+    /// Calls `hook` on each script attached to `intr`.
+    pub(crate) fn run(
+        intr: &mut Interpreter,
+        mut hook: impl FnMut(&mut dyn Script, &mut Machine<'_>),
+    ) {
+        if intr.scripts.0.is_empty() {
+            return;
+        }
+        let mut scripts = core::mem::take(&mut intr.scripts);
+        for script in &mut scripts.0 {
+            hook(script.as_mut(), &mut Machine { intr });
+        }
+        intr.scripts = scripts;
+    }
+}
+
+/// The hooks a [`Rhai`] script can define, and how many arguments each takes.
+#[cfg(feature = "scripting")]
+const HOOKS: [(&str, usize); 4] = [
+    ("on_instruction", 1),
+    ("on_frame", 1),
+    ("on_key", 2),
+    ("on_draw", 0),
+];
+
+/// A [`Script`] written in [Rhai](https://rhai.rs), which hooks into the
+/// interpreter by defining any of `on_instruction(opcode)`, given the
+/// instruction's mnemonic, `on_frame(frame)`, `on_key(key, pressed)`, and
+/// `on_draw()`. They see the machine through `pc()`, `i()`, `set_i(i)`,
+/// `register(x)`, `set_register(x, value)`, `peek(address)`,
+/// `poke(address, value)`, and `cycles()`. A script that fails is logged,
+/// and not run again.
+#[cfg(feature = "scripting")]
+pub struct Rhai {
+    engine: rhai::Engine,
+    ast: rhai::AST,
+    scope: rhai::Scope<'static>,
+    hooks: Vec<&'static str>, // The hooks the script defines, until it fails
+    state: Arc<Mutex<State>>, // What the script's functions see of the machine
+    peeks: bool,              // Whether the script may read memory, which is copied if so
+}
+
+/// A copy of the machine for a [`Rhai`] script to read, and the changes it
+/// makes, written back once its hook returns. Memory is only copied for
+/// scripts that read it.
+#[cfg(feature = "scripting")]
+#[derive(Default)]
+struct State {
+    pc: usize,
+    i: u16,
+    registers: Vec<u8>,
+    memory: Vec<u8>,
+    cycles: u64,
+    writes: BTreeMap<usize, u8>,
+}
+
+#[cfg(feature = "scripting")]
+impl State {
+    /// Copies `machine` in, with its memory if `peeks`, forgetting the last
+    /// hook's writes.
+    fn load(&mut self, machine: &Machine<'_>, peeks: bool) {
+        self.pc = machine.pc();
+        self.i = machine.i();
+        self.registers.clear();
+        self.registers.extend_from_slice(machine.registers());
+        if peeks {
+            self.memory.clear();
+            self.memory.extend_from_slice(machine.memory());
+        }
+        self.cycles = machine.cycles();
+        self.writes.clear();
+    }
+
+    /// Writes what the script changed back to `machine`.
+    fn store(&self, machine: &mut Machine<'_>) {
+        machine.set_i(self.i);
+        for (x, &value) in self.registers.iter().enumerate() {
+            machine.set_register(x, value);
+        }
+        for (&address, &byte) in &self.writes {
+            machine.write(address, &[byte]);
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+type Fallible<T> = Result<T, Box<rhai::EvalAltResult>>;
+
+#[cfg(feature = "scripting")]
+fn lock(state: &Mutex<State>) -> MutexGuard<'_, State> {
+    state.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// `value` as an index into something `len` long, named `what` in the error.
+#[cfg(feature = "scripting")]
+fn index(value: i64, len: usize, what: &str) -> Fallible<usize> {
+    usize::try_from(value)
+        .ok()
+        .filter(|&n| n < len)
+        .ok_or_else(|| format!("There is no {what} {value:#X}").into())
+}
+
+#[cfg(feature = "scripting")]
+fn byte(value: i64) -> Fallible<u8> {
+    u8::try_from(value).map_err(|_| format!("{value} doesn't fit in a byte").into())
+}
+
+#[cfg(feature = "scripting")]
+impl Rhai {
+    /// Compiles `source`, and runs its top level once.
+    ///
+    /// # Errors
+    /// This function will error if `source` doesn't compile, or its top level
+    /// fails.
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let state = Arc::new(Mutex::new(State::default()));
+        let mut engine = rhai::Engine::new();
+        Self::bind(&mut engine, &state);
+        let ast = engine.compile(source).map_err(|e| e.to_string())?;
+        let hooks = HOOKS
+            .into_iter()
+            .filter(|&(name, arity)| {
+                ast.iter_functions()
+                    .any(|f| f.name == name && f.params.len() == arity)
+            })
+            .map(|(name, _)| name)
+            .collect();
+        let peeks = Self::peeks(&ast);
+        let mut scope = rhai::Scope::new();
+        engine
+            .run_ast_with_scope(&mut scope, &ast)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            engine,
+            ast,
+            scope,
+            hooks,
+            state,
+            peeks,
+        })
+    }
+
+    /// Whether `ast` may read memory: whether it calls `peek`, or names it
+    /// to call through a function pointer.
+    fn peeks(ast: &rhai::AST) -> bool {
+        use rhai::{ASTNode, Expr, Stmt};
+        !ast.walk(&mut |path: &[ASTNode<'_>]| match path.last() {
+            Some(
+                ASTNode::Expr(Expr::FnCall(call, _) | Expr::MethodCall(call, _))
+                | ASTNode::Stmt(Stmt::FnCall(call, _)),
+            ) => call.name != "peek",
+            Some(ASTNode::Expr(Expr::StringConstant(name, _))) => name != "peek",
+            Some(ASTNode::Expr(Expr::DynamicConstant(value, _))) => value
+                .read_lock::<rhai::FnPtr>()
+                .is_none_or(|f| f.fn_name() != "peek"),
+            _ => true,
+        })
+    }
+
+    /// Reads and compiles the script at `path`.
+    ///
+    /// # Errors
+    /// This function will error if the file can't be read, or the script
+    /// doesn't compile.
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let source = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read '{}': {e}", path.display()))?;
+        Self::compile(&source)
+            .map_err(|e| format!("Could not load the script '{}': {e}", path.display()))
+    }
+
+    /// Gives scripts compiled by `engine` the machine in `state`.
+    fn bind(engine: &mut rhai::Engine, state: &Arc<Mutex<State>>) {
+        let s = Arc::clone(state);
+        engine.register_fn("pc", move || i64::try_from(lock(&s).pc).unwrap_or_default());
+        let s = Arc::clone(state);
+        engine.register_fn("i", move || i64::from(lock(&s).i));
+        let s = Arc::clone(state);
+        engine.register_fn("set_i", move |i: i64| -> Fallible<()> {
+            lock(&s).i = u16::try_from(i).map_err(|_| format!("{i} doesn't fit in I"))?;
+            Ok(())
+        });
+        let s = Arc::clone(state);
+        engine.register_fn("register", move |x: i64| -> Fallible<i64> {
+            let state = lock(&s);
+            Ok(i64::from(
+                state.registers[index(x, state.registers.len(), "register")?],
+            ))
+        });
+        let s = Arc::clone(state);
+        engine.register_fn("set_register", move |x: i64, value: i64| -> Fallible<()> {
+            let mut state = lock(&s);
+            let x = index(x, state.registers.len(), "register")?;
+            state.registers[x] = byte(value)?;
+            Ok(())
+        });
+        let s = Arc::clone(state);
+        engine.register_fn("peek", move |address: i64| -> Fallible<i64> {
+            let state = lock(&s);
+            let address = index(address, Interpreter::MEMORY_SIZE, "address")?;
+            // Memory isn't copied for scripts that don't seem to read it.
+            let byte = (state.writes.get(&address)).or_else(|| state.memory.get(address));
+            byte.map(|&byte| i64::from(byte))
+                .ok_or_else(|| "Memory can only be read by calling peek by name".into())
+        });
+        let s = Arc::clone(state);
+        engine.register_fn("poke", move |address: i64, value: i64| -> Fallible<()> {
+            let address = index(address, Interpreter::MEMORY_SIZE, "address")?;
+            lock(&s).writes.insert(address, byte(value)?);
+            Ok(())
+        });
+        let s = Arc::clone(state);
+        engine.register_fn("cycles", move || {
+            i64::try_from(lock(&s).cycles).unwrap_or(i64::MAX)
+        });
+    }
+
+    /// Calls the script's `hook` with `args`, if it defines it.
+    fn call(&mut self, machine: &mut Machine<'_>, hook: &'static str, args: impl rhai::FuncArgs) {
+        if !self.hooks.contains(&hook) {
+            return;
+        }
+        lock(&self.state).load(machine, self.peeks);
+        let options = rhai::CallFnOptions::new()
+            .eval_ast(false)
+            .rewind_scope(false);
+        match self.engine.call_fn_with_options::<rhai::Dynamic>(
+            options,
+            &mut self.scope,
+            &self.ast,
+            hook,
+            args,
+        ) {
+            Ok(_) => lock(&self.state).store(machine),
+            Err(e) => {
+                error!("The script's {hook} failed, so it won't be run again: {e}");
+                self.hooks.clear();
+            }
+        }
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl fmt::Debug for Rhai {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Rhai({:?})", self.hooks)
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl Script for Rhai {
+    fn on_instruction(&mut self, machine: &mut Machine<'_>, opcode: Opcode) {
+        self.call(machine, "on_instruction", (opcode.to_string(),));
+    }
+
+    fn on_frame(&mut self, machine: &mut Machine<'_>, frame: u64) {
+        let frame = i64::try_from(frame).unwrap_or(i64::MAX);
+        self.call(machine, "on_frame", (frame,));
+    }
+
+    fn on_key(&mut self, machine: &mut Machine<'_>, event: KeyEvent) {
+        let args = (i64::from(event.key), event.pressed);
+        self.call(machine, "on_key", args);
+    }
+
+    fn on_draw(&mut self, machine: &mut Machine<'_>) {
+        self.call(machine, "on_draw", ());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Keeps `V0` at 9, and records the hooks it sees.
+    struct Lives(Arc<Mutex<Vec<String>>>);
+
+    impl Script for Lives {
+        fn on_instruction(&mut self, machine: &mut Machine<'_>, opcode: Opcode) {
+            machine.set_register(0, 9);
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{:03X} {opcode}", machine.pc()));
+        }
+
+        fn on_frame(&mut self, machine: &mut Machine<'_>, frame: u64) {
+            let cycles = machine.cycles();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("frame {frame} at {cycles}"));
+        }
+
+        fn on_key(&mut self, machine: &mut Machine<'_>, event: KeyEvent) {
+            // Turns the next instruction into V1 = key.
+            machine.write(machine.pc(), &[0x61, event.key]);
+        }
+
+        fn on_draw(&mut self, machine: &mut Machine<'_>) {
+            let vf = machine.registers()[0xF];
+            self.0.lock().unwrap().push(format!("draw VF={vf}"));
+        }
+    }
+
+    #[test]
+    fn hooks() {
+        let rom = [
+            0x70, 0xFF, // V0 += 0xFF
+            0xD0, 0x01, // draw a 1-row sprite
+            0x61, 0x00, // V1 = 0
+        ];
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.attach_script(Lives(Arc::clone(&log)));
+        intr.run_instructions(2).unwrap();
+        intr.handle_key(KeyEvent {
+            key: 7,
+            pressed: true,
+        });
+        intr.run_instructions(1).unwrap();
+        intr.end_frame(0);
+
+        assert_eq!(intr.registers[..2], [9, 7]);
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                "202 ADD V0, 0xFF",
+                "204 DRW V0, V0, 1",
+                "draw VF=0",
+                "206 LD V1, 0x07",
+                "frame 0 at 3",
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "scripting")]
+    fn rhai() {
+        let rom = [
+            0x70, 0x01, // V0 += 1
+            0xD0, 0x01, // draw a 1-row sprite
+            0x61, 0x00, // V1 = 0
+            0x12, 0x06, // loop here
+        ];
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        let script = Rhai::compile(
+            r#"
+            // Top-level code runs once, when the script is loaded.
+            let unused = 1;
+            fn on_instruction(opcode) {
+                if opcode == "ADD V0, 0x01" {
+                    set_register(0, 41);
+                }
+            }
+            fn on_draw() {
+                poke(0x300, register(0) + 1);
+                set_i(pc());
+            }
+            fn on_key(key, pressed) {
+                // Turns the next instruction into V1 = key.
+                if pressed {
+                    poke(pc(), 0x61);
+                    poke(pc() + 1, key);
+                }
+            }
+            fn on_frame(frame) {
+                set_register(2, frame + cycles());
+            }
+            "#,
+        )
+        .unwrap();
+        intr.attach_script(script);
+        intr.run_instructions(2).unwrap();
+        intr.handle_key(KeyEvent {
+            key: 7,
+            pressed: true,
+        });
+        intr.run_instructions(1).unwrap();
+        intr.end_frame(4);
+
+        assert_eq!(intr.registers[..3], [42, 7, 7]);
+        assert_eq!((intr.memory[0x300], intr.i), (43, 0x204));
+
+        // Memory is only copied for scripts that read it, which see their
+        // own writes.
+        let peeks = |source| Rhai::compile(source).unwrap().peeks;
+        assert!(!peeks("fn on_draw() { poke(0x300, peek) }"));
+        assert!(peeks(
+            "fn on_draw() { let f = Fn(\"peek\"); f.call(0x200) }"
+        ));
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        let script = "fn on_draw() { poke(0x300, 5); set_register(3, peek(0x300) + peek(0x200)) }";
+        intr.attach_script(Rhai::compile(script).unwrap());
+        intr.run_instructions(2).unwrap();
+        assert_eq!(intr.registers[3], 5 + 0x70);
+
+        // A script that fails is stopped, leaving the machine as it was.
+        let mut intr = Interpreter::new();
+        intr.load_rom(&rom);
+        intr.attach_script(Rhai::compile("fn on_instruction(opcode) { poke(0x1000, 1) }").unwrap());
+        intr.run_instructions(2).unwrap();
+        assert_eq!(intr.registers[0], 1);
+        assert!(Rhai::compile("fn on_frame(").is_err());
+    }
+}
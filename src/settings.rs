@@ -0,0 +1,569 @@
+#[cfg(feature = "gui")]
+use crate::{
+    config::Config,
+    input::Keymap,
+    quirks::{IndexOverflow, JumpOffset, PcOverflow, Shift},
+    text,
+    timing::Timing,
+};
+use crate::{config::Palette, quirks::Quirks};
+#[cfg(feature = "gui")]
+use std::collections::HashMap;
+#[cfg(feature = "gui")]
+use winit::event::VirtualKeyCode;
+#[cfg(feature = "gui")]
+use winit_input_helper::{TextChar, WinitInputHelper};
+
+/// The settings the panel changes while a ROM runs, sent to the interpreter
+/// with [`Event::Settings`](crate::Event::Settings).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// The colors pixels are drawn in.
+    pub palette: Palette,
+    /// The number of instructions executed per second.
+    pub ips: u64,
+    /// Behavior that differs between interpreters.
+    pub quirks: Quirks,
+    /// How loud the beep is, from `0.0` to `1.0`.
+    pub volume: f32,
+}
+
+/// A key for the panel, read from the window.
+#[cfg(feature = "gui")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Input {
+    /// Selects the setting above.
+    Up,
+    /// Selects the setting below.
+    Down,
+    /// Changes the selected setting to its previous value.
+    Left,
+    /// Changes the selected setting to its next value.
+    Right,
+    /// A letter or digit, which rebinds a key when a row of keys is selected.
+    Char(char),
+}
+
+/// A line of the panel.
+#[cfg(feature = "gui")]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Row {
+    Palette,
+    Speed,
+    Volume,
+    IndexOverflow,
+    PcOverflow,
+    JumpOffset,
+    Shift,
+    VfReset,
+    IndexCarry,
+    HalfScroll,
+    DisplayWait,
+    StackDepth,
+    Keys(usize), // a row of the keymap
+}
+
+#[cfg(feature = "gui")]
+const ROWS: [Row; 16] = [
+    Row::Palette,
+    Row::Speed,
+    Row::Volume,
+    Row::IndexOverflow,
+    Row::PcOverflow,
+    Row::JumpOffset,
+    Row::Shift,
+    Row::VfReset,
+    Row::IndexCarry,
+    Row::HalfScroll,
+    Row::DisplayWait,
+    Row::StackDepth,
+    Row::Keys(0),
+    Row::Keys(1),
+    Row::Keys(2),
+    Row::Keys(3),
+];
+
+/// The settings panel, drawn over the screen while `F2` toggles it open.
+/// The arrow keys choose and change settings, which apply at once, and
+/// typing four letters or digits on a row of keys rebinds it.
+#[cfg(feature = "gui")]
+#[derive(Clone, Debug)]
+pub struct Panel {
+    settings: Settings,
+    keymap: Vec<String>, // the rows of the keymap, as in the config file
+    second_keypad: bool, // whether the CHIP-8X second keypad is mapped too
+    open: bool,          // whether the panel is shown
+    selected: usize,     // the index of the selected row
+    typed: String,       // keys typed so far for the selected row of keys
+    lines: usize,        // the number of lines that fit on the screen
+}
+
+#[cfg(feature = "gui")]
+impl Panel {
+    /// The amount the speed changes by, in instructions per second.
+    const IPS_STEP: u64 = 50;
+    /// The number of steps the volume changes in, from silent to full.
+    const VOLUME_STEPS: f32 = 10.0;
+
+    /// Creates a closed panel for a ROM running with `timing` and `quirks`,
+    /// and the palette, keymap, and scale in `config`.
+    #[must_use]
+    pub fn new(config: &Config, timing: Timing, quirks: Quirks) -> Self {
+        let detail = usize::try_from(text::detail(config.scale)).unwrap_or(1);
+        Self {
+            settings: Settings {
+                palette: config.palette,
+                ips: timing.cycles_before(u64::from(timing.frames_per_second)),
+                quirks,
+                volume: config.beep.volume,
+            },
+            keymap: config.keymap.clone().into(),
+            second_keypad: false,
+            open: false,
+            selected: 0,
+            typed: String::new(),
+            lines: usize::from(crate::screen::Resolution::LORES.height) * detail
+                / text::LINE_HEIGHT,
+        }
+    }
+
+    /// Maps the CHIP-8X second keypad to the numeric keypad, along with the
+    /// keymap.
+    pub fn with_second_keypad(&mut self) {
+        self.second_keypad = true;
+    }
+
+    /// Shows and rebinds `keymap` instead, e.g. the keymap of a ROM's
+    /// profile.
+    pub fn with_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap.into();
+    }
+
+    /// The settings as they stand.
+    #[must_use]
+    pub const fn settings(&self) -> Settings {
+        self.settings
+    }
+
+    /// The keymap as it stands.
+    ///
+    /// # Panics
+    /// This function panics if the keymap has been left invalid, which
+    /// rebinding never does.
+    #[must_use]
+    pub fn keymap(&self) -> Keymap {
+        Keymap::try_from(self.keymap.clone()).expect("the keymap is kept valid")
+    }
+
+    /// The keyboard keys mapped to each CHIP-8 key.
+    ///
+    /// # Panics
+    /// This function panics if the keymap has been left invalid, which
+    /// rebinding never does.
+    #[must_use]
+    pub fn codes(&self) -> HashMap<VirtualKeyCode, u8> {
+        let mut codes = self.keymap().codes();
+        if self.second_keypad {
+            codes.extend(crate::input::second_keypad());
+        }
+        codes
+    }
+
+    /// Whether the panel is shown.
+    #[must_use]
+    pub const fn is_open(&self) -> bool {
+        self.open
+    }
+
+    /// Opens the panel, or closes it, dropping a half-typed row of keys.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.typed.clear();
+    }
+
+    /// Reads the keys for the panel from `input`, if it is open. Returns
+    /// whether a setting changed, or `None` if none of the panel's keys were
+    /// pressed.
+    pub fn update(&mut self, input: &WinitInputHelper) -> Option<bool> {
+        if !self.open {
+            return None;
+        }
+        let arrows = [
+            (VirtualKeyCode::Up, Input::Up),
+            (VirtualKeyCode::Down, Input::Down),
+            (VirtualKeyCode::Left, Input::Left),
+            (VirtualKeyCode::Right, Input::Right),
+        ];
+        let chars = input.text().into_iter().filter_map(|c| match c {
+            TextChar::Char(c) => Some(Input::Char(c)),
+            TextChar::Back => None,
+        });
+        arrows
+            .into_iter()
+            .filter(|&(code, _)| input.key_pressed(code))
+            .map(|(_, input)| input)
+            .chain(chars)
+            .map(|input| self.input(input))
+            .reduce(|a, b| a || b)
+    }
+
+    /// Applies a key pressed while the panel is open. Returns whether a
+    /// setting changed.
+    pub fn input(&mut self, input: Input) -> bool {
+        let forward = input == Input::Right;
+        let Settings {
+            palette,
+            ips,
+            quirks,
+            volume,
+        } = &mut self.settings;
+        match (input, ROWS[self.selected]) {
+            (Input::Up, _) => self.select(self.selected + ROWS.len() - 1),
+            (Input::Down, _) => self.select(self.selected + 1),
+            (Input::Char(c), Row::Keys(row)) => return self.rebind(row, c),
+            (Input::Char(_), _) | (_, Row::Keys(_)) => return false,
+            (_, Row::Palette) => {
+                let presets = Palette::PRESETS;
+                let current = presets.iter().position(|&(_, preset)| preset == *palette);
+                let next = match current {
+                    Some(n) => cycle(n, presets.len(), forward),
+                    None => 0,
+                };
+                *palette = presets[next].1;
+            }
+            (_, Row::Speed) => {
+                *ips = if forward {
+                    ips.saturating_add(Self::IPS_STEP)
+                } else {
+                    ips.saturating_sub(Self::IPS_STEP).max(Self::IPS_STEP)
+                };
+            }
+            (_, Row::Volume) => {
+                let step =
+                    (*volume * Self::VOLUME_STEPS).round() + if forward { 1.0 } else { -1.0 };
+                *volume = step.clamp(0.0, Self::VOLUME_STEPS) / Self::VOLUME_STEPS;
+            }
+            (_, Row::IndexOverflow) => {
+                quirks.index_overflow = next(quirks.index_overflow, forward);
+            }
+            (_, Row::PcOverflow) => quirks.pc_overflow = next(quirks.pc_overflow, forward),
+            (_, Row::JumpOffset) => quirks.jump_offset = next(quirks.jump_offset, forward),
+            (_, Row::Shift) => quirks.shift = next(quirks.shift, forward),
+            (_, Row::VfReset) => quirks.vf_reset = !quirks.vf_reset,
+            (_, Row::IndexCarry) => quirks.index_carry = !quirks.index_carry,
+            (_, Row::HalfScroll) => quirks.half_scroll = !quirks.half_scroll,
+            (_, Row::DisplayWait) => quirks.display_wait = !quirks.display_wait,
+            (_, Row::StackDepth) => {
+                quirks.stack_depth = if forward {
+                    quirks.stack_depth.saturating_add(1)
+                } else {
+                    quirks.stack_depth.saturating_sub(1).max(1)
+                };
+            }
+        }
+        matches!(input, Input::Left | Input::Right)
+    }
+
+    /// The lines of the panel that fit on the screen, scrolled to show the
+    /// selected one.
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        let first = (self.selected + 1).saturating_sub(self.lines);
+        ROWS.iter()
+            .enumerate()
+            .skip(first)
+            .take(self.lines)
+            .map(|(n, &row)| {
+                let marker = if n == self.selected { '>' } else { ' ' };
+                format!("{marker} {}", self.describe(row))
+            })
+            .collect()
+    }
+
+    /// Describes `row`, e.g. `SPEED 700`.
+    fn describe(&self, row: Row) -> String {
+        let Settings {
+            palette,
+            ips,
+            quirks,
+            volume,
+        } = self.settings;
+        match row {
+            Row::Palette => {
+                let name = Palette::PRESETS
+                    .iter()
+                    .find(|&&(_, preset)| preset == palette)
+                    .map_or("custom", |&(name, _)| name);
+                format!("PALETTE {}", name.to_uppercase())
+            }
+            Row::Speed => format!("SPEED {ips}"),
+            Row::Volume => format!("VOLUME {volume:.1}"),
+            Row::IndexOverflow => format!("I OVERFLOW {}", name(quirks.index_overflow)),
+            Row::PcOverflow => format!("PC OVERFLOW {}", name(quirks.pc_overflow)),
+            Row::JumpOffset => format!("JUMP {}", name(quirks.jump_offset)),
+            Row::Shift => format!("SHIFT {}", name(quirks.shift)),
+            Row::VfReset => format!("VF RESET {}", on_off(quirks.vf_reset)),
+            Row::IndexCarry => format!("I CARRY {}", on_off(quirks.index_carry)),
+            Row::HalfScroll => format!("HALF SCROLL {}", on_off(quirks.half_scroll)),
+            Row::DisplayWait => format!("DISPLAY WAIT {}", on_off(quirks.display_wait)),
+            Row::StackDepth => format!("STACK DEPTH {}", quirks.stack_depth),
+            Row::Keys(n) if n == self.row_being_typed().unwrap_or(usize::MAX) => {
+                format!("KEYS {:-<4}", self.typed)
+            }
+            Row::Keys(n) => format!("KEYS {}", self.keymap[n]),
+        }
+    }
+
+    /// The row of keys being rebound, if some of it has been typed.
+    fn row_being_typed(&self) -> Option<usize> {
+        match ROWS[self.selected] {
+            Row::Keys(row) if !self.typed.is_empty() => Some(row),
+            _ => None,
+        }
+    }
+
+    /// Selects row `n`, wrapping around, and drops a half-typed row of keys.
+    fn select(&mut self, n: usize) {
+        self.selected = n % ROWS.len();
+        self.typed.clear();
+    }
+
+    /// Adds `c` to the keys typed for keymap row `row`, rebinding the row
+    /// once four have been typed. Rows that would map a key twice are
+    /// dropped. Returns whether the keymap changed.
+    fn rebind(&mut self, row: usize, c: char) -> bool {
+        if !c.is_ascii_alphanumeric() {
+            return false;
+        }
+        self.typed.push(c.to_ascii_uppercase());
+        if self.typed.len() < 4 {
+            return false;
+        }
+        let mut keymap = self.keymap.clone();
+        keymap[row] = std::mem::take(&mut self.typed);
+        if Keymap::try_from(keymap.clone()).is_err() {
+            return false;
+        }
+        self.keymap = keymap;
+        true
+    }
+}
+
+/// The index after (or before) `n` of `len`, wrapping around.
+#[cfg(feature = "gui")]
+const fn cycle(n: usize, len: usize, forward: bool) -> usize {
+    if forward {
+        (n + 1) % len
+    } else {
+        (n + len - 1) % len
+    }
+}
+
+/// A quirk with a few values, which the panel cycles through.
+#[cfg(feature = "gui")]
+trait Choice: Copy + PartialEq + 'static {
+    /// Every value, in the order they are cycled through.
+    const VALUES: &'static [Self];
+
+    /// The name of the value on the command line.
+    fn name(self) -> &'static str;
+}
+
+#[cfg(feature = "gui")]
+impl Choice for IndexOverflow {
+    const VALUES: &'static [Self] = &[Self::Mask, Self::Fault];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Mask => "mask",
+            Self::Fault => "fault",
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Choice for PcOverflow {
+    const VALUES: &'static [Self] = &[Self::Wrap, Self::Fault];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Wrap => "wrap",
+            Self::Fault => "fault",
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Choice for JumpOffset {
+    const VALUES: &'static [Self] = &[Self::V0, Self::Vx];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::V0 => "v0",
+            Self::Vx => "vx",
+        }
+    }
+}
+
+#[cfg(feature = "gui")]
+impl Choice for Shift {
+    const VALUES: &'static [Self] = &[Self::Vx, Self::Vy];
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Vx => "vx",
+            Self::Vy => "vy",
+        }
+    }
+}
+
+/// The value after (or before) `value`, wrapping around.
+#[cfg(feature = "gui")]
+fn next<T: Choice>(value: T, forward: bool) -> T {
+    let n = T::VALUES.iter().position(|&v| v == value).unwrap_or(0);
+    T::VALUES[cycle(n, T::VALUES.len(), forward)]
+}
+
+/// The name of `value` on the command line, in capitals.
+#[cfg(feature = "gui")]
+fn name<T: Choice>(value: T) -> String {
+    value.name().to_uppercase()
+}
+
+/// A switch's state, in capitals.
+#[cfg(feature = "gui")]
+const fn on_off(on: bool) -> &'static str {
+    if on {
+        "ON"
+    } else {
+        "OFF"
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+    use crate::input;
+
+    fn panel(scale: u32) -> Panel {
+        let config = Config {
+            scale,
+            ..Config::default()
+        };
+        Panel::new(&config, Timing::from_ips(700), Quirks::default())
+    }
+
+    /// The selected line of `panel`.
+    fn selected(panel: &Panel) -> String {
+        panel
+            .lines()
+            .into_iter()
+            .find(|line| line.starts_with('>'))
+            .unwrap()
+    }
+
+    /// Selects the first `row` of `panel`.
+    fn select(panel: &mut Panel, row: Row) {
+        while ROWS[panel.selected] != row {
+            panel.input(Input::Down);
+        }
+    }
+
+    #[test]
+    fn changes_settings() {
+        let mut panel = panel(10);
+        assert_eq!(panel.lines()[..2], ["> PALETTE CLASSIC", "  SPEED 700"]);
+        assert!(panel.input(Input::Left));
+        assert_eq!(panel.settings().palette, Palette::preset("lcd").unwrap());
+
+        assert!(!panel.input(Input::Down));
+        assert!(panel.input(Input::Right));
+        assert_eq!(panel.settings().ips, 750);
+        select(&mut panel, Row::Volume);
+        panel.input(Input::Left);
+        assert!((panel.settings().volume - 0.1).abs() < f32::EPSILON);
+        assert_eq!(selected(&panel), "> VOLUME 0.1");
+        select(&mut panel, Row::IndexOverflow);
+        panel.input(Input::Right);
+        assert_eq!(panel.settings().quirks.index_overflow, IndexOverflow::Fault);
+        select(&mut panel, Row::JumpOffset);
+        panel.input(Input::Left);
+        assert_eq!(panel.settings().quirks.jump_offset, JumpOffset::Vx);
+        assert!(!panel.input(Input::Char('A')));
+        select(&mut panel, Row::StackDepth);
+        panel.input(Input::Left);
+        assert_eq!(panel.settings().quirks.stack_depth, 15);
+        assert_eq!(selected(&panel), "> STACK DEPTH 15");
+
+        // Not every row fits on a 32 pixel high screen at scale 10.
+        let lines = panel.lines();
+        assert_eq!(lines.len(), 10);
+        assert_eq!(lines.last().unwrap(), "> STACK DEPTH 15");
+        select(&mut panel, Row::Keys(3));
+        assert_eq!(selected(&panel), "> KEYS ZXCV");
+        panel.input(Input::Down);
+        assert_eq!(panel.lines()[0], "> PALETTE LCD");
+        assert_eq!(self::panel(5).lines().len(), 5);
+    }
+
+    #[test]
+    fn toggles_quirks() {
+        let mut panel = panel(10);
+        select(&mut panel, Row::Shift);
+        assert!(panel.input(Input::Right));
+        assert_eq!(selected(&panel), "> SHIFT VY");
+        select(&mut panel, Row::VfReset);
+        panel.input(Input::Left);
+        assert_eq!(selected(&panel), "> VF RESET ON");
+        select(&mut panel, Row::IndexCarry);
+        panel.input(Input::Right);
+        assert_eq!(selected(&panel), "> I CARRY ON");
+        select(&mut panel, Row::HalfScroll);
+        panel.input(Input::Left);
+        assert_eq!(selected(&panel), "> HALF SCROLL ON");
+        select(&mut panel, Row::DisplayWait);
+        panel.input(Input::Right);
+        let quirks = panel.settings().quirks;
+        assert_eq!(quirks.shift, Shift::Vy);
+        assert!(quirks.vf_reset && quirks.index_carry && quirks.display_wait);
+        assert!(quirks.half_scroll);
+    }
+
+    #[test]
+    fn volume_stays_in_range() {
+        let mut panel = panel(10);
+        select(&mut panel, Row::Volume);
+        for _ in 0..20 {
+            panel.input(Input::Right);
+        }
+        assert!((panel.settings().volume - 1.0).abs() < f32::EPSILON);
+        for _ in 0..20 {
+            panel.input(Input::Left);
+        }
+        assert!(panel.settings().volume.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn rebinds_keys() {
+        let mut panel = panel(10);
+        select(&mut panel, Row::Keys(1));
+        assert_eq!(selected(&panel), "> KEYS QWER");
+        assert!(!panel.input(Input::Char('p')));
+        assert!(!panel.input(Input::Char('!')));
+        assert_eq!(selected(&panel), "> KEYS P---");
+        for c in "OIU".chars() {
+            panel.input(Input::Char(c));
+        }
+        assert_eq!(selected(&panel), "> KEYS POIU");
+        assert_eq!(panel.codes()[&VirtualKeyCode::P], 0x4);
+        assert!(!panel.codes().contains_key(&VirtualKeyCode::Q));
+
+        // A row that maps a key twice is dropped.
+        for c in "ZZZZ".chars() {
+            assert!(!panel.input(Input::Char(c)));
+        }
+        assert_eq!(selected(&panel), "> KEYS POIU");
+        panel.with_second_keypad();
+        assert_eq!(panel.codes()[&VirtualKeyCode::Numpad0], input::KEYPAD_2);
+    }
+}
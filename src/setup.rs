@@ -0,0 +1,177 @@
+#[cfg(feature = "audio")]
+use crate::audio::{Buzzer, Speaker};
+use crate::{
+    audio::Beep,
+    config::{Config, Palette},
+    input::LAYOUT,
+};
+use std::io::{self, BufRead, Write};
+#[cfg(feature = "audio")]
+use std::{thread, time::Duration};
+
+/// Walks the user through choosing a window scale and palette, checks that
+/// their keyboard matches the keymap, then sets the beep's volume, playing
+/// it to test the sound with the `audio` feature. Prompts are written to `output`
+/// and answers read from `input`; an empty answer (or the end of `input`)
+/// keeps the default.
+///
+/// # Errors
+/// This function will error if `input` cannot be read or `output` cannot be
+/// written.
+pub fn wizard(input: &mut impl BufRead, output: &mut impl Write) -> io::Result<Config> {
+    writeln!(output, "Welcome to etherea! Let's set a few things up.")?;
+    writeln!(
+        output,
+        "Press Enter to keep the default shown in brackets.\n"
+    )?;
+
+    let scale = loop {
+        let answer = ask(input, output, &format!("Window scale [{}]", Config::SCALE))?;
+        if answer.is_empty() {
+            break Config::SCALE;
+        }
+        match answer.parse() {
+            Ok(scale) if scale > 0 => break scale,
+            _ => writeln!(output, "Please enter a whole number above 0.")?,
+        }
+    };
+
+    writeln!(output)?;
+    for (n, (name, palette)) in Palette::PRESETS.iter().enumerate() {
+        writeln!(
+            output,
+            "  {}. {name:<8} {} on {}",
+            n + 1,
+            palette.foreground,
+            palette.background
+        )?;
+    }
+    let palette = loop {
+        let answer = ask(input, output, "Palette [1]")?;
+        if answer.is_empty() {
+            break Palette::default();
+        }
+        let preset = answer
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| Palette::PRESETS.get(n.wrapping_sub(1)))
+            .map(|&(_, palette)| palette)
+            .or_else(|| Palette::preset(&answer));
+        match preset {
+            Some(palette) => break palette,
+            None => writeln!(output, "Please pick one of the palettes above.")?,
+        }
+    };
+
+    writeln!(output, "\nThe CHIP-8 keypad is mapped to these keys:")?;
+    for row in LAYOUT {
+        writeln!(
+            output,
+            "  {}",
+            row.map(|(key, chip8)| format!("{key} ({chip8:X})"))
+                .join("  ")
+        )?;
+    }
+    writeln!(
+        output,
+        "Type each row of keys to check them, or press Enter to skip."
+    )?;
+    for row in LAYOUT {
+        let expected: String = row.iter().map(|&(key, _)| key).collect();
+        let answer = ask(input, output, &format!("  {expected}"))?;
+        if !answer.is_empty() && !answer.eq_ignore_ascii_case(&expected) {
+            writeln!(
+                output,
+                "  Those aren't the same keys: the keymap follows QWERTY positions, \
+                 so on this keyboard press the keys labeled {expected}."
+            )?;
+        }
+    }
+
+    writeln!(output)?;
+    let volume = loop {
+        let answer = ask(
+            input,
+            output,
+            &format!("Beep volume, from 0 to 1 [{}]", Beep::VOLUME),
+        )?;
+        if answer.is_empty() {
+            break Beep::VOLUME;
+        }
+        match answer.parse() {
+            Ok(volume) if (0.0..=1.0).contains(&volume) => break volume,
+            _ => writeln!(output, "Please enter a number from 0 to 1.")?,
+        }
+    };
+    let beep = Beep {
+        volume,
+        ..Beep::default()
+    };
+    #[cfg(feature = "audio")]
+    test_beep(output, beep)?;
+
+    Ok(Config {
+        scale,
+        palette,
+        beep,
+        ..Config::default()
+    })
+}
+
+/// Plays `beep` for half a second on the default audio device, or says why
+/// it can't.
+#[cfg(feature = "audio")]
+fn test_beep(output: &mut impl Write, beep: Beep) -> io::Result<()> {
+    match Speaker::open(beep) {
+        Ok(mut speaker) => {
+            writeln!(output, "Playing a test beep...")?;
+            speaker.buzz(true);
+            thread::sleep(Duration::from_millis(500));
+            speaker.buzz(false);
+            // Lets the beep fade out before the stream stops.
+            thread::sleep(Duration::from_millis(50));
+        }
+        Err(e) => writeln!(output, "Couldn't play a test beep: {e}")?,
+    }
+    Ok(())
+}
+
+/// Writes `prompt` and reads the trimmed answer, which is empty at the end of
+/// `input`.
+fn ask(input: &mut impl BufRead, output: &mut impl Write, prompt: &str) -> io::Result<String> {
+    write!(output, "{prompt}: ")?;
+    output.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(answer.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(answers: &str) -> (Config, String) {
+        let mut output = Vec::new();
+        let config = wizard(&mut answers.as_bytes(), &mut output).unwrap();
+        (config, String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn defaults() {
+        let (config, output) = run("");
+        assert_eq!(config, Config::default());
+        assert!(output.contains("1 (1)  2 (2)  3 (3)  4 (C)"));
+    }
+
+    #[test]
+    fn choices() {
+        let (config, output) = run("0\n4\n5\nlcd\n1234\nazer\n\n\n2\n0.5\n");
+        assert_eq!(config.scale, 4);
+        assert_eq!(config.palette, Palette::PRESETS[3].1);
+        assert!(output.contains("whole number above 0"));
+        assert!(output.contains("pick one of the palettes"));
+        assert_eq!(output.matches("aren't the same keys").count(), 1);
+        assert!((config.beep.volume - 0.5).abs() < f32::EPSILON);
+        assert!(output.contains("from 0 to 1."));
+    }
+}
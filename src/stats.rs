@@ -0,0 +1,307 @@
+use crate::Counters;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// One play session of a ROM, appended to the history file when the window
+/// is closed. The history never leaves this machine.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// The ROM's file name.
+    pub rom: String,
+    /// The ROM's hash, which identifies it even if it is renamed.
+    pub hash: String,
+    /// When the session started, in seconds since the Unix epoch.
+    pub started: u64,
+    /// How long the session lasted, in seconds.
+    pub seconds: u64,
+    /// The speed and quirks the ROM ran with.
+    pub settings: String,
+    #[serde(skip)]
+    clock: Option<Instant>, // when the session started, for timing it
+}
+
+impl Session {
+    /// Starts timing a session of `rom`, named `name`.
+    #[must_use]
+    pub fn start(name: &str, rom: &[u8], settings: String) -> Self {
+        Self {
+            rom: name.to_string(),
+            hash: format!("{:016x}", crate::debug::hash(rom)),
+            started: now(),
+            seconds: 0,
+            settings,
+            clock: Some(Instant::now()),
+        }
+    }
+
+    /// Stops timing the session and appends it to the history file.
+    ///
+    /// # Errors
+    /// This function will error if there is no data directory or the history
+    /// file cannot be written.
+    pub fn finish(mut self) -> io::Result<()> {
+        if let Some(clock) = self.clock {
+            self.seconds = clock.elapsed().as_secs();
+        }
+        let path =
+            path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No data directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        writeln!(file, "{}", serde_json::to_string(&self)?)
+    }
+}
+
+/// The path of the history file, inside the user's data directory.
+#[must_use]
+pub fn path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("etherea").join("history.jsonl"))
+}
+
+/// Reads every recorded session, skipping lines that can't be parsed.
+///
+/// # Errors
+/// This function will error if the history file exists but cannot be read.
+pub fn history() -> io::Result<Vec<Session>> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Deletes the history file, returning whether there was one.
+///
+/// # Errors
+/// This function will error if the history file exists but cannot be deleted.
+pub fn wipe() -> io::Result<bool> {
+    let Some(path) = path() else {
+        return Ok(false);
+    };
+    match fs::remove_file(path) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// The sessions of one ROM, added up.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Summary<'a> {
+    /// The ROM's file name in its most recent session.
+    pub rom: &'a str,
+    /// The number of sessions.
+    pub sessions: usize,
+    /// The total time played, in seconds.
+    pub seconds: u64,
+    /// When the ROM was last played, in seconds since the Unix epoch.
+    pub last_played: u64,
+    /// The settings of the most recent session.
+    pub settings: &'a str,
+}
+
+/// Adds up `sessions` by ROM, most played first.
+#[must_use]
+pub fn summarize(sessions: &[Session]) -> Vec<Summary<'_>> {
+    let mut summaries: BTreeMap<&str, Summary> = BTreeMap::new();
+    for session in sessions {
+        let summary = summaries.entry(&session.hash).or_insert(Summary {
+            rom: &session.rom,
+            sessions: 0,
+            seconds: 0,
+            last_played: 0,
+            settings: &session.settings,
+        });
+        summary.sessions += 1;
+        summary.seconds += session.seconds;
+        if session.started >= summary.last_played {
+            summary.last_played = session.started;
+            summary.rom = &session.rom;
+            summary.settings = &session.settings;
+        }
+    }
+    let mut summaries: Vec<Summary> = summaries.into_values().collect();
+    summaries.sort_by(|a, b| b.seconds.cmp(&a.seconds).then(a.rom.cmp(b.rom)));
+    summaries
+}
+
+/// Formats a number of seconds like `1h 02m` or `3m 05s`.
+#[must_use]
+pub fn duration(seconds: u64) -> String {
+    let (hours, minutes) = (seconds / 3600, seconds / 60 % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes:02}m")
+    } else {
+        format!("{minutes}m {:02}s", seconds % 60)
+    }
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let days = now().saturating_sub(self.last_played) / 86_400;
+        let last_played = match days {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{days} days ago"),
+        };
+        write!(
+            f,
+            "{:<24} {:>8}  {:>3} session(s)  last played {last_played} ({})",
+            self.rom,
+            duration(self.seconds),
+            self.sessions,
+            self.settings
+        )
+    }
+}
+
+/// What one run of a ROM did, shown when it stops with `--exit-stats`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Totals {
+    /// Instructions executed.
+    pub instructions: u64,
+    /// Frames drawn.
+    pub frames: u64,
+    /// Timer ticks that counted the delay or sound timer down.
+    pub timer_ticks: u64,
+    /// Key presses and releases applied.
+    pub key_events: u64,
+    /// How long the window was open, in seconds, paused or not.
+    pub seconds: f64,
+    /// The instructions executed per second, on average.
+    pub ips: f64,
+    /// The instructions per second the ROM was meant to run at.
+    pub requested_ips: u64,
+}
+
+impl Totals {
+    /// The totals of a run that lasted `elapsed`, did what `counters` say,
+    /// and was meant to run at `requested_ips`.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)] // counts that big are rounded anyway
+    pub fn new(counters: Counters, elapsed: Duration, requested_ips: u64) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        let ips = if seconds > 0.0 {
+            counters.instructions as f64 / seconds
+        } else {
+            0.0
+        };
+        Self {
+            instructions: counters.instructions,
+            frames: counters.frames,
+            timer_ticks: counters.timer_ticks,
+            key_events: counters.key_events,
+            seconds,
+            ips,
+            requested_ips,
+        }
+    }
+}
+
+impl fmt::Display for Totals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Instructions  {}", self.instructions)?;
+        writeln!(f, "Frames        {}", self.frames)?;
+        writeln!(f, "Timer ticks   {}", self.timer_ticks)?;
+        writeln!(f, "Key events    {}", self.key_events)?;
+        writeln!(f, "Wall clock    {:.2}s", self.seconds)?;
+        write!(
+            f,
+            "Speed         {:.0} IPS (requested {})",
+            self.ips, self.requested_ips
+        )
+    }
+}
+
+/// The current time, in seconds since the Unix epoch.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(rom: &str, hash: &str, started: u64, seconds: u64) -> Session {
+        Session {
+            rom: rom.to_string(),
+            hash: hash.to_string(),
+            started,
+            seconds,
+            settings: format!("session at {started}"),
+            clock: None,
+        }
+    }
+
+    #[test]
+    fn summaries() {
+        let sessions = [
+            session("pong.ch8", "a", 10, 60),
+            session("tetris.ch8", "b", 20, 30),
+            session("pong2.ch8", "a", 30, 90),
+        ];
+        let summaries = summarize(&sessions);
+        assert_eq!(summaries.len(), 2);
+        assert_eq!(
+            summaries[0],
+            Summary {
+                rom: "pong2.ch8",
+                sessions: 2,
+                seconds: 150,
+                last_played: 30,
+                settings: "session at 30",
+            }
+        );
+        assert_eq!(summaries[1].rom, "tetris.ch8");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(duration(5), "0m 05s");
+        assert_eq!(duration(185), "3m 05s");
+        assert_eq!(duration(3720), "1h 02m");
+    }
+
+    #[test]
+    fn totals() {
+        let counters = Counters {
+            instructions: 1500,
+            frames: 120,
+            timer_ticks: 60,
+            key_events: 4,
+        };
+        let totals = Totals::new(counters, Duration::from_secs(2), 600);
+        assert!((totals.ips - 750.0).abs() < f64::EPSILON);
+        assert_eq!(
+            totals.to_string(),
+            "Instructions  1500\nFrames        120\nTimer ticks   60\nKey events    4\n\
+             Wall clock    2.00s\nSpeed         750 IPS (requested 600)"
+        );
+        assert!(Totals::new(counters, Duration::ZERO, 600).ips.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn round_trips() {
+        let session = session("pong.ch8", "a", 10, 60);
+        let line = serde_json::to_string(&session).unwrap();
+        assert_eq!(serde_json::from_str::<Session>(&line).unwrap(), session);
+    }
+}
@@ -0,0 +1,16 @@
+/// Loading and executing ROMs: instructions, registers, and faults.
+pub const CPU: &str = "etherea::cpu";
+/// Drawing the screen, and showing it in the window.
+pub const DISPLAY: &str = "etherea::display";
+/// Key events, from the keyboard or anywhere else, and the keys they map to.
+pub const INPUT: &str = "etherea::input";
+/// The delay and sound timers.
+pub const TIMER: &str = "etherea::timer";
+
+/// Every subsystem's target, by its short name, as `--log-filter` takes them.
+pub const ALL: [(&str, &str); 4] = [
+    ("cpu", CPU),
+    ("display", DISPLAY),
+    ("input", INPUT),
+    ("timer", TIMER),
+];
@@ -0,0 +1,160 @@
+use alloc::string::String;
+
+/// The width of a glyph, in pixels.
+pub const GLYPH_WIDTH: usize = 3;
+/// The height of a glyph, in pixels.
+pub const GLYPH_HEIGHT: usize = 5;
+/// The space taken by a character, including the gap after it.
+pub const ADVANCE: usize = GLYPH_WIDTH + 1;
+/// The space taken by a line, including the gap after it.
+pub const LINE_HEIGHT: usize = GLYPH_HEIGHT + 1;
+
+/// The most pixels of the display's buffer a CHIP-8 pixel is made of.
+#[cfg(feature = "gui")]
+const MAX_DETAIL: u32 = 4;
+
+/// How many pixels of the display's buffer wide and tall a CHIP-8 pixel is
+/// made of, in a window scaled by `scale`, so that text can be drawn at a
+/// finer resolution. The buffer is scaled up to the window by a whole
+/// number, so the detail has to divide the scale.
+#[cfg(feature = "gui")]
+pub(crate) fn detail(scale: u32) -> u32 {
+    (1..=MAX_DETAIL)
+        .rev()
+        .find(|&detail| scale.is_multiple_of(detail))
+        .unwrap_or(1)
+}
+
+/// The rows of the glyph for `c`, top to bottom, with the leftmost pixel in
+/// the highest of three bits. Characters without a glyph are blank.
+#[rustfmt::skip]
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b010, 0b010, 0b010],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        ',' => [0b000, 0b000, 0b000, 0b010, 0b100],
+        '_' => [0b000, 0b000, 0b000, 0b000, 0b111],
+        '+' => [0b000, 0b010, 0b111, 0b010, 0b000],
+        '[' => [0b110, 0b100, 0b100, 0b100, 0b110],
+        ']' => [0b011, 0b001, 0b001, 0b001, 0b011],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '>' => [0b100, 0b010, 0b001, 0b010, 0b100],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws `lines` of text into the top left corner of `frame`, an RGBA
+/// buffer `width` pixels wide, in `foreground` on a box of `background`.
+/// Text that doesn't fit is cut off.
+#[cfg(feature = "gui")]
+pub fn draw(
+    frame: &mut [u8],
+    width: usize,
+    lines: &[String],
+    foreground: [u8; 4],
+    background: [u8; 4],
+) {
+    draw_at(frame, width, (0, 0), lines, foreground, background);
+}
+
+/// Draws `lines` of text as [`draw`] does, but with the top left corner at
+/// `(left, top)`.
+pub fn draw_at(
+    frame: &mut [u8],
+    width: usize,
+    (left, top): (usize, usize),
+    lines: &[String],
+    foreground: [u8; 4],
+    background: [u8; 4],
+) {
+    let height = frame.len() / 4 / width;
+    for (row, line) in lines.iter().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            let glyph = glyph(c);
+            for dy in 0..LINE_HEIGHT {
+                for dx in 0..ADVANCE {
+                    let (x, y) = (left + column * ADVANCE + dx, top + row * LINE_HEIGHT + dy);
+                    if x >= width || y >= height {
+                        continue;
+                    }
+                    let bits = glyph.get(dy).copied().unwrap_or_default();
+                    let lit = dx < GLYPH_WIDTH && bits & (0b100 >> dx) != 0;
+                    let pixel = (y * width + x) * 4;
+                    frame[pixel..pixel + 4].copy_from_slice(if lit {
+                        &foreground
+                    } else {
+                        &background
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "gui"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draws_text() {
+        const ON: [u8; 4] = [1; 4];
+        const OFF: [u8; 4] = [2; 4];
+        let width = 6;
+        let mut frame = vec![0; width * 6 * 4];
+        draw(&mut frame, width, &["17".to_string()], ON, OFF);
+        let rows: Vec<String> = frame
+            .chunks_exact(width * 4)
+            .map(|row| {
+                row.chunks_exact(4)
+                    .map(|pixel| match pixel[0] {
+                        1 => '#',
+                        2 => '.',
+                        _ => ' ',
+                    })
+                    .collect()
+            })
+            .collect();
+        // The "7" is cut off after two columns.
+        assert_eq!(
+            rows,
+            [".#..##", "##....", ".#...#", ".#...#", "###..#", "......"]
+        );
+    }
+}
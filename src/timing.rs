@@ -0,0 +1,232 @@
+use alloc::{format, string::String};
+#[cfg(feature = "cli")]
+use clap::ValueEnum;
+use core::{fmt, ops::Add, str::FromStr, time::Duration};
+
+/// A non-negative fixed-point number with [`Fixed::FRACTIONAL_BITS`]
+/// fractional bits. Used instead of floating point so every machine
+/// computes exactly the same instruction counts.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(u64);
+
+impl Fixed {
+    /// The number of bits used for the fractional part.
+    pub const FRACTIONAL_BITS: u32 = 16;
+
+    /// Creates a fixed-point number from its raw bit representation.
+    #[must_use]
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    /// The raw bit representation of this number.
+    #[must_use]
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    /// Creates a fixed-point number approximating `numerator / denominator`,
+    /// rounded to the nearest representable value.
+    ///
+    /// # Panics
+    /// This function will panic if `denominator` is zero.
+    #[must_use]
+    pub const fn from_ratio(numerator: u64, denominator: u64) -> Self {
+        Self(((numerator << Self::FRACTIONAL_BITS) + denominator / 2) / denominator)
+    }
+
+    /// The integer part of `self * n`, computed without intermediate rounding.
+    #[must_use]
+    pub const fn mul_floor(self, n: u64) -> u64 {
+        (self.0 * n) >> Self::FRACTIONAL_BITS
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let whole = self.0 >> Self::FRACTIONAL_BITS;
+        let fraction = self.0 & ((1 << Self::FRACTIONAL_BITS) - 1);
+        // Four decimal places are plenty for display purposes.
+        let decimal = (fraction * 10_000) >> Self::FRACTIONAL_BITS;
+        write!(f, "{whole}.{decimal:04}")
+    }
+}
+
+/// The timing model of the interpreter: execution is split into frames,
+/// each of which runs a number of instructions followed by a single tick
+/// of the delay and sound timers.
+///
+/// The number of instructions per frame is accumulated in fixed point, so
+/// frame `n` executes exactly `floor((n + 1) * c) - floor(n * c)` instructions
+/// where `c` is [`cycles_per_frame`](Self::cycles_per_frame). This guarantees
+/// the same instruction/frame interleaving on every machine regardless of
+/// how fast it is, which makes runs (and replays of them) deterministic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timing {
+    /// The number of instructions executed per frame.
+    pub cycles_per_frame: Fixed,
+    /// The number of frames (and timer ticks) per second.
+    pub frames_per_second: u32,
+}
+
+impl Timing {
+    /// The rate at which CHIP-8 timers count down.
+    pub const FRAMES_PER_SECOND: u32 = 60;
+
+    /// Creates a timing model executing approximately `ips` instructions
+    /// per second at [`FRAMES_PER_SECOND`](Self::FRAMES_PER_SECOND).
+    #[must_use]
+    pub const fn from_ips(ips: u64) -> Self {
+        Self {
+            cycles_per_frame: Fixed::from_ratio(ips, Self::FRAMES_PER_SECOND as u64),
+            frames_per_second: Self::FRAMES_PER_SECOND,
+        }
+    }
+
+    /// Creates a timing model executing `cycles` instructions per frame at
+    /// [`FRAMES_PER_SECOND`](Self::FRAMES_PER_SECOND).
+    #[must_use]
+    pub const fn from_cycles_per_frame(cycles: u64) -> Self {
+        Self {
+            cycles_per_frame: Fixed::from_ratio(cycles, 1),
+            frames_per_second: Self::FRAMES_PER_SECOND,
+        }
+    }
+
+    /// The number of instructions to execute during frame number `frame`.
+    #[must_use]
+    pub const fn cycles(&self, frame: u64) -> u64 {
+        self.cycles_per_frame.mul_floor(frame + 1) - self.cycles_per_frame.mul_floor(frame)
+    }
+
+    /// The total number of instructions executed before frame number `frame` starts.
+    #[must_use]
+    pub const fn cycles_before(&self, frame: u64) -> u64 {
+        self.cycles_per_frame.mul_floor(frame)
+    }
+
+    /// The point in time at which frame number `frame` should start, given
+    /// that frame `0` started at `start`. Computed from the frame number
+    /// rather than accumulated, so it doesn't drift. `start` is an
+    /// [`Instant`](std::time::Instant), or the [`Duration`] a
+    /// [`Clock`](crate::platform::Clock) reads.
+    #[must_use]
+    pub fn deadline<T: Add<Duration, Output = T>>(&self, start: T, frame: u64) -> T {
+        let nanos = u128::from(frame) * 1_000_000_000 / u128::from(self.frames_per_second.max(1));
+        start + Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+    }
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::from_ips(700)
+    }
+}
+
+/// Named speeds matching the platforms ROMs are written for, so the number
+/// of instructions per second doesn't have to be guessed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(ValueEnum))]
+pub enum Speed {
+    /// The COSMAC VIP: about 700 instructions per second, with each draw
+    /// waiting for the next frame
+    Vip,
+    /// SUPER-CHIP on the HP-48: 30 instructions per frame
+    Schip,
+    /// XO-CHIP: 1000 instructions per frame
+    Xochip,
+    /// As fast as the machine allows, up to 100,000 instructions per frame
+    Unlimited,
+}
+
+impl Speed {
+    /// The timing model of the preset.
+    #[must_use]
+    pub const fn timing(self) -> Timing {
+        match self {
+            Self::Vip => Timing::from_ips(700),
+            Self::Schip => Timing::from_cycles_per_frame(30),
+            Self::Xochip => Timing::from_cycles_per_frame(1000),
+            Self::Unlimited => Timing::from_cycles_per_frame(100_000),
+        }
+    }
+
+    /// Whether drawing waits for the next frame, as the
+    /// [`display_wait`](crate::quirks::Quirks::display_wait) quirk.
+    #[must_use]
+    pub const fn display_wait(self) -> bool {
+        matches!(self, Self::Vip)
+    }
+}
+
+/// The canonical, lossless text representation of a timing model:
+/// `<cycles_per_frame bits>/<frames_per_second>`.
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}",
+            self.cycles_per_frame.to_bits(),
+            self.frames_per_second
+        )
+    }
+}
+
+impl FromStr for Timing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("Invalid timing: '{s}'");
+        let (cycles, fps) = s.split_once('/').ok_or_else(err)?;
+        Ok(Self {
+            cycles_per_frame: Fixed::from_bits(cycles.trim().parse().map_err(|_| err())?),
+            frames_per_second: fps.trim().parse().map_err(|_| err())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cycles_accumulate_exactly() {
+        let timing = Timing::from_ips(700);
+        let total: u64 = (0..60).map(|frame| timing.cycles(frame)).sum();
+        assert_eq!(total, 700);
+        assert_eq!(timing.cycles_before(60), 700);
+        assert!((0..60).all(|frame| (11..=12).contains(&timing.cycles(frame))));
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let timing = Timing::from_ips(1234);
+        assert_eq!(timing.to_string().parse::<Timing>(), Ok(timing));
+        assert!("12".parse::<Timing>().is_err());
+    }
+
+    #[test]
+    fn speeds() {
+        assert_eq!(Speed::Vip.timing(), Timing::from_ips(700));
+        assert_eq!(Speed::Schip.timing().cycles_before(60), 1800);
+        assert!((0..60).all(|frame| Speed::Xochip.timing().cycles(frame) == 1000));
+    }
+
+    #[test]
+    fn deadlines_dont_drift() {
+        let timing = Timing::default();
+        assert_eq!(
+            timing.deadline(Duration::ZERO, 1),
+            Duration::from_nanos(16_666_666)
+        );
+        assert_eq!(
+            timing.deadline(Duration::ZERO, 216_000),
+            Duration::from_hours(1)
+        );
+    }
+
+    #[test]
+    fn display_fixed() {
+        assert_eq!(Fixed::from_ratio(3, 2).to_string(), "1.5000");
+    }
+}
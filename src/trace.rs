@@ -0,0 +1,269 @@
+use crate::Interpreter;
+use std::{
+    fmt, fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+/// The first line of every trace file.
+const HEADER: &str = "# etherea trace v1";
+
+/// Writes the state of the machine before every instruction to a trace
+/// file, a line each:
+///
+/// ```text
+/// # etherea trace v1
+/// PC=0200 OP=00E0 V0=00 V1=00 … VF=00 I=0000 SP=00 DT=00 ST=00 M=5f1d4f2a
+/// ```
+///
+/// `M` is the CRC-32 of memory, which shows that memory changed but not
+/// where.
+#[derive(Debug)]
+pub struct Writer {
+    writer: io::BufWriter<fs::File>,
+}
+
+impl Writer {
+    /// Creates the trace file at `path`.
+    ///
+    /// # Errors
+    /// This function will error if the file cannot be created or written.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = io::BufWriter::new(fs::File::create(path)?);
+        writeln!(writer, "{HEADER}")?;
+        Ok(Self { writer })
+    }
+
+    /// Appends the state of `intr`, about to execute the instruction at its
+    /// program counter.
+    pub(crate) fn record(&mut self, intr: &Interpreter) -> io::Result<()> {
+        let pc = intr.pc;
+        let opcode = match intr.memory.get(pc..pc + 2) {
+            Some(&[high, low]) => u16::from_be_bytes([high, low]),
+            _ => 0,
+        };
+        write!(self.writer, "PC={:04X} OP={opcode:04X}", intr.pc)?;
+        for (x, v) in intr.registers.iter().enumerate() {
+            write!(self.writer, " V{x:X}={v:02X}")?;
+        }
+        writeln!(
+            self.writer,
+            " I={:04X} SP={:02X} DT={:02X} ST={:02X} M={:08x}",
+            intr.i,
+            intr.stack.len(),
+            intr.timers.delay,
+            intr.timers.sound,
+            crc32fast::hash(&intr.memory[..])
+        )
+    }
+}
+
+/// One line of a trace: the fields it holds, in order. Fields are written
+/// `NAME=value` or `NAME:value`; anything else on the line is ignored, so
+/// logs from other emulators can be compared as long as they name their
+/// fields the same way.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct State(Vec<(String, String)>);
+
+impl State {
+    /// The value of `field`, if the line has it.
+    #[must_use]
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == field)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The first field both lines hold but with different values.
+    fn mismatch(&self, other: &Self) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(name, value)| other.get(name).is_some_and(|theirs| !same(value, theirs)))
+            .map(|(name, _)| name.as_str())
+    }
+}
+
+impl std::str::FromStr for State {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            s.split_whitespace()
+                .filter_map(|token| token.split_once(['=', ':']))
+                .filter(|(name, value)| !name.is_empty() && !value.is_empty())
+                .map(|(name, value)| (name.to_ascii_uppercase(), value.to_string()))
+                .collect(),
+        ))
+    }
+}
+
+impl fmt::Display for State {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .0
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect();
+        write!(f, "{}", fields.join(" "))
+    }
+}
+
+/// Whether two values are the same: as hex numbers if both are, so `0x0A`
+/// matches `a`, and otherwise ignoring case.
+fn same(a: &str, b: &str) -> bool {
+    let number = |s: &str| {
+        let digits = s.strip_prefix("0x").or_else(|| s.strip_prefix('$'));
+        u64::from_str_radix(digits.unwrap_or(s), 16).ok()
+    };
+    match (number(a), number(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.eq_ignore_ascii_case(b),
+    }
+}
+
+/// Where two traces part ways.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// The number of the instruction the traces disagree at, from 1.
+    pub instruction: u64,
+    /// The field that differs, or `None` if one trace ended first.
+    pub field: Option<String>,
+    /// The first trace's state at the instruction, if it got that far.
+    pub first: Option<State>,
+    /// The second trace's state at the instruction, if it got that far.
+    pub second: Option<State>,
+    /// The state both traces agreed on last, before the instruction that
+    /// went differently.
+    pub previous: Option<State>,
+}
+
+/// Describes the divergence, with the lines around it.
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let n = self.instruction;
+        match (&self.field, &self.first, &self.second) {
+            (Some(field), Some(first), Some(second)) => {
+                let name = if field == "M" { "memory" } else { field };
+                let (a, b) = (first.get(field), second.get(field));
+                write!(
+                    f,
+                    "Traces diverge at instruction {n}, in {name}: {} in the first, {} in the second",
+                    a.unwrap_or_default(),
+                    b.unwrap_or_default()
+                )?;
+            }
+            (_, None, _) => write!(f, "The first trace ends at instruction {n}")?,
+            _ => write!(f, "The second trace ends at instruction {n}")?,
+        }
+        if let Some(previous) = &self.previous {
+            write!(f, "\n  after:  {previous}")?;
+        }
+        if let Some(first) = &self.first {
+            write!(f, "\n  first:  {first}")?;
+        }
+        if let Some(second) = &self.second {
+            write!(f, "\n  second: {second}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Compares two traces line by line, and finds the first instruction where
+/// a field held by both differs, or where one trace ends before the other.
+/// Blank lines, comments starting with `#`, and lines without fields are
+/// skipped.
+///
+/// # Errors
+/// This function will error if either trace cannot be read.
+pub fn diff(first: impl BufRead, second: impl BufRead) -> io::Result<Option<Divergence>> {
+    let (mut first, mut second) = (states(first), states(second));
+    let mut previous = None;
+    let mut instruction = 0;
+    loop {
+        instruction += 1;
+        let (a, b) = (first.next().transpose()?, second.next().transpose()?);
+        let field = match (&a, &b) {
+            (None, None) => return Ok(None),
+            (Some(x), Some(y)) => x.mismatch(y).map(str::to_string),
+            _ => None,
+        };
+        if field.is_none() && a.is_some() && b.is_some() {
+            previous = a;
+            continue;
+        }
+        return Ok(Some(Divergence {
+            instruction,
+            field,
+            first: a,
+            second: b,
+            previous,
+        }));
+    }
+}
+
+/// The states in a trace, skipping blank lines, comments, and lines
+/// without fields.
+fn states(trace: impl BufRead) -> impl Iterator<Item = io::Result<State>> {
+    trace.lines().filter_map(|line| match line {
+        Ok(line) if line.trim_start().starts_with('#') => None,
+        Ok(line) => {
+            let state: State = line.parse().unwrap_or_default();
+            (!state.0.is_empty()).then_some(Ok(state))
+        }
+        Err(e) => Some(Err(e)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diff_traces() {
+        let diff = |a: &str, b: &str| diff(a.as_bytes(), b.as_bytes()).unwrap();
+        let trace =
+            "# etherea trace v1\nPC=0200 OP=6005 V0=00 I=0000\nPC=0202 OP=7001 V0=05 I=0000\n";
+        assert_eq!(diff(trace, trace), None);
+        // Only the fields both traces hold are compared, however written.
+        assert_eq!(diff(trace, "pc:0x200 v0:0\n\nPC:202 V0:5 cycles:2\n"), None);
+
+        let other = "PC=0200 OP=6005 V0=00 I=0000\nPC=0202 OP=7001 V0=06 I=0000\n";
+        let divergence = diff(trace, other).unwrap();
+        assert_eq!(divergence.instruction, 2);
+        assert_eq!(divergence.field.as_deref(), Some("V0"));
+        assert_eq!(
+            divergence.to_string(),
+            "Traces diverge at instruction 2, in V0: 05 in the first, 06 in the second\n  \
+             after:  PC=0200 OP=6005 V0=00 I=0000\n  \
+             first:  PC=0202 OP=7001 V0=05 I=0000\n  \
+             second: PC=0202 OP=7001 V0=06 I=0000"
+        );
+
+        let short = diff(trace, "PC=0200\n").unwrap();
+        assert_eq!((short.instruction, short.field.as_deref()), (2, None));
+        assert!(short
+            .to_string()
+            .starts_with("The second trace ends at instruction 2"));
+    }
+
+    #[test]
+    fn write_traces() {
+        let path = std::env::temp_dir().join(format!("etherea-{}.trace", std::process::id()));
+        let mut intr = Interpreter::new();
+        intr.load_rom(&[0x60, 0x05, 0x70, 0x01, 0x12, 0x04]);
+        intr.with_trace(Writer::create(&path).unwrap());
+        intr.run_instructions(3).unwrap();
+        drop(intr);
+
+        let trace = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let states: Vec<State> = trace.lines().skip(1).map(|l| l.parse().unwrap()).collect();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[1].get("PC"), Some("0202"));
+        assert_eq!(states[1].get("OP"), Some("7001"));
+        assert_eq!(states[1].get("V0"), Some("05"));
+        assert_eq!(states[2].get("V0"), Some("06"));
+        assert_eq!(states[0].get("M"), states[2].get("M"));
+    }
+}
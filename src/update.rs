@@ -0,0 +1,171 @@
+use serde::Deserialize;
+use std::{fs, io, path::PathBuf};
+
+/// The GitHub API endpoint for the latest release.
+const LATEST: &str = "https://api.github.com/repos/cecelot/etherea/releases/latest";
+/// The number of changelog lines shown for a new release.
+const HIGHLIGHTS: usize = 5;
+
+/// A published release.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    /// The release's tag, like `v0.3.0`.
+    #[serde(rename = "tag_name")]
+    pub tag: String,
+    /// The release notes, in Markdown.
+    #[serde(default)]
+    pub body: String,
+    /// The files attached to the release.
+    #[serde(default)]
+    pub assets: Vec<Asset>,
+}
+
+/// A file attached to a [`Release`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct Asset {
+    /// The file name.
+    pub name: String,
+    /// Where the file can be downloaded from.
+    #[serde(rename = "browser_download_url")]
+    pub url: String,
+}
+
+impl Release {
+    /// Fetches the latest release.
+    ///
+    /// # Errors
+    /// This function will error if the request fails or the response isn't a
+    /// release.
+    pub fn latest() -> Result<Self, String> {
+        ureq::get(LATEST)
+            .set("User-Agent", concat!("etherea/", env!("CARGO_PKG_VERSION")))
+            .set("Accept", "application/vnd.github+json")
+            .call()
+            .map_err(|e| format!("Could not check for updates: {e}"))?
+            .into_json()
+            .map_err(|e| format!("Could not read the latest release: {e}"))
+    }
+
+    /// The version of the release, without the leading `v` of its tag.
+    #[must_use]
+    pub fn version(&self) -> &str {
+        self.tag.strip_prefix('v').unwrap_or(&self.tag)
+    }
+
+    /// Whether the release is newer than the running version.
+    #[must_use]
+    pub fn is_newer(&self) -> bool {
+        newer(self.version(), env!("CARGO_PKG_VERSION"))
+    }
+
+    /// The first few list items of the release notes.
+    #[must_use]
+    pub fn highlights(&self) -> Vec<&str> {
+        self.body
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim();
+                line.strip_prefix("- ").or_else(|| line.strip_prefix("* "))
+            })
+            .take(HIGHLIGHTS)
+            .collect()
+    }
+
+    /// The asset built for this platform, found by its name mentioning the
+    /// operating system and architecture.
+    #[must_use]
+    pub fn asset(&self) -> Option<&Asset> {
+        asset_for(&self.assets, std::env::consts::OS, std::env::consts::ARCH)
+    }
+
+    /// Downloads the asset built for this platform into the staging
+    /// directory, returning its path. The running binary isn't replaced.
+    ///
+    /// # Errors
+    /// This function will error if there is no asset for this platform, no
+    /// cache directory, or the download fails.
+    pub fn download(&self) -> Result<PathBuf, String> {
+        let asset = self.asset().ok_or_else(|| {
+            format!(
+                "Release {} has no download for {}-{}",
+                self.tag,
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            )
+        })?;
+        let dir = staging().ok_or("No cache directory to download the update to")?;
+        let path = dir.join(&asset.name);
+        let write = || -> io::Result<()> {
+            fs::create_dir_all(&dir)?;
+            let response = ureq::get(&asset.url).call().map_err(io::Error::other)?;
+            io::copy(&mut response.into_reader(), &mut fs::File::create(&path)?)?;
+            Ok(())
+        };
+        write().map_err(|e| format!("Could not download '{}': {e}", asset.url))?;
+        Ok(path)
+    }
+}
+
+/// The directory downloaded updates are staged in.
+#[must_use]
+pub fn staging() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("etherea").join("updates"))
+}
+
+/// Whether `version` is newer than `current`. Both are compared as
+/// dot-separated numbers, ignoring any pre-release or build suffix.
+fn newer(version: &str, current: &str) -> bool {
+    let parse = |version: &str| -> Vec<u64> {
+        version
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect()
+    };
+    parse(version) > parse(current)
+}
+
+/// The asset in `assets` whose name mentions `os` and `arch`.
+fn asset_for<'a>(assets: &'a [Asset], os: &str, arch: &str) -> Option<&'a Asset> {
+    assets.iter().find(|asset| {
+        let name = asset.name.to_lowercase();
+        name.contains(os) && name.contains(arch)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions() {
+        assert!(newer("0.3.0", "0.2.3"));
+        assert!(newer("0.10.0", "0.9.9"));
+        assert!(newer("1.0.0", "0.2.3-beta"));
+        assert!(!newer("0.2.3", "0.2.3"));
+        assert!(!newer("0.2.3-rc.1", "0.2.3"));
+        assert!(!newer("0.2.2", "0.2.3"));
+    }
+
+    #[test]
+    fn release() {
+        let release: Release = serde_json::from_str(
+            r#"{
+                "tag_name": "v0.3.0",
+                "body": "Changes:\n- Added a debugger\n* Faster drawing\nThanks!",
+                "assets": [
+                    {"name": "etherea-macos-aarch64", "browser_download_url": "https://a"},
+                    {"name": "etherea-linux-x86_64", "browser_download_url": "https://b"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(release.version(), "0.3.0");
+        assert_eq!(release.highlights(), ["Added a debugger", "Faster drawing"]);
+        let asset = asset_for(&release.assets, "linux", "x86_64").unwrap();
+        assert_eq!(asset.url, "https://b");
+        assert_eq!(asset_for(&release.assets, "windows", "x86_64"), None);
+    }
+}
@@ -0,0 +1,242 @@
+use crate::opcode::{Opcode, DOCS};
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+};
+use core::{fmt, str::FromStr};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// The COSMAC VIP's machine cycles per second: its 1.76064 MHz clock, with
+/// 8 clock pulses to each machine cycle.
+pub const CYCLES_PER_SECOND: u64 = 1_760_640 / 8;
+/// The machine cycles of each frame the display takes from the
+/// interpreter: the CDP1861's DMA of 8 bytes for each of 128 scanlines, and
+/// about 50 more for the interrupt routine that starts it and ticks the
+/// timers.
+pub const DISPLAY_CYCLES: u64 = 128 * 8 + 50;
+/// The machine cycles the interpreter takes to fetch and decode each
+/// instruction, before executing it.
+pub const FETCH_CYCLES: u32 = 40;
+
+/// The machine cycles left for instructions in each frame, at
+/// `frames_per_second`.
+#[must_use]
+pub fn cycles_per_frame(frames_per_second: u32) -> u64 {
+    (CYCLES_PER_SECOND / u64::from(frames_per_second.max(1))).saturating_sub(DISPLAY_CYCLES)
+}
+
+/// What each instruction costs to execute on the COSMAC VIP, in machine
+/// cycles, for timing ROMs as the original hardware ran them rather than at
+/// a flat number of instructions per frame. The costs are approximate, and
+/// any of them can be changed, by pattern, e.g. `"8XY4" = 50`.
+///
+/// Some instructions cost more the more work they do: `DXYN` costs its
+/// base plus a cost for each row, which is higher when the sprite isn't
+/// aligned to a byte and has to be shifted; `FX33` its base plus a cost for
+/// each unit of the digits it stores; and `FX55` and `FX65` their base plus
+/// a cost for each register.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(Serialize, Deserialize),
+    serde(try_from = "BTreeMap<String, u32>", into = "BTreeMap<String, u32>")
+)]
+pub struct Costs {
+    overrides: BTreeMap<String, u32>, // Base costs changed from the defaults, by pattern
+}
+
+impl Costs {
+    /// The cost of each row of a sprite drawn aligned to a byte.
+    const ROW: u32 = 23;
+    /// The cost of each row of a sprite that has to be shifted into place.
+    const SHIFTED_ROW: u32 = 45;
+    /// The cost of each unit of the digits `FX33` stores.
+    const DIGIT: u32 = 14;
+    /// The cost of each register `FX55` and `FX65` store or load.
+    const REGISTER: u32 = 14;
+
+    /// The default costs, with the base costs of the instructions in
+    /// `overrides` changed, by pattern.
+    ///
+    /// # Errors
+    /// This function will error if a pattern isn't one of an instruction.
+    pub fn new(overrides: BTreeMap<String, u32>) -> Result<Self, String> {
+        let overrides = overrides
+            .into_iter()
+            .map(|(pattern, cost)| {
+                DOCS.iter()
+                    .find(|doc| doc.pattern.eq_ignore_ascii_case(&pattern))
+                    .map(|doc| (doc.pattern.to_string(), cost))
+                    .ok_or_else(|| format!("Unknown instruction pattern '{pattern}'"))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { overrides })
+    }
+
+    /// The cost of `inst` without the parts that vary, unless it's been
+    /// changed.
+    #[must_use]
+    pub fn base(&self, inst: Opcode) -> u32 {
+        self.overrides
+            .get(inst.pattern())
+            .copied()
+            .unwrap_or_else(|| default(inst))
+    }
+
+    /// The machine cycles fetching, decoding, and executing `inst` costs,
+    /// given the variable registers before it runs.
+    #[must_use]
+    pub fn cost(&self, inst: Opcode, registers: &[u8; 16]) -> u32 {
+        let v = |x: u8| registers[usize::from(x & 0xF)];
+        let variable = match inst {
+            Opcode::Draw { x, n, .. } if v(x) % 8 == 0 => u32::from(n) * Self::ROW,
+            Opcode::Draw { n, .. } => u32::from(n) * Self::SHIFTED_ROW,
+            Opcode::Bcd { x } => {
+                let vx = v(x);
+                u32::from(vx / 100 + vx / 10 % 10 + vx % 10) * Self::DIGIT
+            }
+            Opcode::Store { x }
+            | Opcode::LoadMemory { x }
+            | Opcode::SaveFlags { x }
+            | Opcode::LoadFlags { x } => (u32::from(x) + 1) * Self::REGISTER,
+            _ => 0,
+        };
+        FETCH_CYCLES + self.base(inst) + variable
+    }
+}
+
+impl TryFrom<BTreeMap<String, u32>> for Costs {
+    type Error = String;
+
+    fn try_from(overrides: BTreeMap<String, u32>) -> Result<Self, Self::Error> {
+        Self::new(overrides)
+    }
+}
+
+impl From<Costs> for BTreeMap<String, u32> {
+    fn from(costs: Costs) -> Self {
+        costs.overrides
+    }
+}
+
+/// The changed base costs, like `DXYN=30 8XY4=50`, or nothing for the
+/// defaults.
+impl fmt::Display for Costs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (n, (pattern, cost)) in self.overrides.iter().enumerate() {
+            if n > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{pattern}={cost}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Costs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let overrides = s
+            .split_whitespace()
+            .map(|word| {
+                let err = || format!("Invalid cost: '{word}'");
+                let (pattern, cost) = word.split_once('=').ok_or_else(err)?;
+                Ok((pattern.to_string(), cost.parse().map_err(|_| err())?))
+            })
+            .collect::<Result<_, String>>()?;
+        Self::new(overrides)
+    }
+}
+
+/// The default cost of executing `inst`, without the parts that vary.
+const fn default(inst: Opcode) -> u32 {
+    match inst {
+        Opcode::Load { .. } => 6,
+        Opcode::AddImm { .. }
+        | Opcode::LoadDelay { .. }
+        | Opcode::WaitKey { .. }
+        | Opcode::SetDelay { .. }
+        | Opcode::SetSound { .. } => 10,
+        Opcode::SkipEq { .. } | Opcode::SkipNe { .. } | Opcode::LoadIndex(_) => 12,
+        Opcode::Store { .. }
+        | Opcode::LoadMemory { .. }
+        | Opcode::SaveFlags { .. }
+        | Opcode::LoadFlags { .. } => 14,
+        Opcode::SkipEqReg { .. }
+        | Opcode::SkipNeReg { .. }
+        | Opcode::SkipKey { .. }
+        | Opcode::SkipNotKey { .. } => 16,
+        Opcode::AddIndex { .. } => 19,
+        Opcode::Font { .. } => 20,
+        Opcode::Sys(_)
+        | Opcode::Return
+        | Opcode::Jump(_)
+        | Opcode::Call(_)
+        | Opcode::JumpOffset(_) => 23,
+        Opcode::ClearScreen
+        | Opcode::ScrollDown { .. }
+        | Opcode::ScrollUp { .. }
+        | Opcode::ScrollRight
+        | Opcode::ScrollLeft => 24,
+        Opcode::Draw { .. } => 26,
+        Opcode::Random { .. } => 36,
+        Opcode::Move { .. }
+        | Opcode::Or { .. }
+        | Opcode::And { .. }
+        | Opcode::Xor { .. }
+        | Opcode::Add { .. }
+        | Opcode::Sub { .. }
+        | Opcode::ShiftRight { .. }
+        | Opcode::SubN { .. }
+        | Opcode::ShiftLeft { .. } => 44,
+        Opcode::Bcd { .. } => 80,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn costs() {
+        let costs = Costs::default();
+        let mut registers = [0; 16];
+        assert_eq!(costs.cost(Opcode::Load { x: 0, nn: 1 }, &registers), 46);
+        let draw = Opcode::Draw { x: 0, y: 1, n: 5 };
+        assert_eq!(costs.cost(draw, &registers), 40 + 26 + 5 * 23);
+        registers[0] = 3;
+        assert_eq!(costs.cost(draw, &registers), 40 + 26 + 5 * 45);
+        registers[2] = 123;
+        assert_eq!(
+            costs.cost(Opcode::Bcd { x: 2 }, &registers),
+            40 + 80 + 6 * 14
+        );
+        assert_eq!(
+            costs.cost(Opcode::Store { x: 3 }, &registers),
+            40 + 14 + 4 * 14
+        );
+        assert_eq!(cycles_per_frame(60), 3668 - 1074);
+    }
+
+    #[test]
+    fn overrides() {
+        let costs = Costs::new([("8xy4".to_string(), 50)].into()).unwrap();
+        assert_eq!(costs.base(Opcode::Add { x: 0, y: 1 }), 50);
+        assert_eq!(costs.base(Opcode::Sub { x: 0, y: 1 }), 44);
+        assert!(Costs::new([("8XY9".to_string(), 1)].into()).is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let costs: Costs = "dxyn=30 8XY4=50".parse().unwrap();
+        assert_eq!(costs.base(Opcode::Draw { x: 0, y: 0, n: 1 }), 30);
+        assert_eq!(costs.to_string(), "8XY4=50 DXYN=30");
+        assert_eq!(costs.to_string().parse(), Ok(costs));
+        assert_eq!("".parse(), Ok(Costs::default()));
+        assert!("DXYN".parse::<Costs>().is_err());
+        assert!("ZZZZ=1".parse::<Costs>().is_err());
+    }
+}
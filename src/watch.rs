@@ -0,0 +1,120 @@
+use crate::{Event, SwapMode};
+use log::{error, info};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::mpsc::Sender,
+};
+
+/// Rereads a ROM file, keeping what was last read so that the several
+/// change events an editor or assembler causes for one save only reload the
+/// ROM once.
+#[derive(Debug)]
+pub struct Reloader {
+    path: PathBuf,
+    loaded: Vec<u8>, // The contents of the file when it was last read
+}
+
+impl Reloader {
+    /// Starts with the contents of `path`, which `rom` was loaded from.
+    #[must_use]
+    pub fn new(path: &Path, rom: &[u8]) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            loaded: fs::read(path).unwrap_or_else(|_| rom.to_vec()),
+        }
+    }
+
+    /// Rereads the file, returning the ROM in it if its contents changed.
+    /// Octo cartridges and source are assembled, as when they were first
+    /// loaded. An empty file is taken to be partway through being written
+    /// and is ignored.
+    pub fn reload(&mut self) -> Option<Vec<u8>> {
+        match fs::read(&self.path) {
+            Ok(file) if !file.is_empty() && file != self.loaded => {
+                self.loaded.clone_from(&file);
+                crate::octo::load(&self.path, file)
+                    .map_err(|e| error!("Could not reload the ROM: {}", e))
+                    .ok()
+            }
+            Ok(_) => None,
+            Err(e) => {
+                error!("Could not read '{}': {}", self.path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// Watches the ROM at `path`, sending an [`Event::SwapRom`] to `tx` with
+/// its new contents whenever it changes. `rom` is what was loaded from it
+/// to begin with. The file is watched until the returned watcher is dropped.
+///
+/// The directory is watched rather than the file itself, since many editors
+/// save by replacing the file instead of writing to it.
+///
+/// # Errors
+/// This function will error if the directory containing `path` cannot be
+/// watched.
+pub fn watch(path: &Path, rom: &[u8], tx: Sender<Event>) -> notify::Result<RecommendedWatcher> {
+    let name = path.file_name().map(ToOwned::to_owned);
+    let mut reloader = Reloader::new(path, rom);
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => return error!("Could not watch the ROM: {}", e),
+        };
+        let written = matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+        if !written || !event.paths.iter().any(|p| p.file_name() == name.as_deref()) {
+            return;
+        }
+        if let Some(rom) = reloader.reload() {
+            info!("Reloading '{}'", reloader.path.display());
+            let mode = SwapMode::Reset;
+            // The interpreter's thread only stops when the process exits.
+            let _ = tx.send(Event::SwapRom { rom, mode });
+        }
+    })?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time::Duration};
+
+    #[test]
+    fn reloads_changes() {
+        let dir = std::env::temp_dir().join(format!("etherea-watch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("rom.ch8");
+        fs::write(&path, [0x00, 0xE0]).unwrap();
+
+        let mut reloader = Reloader::new(&path, &[0x00, 0xE0]);
+        assert_eq!(reloader.reload(), None);
+        fs::write(&path, []).unwrap();
+        assert_eq!(reloader.reload(), None);
+        fs::write(&path, [0x12, 0x00]).unwrap();
+        assert_eq!(reloader.reload(), Some(vec![0x12, 0x00]));
+        assert_eq!(reloader.reload(), None);
+
+        let (tx, rx) = mpsc::channel();
+        let _watcher = watch(&path, &[0x12, 0x00], tx).unwrap();
+        fs::write(&path, [0x00, 0xEE]).unwrap();
+        let event = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(
+            event,
+            Event::SwapRom {
+                rom: vec![0x00, 0xEE],
+                mode: SwapMode::Reset
+            }
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
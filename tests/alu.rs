@@ -0,0 +1,69 @@
+//! Checks the results and `VF` flags of the arithmetic instructions against
+//! their definitions, for any register values, including when `VX` is `VF`
+//! itself: the flag is written last, so it wins over the result.
+
+use etherea::Interpreter;
+use proptest::prelude::*;
+
+/// Sets `VX` to `vx` and `VY` to `vy` on a new interpreter, in that order,
+/// then executes the `8XYN` instruction `n`, and returns the registers.
+fn execute(x: usize, y: usize, n: u16, vx: u8, vy: u8) -> [u8; 16] {
+    let mut intr = Interpreter::new();
+    intr.load_rom(&[]);
+    intr.set_register(x, vx);
+    intr.set_register(y, vy);
+    let opcode = 0x8000 | u16::try_from(x << 8 | y << 4).unwrap() | n;
+    intr.execute_opcode(opcode).unwrap();
+    *intr.registers()
+}
+
+/// What `VX` and `VF` should be after the `8XYN` instruction `n`, given
+/// `VX` and `VY` before it.
+fn expected(n: u16, vx: u8, vy: u8) -> (u8, bool) {
+    match n {
+        0x4 => (vx.wrapping_add(vy), u16::from(vx) + u16::from(vy) > 0xFF),
+        0x5 => (vx.wrapping_sub(vy), vx >= vy),
+        0x6 => (vx >> 1, vx & 1 == 1),
+        0x7 => (vy.wrapping_sub(vx), vy >= vx),
+        0xE => (vx << 1, vx & 0x80 != 0),
+        _ => unreachable!(),
+    }
+}
+
+/// The `N` of any of the arithmetic `8XYN` instructions.
+fn alu() -> impl Strategy<Value = u16> {
+    prop::sample::select(vec![0x4, 0x5, 0x6, 0x7, 0xE])
+}
+
+proptest! {
+    #[test]
+    fn flags(n in alu(), x in 0..0xFusize, y in 0..0xFusize, vx: u8, vy: u8) {
+        // When X is Y, both hold the value set last.
+        let vx = if x == y { vy } else { vx };
+        let registers = execute(x, y, n, vx, vy);
+        let (result, flag) = expected(n, vx, vy);
+        prop_assert_eq!(registers[x], result);
+        prop_assert_eq!(registers[0xF], u8::from(flag));
+        for (register, &value) in registers.iter().enumerate().take(0xF) {
+            if register != x && register != y {
+                prop_assert_eq!(value, 0);
+            }
+        }
+    }
+
+    #[test]
+    fn flag_register(n in alu(), y in 0..0xFusize, vf: u8, vy: u8) {
+        let registers = execute(0xF, y, n, vf, vy);
+        let (_, flag) = expected(n, vf, vy);
+        prop_assert_eq!(registers[0xF], u8::from(flag));
+        prop_assert_eq!(registers[y], vy);
+    }
+
+    #[test]
+    fn flag_operand(n in alu(), x in 0..0xFusize, vx: u8, vf: u8) {
+        let registers = execute(x, 0xF, n, vx, vf);
+        let (result, flag) = expected(n, vx, vf);
+        prop_assert_eq!(registers[x], result);
+        prop_assert_eq!(registers[0xF], u8::from(flag));
+    }
+}
@@ -0,0 +1,58 @@
+//! Runs every ROM in `roms/` headlessly and compares its screen with the
+//! ASCII snapshot of the same name in `roms/expected/`, so that changes to
+//! instructions can't quietly change what ROMs draw.
+//!
+//! After a change that is meant to alter a screen, check the new one and
+//! rewrite the snapshots with `UPDATE_SNAPSHOTS=1 cargo test --test snapshots`.
+
+use etherea::Interpreter;
+use std::{env, fs, path::Path};
+
+/// How many frames each ROM runs before its screen is compared.
+const FRAMES: u64 = 60;
+
+/// The screen of `rom` after [`FRAMES`] frames, as ASCII art.
+fn screen(rom: &[u8]) -> String {
+    let mut intr = Interpreter::new();
+    intr.with_seed(0);
+    intr.load_rom(rom);
+    if let Err(fault) = intr.run_frames(FRAMES) {
+        panic!("the ROM faulted: {fault}");
+    }
+    intr.framebuffer().to_string()
+}
+
+#[test]
+fn screens_match_snapshots() {
+    let update = env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut failures = Vec::new();
+    let mut roms: Vec<_> = fs::read_dir("roms")
+        .unwrap()
+        .map(|file| file.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ch8"))
+        .collect();
+    roms.sort();
+
+    for path in &roms {
+        let name = path.file_stem().unwrap().to_string_lossy();
+        let snapshot = Path::new("roms/expected").join(format!("{name}.txt"));
+        let actual = screen(&fs::read(path).unwrap());
+        if update {
+            fs::write(&snapshot, &actual).unwrap();
+            continue;
+        }
+        match fs::read_to_string(&snapshot) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => failures.push(format!(
+                "{name}: the screen doesn't match {}\nexpected:\n{expected}\nactual:\n{actual}",
+                snapshot.display()
+            )),
+            Err(_) => failures.push(format!(
+                "{name}: no snapshot at {}; run with UPDATE_SNAPSHOTS=1 to write it",
+                snapshot.display()
+            )),
+        }
+    }
+    assert!(!roms.is_empty(), "no ROMs in roms/");
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}